@@ -4,5 +4,65 @@ echo Building WASM module...
 REM Build the WASM module
 wasm-pack build --target web --out-dir ../web/wasm
 
+REM Optionally build a second copy with WebAssembly SIMD enabled. This only
+REM allows LLVM auto-vectorisation; there is no hand-written SIMD code.
+REM web/wasm-crypto.js loads it instead when the browser supports simd128.
+if "%1"=="simd" (
+    echo Building SIMD WASM module...
+    set RUSTFLAGS=-C target-feature=+simd128
+    wasm-pack build --target web --out-dir ../web/wasm-simd
+    set RUSTFLAGS=
+)
+
+REM Optionally build a multi-threaded copy (rayon over wasm threads). Needs a
+REM nightly toolchain and a page served cross-origin isolated (COOP/COEP).
+if "%1"=="threads" (
+    echo Building threaded WASM module...
+    set RUSTFLAGS=-C target-feature=+atomics,+bulk-memory
+    rustup run nightly wasm-pack build --target web --out-dir ../web/wasm-threads -- --features parallel -Z build-std=panic_abort,std
+    set RUSTFLAGS=
+)
+
+REM Optionally build with wee_alloc instead of dlmalloc, trading allocation
+REM speed for a slightly smaller binary.
+if "%1"=="small" (
+    echo Building small-binary WASM module...
+    wasm-pack build --target web --out-dir ../web/wasm -- --features small-binary
+)
+
+REM Optionally build a copy for Node.js (CommonJS, loads the .wasm from disk)
+REM for server-side tests and CLI tooling; see js-tests/node.test.mjs.
+if "%1"=="node" (
+    echo Building Node.js WASM module...
+    wasm-pack build --target nodejs --out-dir pkg-node
+)
+
+REM Optionally build an ES-module copy for Deno, Bun and edge runtimes; load it
+REM with js/load.mjs, see js-tests/runtime-smoke.mjs.
+if "%1"=="esm" (
+    echo Building ES-module WASM module...
+    wasm-pack build --target web --out-dir pkg-esm
+)
+
+REM Optionally build the furl CLI for WASI (furl-core only, no js-sys/web-sys)
+REM for Cloudflare Workers, Fastly Compute and other WASI hosts; see
+REM js-tests/wasi-smoke.mjs.
+if "%1"=="wasi" (
+    echo Building WASI module...
+    cargo build --release -p furl-cli --target wasm32-wasip1
+    if not exist pkg-wasi mkdir pkg-wasi
+    copy /Y target\wasm32-wasip1\release\furl.wasm pkg-wasi\
+)
+
+REM Optionally generate the Kotlin and Swift bindings (furl-core via uniffi)
+REM into furl-mobile\bindings\, from a host build of the furl-mobile library.
+if "%1"=="mobile" (
+    echo Generating Kotlin and Swift bindings...
+    cargo build --release -p furl-mobile
+    for %%L in (kotlin swift) do (
+        cargo run --release -p furl-mobile --bin uniffi-bindgen -- generate --library target\release\furl_mobile.dll --language %%L --out-dir furl-mobile\bindings\%%L
+    )
+)
+
 echo WASM module built successfully!
 echo Files generated in ../web/wasm/
@@ -5,5 +5,65 @@ echo "Building WASM module..."
 # Build the WASM module
 wasm-pack build --target web --out-dir ../web/wasm
 
+# Optionally build a second copy with WebAssembly SIMD enabled. This only
+# allows LLVM auto-vectorisation; there is no hand-written SIMD code.
+# web/wasm-crypto.js loads it instead when the browser supports simd128.
+if [ "$1" == "simd" ]; then
+    echo "Building SIMD WASM module..."
+    RUSTFLAGS="-C target-feature=+simd128" wasm-pack build --target web --out-dir ../web/wasm-simd
+fi
+
+# Optionally build a multi-threaded copy (rayon over wasm threads). Needs a
+# nightly toolchain and a page served cross-origin isolated (COOP/COEP).
+if [ "$1" == "threads" ]; then
+    echo "Building threaded WASM module..."
+    RUSTFLAGS="-C target-feature=+atomics,+bulk-memory" rustup run nightly \
+        wasm-pack build --target web --out-dir ../web/wasm-threads -- \
+        --features parallel -Z build-std=panic_abort,std
+fi
+
+# Optionally build with wee_alloc instead of dlmalloc, trading allocation
+# speed for a slightly smaller binary.
+if [ "$1" == "small" ]; then
+    echo "Building small-binary WASM module..."
+    wasm-pack build --target web --out-dir ../web/wasm -- --features small-binary
+fi
+
+# Optionally build a copy for Node.js (CommonJS, loads the .wasm from disk)
+# for server-side tests and CLI tooling; see js-tests/node.test.mjs.
+if [ "$1" == "node" ]; then
+    echo "Building Node.js WASM module..."
+    wasm-pack build --target nodejs --out-dir pkg-node
+fi
+
+# Optionally build an ES-module copy for Deno, Bun and edge runtimes; load it
+# with js/load.mjs, see js-tests/runtime-smoke.mjs.
+if [ "$1" == "esm" ]; then
+    echo "Building ES-module WASM module..."
+    wasm-pack build --target web --out-dir pkg-esm
+fi
+
+# Optionally build the furl CLI for WASI (furl-core only, no js-sys/web-sys)
+# for Cloudflare Workers, Fastly Compute and other WASI hosts; see
+# js-tests/wasi-smoke.mjs.
+if [ "$1" == "wasi" ]; then
+    echo "Building WASI module..."
+    cargo build --release -p furl-cli --target wasm32-wasip1
+    mkdir -p pkg-wasi
+    cp target/wasm32-wasip1/release/furl.wasm pkg-wasi/
+fi
+
+# Optionally generate the Kotlin and Swift bindings (furl-core via uniffi)
+# into furl-mobile/bindings/, from a host build of the furl-mobile library.
+if [ "$1" == "mobile" ]; then
+    echo "Generating Kotlin and Swift bindings..."
+    cargo build --release -p furl-mobile
+    for language in kotlin swift; do
+        cargo run --release -p furl-mobile --bin uniffi-bindgen -- generate \
+            --library target/release/libfurl_mobile.so --language $language \
+            --out-dir furl-mobile/bindings/$language
+    done
+fi
+
 echo "WASM module built successfully!"
 echo "Files generated in ../web/wasm/"
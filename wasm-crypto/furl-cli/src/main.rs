@@ -0,0 +1,346 @@
+//! `furl`: encrypt and decrypt furl containers from the terminal
+//! 
+//! Uses the same `furl-core` container code as the browser module, so a file
+//! sealed here opens with `decrypt_container` / `ContainerDecryptor` in the
+//! browser and the other way round.
+//! 
+//! ```text
+//! furl keygen
+//! furl encrypt --key-file share.key report.pdf -o report.pdf.furl
+//! furl decrypt --key-file share.key report.pdf.furl -o report.pdf
+//! cat data | furl encrypt --key "$FURL_KEY" | upload
+//! furl open --link "$URL" --offset 1048576 < part.bin > part.plain
+//! ```
+//! 
+//! Keys are 32 bytes, given as hex or URL-safe base64 (the form used in
+//! share links). Input defaults to stdin and output to stdout; errors go to
+//! stderr with exit status 1, or 2 for a bad command line.
+//! 
+//! Nothing here needs more than WASI preview 1, so the same binary built for
+//! `wasm32-wasip1` (`./build.sh wasi`) runs in edge runtimes.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use base64::Engine;
+use base64::engine::general_purpose::{URL_SAFE_NO_PAD, STANDARD};
+use furl_core::container::{Algorithm, ContainerReader, ContainerWriter, DEFAULT_CHUNK_SIZE};
+use furl_core::ctr::AesCtr;
+use furl_core::encoding::decode_hex;
+use furl_core::sharelink::parse_fragment;
+use furl_core::CryptoError;
+
+/// Bytes read from the input per container update
+const READ_SIZE: usize = 64 * 1024;
+
+const USAGE: &str = "\
+Usage:
+  furl keygen
+  furl encrypt (--key KEY | --key-file PATH) [--algorithm NAME] [--chunk-size BYTES] [INPUT] [-o OUTPUT]
+  furl decrypt (--key KEY | --key-file PATH) [INPUT] [-o OUTPUT]
+  furl open [--link LINK] [--offset BYTES] [INPUT] [-o OUTPUT]
+
+KEY is 32 bytes as hex or URL-safe base64; FURL_KEY is used when neither
+--key nor --key-file is given. INPUT and OUTPUT default to stdin and stdout.
+--algorithm is aes-256-gcm (default) or chacha20-poly1305.
+
+open decrypts an AES-CTR share with the key and IV in LINK (a share URL or
+its fragment, default FURL_LINK); --offset is where INPUT starts in the
+file, e.g. the start of a Range response.";
+
+/// What to do, parsed from the command line
+#[derive(Debug, PartialEq)]
+enum Command {
+    Keygen,
+    Encrypt(Options),
+    Decrypt(Options),
+    Open(Options),
+    Help,
+}
+
+/// Arguments shared by `encrypt` and `decrypt`
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    key: Option<String>,
+    key_file: Option<String>,
+    algorithm: Option<String>,
+    chunk_size: Option<u32>,
+    /// Fixed base nonce (hex) instead of a random one, for reproducing test vectors
+    nonce: Option<String>,
+    link: Option<String>,
+    offset: Option<u64>,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+/// Failures, split by exit status
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Crypto(CryptoError),
+    Io(io::Error),
+}
+
+impl From<CryptoError> for CliError {
+    fn from(error: CryptoError) -> Self {
+        CliError::Crypto(error)
+    }
+}
+
+impl From<furl_core::container::ContainerError> for CliError {
+    fn from(error: furl_core::container::ContainerError) -> Self {
+        CliError::Crypto(error.into())
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(error: io::Error) -> Self {
+        CliError::Io(error)
+    }
+}
+
+fn main() -> ExitCode {
+    let result = parse_args(std::env::args().skip(1)).and_then(run);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::Usage(message)) => {
+            eprintln!("furl: {}\n\n{}", message, USAGE);
+            ExitCode::from(2)
+        }
+        Err(CliError::Crypto(error)) => {
+            eprintln!("furl: {}: {}", error.name(), error);
+            ExitCode::FAILURE
+        }
+        // A closed pipe (e.g. `furl decrypt ... | head`) is not an error
+        Err(CliError::Io(error)) if error.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(CliError::Io(error)) => {
+            eprintln!("furl: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or_else(|| CliError::Usage("missing command".into()))?;
+
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| CliError::Usage(format!("{} needs a value", arg)));
+        match arg.as_str() {
+            "--key" => options.key = Some(value()?),
+            "--key-file" => options.key_file = Some(value()?),
+            "--algorithm" => options.algorithm = Some(value()?),
+            "--nonce" => options.nonce = Some(value()?),
+            "--link" => options.link = Some(value()?),
+            "--offset" => {
+                let offset = value()?;
+                options.offset = Some(offset.parse().map_err(|_| CliError::Usage(format!("invalid offset: {}", offset)))?);
+            }
+            "--chunk-size" => {
+                let size = value()?;
+                options.chunk_size = Some(size.parse().map_err(|_| CliError::Usage(format!("invalid chunk size: {}", size)))?);
+            }
+            "-o" | "--output" => options.output = Some(value()?),
+            "-h" | "--help" => return Ok(Command::Help),
+            flag if flag.starts_with('-') && flag != "-" => return Err(CliError::Usage(format!("unknown option {}", flag))),
+            _ if options.input.is_none() => options.input = Some(arg),
+            _ => return Err(CliError::Usage(format!("unexpected argument {}", arg))),
+        }
+    }
+
+    match command.as_str() {
+        "keygen" => Ok(Command::Keygen),
+        "encrypt" => Ok(Command::Encrypt(options)),
+        "decrypt" => Ok(Command::Decrypt(options)),
+        "open" => Ok(Command::Open(options)),
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        other => Err(CliError::Usage(format!("unknown command {}", other))),
+    }
+}
+
+fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Keygen => {
+            println!("{}", encode_hex(&random_bytes::<32>()?));
+            Ok(())
+        }
+        Command::Encrypt(options) => {
+            let key = read_key(&options)?;
+            let algorithm = Algorithm::from_name(options.algorithm.as_deref())?;
+            let nonce = match &options.nonce {
+                Some(nonce) => decode_hex(nonce).map_err(|_| CliError::Usage("--nonce must be hex".into()))?,
+                None => random_bytes::<12>()?.to_vec(),
+            };
+            let mut writer = ContainerWriter::new(&key, &nonce, options.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE), algorithm)?;
+
+            with_files(&options, |input, output| {
+                pump(input, output, |data| Ok(writer.update(data)?))?;
+                output.write_all(&writer.finish()?)?;
+                Ok(())
+            })
+        }
+        Command::Decrypt(options) => {
+            let key = read_key(&options)?;
+            let mut reader = ContainerReader::new(&key)?;
+
+            with_files(&options, |input, output| {
+                pump(input, output, |data| Ok(reader.update(data)?))?;
+                reader.finish()?;
+                Ok(())
+            })
+        }
+        Command::Open(options) => {
+            let link = match &options.link {
+                Some(link) => link.clone(),
+                None => std::env::var("FURL_LINK").map_err(|_| CliError::Usage("no link: pass --link or set FURL_LINK".into()))?,
+            };
+            let fragment = parse_fragment(link.split_once('#').map_or(link.as_str(), |(_, fragment)| fragment))?;
+            let mut cipher = AesCtr::new(&fragment.key, &fragment.iv)?;
+            cipher.seek(options.offset.unwrap_or(0))?;
+
+            with_files(&options, |input, output| {
+                pump(input, output, |data| {
+                    let mut data = data.to_vec();
+                    cipher.apply_keystream(&mut data)?;
+                    Ok(data)
+                })
+            })
+        }
+    }
+}
+
+/// Open the input and output, run `body`, and remove a partly written output file on failure
+fn with_files(
+    options: &Options,
+    body: impl FnOnce(&mut dyn Read, &mut dyn Write) -> Result<(), CliError>
+) -> Result<(), CliError> {
+    let mut input: Box<dyn Read> = match options.input.as_deref() {
+        None | Some("-") => Box::new(io::stdin().lock()),
+        Some(path) => Box::new(File::open(path)?),
+    };
+
+    match options.output.as_deref() {
+        None | Some("-") => {
+            let mut output = BufWriter::new(io::stdout().lock());
+            body(&mut input, &mut output)?;
+            output.flush()?;
+            Ok(())
+        }
+        Some(path) => {
+            let mut output = BufWriter::new(File::create(path)?);
+            let result = body(&mut input, &mut output).and_then(|()| Ok(output.flush()?));
+            if result.is_err() {
+                drop(output);
+                let _ = std::fs::remove_file(path);
+            }
+            result
+        }
+    }
+}
+
+/// Feed `input` through `step` in `READ_SIZE` pieces, writing what it returns
+fn pump(
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    mut step: impl FnMut(&[u8]) -> Result<Vec<u8>, CliError>
+) -> Result<(), CliError> {
+    let mut buffer = vec![0u8; READ_SIZE];
+    loop {
+        let read = match input.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        output.write_all(&step(&buffer[..read])?)?;
+    }
+}
+
+/// Key from `--key`, `--key-file` or `FURL_KEY`, as hex or URL-safe base64
+fn read_key(options: &Options) -> Result<Vec<u8>, CliError> {
+    let text = match (&options.key, &options.key_file) {
+        (Some(_), Some(_)) => return Err(CliError::Usage("give only one of --key and --key-file".into())),
+        (Some(key), None) => key.clone(),
+        (None, Some(path)) => std::fs::read_to_string(path)?,
+        (None, None) => std::env::var("FURL_KEY").map_err(|_| CliError::Usage("no key: pass --key or --key-file, or set FURL_KEY".into()))?,
+    };
+    parse_key(text.trim())
+}
+
+fn parse_key(text: &str) -> Result<Vec<u8>, CliError> {
+    let key = match decode_hex(text) {
+        Ok(key) if text.len() == 64 => key,
+        _ => {
+            let unpadded = text.trim_end_matches('=');
+            URL_SAFE_NO_PAD
+                .decode(unpadded)
+                .or_else(|_| STANDARD.decode(text))
+                .map_err(|_| CryptoError::InvalidKey("Invalid key: expected hex or base64".into()))?
+        }
+    };
+    if key.len() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.len())).into());
+    }
+    Ok(key)
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], CliError> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|error| CryptoError::Internal(format!("Random number generator failed: {}", error)))?;
+    Ok(bytes)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Command, CliError> {
+        parse_args(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parses_commands_and_options() {
+        assert_eq!(args("keygen").unwrap(), Command::Keygen);
+        assert_eq!(
+            args("encrypt --key k --chunk-size 1024 in.bin -o out.furl").unwrap(),
+            Command::Encrypt(Options {
+                key: Some("k".into()),
+                chunk_size: Some(1024),
+                input: Some("in.bin".into()),
+                output: Some("out.furl".into()),
+                ..Options::default()
+            })
+        );
+        assert_eq!(args("decrypt -").unwrap(), Command::Decrypt(Options { input: Some("-".into()), ..Options::default() }));
+
+        assert_eq!(
+            args("open --link #v=1 --offset 4096").unwrap(),
+            Command::Open(Options { link: Some("#v=1".into()), offset: Some(4096), ..Options::default() })
+        );
+
+        for bad in ["", "shred", "encrypt --bogus", "encrypt --key", "encrypt a b", "encrypt --chunk-size big", "open --offset -1"] {
+            assert!(matches!(args(bad), Err(CliError::Usage(_))), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn keys_are_hex_or_base64() {
+        let key: Vec<u8> = (0..32).collect();
+        assert_eq!(parse_key(&encode_hex(&key)).unwrap(), key);
+        assert_eq!(parse_key(&URL_SAFE_NO_PAD.encode(&key)).unwrap(), key);
+        assert_eq!(parse_key(&STANDARD.encode(&key)).unwrap(), key);
+
+        assert!(matches!(parse_key("00ff"), Err(CliError::Crypto(CryptoError::InvalidKeyLength(_)))));
+        assert!(matches!(parse_key("not a key!"), Err(CliError::Crypto(CryptoError::InvalidKey(_)))));
+    }
+}
@@ -0,0 +1,89 @@
+//! Runs the `furl` binary against the container test vectors shared with
+//! furl-core and the wasm module
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use furl_core::encoding::decode_hex;
+
+fn furl(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_furl"))
+        .args(args)
+        .env_remove("FURL_KEY")
+        .env_remove("FURL_LINK")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn vectors() -> Vec<serde_json::Value> {
+    let vectors: serde_json::Value = serde_json::from_str(include_str!("../../test-vectors/container.json")).unwrap();
+    vectors["vectors"].as_array().unwrap().clone()
+}
+
+#[test]
+fn encrypt_reproduces_shared_vectors() {
+    for vector in vectors() {
+        let field = |name: &str| vector[name].as_str().unwrap().to_string();
+        let chunk_size = vector["chunk_size"].to_string();
+        let args = ["encrypt", "--key", &field("key"), "--nonce", &field("base_nonce"), "--chunk-size", &chunk_size, "--algorithm", &field("algorithm")];
+
+        let output = furl(&args, &decode_hex(&field("plaintext")).unwrap());
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(output.stdout, decode_hex(&field("container")).unwrap(), "{}", vector["description"]);
+    }
+}
+
+#[test]
+fn decrypt_opens_shared_vectors() {
+    for vector in vectors() {
+        let field = |name: &str| vector[name].as_str().unwrap().to_string();
+
+        let output = furl(&["decrypt", "--key", &field("key")], &decode_hex(&field("container")).unwrap());
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(output.stdout, decode_hex(&field("plaintext")).unwrap(), "{}", vector["description"]);
+    }
+}
+
+#[test]
+fn tampered_containers_fail_and_leave_no_output_file() {
+    let vector = &vectors()[1];
+    let field = |name: &str| vector[name].as_str().unwrap().to_string();
+    let mut container = decode_hex(&field("container")).unwrap();
+    let last = container.len() - 1;
+    container[last] ^= 1;
+
+    let path = std::env::temp_dir().join(format!("furl-cli-test-{}.out", std::process::id()));
+    let output = furl(&["decrypt", "--key", &field("key"), "-o", path.to_str().unwrap()], &container);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("AuthenticationFailed"));
+    assert!(!path.exists());
+}
+
+#[test]
+fn open_decrypts_a_share_from_any_offset() {
+    // AES-256-CTR, computed independently of furl
+    let link = "https://furl.example/s/abc#v=1&key=AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8&iv=8PHy8_T19vf4-fr7_P3-_w";
+    let ciphertext = decode_hex("f475bfe103e5e8aa280cc6243257157daf285b26845858e11dcf34532f57f6fcfc1a483e633893ed0ae90063fd3580ea6918b148a0998255337da98690432bccd7761ef9090e9f9493e868f9").unwrap();
+    let plaintext = b"furl share preview ".repeat(4);
+
+    let output = furl(&["open", "--link", link], &ciphertext);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, plaintext);
+
+    let output = furl(&["open", "--link", link, "--offset", "20"], &ciphertext[20..]);
+    assert_eq!(output.stdout, &plaintext[20..]);
+
+    assert_eq!(furl(&["open", "--link", "#v=1&key=AA"], &ciphertext).status.code(), Some(1));
+}
+
+#[test]
+fn bad_command_lines_exit_with_status_2() {
+    assert_eq!(furl(&["encrypt"], b"").status.code(), Some(2));
+    assert_eq!(furl(&["frobnicate"], b"").status.code(), Some(2));
+}
@@ -0,0 +1,520 @@
+//! Chunked authenticated container format
+//! 
+//! Splits a file into independently sealed AEAD chunks so a download can be
+//! verified and decrypted chunk by chunk while still detecting truncation,
+//! reordering and chunks spliced in from another file.
+//! 
+//! Layout (integers are big-endian):
+//! 
+//! ```text
+//! header (22 bytes)
+//!   magic       4 bytes   "FURL"
+//!   version     1 byte    1
+//!   algorithm   1 byte    1 = AES-256-GCM, 2 = ChaCha20-Poly1305
+//!   chunk_size  4 bytes   plaintext bytes in every chunk except the last
+//!   base_nonce 12 bytes
+//! 
+//! chunk record (repeated)
+//!   length      4 bytes   ciphertext length including the 16-byte tag
+//!   flags       1 byte    bit 0 set on the final chunk
+//!   ciphertext  `length` bytes
+//! ```
+//! 
+//! Chunk `i` is sealed with nonce `base_nonce XOR i` (counter in the low 8
+//! bytes) and associated data `header || i || flags`. The counter stops
+//! reordering, the final flag stops truncation at a chunk boundary, and
+//! binding the header stops chunks moving between files.
+
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use chacha20poly1305::ChaCha20Poly1305;
+use core::fmt;
+
+use crate::error::CryptoError;
+
+const MAGIC: &[u8; 4] = b"FURL";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 22;
+const RECORD_HEADER_LEN: usize = 5;
+const TAG_LEN: usize = 16;
+const FLAG_FINAL: u8 = 0x01;
+
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+/// Upper bound on chunk size, so a hostile header can't force huge buffers
+pub const MAX_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+/// Errors produced while building or parsing a container
+#[derive(Debug, PartialEq)]
+pub enum ContainerError {
+    InvalidKey(usize),
+    InvalidNonce(usize),
+    InvalidChunkSize(u32),
+    UnsupportedAlgorithm(String),
+    BadHeader(&'static str),
+    BadRecord(&'static str),
+    AuthenticationFailed(u64),
+    Truncated,
+    TrailingData,
+    Finished,
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContainerError::InvalidKey(n) => write!(f, "Invalid key size: expected 32 bytes, got {}", n),
+            ContainerError::InvalidNonce(n) => write!(f, "Invalid nonce size: expected 12 bytes, got {}", n),
+            ContainerError::InvalidChunkSize(n) => write!(f, "Invalid chunk size: expected 1 to {} bytes, got {}", MAX_CHUNK_SIZE, n),
+            ContainerError::UnsupportedAlgorithm(name) => write!(f, "Unsupported container algorithm: {}", name),
+            ContainerError::BadHeader(reason) => write!(f, "Invalid container header: {}", reason),
+            ContainerError::BadRecord(reason) => write!(f, "Invalid container chunk: {}", reason),
+            ContainerError::AuthenticationFailed(index) => write!(f, "Authentication failed for chunk {}: data is corrupted, reordered or from another file", index),
+            ContainerError::Truncated => write!(f, "Container is truncated: final chunk is missing"),
+            ContainerError::TrailingData => write!(f, "Unexpected data after the final chunk"),
+            ContainerError::Finished => write!(f, "Container stream has already been finalized"),
+        }
+    }
+}
+
+impl From<ContainerError> for CryptoError {
+    fn from(error: ContainerError) -> CryptoError {
+        let message = error.to_string();
+        match error {
+            ContainerError::InvalidKey(_) => CryptoError::InvalidKeyLength(message),
+            ContainerError::InvalidNonce(_) => CryptoError::InvalidIv(message),
+            ContainerError::InvalidChunkSize(_) => CryptoError::InvalidInput(message),
+            ContainerError::UnsupportedAlgorithm(_) => CryptoError::Unsupported(message),
+            ContainerError::BadHeader(_) | ContainerError::BadRecord(_) | ContainerError::TrailingData => CryptoError::InvalidFormat(message),
+            ContainerError::AuthenticationFailed(_) => CryptoError::AuthenticationFailed(message),
+            ContainerError::Truncated => CryptoError::Truncated(message),
+            ContainerError::Finished => CryptoError::InvalidState(message),
+        }
+    }
+}
+
+#[cfg(feature = "js")]
+impl From<ContainerError> for wasm_bindgen::JsValue {
+    fn from(error: ContainerError) -> wasm_bindgen::JsValue {
+        CryptoError::from(error).into()
+    }
+}
+
+/// AEAD used to seal container chunks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Aes256Gcm = 1,
+    ChaCha20Poly1305 = 2,
+}
+
+impl Algorithm {
+    /// Algorithm for a name such as `"aes-256-gcm"`; `None` picks the default
+    pub fn from_name(name: Option<&str>) -> Result<Self, ContainerError> {
+        match name.map(|name| name.to_ascii_lowercase()).as_deref() {
+            None | Some("aes-256-gcm") => Ok(Algorithm::Aes256Gcm),
+            Some("chacha20-poly1305") => Ok(Algorithm::ChaCha20Poly1305),
+            Some(other) => Err(ContainerError::UnsupportedAlgorithm(other.to_string())),
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, ContainerError> {
+        match id {
+            1 => Ok(Algorithm::Aes256Gcm),
+            2 => Ok(Algorithm::ChaCha20Poly1305),
+            _ => Err(ContainerError::BadHeader("unknown algorithm")),
+        }
+    }
+}
+
+/// Keyed AEAD instance for one container (or one `stream` epoch)
+pub(crate) enum ChunkCipher {
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(ChaCha20Poly1305),
+}
+
+impl ChunkCipher {
+    pub(crate) fn new(algorithm: Algorithm, key: &[u8]) -> Result<Self, ContainerError> {
+        let invalid = |_| ContainerError::InvalidKey(key.len());
+        match algorithm {
+            Algorithm::Aes256Gcm => Aes256Gcm::new_from_slice(key).map(|c| ChunkCipher::Aes256Gcm(Box::new(c))).map_err(invalid),
+            Algorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key).map(ChunkCipher::ChaCha20Poly1305).map_err(invalid),
+        }
+    }
+
+    pub(crate) fn seal(&self, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        let payload = Payload { msg: plaintext, aad };
+        let sealed = match self {
+            ChunkCipher::Aes256Gcm(cipher) => cipher.encrypt(nonce.into(), payload),
+            ChunkCipher::ChaCha20Poly1305(cipher) => cipher.encrypt(nonce.into(), payload),
+        };
+        // Encryption only fails for messages beyond the AEAD's length limit,
+        // which MAX_CHUNK_SIZE keeps us far below
+        sealed.unwrap_or_default()
+    }
+
+    pub(crate) fn open(&self, nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        let payload = Payload { msg: ciphertext, aad };
+        match self {
+            ChunkCipher::Aes256Gcm(cipher) => cipher.decrypt(nonce.into(), payload).ok(),
+            ChunkCipher::ChaCha20Poly1305(cipher) => cipher.decrypt(nonce.into(), payload).ok(),
+        }
+    }
+}
+
+/// Nonce for chunk `index`: the base nonce with the counter XORed into its low 8 bytes
+fn chunk_nonce(base_nonce: &[u8; 12], index: u64) -> [u8; 12] {
+    let mut nonce = *base_nonce;
+    nonce[4..].iter_mut().zip(index.to_be_bytes()).for_each(|(n, c)| *n ^= c);
+    nonce
+}
+
+/// Associated data for a chunk: header || index || flags
+fn chunk_aad(header: &[u8; HEADER_LEN], index: u64, flags: u8) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(HEADER_LEN + 9);
+    aad.extend_from_slice(header);
+    aad.extend_from_slice(&index.to_be_bytes());
+    aad.push(flags);
+    aad
+}
+
+/// Writes a container, buffering plaintext into fixed-size chunks
+pub struct ContainerWriter {
+    cipher: ChunkCipher,
+    header: [u8; HEADER_LEN],
+    base_nonce: [u8; 12],
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    index: u64,
+    header_written: bool,
+    finished: bool,
+}
+
+impl ContainerWriter {
+    pub fn new(key: &[u8], base_nonce: &[u8], chunk_size: u32, algorithm: Algorithm) -> Result<Self, ContainerError> {
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(ContainerError::InvalidChunkSize(chunk_size));
+        }
+
+        let base_nonce: [u8; 12] = base_nonce
+            .try_into()
+            .map_err(|_| ContainerError::InvalidNonce(base_nonce.len()))?;
+        let cipher = ChunkCipher::new(algorithm, key)?;
+
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4] = VERSION;
+        header[5] = algorithm as u8;
+        header[6..10].copy_from_slice(&chunk_size.to_be_bytes());
+        header[10..].copy_from_slice(&base_nonce);
+
+        Ok(ContainerWriter {
+            cipher,
+            header,
+            base_nonce,
+            chunk_size: chunk_size as usize,
+            buffer: Vec::new(),
+            index: 0,
+            header_written: false,
+            finished: false,
+        })
+    }
+
+    /// Buffer plaintext and return container bytes for every completed chunk
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        if self.finished {
+            return Err(ContainerError::Finished);
+        }
+
+        let mut out = self.take_header();
+        self.buffer.extend_from_slice(data);
+
+        // Always hold back the last full chunk: only finish() knows it is final
+        let mut consumed = 0;
+        while self.buffer.len() - consumed > self.chunk_size {
+            let end = consumed + self.chunk_size;
+            let record = self.seal_record(consumed, end, 0);
+            out.extend_from_slice(&record);
+            consumed = end;
+        }
+        self.buffer.drain(..consumed);
+
+        Ok(out)
+    }
+
+    /// Seal the remaining plaintext as the final chunk
+    pub fn finish(&mut self) -> Result<Vec<u8>, ContainerError> {
+        if self.finished {
+            return Err(ContainerError::Finished);
+        }
+
+        let mut out = self.take_header();
+        let record = self.seal_record(0, self.buffer.len(), FLAG_FINAL);
+        out.extend_from_slice(&record);
+        self.buffer.clear();
+        self.finished = true;
+
+        Ok(out)
+    }
+
+    fn take_header(&mut self) -> Vec<u8> {
+        if self.header_written {
+            return Vec::new();
+        }
+        self.header_written = true;
+        self.header.to_vec()
+    }
+
+    fn seal_record(&mut self, start: usize, end: usize, flags: u8) -> Vec<u8> {
+        let nonce = chunk_nonce(&self.base_nonce, self.index);
+        let aad = chunk_aad(&self.header, self.index, flags);
+        let ciphertext = self.cipher.seal(&nonce, &aad, &self.buffer[start..end]);
+        self.index += 1;
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN + ciphertext.len());
+        record.extend_from_slice(&(ciphertext.len() as u32).to_be_bytes());
+        record.push(flags);
+        record.extend_from_slice(&ciphertext);
+        record
+    }
+}
+
+/// Incrementally parses and decrypts a container
+pub struct ContainerReader {
+    key: Vec<u8>,
+    state: Option<ReaderState>,
+    buffer: Vec<u8>,
+    final_seen: bool,
+    finished: bool,
+}
+
+/// Parsed header and keyed cipher, available once the header has arrived
+struct ReaderState {
+    cipher: ChunkCipher,
+    header: [u8; HEADER_LEN],
+    base_nonce: [u8; 12],
+    chunk_size: usize,
+    index: u64,
+}
+
+impl ContainerReader {
+    pub fn new(key: &[u8]) -> Result<Self, ContainerError> {
+        if key.len() != 32 {
+            return Err(ContainerError::InvalidKey(key.len()));
+        }
+
+        Ok(ContainerReader {
+            key: key.to_vec(),
+            state: None,
+            buffer: Vec::new(),
+            final_seen: false,
+            finished: false,
+        })
+    }
+
+    /// Consume container bytes and return the plaintext of every complete chunk
+    pub fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        if self.finished {
+            return Err(ContainerError::Finished);
+        }
+
+        self.buffer.extend_from_slice(data);
+        let mut plaintext = Vec::new();
+        let mut consumed = 0;
+
+        if self.state.is_none() {
+            if self.buffer.len() < HEADER_LEN {
+                return Ok(plaintext);
+            }
+            self.state = Some(self.parse_header()?);
+            consumed = HEADER_LEN;
+        }
+
+        while let Some(state) = self.state.as_mut() {
+            let pending = &self.buffer[consumed..];
+            if pending.is_empty() {
+                break;
+            }
+            if self.final_seen {
+                return Err(ContainerError::TrailingData);
+            }
+            if pending.len() < RECORD_HEADER_LEN {
+                break;
+            }
+
+            let length = u32::from_be_bytes([pending[0], pending[1], pending[2], pending[3]]) as usize;
+            let flags = pending[4];
+
+            if flags & !FLAG_FINAL != 0 {
+                return Err(ContainerError::BadRecord("unknown flags"));
+            }
+            if length < TAG_LEN || length > state.chunk_size + TAG_LEN {
+                return Err(ContainerError::BadRecord("length out of range"));
+            }
+            if flags & FLAG_FINAL == 0 && length != state.chunk_size + TAG_LEN {
+                return Err(ContainerError::BadRecord("short chunk before the final chunk"));
+            }
+            if pending.len() < RECORD_HEADER_LEN + length {
+                break;
+            }
+
+            let ciphertext = &pending[RECORD_HEADER_LEN..RECORD_HEADER_LEN + length];
+            let nonce = chunk_nonce(&state.base_nonce, state.index);
+            let aad = chunk_aad(&state.header, state.index, flags);
+            let opened = state
+                .cipher
+                .open(&nonce, &aad, ciphertext)
+                .ok_or(ContainerError::AuthenticationFailed(state.index))?;
+
+            plaintext.extend_from_slice(&opened);
+            state.index += 1;
+            consumed += RECORD_HEADER_LEN + length;
+            self.final_seen = flags & FLAG_FINAL != 0;
+        }
+
+        self.buffer.drain(..consumed);
+
+        Ok(plaintext)
+    }
+
+    /// Check that the stream ended exactly after the final chunk
+    pub fn finish(&mut self) -> Result<(), ContainerError> {
+        if self.finished {
+            return Err(ContainerError::Finished);
+        }
+        self.finished = true;
+
+        if !self.final_seen {
+            return Err(ContainerError::Truncated);
+        }
+        if !self.buffer.is_empty() {
+            return Err(ContainerError::TrailingData);
+        }
+
+        Ok(())
+    }
+
+    fn parse_header(&self) -> Result<ReaderState, ContainerError> {
+        let mut header = [0u8; HEADER_LEN];
+        header.copy_from_slice(&self.buffer[..HEADER_LEN]);
+
+        if &header[..4] != MAGIC {
+            return Err(ContainerError::BadHeader("not a furl container"));
+        }
+        if header[4] != VERSION {
+            return Err(ContainerError::BadHeader("unsupported version"));
+        }
+
+        let algorithm = Algorithm::from_id(header[5])?;
+        let chunk_size = u32::from_be_bytes([header[6], header[7], header[8], header[9]]);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(ContainerError::BadHeader("chunk size out of range"));
+        }
+
+        let mut base_nonce = [0u8; 12];
+        base_nonce.copy_from_slice(&header[10..]);
+
+        Ok(ReaderState {
+            cipher: ChunkCipher::new(algorithm, &self.key)?,
+            header,
+            base_nonce,
+            chunk_size: chunk_size as usize,
+            index: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_hex;
+
+    const KEY: [u8; 32] = [0x24; 32];
+    const NONCE: [u8; 12] = [0x99; 12];
+
+    fn seal(data: &[u8], chunk_size: u32, algorithm: Algorithm) -> Vec<u8> {
+        let mut writer = ContainerWriter::new(&KEY, &NONCE, chunk_size, algorithm).unwrap();
+        let mut out = writer.update(data).unwrap();
+        out.extend(writer.finish().unwrap());
+        out
+    }
+
+    fn open(container: &[u8]) -> Result<Vec<u8>, ContainerError> {
+        let mut reader = ContainerReader::new(&KEY)?;
+        let plaintext = reader.update(container)?;
+        reader.finish()?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn round_trips_for_both_algorithms_and_any_split() {
+        let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+
+        for algorithm in [Algorithm::Aes256Gcm, Algorithm::ChaCha20Poly1305] {
+            for len in [0, 1, 99, 100, 101, 1000] {
+                let container = seal(&data[..len], 100, algorithm);
+                assert_eq!(open(&container).unwrap(), &data[..len]);
+
+                // Feed the reader one byte at a time
+                let mut reader = ContainerReader::new(&KEY).unwrap();
+                let mut plaintext = Vec::new();
+                for byte in &container {
+                    plaintext.extend(reader.update(std::slice::from_ref(byte)).unwrap());
+                }
+                reader.finish().unwrap();
+                assert_eq!(plaintext, &data[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn detects_truncation_at_chunk_boundary() {
+        let container = seal(&[7u8; 250], 100, Algorithm::Aes256Gcm);
+        let first_two = HEADER_LEN + 2 * (RECORD_HEADER_LEN + 100 + TAG_LEN);
+        assert_eq!(open(&container[..first_two]), Err(ContainerError::Truncated));
+    }
+
+    #[test]
+    fn detects_reordered_and_tampered_chunks() {
+        let container = seal(&[7u8; 300], 100, Algorithm::ChaCha20Poly1305);
+        let record = RECORD_HEADER_LEN + 100 + TAG_LEN;
+
+        let mut swapped = container.clone();
+        let (first, second) = (HEADER_LEN, HEADER_LEN + record);
+        let chunk0 = container[first..first + record].to_vec();
+        swapped[first..first + record].copy_from_slice(&container[second..second + record]);
+        swapped[second..second + record].copy_from_slice(&chunk0);
+        assert_eq!(open(&swapped), Err(ContainerError::AuthenticationFailed(0)));
+
+        let mut flipped = container.clone();
+        flipped[HEADER_LEN + RECORD_HEADER_LEN] ^= 1;
+        assert_eq!(open(&flipped), Err(ContainerError::AuthenticationFailed(0)));
+    }
+
+    #[test]
+    fn rejects_data_after_final_chunk() {
+        let mut container = seal(b"hello", 100, Algorithm::Aes256Gcm);
+        container.push(0);
+        assert_eq!(open(&container), Err(ContainerError::TrailingData));
+    }
+
+    #[test]
+    fn matches_shared_test_vectors() {
+        let vectors: serde_json::Value = serde_json::from_str(include_str!("../../test-vectors/container.json")).unwrap();
+
+        for vector in vectors["vectors"].as_array().unwrap() {
+            let field = |name: &str| decode_hex(vector[name].as_str().unwrap()).unwrap();
+            let algorithm = Algorithm::from_name(vector["algorithm"].as_str()).unwrap();
+            let chunk_size = vector["chunk_size"].as_u64().unwrap() as u32;
+
+            let mut writer = ContainerWriter::new(&field("key"), &field("base_nonce"), chunk_size, algorithm).unwrap();
+            let mut container = writer.update(&field("plaintext")).unwrap();
+            container.extend(writer.finish().unwrap());
+            assert_eq!(container, field("container"), "{}", vector["description"]);
+
+            let mut reader = ContainerReader::new(&field("key")).unwrap();
+            assert_eq!(reader.update(&container).unwrap(), field("plaintext"));
+            reader.finish().unwrap();
+        }
+    }
+}
@@ -0,0 +1,255 @@
+//! AES-CTR with a selectable counter width
+//! 
+//! Encryptors disagree on how much of the 16-byte counter block is counter.
+//! furl, OpenSSL and Dart's `encrypt` increment all 128 bits; Web Crypto
+//! callers commonly pass `length: 64`, and GCM-derived code increments only
+//! the low 32 bits. The keystreams are identical until the counter part
+//! overflows, so a mismatch only shows up part-way through a large file (or
+//! early, when the IV's low bytes start near the top).
+//! 
+//! RFC 3686 (IPsec) encryptors build the counter block as a 4-byte nonce from
+//! the keying material, the 8-byte per-message IV and a 32-bit block counter
+//! starting at 1, so they hand over 12 bytes rather than a full counter block.
+
+use aes::{Aes128, Aes192, Aes256};
+use alloc::format;
+use ctr::{Ctr128BE, Ctr32BE, Ctr64BE};
+use ctr::cipher::generic_array::GenericArray;
+use ctr::cipher::{InnerIvInit, KeyInit, StreamCipher, StreamCipherCoreWrapper, StreamCipherSeek};
+use ctr::CtrCore;
+
+use crate::error::CryptoError;
+
+// Type aliases for AES-CTR with the full 128-bit counter
+type Aes128Ctr = Ctr128BE<Aes128>;
+type Aes192Ctr = Ctr128BE<Aes192>;
+type Aes256Ctr = Ctr128BE<Aes256>;
+
+/// Number of low bits of the counter block that are incremented, exported to JS as `CounterWidth`
+#[cfg_attr(feature = "js", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CounterWidth {
+    Bits32 = 32,
+    Bits64 = 64,
+    Bits128 = 128,
+}
+
+impl CounterWidth {
+    /// Width for a bit count given as a JS number
+    pub fn from_bits(bits: f64) -> Result<Self, CryptoError> {
+        match bits {
+            32.0 => Ok(CounterWidth::Bits32),
+            64.0 => Ok(CounterWidth::Bits64),
+            128.0 => Ok(CounterWidth::Bits128),
+            _ => Err(CryptoError::InvalidInput(format!("Invalid counter_width: expected 32, 64 or 128, got {}", bits))),
+        }
+    }
+}
+
+/// How the counter block is built from the IV and incremented
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CtrOptions {
+    pub counter_width: CounterWidth,
+    pub rfc3686: bool,
+}
+
+impl Default for CtrOptions {
+    fn default() -> Self {
+        CtrOptions { counter_width: CounterWidth::Bits128, rfc3686: false }
+    }
+}
+
+impl CtrOptions {
+    /// Number of IV bytes the caller passes
+    pub fn iv_length(&self) -> usize {
+        if self.rfc3686 { 12 } else { 16 }
+    }
+
+    /// Build the initial counter block from the caller's IV
+    pub fn counter_block(&self, iv: &[u8]) -> Result<[u8; 16], CryptoError> {
+        if iv.len() != self.iv_length() {
+            return Err(CryptoError::InvalidIv(format!("Invalid IV size: expected {} bytes, got {}", self.iv_length(), iv.len())));
+        }
+
+        let mut block = [0u8; 16];
+        if self.rfc3686 {
+            block[..12].copy_from_slice(iv);
+            block[12..].copy_from_slice(&1u32.to_be_bytes());
+        } else {
+            block.copy_from_slice(iv);
+        }
+        Ok(block)
+    }
+}
+
+/// An expanded AES key (16, 24 or 32 bytes), for starting many CTR streams
+/// without repeating the key schedule
+#[derive(Clone)]
+pub enum AesKey {
+    Aes128(Aes128),
+    Aes192(Aes192),
+    Aes256(Aes256),
+}
+
+impl AesKey {
+    pub fn new(key: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = |_| CryptoError::InvalidKey("Invalid key format".into());
+        match key.len() {
+            16 => Aes128::new_from_slice(key).map(AesKey::Aes128).map_err(invalid),
+            24 => Aes192::new_from_slice(key).map(AesKey::Aes192).map_err(invalid),
+            32 => Aes256::new_from_slice(key).map(AesKey::Aes256).map_err(invalid),
+            n => Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 16, 24 or 32 bytes, got {}", n))),
+        }
+    }
+
+    /// Key size in bytes
+    pub fn key_length(&self) -> usize {
+        match self {
+            AesKey::Aes128(_) => 16,
+            AesKey::Aes192(_) => 24,
+            AesKey::Aes256(_) => 32,
+        }
+    }
+}
+
+/// AES-CTR cipher selected by key length (16, 24 or 32 bytes) and counter width
+#[derive(Clone)]
+pub enum AesCtr {
+    Aes128(Aes128Ctr),
+    Aes192(Aes192Ctr),
+    Aes256(Aes256Ctr),
+    Aes128Ctr64(Ctr64BE<Aes128>),
+    Aes192Ctr64(Ctr64BE<Aes192>),
+    Aes256Ctr64(Ctr64BE<Aes256>),
+    Aes128Ctr32(Ctr32BE<Aes128>),
+    Aes192Ctr32(Ctr32BE<Aes192>),
+    Aes256Ctr32(Ctr32BE<Aes256>),
+}
+
+// Run the same expression against whichever cipher variant is held
+macro_rules! with_aes_ctr {
+    ( $self:expr, $cipher:ident => $body:expr ) => {
+        match $self {
+            AesCtr::Aes128($cipher) => $body,
+            AesCtr::Aes192($cipher) => $body,
+            AesCtr::Aes256($cipher) => $body,
+            AesCtr::Aes128Ctr64($cipher) => $body,
+            AesCtr::Aes192Ctr64($cipher) => $body,
+            AesCtr::Aes256Ctr64($cipher) => $body,
+            AesCtr::Aes128Ctr32($cipher) => $body,
+            AesCtr::Aes192Ctr32($cipher) => $body,
+            AesCtr::Aes256Ctr32($cipher) => $body,
+        }
+    };
+}
+
+impl AesCtr {
+    /// Cipher with the full 128-bit counter
+    pub fn new(key: &[u8], iv: &[u8]) -> Result<Self, CryptoError> {
+        AesCtr::with_options(key, iv, &CtrOptions::default())
+    }
+
+    /// Cipher for `options`; `iv` is 16 bytes, or 12 (`nonce || IV`) for RFC 3686
+    pub fn with_options(key: &[u8], iv: &[u8], options: &CtrOptions) -> Result<Self, CryptoError> {
+        AesCtr::from_key(&AesKey::new(key)?, iv, options)
+    }
+
+    /// Cipher for `options` from an already expanded key, which is copied rather than re-derived
+    pub fn from_key(key: &AesKey, iv: &[u8], options: &CtrOptions) -> Result<Self, CryptoError> {
+        let block = options.counter_block(iv)?;
+        let iv = GenericArray::from_slice(&block);
+        Ok(match (key.clone(), options.counter_width) {
+            (AesKey::Aes128(key), CounterWidth::Bits128) => AesCtr::Aes128(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes192(key), CounterWidth::Bits128) => AesCtr::Aes192(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes256(key), CounterWidth::Bits128) => AesCtr::Aes256(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes128(key), CounterWidth::Bits64) => AesCtr::Aes128Ctr64(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes192(key), CounterWidth::Bits64) => AesCtr::Aes192Ctr64(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes256(key), CounterWidth::Bits64) => AesCtr::Aes256Ctr64(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes128(key), CounterWidth::Bits32) => AesCtr::Aes128Ctr32(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes192(key), CounterWidth::Bits32) => AesCtr::Aes192Ctr32(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+            (AesKey::Aes256(key), CounterWidth::Bits32) => AesCtr::Aes256Ctr32(StreamCipherCoreWrapper::from_core(CtrCore::inner_iv_init(key, iv))),
+        })
+    }
+
+    /// XOR the keystream into `data`
+    /// 
+    /// Only a 32-bit counter can run out: it covers 2^32 blocks (64 GB) from
+    /// the IV before it would repeat, which is reported as an error.
+    pub fn apply_keystream(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.try_apply_keystream(data)
+            .map_err(|_| CryptoError::InvalidInput("Data runs past the end of the keystream for this counter width".into()))
+    }
+
+    fn try_apply_keystream(&mut self, data: &mut [u8]) -> Result<(), ctr::cipher::StreamCipherError> {
+        with_aes_ctr!(self, cipher => cipher.try_apply_keystream(data))
+    }
+
+    /// Move the keystream to an absolute byte position
+    pub fn seek(&mut self, position: u64) -> Result<(), CryptoError> {
+        self.try_seek(position)
+            .map_err(|_| CryptoError::InvalidInput("Offset is beyond the end of the keystream".into()))
+    }
+
+    fn try_seek(&mut self, position: u64) -> Result<(), ctr::cipher::StreamCipherError> {
+        with_aes_ctr!(self, cipher => cipher.try_seek(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_width_accepts_only_supported_sizes() {
+        assert_eq!(CounterWidth::from_bits(32.0), Ok(CounterWidth::Bits32));
+        assert_eq!(CounterWidth::from_bits(64.0), Ok(CounterWidth::Bits64));
+        assert_eq!(CounterWidth::from_bits(128.0), Ok(CounterWidth::Bits128));
+        assert_eq!(CounterWidth::from_bits(CounterWidth::Bits32 as u32 as f64), Ok(CounterWidth::Bits32));
+
+        for bits in [0.0, 16.0, 32.5, 96.0, f64::NAN] {
+            assert_eq!(CounterWidth::from_bits(bits).unwrap_err().name(), "InvalidInput");
+        }
+    }
+
+    #[test]
+    fn rfc3686_counter_block_starts_at_one() {
+        let options = CtrOptions { counter_width: CounterWidth::Bits32, rfc3686: true };
+        let nonce_iv: Vec<u8> = (1..=12).collect();
+
+        let block = options.counter_block(&nonce_iv).unwrap();
+        assert_eq!(block[..12], nonce_iv[..]);
+        assert_eq!(block[12..], [0, 0, 0, 1]);
+
+        assert_eq!(options.counter_block(&[0u8; 16]).unwrap_err().name(), "InvalidIv");
+        assert_eq!(CtrOptions::default().counter_block(&nonce_iv).unwrap_err().name(), "InvalidIv");
+        assert_eq!(CtrOptions::default().counter_block(&[7u8; 16]), Ok([7u8; 16]));
+    }
+
+    #[test]
+    fn counter_32_stops_at_end_of_keystream() {
+        let mut cipher = AesCtr::with_options(&[0x2b; 16], &[0u8; 16], &CtrOptions { counter_width: CounterWidth::Bits32, rfc3686: false }).unwrap();
+        assert!(cipher.seek(16 << 32).is_err());
+
+        cipher.seek((16 << 32) - 32).unwrap();
+        let mut tail = [0u8; 33];
+        assert!(cipher.apply_keystream(&mut tail).is_err());
+        assert!(cipher.apply_keystream(&mut tail[..16]).is_ok());
+    }
+
+    #[test]
+    fn expanded_keys_start_independent_streams() {
+        // NIST SP 800-38A F.5.1, first block
+        let key = AesKey::new(&[0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c]).unwrap();
+        let iv: [u8; 16] = core::array::from_fn(|i| 0xf0 + i as u8);
+        let plaintext = [0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a];
+        let expected = [0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d, 0xb6, 0xce];
+
+        for _ in 0..2 {
+            let mut block = plaintext;
+            AesCtr::from_key(&key, &iv, &CtrOptions::default()).unwrap().apply_keystream(&mut block).unwrap();
+            assert_eq!(block, expected);
+        }
+        assert_eq!(key.key_length(), 16);
+        assert_eq!(AesKey::new(&[0; 20]).err().map(|error| error.name()), Some("InvalidKeyLength"));
+    }
+}
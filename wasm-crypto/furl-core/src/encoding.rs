@@ -0,0 +1,43 @@
+//! Lenient base64 and hex for keys and IVs from links and manifests
+
+use alloc::format;
+use alloc::vec::Vec;
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+
+use crate::error::CryptoError;
+
+const PADDING_OPTIONAL: GeneralPurposeConfig = GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const STANDARD_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, PADDING_OPTIONAL);
+const URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, PADDING_OPTIONAL);
+
+/// Decode standard or URL-safe base64, with or without padding
+pub fn decode_base64(text: &str) -> Result<Vec<u8>, CryptoError> {
+    let text = text.trim();
+    let engine = if text.contains(['-', '_']) { &URL_SAFE_LENIENT } else { &STANDARD_LENIENT };
+
+    engine
+        .decode(text)
+        .map_err(|_| CryptoError::InvalidFormat("Invalid base64: expected standard or URL-safe base64 text".into()))
+}
+
+/// Decode hex in either case, ignoring surrounding whitespace
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, CryptoError> {
+    let text = text.trim().as_bytes();
+    if !text.len().is_multiple_of(2) {
+        return Err(CryptoError::InvalidFormat(format!("Invalid hex: expected an even number of digits, got {}", text.len())));
+    }
+
+    let nibble = |digit: u8| match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    };
+
+    text.chunks_exact(2)
+        .map(|pair| Some(nibble(pair[0])? << 4 | nibble(pair[1])?))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| CryptoError::InvalidFormat("Invalid hex: non-hex digit".into()))
+}
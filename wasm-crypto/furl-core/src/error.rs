@@ -0,0 +1,195 @@
+//! Error kinds shared by every furl front end
+//! 
+//! Each kind has a stable numeric `code` and a `name`; the wasm module uses
+//! them as the JS `Error.code` and `Error.name`, and native callers can
+//! match on the enum directly.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Numeric error codes, exported to JS as `CryptoErrorCode`
+#[cfg_attr(feature = "js", wasm_bindgen::prelude::wasm_bindgen)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CryptoErrorCode {
+    InvalidKeyLength = 1,
+    InvalidKey = 2,
+    InvalidIv = 3,
+    InvalidInput = 4,
+    InvalidFormat = 5,
+    AuthenticationFailed = 6,
+    InvalidPadding = 7,
+    Truncated = 8,
+    Unsupported = 9,
+    InvalidState = 10,
+    StreamError = 11,
+    OutOfMemory = 12,
+    Internal = 13,
+    Aborted = 14,
+    NotFound = 15,
+    WrongPassword = 16,
+    Expired = 17,
+    NotYetValid = 18,
+    NetworkError = 19,
+}
+
+/// Error kinds with a human-readable message
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CryptoError {
+    /// A key has the wrong number of bytes
+    InvalidKeyLength(String),
+    /// A key has the right size but can't be used (bad encoding, weak point)
+    InvalidKey(String),
+    /// An IV or nonce has the wrong size
+    InvalidIv(String),
+    /// Any other argument is out of range
+    InvalidInput(String),
+    /// Encoded data (headers, containers, archives, tokens) is malformed
+    InvalidFormat(String),
+    /// A tag, MAC, hash or password check failed
+    AuthenticationFailed(String),
+    /// PKCS#7 padding is malformed, usually from a wrong key or passphrase
+    InvalidPadding(String),
+    /// Data ended before the format said it would
+    Truncated(String),
+    /// An algorithm or feature isn't implemented
+    Unsupported(String),
+    /// An object was used after it was finalized or closed
+    InvalidState(String),
+    /// A stream produced something other than Uint8Array chunks
+    StreamError(String),
+    /// A buffer couldn't be allocated
+    OutOfMemory(String),
+    /// An underlying library failed unexpectedly
+    Internal(String),
+    /// The operation was cancelled by the caller
+    Aborted(String),
+    /// A stored item (e.g. a vault entry) doesn't exist
+    NotFound(String),
+    /// A passphrase didn't match the share's verifier; nothing was decrypted
+    WrongPassword(String),
+    /// A signed share is past its `expires_at` time
+    Expired(String),
+    /// A signed share is before its `not_before` time
+    NotYetValid(String),
+    /// A download failed, or kept failing after its retries
+    NetworkError(String),
+}
+
+impl CryptoError {
+    pub fn code(&self) -> CryptoErrorCode {
+        match self {
+            CryptoError::InvalidKeyLength(_) => CryptoErrorCode::InvalidKeyLength,
+            CryptoError::InvalidKey(_) => CryptoErrorCode::InvalidKey,
+            CryptoError::InvalidIv(_) => CryptoErrorCode::InvalidIv,
+            CryptoError::InvalidInput(_) => CryptoErrorCode::InvalidInput,
+            CryptoError::InvalidFormat(_) => CryptoErrorCode::InvalidFormat,
+            CryptoError::AuthenticationFailed(_) => CryptoErrorCode::AuthenticationFailed,
+            CryptoError::InvalidPadding(_) => CryptoErrorCode::InvalidPadding,
+            CryptoError::Truncated(_) => CryptoErrorCode::Truncated,
+            CryptoError::Unsupported(_) => CryptoErrorCode::Unsupported,
+            CryptoError::InvalidState(_) => CryptoErrorCode::InvalidState,
+            CryptoError::StreamError(_) => CryptoErrorCode::StreamError,
+            CryptoError::OutOfMemory(_) => CryptoErrorCode::OutOfMemory,
+            CryptoError::Internal(_) => CryptoErrorCode::Internal,
+            CryptoError::Aborted(_) => CryptoErrorCode::Aborted,
+            CryptoError::NotFound(_) => CryptoErrorCode::NotFound,
+            CryptoError::WrongPassword(_) => CryptoErrorCode::WrongPassword,
+            CryptoError::Expired(_) => CryptoErrorCode::Expired,
+            CryptoError::NotYetValid(_) => CryptoErrorCode::NotYetValid,
+            CryptoError::NetworkError(_) => CryptoErrorCode::NetworkError,
+        }
+    }
+
+    /// Kind name, used as the JS `Error.name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            CryptoError::InvalidKeyLength(_) => "InvalidKeyLength",
+            CryptoError::InvalidKey(_) => "InvalidKey",
+            CryptoError::InvalidIv(_) => "InvalidIv",
+            CryptoError::InvalidInput(_) => "InvalidInput",
+            CryptoError::InvalidFormat(_) => "InvalidFormat",
+            CryptoError::AuthenticationFailed(_) => "AuthenticationFailed",
+            CryptoError::InvalidPadding(_) => "InvalidPadding",
+            CryptoError::Truncated(_) => "Truncated",
+            CryptoError::Unsupported(_) => "Unsupported",
+            CryptoError::InvalidState(_) => "InvalidState",
+            CryptoError::StreamError(_) => "StreamError",
+            CryptoError::OutOfMemory(_) => "OutOfMemory",
+            CryptoError::Internal(_) => "Internal",
+            CryptoError::Aborted(_) => "Aborted",
+            CryptoError::NotFound(_) => "NotFound",
+            CryptoError::WrongPassword(_) => "WrongPassword",
+            CryptoError::Expired(_) => "Expired",
+            CryptoError::NotYetValid(_) => "NotYetValid",
+            CryptoError::NetworkError(_) => "NetworkError",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            CryptoError::InvalidKeyLength(message)
+            | CryptoError::InvalidKey(message)
+            | CryptoError::InvalidIv(message)
+            | CryptoError::InvalidInput(message)
+            | CryptoError::InvalidFormat(message)
+            | CryptoError::AuthenticationFailed(message)
+            | CryptoError::InvalidPadding(message)
+            | CryptoError::Truncated(message)
+            | CryptoError::Unsupported(message)
+            | CryptoError::InvalidState(message)
+            | CryptoError::StreamError(message)
+            | CryptoError::OutOfMemory(message)
+            | CryptoError::Internal(message)
+            | CryptoError::Aborted(message)
+            | CryptoError::NotFound(message)
+            | CryptoError::WrongPassword(message)
+            | CryptoError::Expired(message)
+            | CryptoError::NotYetValid(message)
+            | CryptoError::NetworkError(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl core::error::Error for CryptoError {}
+
+#[cfg(feature = "js")]
+impl From<CryptoError> for wasm_bindgen::JsValue {
+    fn from(error: CryptoError) -> wasm_bindgen::JsValue {
+        let js_error = js_sys::Error::new(error.message());
+        js_error.set_name(error.name());
+        let _ = js_sys::Reflect::set(&js_error, &"code".into(), &(error.code() as u32).into());
+        js_error.into()
+    }
+}
+
+/// Allocate a zero-filled buffer, reporting failure instead of aborting
+/// 
+/// Sizes that come from callers or from parsed headers go through here, so
+/// a huge request fails with `OutOfMemory` rather than killing the instance.
+pub fn try_zeroed(len: usize) -> Result<Vec<u8>, CryptoError> {
+    let mut buffer = Vec::new();
+    buffer
+        .try_reserve_exact(len)
+        .map_err(|_| CryptoError::OutOfMemory(format!("Failed to allocate {} bytes", len)))?;
+    buffer.resize(len, 0);
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_zeroed_reports_impossible_allocations() {
+        assert_eq!(try_zeroed(16).unwrap(), vec![0u8; 16]);
+        assert_eq!(try_zeroed(usize::MAX).unwrap_err().code(), CryptoErrorCode::OutOfMemory);
+    }
+}
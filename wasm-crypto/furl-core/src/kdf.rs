@@ -0,0 +1,95 @@
+//! Key derivation: PBKDF2 for passphrases and HKDF for expanding one
+//! secret into independent per-purpose keys
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use hkdf::Hkdf;
+
+use crate::error::CryptoError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// HKDF-SHA256 extract-and-expand into `okm`
+pub fn hkdf_sha256(ikm: &[u8], salt: Option<&[u8]>, info: &[u8], okm: &mut [u8]) -> Result<(), CryptoError> {
+    Hkdf::<Sha256>::new(salt, ikm)
+        .expand(info, okm)
+        .map_err(|_| CryptoError::InvalidInput("Invalid derived key length for HKDF-SHA256".into()))
+}
+
+/// PBKDF2-HMAC-SHA256 (RFC 8018) filling `output`
+/// 
+/// Computed by hand rather than with the `pbkdf2` crate so that long runs can
+/// report progress (as a percentage, at most once per percent).
+pub fn pbkdf2_sha256<F>(password: &[u8], salt: &[u8], iterations: u32, output: &mut [u8], mut progress: F)
+where
+    F: FnMut(u32),
+{
+    // Keying HMAC once and cloning it saves re-hashing the password per iteration
+    let prf = HmacSha256::new_from_slice(password).expect("HMAC accepts keys of any length");
+
+    let total_work = output.chunks(32).len() as u64 * iterations as u64;
+    let mut work_done = 0u64;
+    let mut last_reported = 0u32;
+
+    for (block_idx, block) in output.chunks_mut(32).enumerate() {
+        // U_1 = PRF(P, S || INT(i))
+        let mut mac = prf.clone();
+        mac.update(salt);
+        mac.update(&(block_idx as u32 + 1).to_be_bytes());
+        let mut u = mac.finalize().into_bytes();
+        let mut t = u;
+
+        // U_j = PRF(P, U_{j-1}); T = U_1 ^ U_2 ^ ... ^ U_c
+        for _ in 1..iterations {
+            let mut mac = prf.clone();
+            mac.update(&u);
+            u = mac.finalize().into_bytes();
+            t.iter_mut().zip(u.iter()).for_each(|(t, u)| *t ^= u);
+
+            work_done += 1;
+            let percent = (work_done * 100 / total_work) as u32;
+            if percent > last_reported {
+                last_reported = percent;
+                progress(percent);
+            }
+        }
+        work_done += 1;
+
+        block.copy_from_slice(&t[..block.len()]);
+    }
+
+    if last_reported < 100 {
+        progress(100);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn pbkdf2_sha256_known_answers() {
+        // RFC 7914 section 11
+        let mut out = [0u8; 64];
+        pbkdf2_sha256(b"passwd", b"salt", 1, &mut out, |_| {});
+        assert_eq!(hex(&out), "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783");
+
+        // Partial final block
+        let mut out = [0u8; 40];
+        pbkdf2_sha256(b"password", b"NaCl", 80000, &mut out, |_| {});
+        assert_eq!(hex(&out), "a18495e3ce61675c4dd12a6ab7f919f2ec4e4ebf1978351eb3d2fb84839ca56e37f51d2ce5d30303");
+    }
+
+    #[test]
+    fn pbkdf2_progress_is_monotonic_and_completes() {
+        let mut reports = Vec::new();
+        let mut out = [0u8; 32];
+        pbkdf2_sha256(b"pw", b"saltsalt", 1000, &mut out, |p| reports.push(p));
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(reports.last(), Some(&100));
+    }
+}
@@ -0,0 +1,23 @@
+//! Cipher, key derivation and container logic shared by every furl front end
+//! 
+//! The wasm-bindgen module (`furl-crypto`), native tools and future
+//! bindings all call into this crate, so a change to an algorithm or a
+//! format lands everywhere at once. It has no JS or browser types in its
+//! API and builds without `std` (it needs `alloc`); the `js` feature adds
+//! the conversions the wasm wrapper uses at the boundary.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod container;
+pub mod ctr;
+pub mod encoding;
+pub mod error;
+pub mod kdf;
+pub mod mnemonic;
+pub mod shamir;
+pub mod sharelink;
+pub mod stream;
+
+pub use error::{CryptoError, CryptoErrorCode};
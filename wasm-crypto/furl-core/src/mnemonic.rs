@@ -0,0 +1,189 @@
+//! BIP39 mnemonic encoding of keys
+//! 
+//! A key read aloud is easier as words than as base64. The key is the BIP39
+//! entropy: a SHA-256 checksum of `len / 4` bits is appended and the result
+//! cut into 11-bit indices into the English wordlist, so a 16, 24 or 32-byte
+//! key becomes 12, 18 or 24 words. The checksum catches most misheard or
+//! swapped words; a word not on the list is reported with its position.
+//! 
+//! This is the BIP39 encoding only. Wallet seed derivation (PBKDF2 over the
+//! phrase) plays no part, so the words are the key itself.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use sha2::{Digest, Sha256};
+
+use crate::error::CryptoError;
+
+/// The 2048-word BIP39 English list, one word per line, sorted
+const ENGLISH: &str = include_str!("bip39-english.txt");
+
+/// Errors produced while encoding or decoding a mnemonic
+#[derive(Debug, PartialEq)]
+pub enum MnemonicError {
+    /// Key length in bytes; BIP39 takes 16 to 32 bytes in steps of 4
+    InvalidLength(usize),
+    /// Number of words given; BIP39 uses 12, 15, 18, 21 or 24
+    WordCount(usize),
+    /// A word that isn't on the list, with its 1-based position
+    UnknownWord { position: usize, word: String },
+    /// Every word is on the list but the checksum doesn't match
+    Checksum,
+}
+
+impl fmt::Display for MnemonicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MnemonicError::InvalidLength(n) => write!(f, "Invalid key size: expected 16, 20, 24, 28 or 32 bytes, got {}", n),
+            MnemonicError::WordCount(n) => write!(f, "Invalid mnemonic: expected 12, 15, 18, 21 or 24 words, got {}", n),
+            MnemonicError::UnknownWord { position, word } => write!(f, "Invalid mnemonic: word {} ({:?}) is not a BIP39 word", position, word),
+            MnemonicError::Checksum => write!(f, "Invalid mnemonic: checksum does not match, a word is wrong or out of order"),
+        }
+    }
+}
+
+impl From<MnemonicError> for CryptoError {
+    fn from(error: MnemonicError) -> CryptoError {
+        let message = error.to_string();
+        match error {
+            MnemonicError::InvalidLength(_) => CryptoError::InvalidKeyLength(message),
+            MnemonicError::WordCount(_) | MnemonicError::UnknownWord { .. } => CryptoError::InvalidInput(message),
+            MnemonicError::Checksum => CryptoError::InvalidFormat(message),
+        }
+    }
+}
+
+#[cfg(feature = "js")]
+impl From<MnemonicError> for wasm_bindgen::JsValue {
+    fn from(error: MnemonicError) -> wasm_bindgen::JsValue {
+        CryptoError::from(error).into()
+    }
+}
+
+fn wordlist() -> Vec<&'static str> {
+    ENGLISH.lines().collect()
+}
+
+/// First `bits` bits of SHA-256(entropy)
+fn checksum(entropy: &[u8], bits: usize) -> u32 {
+    let digest = Sha256::digest(entropy);
+    (u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]])) >> (32 - bits)
+}
+
+/// Encode `entropy` (e.g. a share key) as space-separated words
+pub fn to_mnemonic(entropy: &[u8]) -> Result<String, MnemonicError> {
+    if !(16..=32).contains(&entropy.len()) || !entropy.len().is_multiple_of(4) {
+        return Err(MnemonicError::InvalidLength(entropy.len()));
+    }
+
+    let words = wordlist();
+    let checksum_bits = entropy.len() / 4;
+    let mut phrase = Vec::with_capacity((entropy.len() * 8 + checksum_bits) / 11);
+    let bytes = entropy.iter().map(|&byte| (byte as u32, 8)).chain(core::iter::once((checksum(entropy, checksum_bits), checksum_bits)));
+
+    // Shift bits in, taking 11 off the top whenever there are enough
+    let (mut buffer, mut buffered) = (0u32, 0usize);
+    for (value, bits) in bytes {
+        buffer = (buffer << bits) | value;
+        buffered += bits;
+        while buffered >= 11 {
+            buffered -= 11;
+            phrase.push(words[(buffer >> buffered) as usize & 0x7ff]);
+        }
+        buffer &= (1 << buffered) - 1;
+    }
+
+    Ok(phrase.join(" "))
+}
+
+/// Decode a phrase from `to_mnemonic` (or any BIP39 English mnemonic) back to its entropy
+/// 
+/// Words may be separated by any whitespace and in any letter case.
+pub fn from_mnemonic(phrase: &str) -> Result<Vec<u8>, MnemonicError> {
+    let given: Vec<String> = phrase.split_whitespace().map(|word| word.to_ascii_lowercase()).collect();
+    if !matches!(given.len(), 12 | 15 | 18 | 21 | 24) {
+        return Err(MnemonicError::WordCount(given.len()));
+    }
+
+    let words = wordlist();
+    let mut entropy = Vec::with_capacity(given.len() * 11 / 8);
+    let (mut buffer, mut buffered) = (0u32, 0usize);
+    for (position, word) in given.iter().enumerate() {
+        let index = words
+            .binary_search(&word.as_str())
+            .map_err(|_| MnemonicError::UnknownWord { position: position + 1, word: word.clone() })?;
+        buffer = (buffer << 11) | index as u32;
+        buffered += 11;
+        while buffered >= 8 {
+            buffered -= 8;
+            entropy.push((buffer >> buffered) as u8);
+        }
+        buffer &= (1 << buffered) - 1;
+    }
+
+    // The checksum is the last byte plus the leftover bits, `len / 4` in all
+    let checksum_bits = given.len() / 3;
+    let entropy_len = given.len() * 4 / 3;
+    let tail = entropy.split_off(entropy_len);
+    let given_checksum = tail.iter().fold(0u32, |acc, &byte| (acc << 8) | byte as u32) << buffered | buffer;
+    if given_checksum != checksum(&entropy, checksum_bits) {
+        return Err(MnemonicError::Checksum);
+    }
+
+    Ok(entropy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_hex;
+
+    /// From the Trezor reference test vectors
+    const VECTORS: [(&str, &str); 6] = [
+        ("00000000000000000000000000000000", "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
+        ("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f", "legal winner thank year wave sausage worth useful legal winner thank yellow"),
+        ("ffffffffffffffffffffffffffffffff", "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong"),
+        ("9e885d952ad362caeb4efe34a8e91bd2", "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic"),
+        (
+            "000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent",
+        ),
+        (
+            "f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f",
+            "void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold",
+        ),
+    ];
+
+    #[test]
+    fn wordlist_is_the_bip39_english_list() {
+        let words = wordlist();
+        assert_eq!(words.len(), 2048);
+        assert!(words.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(Sha256::digest(ENGLISH.as_bytes())[..4], [0x2f, 0x5e, 0xed, 0x53]);
+    }
+
+    #[test]
+    fn matches_reference_vectors() {
+        for (entropy, phrase) in VECTORS {
+            let entropy = decode_hex(entropy).unwrap();
+            assert_eq!(to_mnemonic(&entropy).unwrap(), phrase);
+            assert_eq!(from_mnemonic(phrase).unwrap(), entropy);
+        }
+        assert_eq!(from_mnemonic("  Legal winner THANK year wave sausage\nworth useful legal winner thank yellow ").unwrap(), [0x7f; 16]);
+    }
+
+    #[test]
+    fn bad_phrases_are_typed() {
+        assert_eq!(to_mnemonic(&[0; 15]), Err(MnemonicError::InvalidLength(15)));
+        assert_eq!(from_mnemonic("abandon about"), Err(MnemonicError::WordCount(2)));
+        assert_eq!(
+            from_mnemonic("legal winner thank year wave sausage worth useful legal winnr thank yellow"),
+            Err(MnemonicError::UnknownWord { position: 10, word: "winnr".into() })
+        );
+        // Two words swapped still decode, but not to a matching checksum
+        assert_eq!(from_mnemonic("legal winner thank year wave sausage worth useful legal winner yellow thank"), Err(MnemonicError::Checksum));
+        assert_eq!(from_mnemonic(&["abandon"; 12].join(" ")), Err(MnemonicError::Checksum));
+        assert_eq!(CryptoError::from(MnemonicError::Checksum).name(), "InvalidFormat");
+    }
+}
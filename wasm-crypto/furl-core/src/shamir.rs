@@ -0,0 +1,222 @@
+//! Shamir secret sharing of a share key over GF(2^8)
+//! 
+//! A key split `threshold`-of-`count` can be sent as parts over different
+//! channels; any `threshold` of them rebuild it and fewer reveal nothing
+//! about it. The shared secret is `key || tag`, where `tag` is the first 4
+//! bytes of SHA-256("furl shamir" || key). Each of its bytes is the constant
+//! term of its own random polynomial of degree `threshold - 1`, and part `x`
+//! holds every polynomial evaluated at `x`. Each part is
+//! 
+//! ```text
+//! version (1) || threshold (1) || x (1) || split id (4) || y (key length + 4)
+//! ```
+//! 
+//! where `split id` is random and the same for every part of one split.
+//! Parts whose ids differ come from different splits and are refused
+//! outright. The tag is only known once the secret has been rebuilt, and is
+//! then compared with the rebuilt key, so a corrupted part is reported
+//! rather than yielding a wrong key.
+//! 
+//! Field arithmetic uses the AES polynomial and no lookup tables, so its
+//! timing doesn't depend on the key.
+
+use alloc::format;
+use alloc::vec::Vec;
+use sha2::{Digest, Sha256};
+
+use crate::error::CryptoError;
+
+const SHARE_VERSION: u8 = 1;
+const ID_LEN: usize = 4;
+const TAG_LEN: usize = 4;
+const HEADER_LEN: usize = 3 + ID_LEN;
+
+/// Multiply in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1
+fn mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        a = (a << 1) ^ (0x1b & (a >> 7).wrapping_neg());
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse, as a^254; `a` must not be 0
+fn inv(a: u8) -> u8 {
+    let mut result = 1;
+    let mut square = a;
+    for bit in 0..8 {
+        if 254 >> bit & 1 == 1 {
+            result = mul(result, square);
+        }
+        square = mul(square, square);
+    }
+    result
+}
+
+fn tag(secret: &[u8]) -> [u8; TAG_LEN] {
+    let digest = Sha256::new().chain_update(b"furl shamir").chain_update(secret).finalize();
+    let mut tag = [0u8; TAG_LEN];
+    tag.copy_from_slice(&digest[..TAG_LEN]);
+    tag
+}
+
+/// Split `secret` into `count` parts, any `threshold` of which rebuild it
+/// 
+/// `fill_random` must fill its buffer from a cryptographically secure source;
+/// it supplies the split id and the polynomial coefficients.
+pub fn split<F>(secret: &[u8], threshold: u8, count: u8, mut fill_random: F) -> Result<Vec<Vec<u8>>, CryptoError>
+where
+    F: FnMut(&mut [u8]),
+{
+    if secret.is_empty() {
+        return Err(CryptoError::InvalidInput("Invalid secret: nothing to split".into()));
+    }
+    if threshold < 2 || count < threshold {
+        return Err(CryptoError::InvalidInput(format!(
+            "Invalid split: need 2 <= threshold <= count <= 255, got {} of {}",
+            threshold, count
+        )));
+    }
+
+    let mut id = [0u8; ID_LEN];
+    fill_random(&mut id);
+    let mut shares: Vec<Vec<u8>> = (1..=count)
+        .map(|x| {
+            let mut share = Vec::with_capacity(HEADER_LEN + secret.len() + TAG_LEN);
+            share.extend_from_slice(&[SHARE_VERSION, threshold, x]);
+            share.extend_from_slice(&id);
+            share
+        })
+        .collect();
+
+    let tag = tag(secret);
+    let mut coefficients = alloc::vec![0u8; threshold as usize - 1];
+    for &byte in secret.iter().chain(&tag) {
+        fill_random(&mut coefficients);
+        for share in &mut shares {
+            let x = share[2];
+            // Horner's rule, highest coefficient first
+            let y = coefficients.iter().rev().fold(0, |acc, &coefficient| mul(acc, x) ^ coefficient);
+            share.push(mul(y, x) ^ byte);
+        }
+    }
+    coefficients.fill(0);
+
+    Ok(shares)
+}
+
+/// Rebuild a secret from at least `threshold` parts of one split
+pub fn combine(shares: &[&[u8]]) -> Result<Vec<u8>, CryptoError> {
+    let first = shares.first().ok_or_else(|| CryptoError::InvalidInput("Invalid shares: none given".into()))?;
+    for share in shares {
+        if share.len() <= HEADER_LEN + TAG_LEN {
+            return Err(CryptoError::InvalidFormat(format!("Invalid share: expected more than {} bytes, got {}", HEADER_LEN + TAG_LEN, share.len())));
+        }
+        if share[0] != SHARE_VERSION {
+            return Err(CryptoError::Unsupported(format!("Unsupported share version {}", share[0])));
+        }
+        if share[2] == 0 {
+            return Err(CryptoError::InvalidFormat("Invalid share: part number 0".into()));
+        }
+        if share[1] != first[1] || share[3..HEADER_LEN] != first[3..HEADER_LEN] || share.len() != first.len() {
+            return Err(CryptoError::InvalidInput("Invalid shares: they come from different splits".into()));
+        }
+    }
+
+    let threshold = first[1] as usize;
+    if shares.len() < threshold {
+        return Err(CryptoError::InvalidInput(format!("Not enough shares: need {}, got {}", threshold, shares.len())));
+    }
+    let xs: Vec<u8> = shares.iter().map(|share| share[2]).collect();
+    if xs.iter().enumerate().any(|(i, x)| xs[..i].contains(x)) {
+        return Err(CryptoError::InvalidInput("Invalid shares: the same part was given twice".into()));
+    }
+
+    // Lagrange basis at 0: prod x_j / (x_j - x_i), where subtraction is XOR
+    let basis: Vec<u8> = xs
+        .iter()
+        .map(|&xi| xs.iter().filter(|&&xj| xj != xi).fold(1, |acc, &xj| mul(acc, mul(xj, inv(xj ^ xi)))))
+        .collect();
+
+    let mut secret: Vec<u8> = (HEADER_LEN..first.len())
+        .map(|position| shares.iter().zip(&basis).fold(0, |acc, (share, &weight)| acc ^ mul(share[position], weight)))
+        .collect();
+    let expected = secret.split_off(secret.len() - TAG_LEN);
+
+    if tag(&secret) != expected[..] {
+        secret.fill(0);
+        return Err(CryptoError::AuthenticationFailed("Shares do not combine to the original key: one is corrupted".into()));
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic stand-in for a CSPRNG
+    fn counter_rng() -> impl FnMut(&mut [u8]) {
+        let mut next = 0u8;
+        move |buffer| {
+            for byte in buffer {
+                next = next.wrapping_mul(37).wrapping_add(11);
+                *byte = next;
+            }
+        }
+    }
+
+    #[test]
+    fn field_arithmetic() {
+        // FIPS 197 section 4.2
+        assert_eq!(mul(0x57, 0x83), 0xc1);
+        assert_eq!(mul(0x57, 0x13), 0xfe);
+        for a in 1..=255u8 {
+            assert_eq!(mul(a, inv(a)), 1, "{}", a);
+        }
+    }
+
+    #[test]
+    fn any_threshold_subset_rebuilds_the_key() {
+        let key: Vec<u8> = (0..32).collect();
+        let shares = split(&key, 3, 5, counter_rng()).unwrap();
+        assert_eq!(shares.len(), 5);
+        assert!(shares.iter().all(|share| share.len() == HEADER_LEN + 32 + TAG_LEN));
+
+        for (a, b, c) in [(0, 1, 2), (4, 2, 0), (1, 3, 4)] {
+            assert_eq!(combine(&[&shares[a][..], &shares[b], &shares[c]]).unwrap(), key);
+        }
+        let all: Vec<&[u8]> = shares.iter().map(Vec::as_slice).collect();
+        assert_eq!(combine(&all).unwrap(), key);
+        assert_eq!(combine(&all[..2]).unwrap_err().name(), "InvalidInput");
+    }
+
+    #[test]
+    fn parts_carry_no_fingerprint_of_the_key() {
+        // With the same randomness, parts of different keys share a header
+        let a = split(&[7; 16], 2, 3, counter_rng()).unwrap();
+        let b = split(&[8; 16], 2, 3, counter_rng()).unwrap();
+        for (a, b) in a.iter().zip(&b) {
+            assert_eq!(a[..HEADER_LEN], b[..HEADER_LEN]);
+        }
+    }
+
+    #[test]
+    fn mismatched_or_corrupted_shares_are_refused() {
+        let shares = split(&[7; 16], 2, 3, counter_rng()).unwrap();
+        let other = split(&[8; 16], 2, 3, |buffer| buffer.fill(0x33)).unwrap();
+
+        assert_eq!(combine(&[&shares[0][..], &other[1]]).unwrap_err().name(), "InvalidInput");
+        assert_eq!(combine(&[&shares[0][..], &shares[0]]).unwrap_err().name(), "InvalidInput");
+        let resplit = split(&[7; 16], 2, 3, |buffer| buffer.fill(0x5a)).unwrap();
+        assert_eq!(combine(&[&shares[0][..], &resplit[1]]).unwrap_err().name(), "InvalidInput");
+        let mut corrupted = shares[1].clone();
+        corrupted[HEADER_LEN + 3] ^= 1;
+        assert_eq!(combine(&[&shares[0][..], &corrupted]).unwrap_err().name(), "AuthenticationFailed");
+        assert_eq!(combine(&[&shares[0][..HEADER_LEN + TAG_LEN]]).unwrap_err().name(), "InvalidFormat");
+
+        assert_eq!(split(&[7; 16], 1, 3, counter_rng()).unwrap_err().name(), "InvalidInput");
+        assert_eq!(split(&[7; 16], 4, 3, counter_rng()).unwrap_err().name(), "InvalidInput");
+    }
+}
@@ -0,0 +1,206 @@
+//! furl link fragments
+//! 
+//! The secret half of a furl link travels in the URL fragment, which browsers
+//! never send to the server:
+//! 
+//! ```text
+//! https://host/furl.html?atSign=@alice&key=report#v=1&key=<base64>&iv=<base64>&name=Q3%20report.pdf
+//! ```
+//! 
+//! * `v` - fragment format version; absent means 1
+//! * `key` - 16, 24 or 32-byte AES key, standard or URL-safe base64
+//! * `iv` - 16-byte AES-CTR IV, same encoding
+//! * `name` - optional percent-encoded original filename
+//! * `pk` - optional 32-byte Ed25519 public key of the uploader, same
+//!   encoding as `key`; a viewer given one must check the manifest's
+//!   signature against it before decrypting anything
+//! 
+//! Parameters a version doesn't define are kept in `Fragment::extra`,
+//! so a newer uploader can add fields without breaking older viewers, while
+//! a fragment from a newer major version is refused outright. A literal `+`
+//! is kept as `+` rather than read as a space (as `URLSearchParams` would),
+//! since it is part of the standard base64 alphabet.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+use crate::encoding::decode_base64;
+use crate::error::CryptoError;
+
+/// Newest fragment version this build understands
+const SHARE_LINK_VERSION: u32 = 1;
+
+/// Parameters defined by version 1
+const KNOWN_PARAMS: [&str; 5] = ["v", "key", "iv", "name", "pk"];
+
+/// Decode `%XX` escapes, leaving every other byte (including `+`) alone
+fn percent_decode(text: &str) -> Result<String, CryptoError> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3).and_then(|hex| core::str::from_utf8(hex).ok());
+            let byte = hex
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| CryptoError::InvalidFormat("Invalid share link: bad percent escape".into()))?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).map_err(|_| CryptoError::InvalidFormat("Invalid share link: parameter is not UTF-8".into()))
+}
+
+/// Escape everything but RFC 3986 unreserved characters
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Build the fragment for a new share, without the leading `#`
+pub fn format_fragment(key: &[u8], iv: &[u8; 16], name: Option<&str>) -> String {
+    let mut fragment = format!("v={}&key={}&iv={}", SHARE_LINK_VERSION, URL_SAFE_NO_PAD.encode(key), URL_SAFE_NO_PAD.encode(iv));
+    if let Some(name) = name {
+        fragment.push_str("&name=");
+        fragment.push_str(&percent_encode(name));
+    }
+    fragment
+}
+
+/// Contents of a parsed fragment
+#[derive(Debug, PartialEq, Eq)]
+pub struct Fragment {
+    pub version: u32,
+    pub key: Vec<u8>,
+    pub iv: [u8; 16],
+    pub name: Option<String>,
+    pub public_key: Option<[u8; 32]>,
+    /// Parameters version 1 doesn't define, in fragment order
+    pub extra: Vec<(String, String)>,
+}
+
+/// Parse and validate a fragment, with or without the leading `#`
+pub fn parse_fragment(fragment: &str) -> Result<Fragment, CryptoError> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+
+    let mut params: Vec<(String, String)> = Vec::new();
+    for pair in fragment.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let (name, value) = (percent_decode(name)?, percent_decode(value)?);
+        if params.iter().any(|(seen, _)| *seen == name) {
+            return Err(CryptoError::InvalidFormat(format!("Invalid share link: {} appears more than once", name)));
+        }
+        params.push((name, value));
+    }
+    let get = |name: &str| params.iter().find(|(param, _)| param == name).map(|(_, value)| value.as_str());
+
+    // Check the version first so a newer link reports that, not some field it lacks
+    let version = match get("v") {
+        None => 1,
+        Some(v) => v
+            .parse::<u32>()
+            .ok()
+            .filter(|&v| v > 0)
+            .ok_or_else(|| CryptoError::InvalidFormat(format!("Invalid share link: bad version {:?}", v)))?,
+    };
+    if version > SHARE_LINK_VERSION {
+        return Err(CryptoError::Unsupported(format!(
+            "Share link version {} is newer than this viewer supports ({}); please update furl",
+            version, SHARE_LINK_VERSION
+        )));
+    }
+
+    let key = decode_base64(get("key").ok_or_else(|| CryptoError::InvalidFormat("Invalid share link: missing key".into()))?)?;
+    if !matches!(key.len(), 16 | 24 | 32) {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid share link: key must be 16, 24 or 32 bytes, got {}", key.len())));
+    }
+
+    let iv = decode_base64(get("iv").ok_or_else(|| CryptoError::InvalidFormat("Invalid share link: missing iv".into()))?)?;
+    let iv = <[u8; 16]>::try_from(iv.as_slice())
+        .map_err(|_| CryptoError::InvalidIv(format!("Invalid share link: iv must be 16 bytes, got {}", iv.len())))?;
+
+    let name = get("name").map(str::to_string);
+    if name.as_deref().is_some_and(|name| name.is_empty() || name.chars().any(char::is_control)) {
+        return Err(CryptoError::InvalidFormat("Invalid share link: name is empty or contains control characters".into()));
+    }
+
+    let public_key = get("pk")
+        .map(|pk| {
+            let pk = decode_base64(pk)?;
+            <[u8; 32]>::try_from(pk.as_slice())
+                .map_err(|_| CryptoError::InvalidKeyLength(format!("Invalid share link: pk must be 32 bytes, got {}", pk.len())))
+        })
+        .transpose()?;
+
+    let extra = params.into_iter().filter(|(name, _)| !KNOWN_PARAMS.contains(&name.as_str())).collect();
+
+    Ok(Fragment { version, key, iv, name, public_key, extra })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8";
+    const IV: &str = "-_-_-_-_-_-_-_-_-_-_-w";
+
+    #[test]
+    fn fragment_fields_are_decoded() {
+        let link = parse_fragment(&format!("#v=1&key={}&iv={}&name=Q3%20report%E2%80%94final.pdf&thumb=abc", KEY, IV)).unwrap();
+        assert_eq!(link.version, 1);
+        assert_eq!(link.key, (0..32).collect::<Vec<u8>>());
+        assert_eq!(link.iv[..6], [0xfb, 0xff, 0xbf, 0xfb, 0xff, 0xbf]);
+        assert_eq!(link.name.as_deref(), Some("Q3 report\u{2014}final.pdf"));
+        assert_eq!(link.extra, [("thumb".to_string(), "abc".to_string())]);
+        assert_eq!(link.public_key, None);
+
+        let link = parse_fragment(&format!("key={}&iv={}&pk={}", KEY, IV, KEY)).unwrap();
+        assert_eq!(link.public_key, Some(core::array::from_fn(|i| i as u8)));
+
+        // No version means 1; standard base64 keeps its `+` and `/`
+        let link = parse_fragment("key=+++++++++++++++++++++w&iv=/////////////////////g==").unwrap();
+        assert_eq!((link.version, &link.key[..3], link.name), (1, &[0xfb, 0xef, 0xbe][..], None));
+        assert_eq!(link.iv[..15], [0xff; 15]);
+    }
+
+    #[test]
+    fn formatted_fragments_parse_back() {
+        let key: Vec<u8> = (0..32).collect();
+        let fragment = format_fragment(&key, &[0xfb; 16], Some("Q3 report & notes+final.pdf"));
+        assert!(!fragment.contains(' ') && !fragment.contains('+'));
+
+        let link = parse_fragment(&fragment).unwrap();
+        assert_eq!((link.key, link.iv, link.name.as_deref()), (key, [0xfb; 16], Some("Q3 report & notes+final.pdf")));
+        assert_eq!(parse_fragment(&format_fragment(&[1; 16], &[2; 16], None)).unwrap().name, None);
+    }
+
+    #[test]
+    fn damaged_or_newer_links_are_rejected() {
+        let error = |fragment: String| parse_fragment(&fragment).unwrap_err().name();
+
+        assert_eq!(error(format!("v=2&key={}", KEY)), "Unsupported");
+        assert_eq!(error(format!("v=one&key={}&iv={}", KEY, IV)), "InvalidFormat");
+        assert_eq!(error(format!("v=0&key={}&iv={}", KEY, IV)), "InvalidFormat");
+        assert_eq!(error(format!("key={}", KEY)), "InvalidFormat");
+        assert_eq!(error(format!("iv={}", IV)), "InvalidFormat");
+        assert_eq!(error(format!("key=AAAA&iv={}", IV)), "InvalidKeyLength");
+        assert_eq!(error(format!("key={}&iv=AAAA", KEY)), "InvalidIv");
+        assert_eq!(error(format!("key={}&iv={}&pk=AAAA", KEY, IV)), "InvalidKeyLength");
+        assert_eq!(error(format!("key={}&key={}&iv={}", KEY, KEY, IV)), "InvalidFormat");
+        assert_eq!(error(format!("key={}&iv={}&name=a%0Ab", KEY, IV)), "InvalidFormat");
+        assert_eq!(error(format!("key={}&iv={}&name=%zz", KEY, IV)), "InvalidFormat");
+        assert_eq!(error(format!("key={}&iv={}&name=%FF", KEY, IV)), "InvalidFormat");
+        assert_eq!(error(String::new()), "InvalidFormat");
+    }
+}
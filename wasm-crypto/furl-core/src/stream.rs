@@ -0,0 +1,327 @@
+//! STREAM chunked AEAD (Hoang, Reyhanitabar, Rogaway and Vizár, "Online
+//! Authenticated-Encryption and its Nonce-Reuse Misuse-Resistance")
+//! 
+//! A long stream is cut into segments, each sealed with AES-256-GCM or
+//! ChaCha20-Poly1305 under the nonce
+//! 
+//! ```text
+//! prefix (7 bytes) || counter (4 bytes, big-endian) || last (1 byte)
+//! ```
+//! 
+//! where `counter` numbers the segments and `last` is 1 only on the final
+//! one. This is the construction proven OAE2-secure: reordering, dropping,
+//! duplicating or truncating segments always fails authentication, unlike a
+//! framing scheme that has to get those checks right itself. The prefix is
+//! random per stream and must never repeat under the same key.
+//! 
+//! Every `rekey_interval` segments the key is replaced by
+//! `HKDF-SHA256(key, info = "furl stream rekey")` and the counter restarts,
+//! so no key seals more than `rekey_interval` segments. Both sides ratchet
+//! at the same point, so rekeying needs nothing on the wire, and a segment
+//! carried over from another epoch fails like any other misplaced segment.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::container::{Algorithm, ChunkCipher, MAX_CHUNK_SIZE};
+use crate::error::CryptoError;
+
+pub const NONCE_PREFIX_LEN: usize = 7;
+pub const TAG_LEN: usize = 16;
+/// Segments sealed under one key before it is ratcheted forward
+pub const DEFAULT_REKEY_INTERVAL: u32 = 1 << 20;
+
+const REKEY_INFO: &[u8] = b"furl stream rekey";
+
+/// Errors produced while sealing or opening a STREAM
+#[derive(Debug, PartialEq)]
+pub enum StreamError {
+    InvalidKey(usize),
+    InvalidNoncePrefix(usize),
+    InvalidRekeyInterval,
+    InvalidSegmentSize(usize),
+    AuthenticationFailed(u64),
+    Truncated,
+    Finished,
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::InvalidKey(n) => write!(f, "Invalid key size: expected 32 bytes, got {}", n),
+            StreamError::InvalidNoncePrefix(n) => write!(f, "Invalid nonce prefix size: expected {} bytes, got {}", NONCE_PREFIX_LEN, n),
+            StreamError::InvalidRekeyInterval => write!(f, "Invalid rekey interval: expected at least 1 segment"),
+            StreamError::InvalidSegmentSize(n) => write!(f, "Invalid segment size: expected at most {} bytes, got {}", MAX_CHUNK_SIZE, n),
+            StreamError::AuthenticationFailed(index) => write!(f, "Authentication failed for segment {}: data is corrupted, reordered or from another stream", index),
+            StreamError::Truncated => write!(f, "Stream is truncated: last segment is missing"),
+            StreamError::Finished => write!(f, "Stream has already ended with its last segment"),
+        }
+    }
+}
+
+impl From<StreamError> for CryptoError {
+    fn from(error: StreamError) -> CryptoError {
+        let message = error.to_string();
+        match error {
+            StreamError::InvalidKey(_) => CryptoError::InvalidKeyLength(message),
+            StreamError::InvalidNoncePrefix(_) => CryptoError::InvalidIv(message),
+            StreamError::InvalidRekeyInterval | StreamError::InvalidSegmentSize(_) => CryptoError::InvalidInput(message),
+            StreamError::AuthenticationFailed(_) => CryptoError::AuthenticationFailed(message),
+            StreamError::Truncated => CryptoError::Truncated(message),
+            StreamError::Finished => CryptoError::InvalidState(message),
+        }
+    }
+}
+
+#[cfg(feature = "js")]
+impl From<StreamError> for wasm_bindgen::JsValue {
+    fn from(error: StreamError) -> wasm_bindgen::JsValue {
+        CryptoError::from(error).into()
+    }
+}
+
+/// Key, counter and epoch shared by both directions
+struct Stream {
+    algorithm: Algorithm,
+    key: [u8; 32],
+    cipher: ChunkCipher,
+    prefix: [u8; NONCE_PREFIX_LEN],
+    counter: u32,
+    rekey_interval: u32,
+    /// Segments processed so far, across epochs, for error messages
+    index: u64,
+    finished: bool,
+}
+
+impl Stream {
+    fn new(key: &[u8], nonce_prefix: &[u8], algorithm: Algorithm, rekey_interval: u32) -> Result<Self, StreamError> {
+        let key: [u8; 32] = key.try_into().map_err(|_| StreamError::InvalidKey(key.len()))?;
+        let prefix = nonce_prefix
+            .try_into()
+            .map_err(|_| StreamError::InvalidNoncePrefix(nonce_prefix.len()))?;
+        if rekey_interval == 0 {
+            return Err(StreamError::InvalidRekeyInterval);
+        }
+
+        Ok(Stream {
+            algorithm,
+            cipher: ChunkCipher::new(algorithm, &key).map_err(|_| StreamError::InvalidKey(32))?,
+            key,
+            prefix,
+            counter: 0,
+            rekey_interval,
+            index: 0,
+            finished: false,
+        })
+    }
+
+    fn nonce(&self, last: bool) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..NONCE_PREFIX_LEN].copy_from_slice(&self.prefix);
+        nonce[NONCE_PREFIX_LEN..11].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[11] = last as u8;
+        nonce
+    }
+
+    /// Segments are capped at the container's chunk limit, far below the AEAD limits
+    fn check(&self, plaintext_len: usize) -> Result<(), StreamError> {
+        if self.finished {
+            return Err(StreamError::Finished);
+        }
+        if plaintext_len > MAX_CHUNK_SIZE as usize {
+            return Err(StreamError::InvalidSegmentSize(plaintext_len));
+        }
+        Ok(())
+    }
+
+    /// Move to the next segment, ratcheting the key at the end of an epoch
+    fn advance(&mut self, last: bool) {
+        self.index += 1;
+        self.finished = last;
+        self.counter += 1;
+        if self.counter == self.rekey_interval {
+            let mut next = [0u8; 32];
+            Hkdf::<Sha256>::new(None, &self.key)
+                .expand(REKEY_INFO, &mut next)
+                .expect("32 bytes is within HKDF-SHA256's output limit");
+            self.key = next;
+            self.cipher = ChunkCipher::new(self.algorithm, &self.key).expect("ratcheted keys are 32 bytes");
+            self.counter = 0;
+        }
+    }
+}
+
+/// Seals a stream one segment at a time
+pub struct StreamEncryptor {
+    stream: Stream,
+}
+
+impl StreamEncryptor {
+    pub fn new(key: &[u8], nonce_prefix: &[u8], algorithm: Algorithm, rekey_interval: u32) -> Result<Self, StreamError> {
+        Ok(StreamEncryptor { stream: Stream::new(key, nonce_prefix, algorithm, rekey_interval)? })
+    }
+
+    /// Seal a segment other than the last; the output is 16 bytes longer
+    pub fn encrypt_next(&mut self, segment: &[u8], aad: &[u8]) -> Result<Vec<u8>, StreamError> {
+        self.seal(segment, aad, false)
+    }
+
+    /// Seal the last segment, which may be empty, and end the stream
+    pub fn encrypt_last(&mut self, segment: &[u8], aad: &[u8]) -> Result<Vec<u8>, StreamError> {
+        self.seal(segment, aad, true)
+    }
+
+    fn seal(&mut self, segment: &[u8], aad: &[u8], last: bool) -> Result<Vec<u8>, StreamError> {
+        self.stream.check(segment.len())?;
+        let sealed = self.stream.cipher.seal(&self.stream.nonce(last), aad, segment);
+        self.stream.advance(last);
+        Ok(sealed)
+    }
+}
+
+/// Opens a stream one segment at a time, in order
+pub struct StreamDecryptor {
+    stream: Stream,
+}
+
+impl StreamDecryptor {
+    pub fn new(key: &[u8], nonce_prefix: &[u8], algorithm: Algorithm, rekey_interval: u32) -> Result<Self, StreamError> {
+        Ok(StreamDecryptor { stream: Stream::new(key, nonce_prefix, algorithm, rekey_interval)? })
+    }
+
+    /// Open the next segment, which the sender says is not the last
+    pub fn decrypt_next(&mut self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, StreamError> {
+        self.open(sealed, aad, false)
+    }
+
+    /// Open the last segment and end the stream
+    pub fn decrypt_last(&mut self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, StreamError> {
+        self.open(sealed, aad, true)
+    }
+
+    /// Check that the last segment was opened, i.e. the stream wasn't cut short
+    pub fn finish(&self) -> Result<(), StreamError> {
+        if !self.stream.finished {
+            return Err(StreamError::Truncated);
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, sealed: &[u8], aad: &[u8], last: bool) -> Result<Vec<u8>, StreamError> {
+        self.stream.check(sealed.len().saturating_sub(TAG_LEN))?;
+        // A failed segment leaves the position unchanged; the caller can't
+        // skip past it, since the next segment needs this one's counter
+        let plaintext = self
+            .stream
+            .cipher
+            .open(&self.stream.nonce(last), aad, sealed)
+            .ok_or(StreamError::AuthenticationFailed(self.stream.index))?;
+        self.stream.advance(last);
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [0x42; 32];
+    const PREFIX: [u8; 7] = [0x07; 7];
+
+    fn seal(segments: &[&[u8]], algorithm: Algorithm, rekey_interval: u32) -> Vec<Vec<u8>> {
+        let mut encryptor = StreamEncryptor::new(&KEY, &PREFIX, algorithm, rekey_interval).unwrap();
+        let (last, rest) = segments.split_last().unwrap();
+        let mut sealed: Vec<Vec<u8>> = rest.iter().map(|segment| encryptor.encrypt_next(segment, b"").unwrap()).collect();
+        sealed.push(encryptor.encrypt_last(last, b"").unwrap());
+        sealed
+    }
+
+    fn open(sealed: &[Vec<u8>], rekey_interval: u32) -> Result<Vec<Vec<u8>>, StreamError> {
+        let mut decryptor = StreamDecryptor::new(&KEY, &PREFIX, Algorithm::Aes256Gcm, rekey_interval)?;
+        let mut segments = Vec::new();
+        for (i, segment) in sealed.iter().enumerate() {
+            segments.push(match i + 1 == sealed.len() {
+                true => decryptor.decrypt_last(segment, b"")?,
+                false => decryptor.decrypt_next(segment, b"")?,
+            });
+        }
+        decryptor.finish()?;
+        Ok(segments)
+    }
+
+    #[test]
+    fn round_trips_across_rekeys() {
+        let segments: Vec<Vec<u8>> = (0..10u8).map(|n| vec![n; n as usize * 7]).collect();
+        let borrowed: Vec<&[u8]> = segments.iter().map(Vec::as_slice).collect();
+
+        for algorithm in [Algorithm::Aes256Gcm, Algorithm::ChaCha20Poly1305] {
+            let mut decryptor = StreamDecryptor::new(&KEY, &PREFIX, algorithm, 3).unwrap();
+            let sealed = seal(&borrowed, algorithm, 3);
+            for (segment, plaintext) in sealed[..9].iter().zip(&segments) {
+                assert_eq!(&decryptor.decrypt_next(segment, b"").unwrap(), plaintext);
+            }
+            assert_eq!(decryptor.decrypt_last(&sealed[9], b"").unwrap(), segments[9]);
+            decryptor.finish().unwrap();
+        }
+    }
+
+    #[test]
+    fn nonces_follow_the_stream_layout() {
+        // Segment 2 of a stream with no rekeying is plain AES-GCM under
+        // prefix || 00000002 || 00, and the last flag changes the nonce
+        let sealed = seal(&[b"a", b"b", b"c", b"d"], Algorithm::Aes256Gcm, DEFAULT_REKEY_INTERVAL);
+        let cipher = ChunkCipher::new(Algorithm::Aes256Gcm, &KEY).unwrap();
+        let mut nonce = [0x07; 12];
+        nonce[7..].copy_from_slice(&[0, 0, 0, 2, 0]);
+        assert_eq!(cipher.seal(&nonce, b"", b"c"), sealed[2]);
+        nonce[7..].copy_from_slice(&[0, 0, 0, 3, 1]);
+        assert_eq!(cipher.seal(&nonce, b"", b"d"), sealed[3]);
+    }
+
+    #[test]
+    fn rekeying_ratchets_the_key_with_hkdf() {
+        let sealed = seal(&[b"a", b"b", b"c"], Algorithm::Aes256Gcm, 2);
+        let mut next = [0u8; 32];
+        crate::kdf::hkdf_sha256(&KEY, None, b"furl stream rekey", &mut next).unwrap();
+        let mut nonce = [0x07; 12];
+        nonce[7..].copy_from_slice(&[0, 0, 0, 0, 1]);
+        assert_eq!(ChunkCipher::new(Algorithm::Aes256Gcm, &next).unwrap().seal(&nonce, b"", b"c"), sealed[2]);
+    }
+
+    #[test]
+    fn reordering_dropping_and_truncation_fail() {
+        let sealed = seal(&[b"one", b"two", b"three", b"four"], Algorithm::Aes256Gcm, 2);
+
+        let mut swapped = sealed.clone();
+        swapped.swap(1, 2);
+        assert_eq!(open(&swapped, 2), Err(StreamError::AuthenticationFailed(1)));
+
+        let dropped = [sealed[0].clone(), sealed[2].clone(), sealed[3].clone()];
+        assert_eq!(open(&dropped, 2), Err(StreamError::AuthenticationFailed(1)));
+
+        // Cut after a segment that wasn't sealed as the last: opening it as
+        // the last fails, and stopping without a last segment is Truncated
+        assert_eq!(open(&sealed[..2], 2), Err(StreamError::AuthenticationFailed(1)));
+        let mut decryptor = StreamDecryptor::new(&KEY, &PREFIX, Algorithm::Aes256Gcm, 2).unwrap();
+        decryptor.decrypt_next(&sealed[0], b"").unwrap();
+        assert_eq!(decryptor.finish(), Err(StreamError::Truncated));
+
+        // Nothing may follow the last segment
+        let mut decryptor = StreamDecryptor::new(&KEY, &PREFIX, Algorithm::Aes256Gcm, 2).unwrap();
+        for segment in &sealed[..3] {
+            decryptor.decrypt_next(segment, b"").unwrap();
+        }
+        decryptor.decrypt_last(&sealed[3], b"").unwrap();
+        assert_eq!(decryptor.decrypt_next(&sealed[3], b""), Err(StreamError::Finished));
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        assert_eq!(StreamEncryptor::new(&KEY[..16], &PREFIX, Algorithm::Aes256Gcm, 1).err(), Some(StreamError::InvalidKey(16)));
+        assert_eq!(StreamEncryptor::new(&KEY, &[0; 12], Algorithm::Aes256Gcm, 1).err(), Some(StreamError::InvalidNoncePrefix(12)));
+        assert_eq!(StreamEncryptor::new(&KEY, &PREFIX, Algorithm::Aes256Gcm, 0).err(), Some(StreamError::InvalidRekeyInterval));
+    }
+}
@@ -0,0 +1,42 @@
+/*
+ * Seal and open a container through the C API
+ *
+ *   cargo build -p furl-ffi
+ *   cc furl-ffi/examples/roundtrip.c -Ifurl-ffi/include -Ltarget/debug -lfurl -o roundtrip
+ *   LD_LIBRARY_PATH=target/debug ./roundtrip
+ */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "furl.h"
+
+int main(void) {
+    uint8_t key[32], nonce[12];
+    const char *message = "hello from C";
+    FurlBuffer sealed = { 0 }, opened = { 0 };
+
+    memset(key, 0x24, sizeof key);
+    memset(nonce, 0x99, sizeof nonce);
+
+    if (furl_container_encrypt(key, sizeof key, nonce, sizeof nonce, 0, FURL_ALGORITHM_AES_256_GCM,
+                               (const uint8_t *)message, strlen(message), &sealed) != FURL_OK) {
+        fprintf(stderr, "encrypt failed: %s\n", furl_last_error());
+        return 1;
+    }
+
+    sealed.data[sealed.len - 1] ^= 1;
+    int32_t code = furl_container_decrypt(key, sizeof key, sealed.data, sealed.len, &opened);
+    printf("tampered: %d (%s)\n", code, furl_last_error());
+    sealed.data[sealed.len - 1] ^= 1;
+
+    if (furl_container_decrypt(key, sizeof key, sealed.data, sealed.len, &opened) != FURL_OK) {
+        fprintf(stderr, "decrypt failed: %s\n", furl_last_error());
+        return 1;
+    }
+    printf("%zu container bytes -> \"%.*s\"\n", sealed.len, (int)opened.len, opened.data);
+
+    furl_buffer_free(sealed);
+    furl_buffer_free(opened);
+    return code == FURL_ERR_AUTHENTICATION_FAILED ? 0 : 1;
+}
@@ -0,0 +1,111 @@
+/*
+ * furl C API: the furl-core cipher, KDF and container code
+ *
+ * Link against libfurl (cargo build --release -p furl-ffi). Functions that
+ * can fail return FURL_OK or one of the FURL_ERR_* codes, which match the
+ * JS module's CryptoErrorCode; furl_last_error() describes the failure.
+ * Buffers filled in by furl are released with furl_buffer_free.
+ */
+
+#ifndef FURL_H
+#define FURL_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define FURL_OK 0
+#define FURL_ERR_INVALID_KEY_LENGTH 1
+#define FURL_ERR_INVALID_KEY 2
+#define FURL_ERR_INVALID_IV 3
+#define FURL_ERR_INVALID_INPUT 4
+#define FURL_ERR_INVALID_FORMAT 5
+#define FURL_ERR_AUTHENTICATION_FAILED 6
+#define FURL_ERR_INVALID_PADDING 7
+#define FURL_ERR_TRUNCATED 8
+#define FURL_ERR_UNSUPPORTED 9
+#define FURL_ERR_INVALID_STATE 10
+#define FURL_ERR_STREAM_ERROR 11
+#define FURL_ERR_OUT_OF_MEMORY 12
+#define FURL_ERR_INTERNAL 13
+#define FURL_ERR_ABORTED 14
+#define FURL_ERR_NOT_FOUND 15
+#define FURL_ERR_WRONG_PASSWORD 16
+#define FURL_ERR_EXPIRED 17
+#define FURL_ERR_NOT_YET_VALID 18
+#define FURL_ERR_NETWORK_ERROR 19
+
+/* Container algorithms; 0 selects the default (AES-256-GCM) */
+#define FURL_ALGORITHM_AES_256_GCM 1
+#define FURL_ALGORITHM_CHACHA20_POLY1305 2
+
+typedef struct FurlBuffer {
+    uint8_t *data;
+    size_t len;
+} FurlBuffer;
+
+typedef struct FurlContainerWriter FurlContainerWriter;
+typedef struct FurlContainerReader FurlContainerReader;
+
+/* Message for the last failed call on this thread, or NULL. Valid until
+ * the next furl call on the same thread. */
+const char *furl_last_error(void);
+
+/* Wipe and free a buffer filled in by furl */
+void furl_buffer_free(FurlBuffer buffer);
+
+/* AES-CTR keystream applied in place from byte `offset`; counter_width is
+ * 32, 64 or 128 (0 = 128) */
+int32_t furl_aes_ctr_apply(const uint8_t *key, size_t key_len,
+                           const uint8_t *iv, size_t iv_len,
+                           uint32_t counter_width, uint64_t offset,
+                           uint8_t *data, size_t data_len);
+
+/* Chunked container, one shot; chunk_size 0 = 64 KiB */
+int32_t furl_container_encrypt(const uint8_t *key, size_t key_len,
+                               const uint8_t *base_nonce, size_t base_nonce_len,
+                               uint32_t chunk_size, uint32_t algorithm,
+                               const uint8_t *data, size_t data_len,
+                               FurlBuffer *out);
+int32_t furl_container_decrypt(const uint8_t *key, size_t key_len,
+                               const uint8_t *container, size_t container_len,
+                               FurlBuffer *out);
+
+/* Chunked container, streaming */
+int32_t furl_container_writer_new(const uint8_t *key, size_t key_len,
+                                  const uint8_t *base_nonce, size_t base_nonce_len,
+                                  uint32_t chunk_size, uint32_t algorithm,
+                                  FurlContainerWriter **writer);
+int32_t furl_container_writer_update(FurlContainerWriter *writer,
+                                     const uint8_t *data, size_t data_len,
+                                     FurlBuffer *out);
+int32_t furl_container_writer_finish(FurlContainerWriter *writer, FurlBuffer *out);
+void furl_container_writer_free(FurlContainerWriter *writer);
+
+int32_t furl_container_reader_new(const uint8_t *key, size_t key_len,
+                                  FurlContainerReader **reader);
+int32_t furl_container_reader_update(FurlContainerReader *reader,
+                                     const uint8_t *data, size_t data_len,
+                                     FurlBuffer *out);
+/* Fails with FURL_ERR_TRUNCATED if the final chunk never arrived */
+int32_t furl_container_reader_finish(FurlContainerReader *reader);
+void furl_container_reader_free(FurlContainerReader *reader);
+
+/* Key derivation; a NULL salt means no salt for HKDF */
+int32_t furl_hkdf_sha256(const uint8_t *ikm, size_t ikm_len,
+                         const uint8_t *salt, size_t salt_len,
+                         const uint8_t *info, size_t info_len,
+                         uint8_t *out, size_t out_len);
+int32_t furl_pbkdf2_sha256(const uint8_t *password, size_t password_len,
+                           const uint8_t *salt, size_t salt_len,
+                           uint32_t iterations,
+                           uint8_t *out, size_t out_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FURL_H */
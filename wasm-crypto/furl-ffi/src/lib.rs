@@ -0,0 +1,471 @@
+//! C API over furl-core
+//! 
+//! Desktop apps and other languages link `libfurl` (or the static library)
+//! and call the same cipher, KDF and container code the browser module
+//! uses. Declarations are in `include/furl.h`.
+//! 
+//! Conventions, in C terms:
+//! 
+//! * Functions that can fail return `FURL_OK` (0) or a `CryptoErrorCode`
+//!   value; `furl_last_error()` then describes the failure on that thread.
+//! * Inputs are `(pointer, length)` pairs; a null pointer is fine when the
+//!   length is 0.
+//! * Output buffers are `FurlBuffer`s owned by the caller, released with
+//!   `furl_buffer_free` (which wipes them first).
+//! * Streaming objects are opaque pointers created by `*_new` and released
+//!   with the matching `*_free`.
+//! 
+//! Panics are caught at the boundary and reported as `Internal` in builds
+//! that unwind; the workspace release profile aborts on panic instead.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use furl_core::container::{Algorithm, ContainerReader, ContainerWriter, DEFAULT_CHUNK_SIZE};
+use furl_core::ctr::{AesCtr, CounterWidth, CtrOptions};
+use furl_core::kdf::{hkdf_sha256, pbkdf2_sha256};
+use furl_core::CryptoError;
+use zeroize::Zeroize;
+
+/// Return value of a call that succeeded
+pub const FURL_OK: i32 = 0;
+
+/// Bytes returned to the caller; free with `furl_buffer_free`
+#[repr(C)]
+pub struct FurlBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Streaming container encryptor
+pub struct FurlContainerWriter(ContainerWriter);
+
+/// Streaming container decryptor
+pub struct FurlContainerReader(ContainerReader);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Run a call body, recording its error (or a caught panic) for `furl_last_error`
+fn guard(body: impl FnOnce() -> Result<(), CryptoError>) -> i32 {
+    let result = catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|_| Err(CryptoError::Internal("Internal error: panic inside furl".into())));
+
+    let (code, message) = match result {
+        Ok(()) => (FURL_OK, None),
+        Err(error) => (error.code() as i32, CString::new(error.to_string()).ok()),
+    };
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    code
+}
+
+/// Borrow a caller's `(pointer, length)` input
+unsafe fn input<'a>(data: *const u8, len: usize) -> Result<&'a [u8], CryptoError> {
+    match (data.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(CryptoError::InvalidInput("Null pointer passed with a non-zero length".into())),
+        (false, _) => Ok(slice::from_raw_parts(data, len)),
+    }
+}
+
+/// Borrow a caller's `(pointer, length)` buffer for writing
+unsafe fn output<'a>(data: *mut u8, len: usize) -> Result<&'a mut [u8], CryptoError> {
+    match (data.is_null(), len) {
+        (_, 0) => Ok(&mut []),
+        (true, _) => Err(CryptoError::InvalidInput("Null pointer passed with a non-zero length".into())),
+        (false, _) => Ok(slice::from_raw_parts_mut(data, len)),
+    }
+}
+
+/// Hand `bytes` to the caller through `out`
+unsafe fn give(bytes: Vec<u8>, out: *mut FurlBuffer) -> Result<(), CryptoError> {
+    let out = out.as_mut().ok_or_else(|| CryptoError::InvalidInput("Null output buffer".into()))?;
+    let bytes = Box::into_raw(bytes.into_boxed_slice());
+    *out = FurlBuffer { data: bytes as *mut u8, len: bytes.len() };
+    Ok(())
+}
+
+unsafe fn object<'a, T>(object: *mut T) -> Result<&'a mut T, CryptoError> {
+    object.as_mut().ok_or_else(|| CryptoError::InvalidInput("Null object pointer".into()))
+}
+
+fn algorithm(id: u32) -> Result<Algorithm, CryptoError> {
+    match id {
+        0 | 1 => Ok(Algorithm::Aes256Gcm),
+        2 => Ok(Algorithm::ChaCha20Poly1305),
+        other => Err(CryptoError::Unsupported(format!("Unsupported container algorithm: {}", other))),
+    }
+}
+
+fn chunk_size(size: u32) -> u32 {
+    if size == 0 { DEFAULT_CHUNK_SIZE } else { size }
+}
+
+/// Message for the last failed call on this thread, or null
+/// 
+/// The string stays valid until the next furl call on the same thread.
+#[no_mangle]
+pub extern "C" fn furl_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Wipe and free a buffer returned by furl; null buffers are ignored
+/// 
+/// # Safety
+/// `buffer` must come from a furl call and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn furl_buffer_free(buffer: FurlBuffer) {
+    if buffer.data.is_null() {
+        return;
+    }
+    let mut bytes = Box::from_raw(ptr::slice_from_raw_parts_mut(buffer.data, buffer.len));
+    bytes.zeroize();
+}
+
+/// Apply the AES-CTR keystream to `data` in place, starting `offset` bytes into it
+/// 
+/// `counter_width` is 32, 64 or 128 (0 means 128). Encryption and
+/// decryption are the same operation.
+/// 
+/// # Safety
+/// Every pointer must be valid for its length, or that length must be 0.
+#[no_mangle]
+pub unsafe extern "C" fn furl_aes_ctr_apply(
+    key: *const u8,
+    key_len: usize,
+    iv: *const u8,
+    iv_len: usize,
+    counter_width: u32,
+    offset: u64,
+    data: *mut u8,
+    data_len: usize,
+) -> i32 {
+    guard(|| {
+        let counter_width = match counter_width {
+            0 => CounterWidth::Bits128,
+            bits => CounterWidth::from_bits(bits as f64)?,
+        };
+        let options = CtrOptions { counter_width, rfc3686: false };
+        let mut cipher = AesCtr::with_options(input(key, key_len)?, input(iv, iv_len)?, &options)?;
+        cipher.seek(offset)?;
+        cipher.apply_keystream(output(data, data_len)?)
+    })
+}
+
+/// Encrypt `data` into a complete container
+/// 
+/// `chunk_size` 0 picks the default (64 KiB); `algorithm` is 1 for
+/// AES-256-GCM (or 0 for the default, the same) and 2 for ChaCha20-Poly1305.
+/// 
+/// # Safety
+/// Every pointer must be valid for its length, or that length must be 0;
+/// `out` must point to a `FurlBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_encrypt(
+    key: *const u8,
+    key_len: usize,
+    base_nonce: *const u8,
+    base_nonce_len: usize,
+    chunk_size: u32,
+    algorithm: u32,
+    data: *const u8,
+    data_len: usize,
+    out: *mut FurlBuffer,
+) -> i32 {
+    guard(|| {
+        let mut writer = ContainerWriter::new(input(key, key_len)?, input(base_nonce, base_nonce_len)?, self::chunk_size(chunk_size), self::algorithm(algorithm)?)?;
+        let mut container = writer.update(input(data, data_len)?)?;
+        container.extend_from_slice(&writer.finish()?);
+        give(container, out)
+    })
+}
+
+/// Decrypt and verify a complete container
+/// 
+/// # Safety
+/// Every pointer must be valid for its length, or that length must be 0;
+/// `out` must point to a `FurlBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_decrypt(
+    key: *const u8,
+    key_len: usize,
+    container: *const u8,
+    container_len: usize,
+    out: *mut FurlBuffer,
+) -> i32 {
+    guard(|| {
+        let mut reader = ContainerReader::new(input(key, key_len)?)?;
+        let plaintext = reader.update(input(container, container_len)?)?;
+        reader.finish()?;
+        give(plaintext, out)
+    })
+}
+
+/// Start a streaming container encryptor; arguments as for `furl_container_encrypt`
+/// 
+/// # Safety
+/// Every pointer must be valid for its length, or that length must be 0;
+/// `writer` must point to writable storage for the new object.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_writer_new(
+    key: *const u8,
+    key_len: usize,
+    base_nonce: *const u8,
+    base_nonce_len: usize,
+    chunk_size: u32,
+    algorithm: u32,
+    writer: *mut *mut FurlContainerWriter,
+) -> i32 {
+    guard(|| {
+        let slot = object(writer)?;
+        let inner = ContainerWriter::new(input(key, key_len)?, input(base_nonce, base_nonce_len)?, self::chunk_size(chunk_size), self::algorithm(algorithm)?)?;
+        *slot = Box::into_raw(Box::new(FurlContainerWriter(inner)));
+        Ok(())
+    })
+}
+
+/// Add plaintext; `out` receives the container bytes that are ready (possibly none)
+/// 
+/// # Safety
+/// `writer` must come from `furl_container_writer_new`; `data` must be
+/// valid for `data_len` bytes and `out` must point to a `FurlBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_writer_update(writer: *mut FurlContainerWriter, data: *const u8, data_len: usize, out: *mut FurlBuffer) -> i32 {
+    guard(|| {
+        let bytes = object(writer)?.0.update(input(data, data_len)?)?;
+        give(bytes, out)
+    })
+}
+
+/// Seal the final chunk; `out` receives the last container bytes
+/// 
+/// # Safety
+/// `writer` must come from `furl_container_writer_new` and `out` must
+/// point to a `FurlBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_writer_finish(writer: *mut FurlContainerWriter, out: *mut FurlBuffer) -> i32 {
+    guard(|| {
+        let bytes = object(writer)?.0.finish()?;
+        give(bytes, out)
+    })
+}
+
+/// Free an encryptor; null is ignored
+/// 
+/// # Safety
+/// `writer` must come from `furl_container_writer_new` and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_writer_free(writer: *mut FurlContainerWriter) {
+    if !writer.is_null() {
+        drop(Box::from_raw(writer));
+    }
+}
+
+/// Start a streaming container decryptor for a 32-byte key
+/// 
+/// # Safety
+/// `key` must be valid for `key_len` bytes and `reader` must point to
+/// writable storage for the new object.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_reader_new(key: *const u8, key_len: usize, reader: *mut *mut FurlContainerReader) -> i32 {
+    guard(|| {
+        let slot = object(reader)?;
+        *slot = Box::into_raw(Box::new(FurlContainerReader(ContainerReader::new(input(key, key_len)?)?)));
+        Ok(())
+    })
+}
+
+/// Add container bytes; `out` receives the newly authenticated plaintext (possibly none)
+/// 
+/// # Safety
+/// `reader` must come from `furl_container_reader_new`; `data` must be
+/// valid for `data_len` bytes and `out` must point to a `FurlBuffer`.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_reader_update(reader: *mut FurlContainerReader, data: *const u8, data_len: usize, out: *mut FurlBuffer) -> i32 {
+    guard(|| {
+        let bytes = object(reader)?.0.update(input(data, data_len)?)?;
+        give(bytes, out)
+    })
+}
+
+/// Check that the stream ended after its final chunk
+/// 
+/// Fails with `Truncated` if it did not; plaintext already returned must
+/// then be discarded.
+/// 
+/// # Safety
+/// `reader` must come from `furl_container_reader_new`.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_reader_finish(reader: *mut FurlContainerReader) -> i32 {
+    guard(|| Ok(object(reader)?.0.finish()?))
+}
+
+/// Free a decryptor; null is ignored
+/// 
+/// # Safety
+/// `reader` must come from `furl_container_reader_new` and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn furl_container_reader_free(reader: *mut FurlContainerReader) {
+    if !reader.is_null() {
+        drop(Box::from_raw(reader));
+    }
+}
+
+/// HKDF-SHA256 (RFC 5869) into `out`; a null `salt` means no salt
+/// 
+/// # Safety
+/// Every pointer must be valid for its length, or that length must be 0.
+#[no_mangle]
+pub unsafe extern "C" fn furl_hkdf_sha256(
+    ikm: *const u8,
+    ikm_len: usize,
+    salt: *const u8,
+    salt_len: usize,
+    info: *const u8,
+    info_len: usize,
+    out: *mut u8,
+    out_len: usize,
+) -> i32 {
+    guard(|| {
+        let salt = if salt.is_null() { None } else { Some(input(salt, salt_len)?) };
+        hkdf_sha256(input(ikm, ikm_len)?, salt, input(info, info_len)?, output(out, out_len)?)
+    })
+}
+
+/// PBKDF2-HMAC-SHA256 (RFC 8018) into `out`
+/// 
+/// # Safety
+/// Every pointer must be valid for its length, or that length must be 0.
+#[no_mangle]
+pub unsafe extern "C" fn furl_pbkdf2_sha256(
+    password: *const u8,
+    password_len: usize,
+    salt: *const u8,
+    salt_len: usize,
+    iterations: u32,
+    out: *mut u8,
+    out_len: usize,
+) -> i32 {
+    guard(|| {
+        if iterations == 0 {
+            return Err(CryptoError::InvalidInput("Invalid iteration count: must be at least 1".into()));
+        }
+        pbkdf2_sha256(input(password, password_len)?, input(salt, salt_len)?, iterations, output(out, out_len)?, |_| {});
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    const KEY: [u8; 32] = [0x24; 32];
+    const NONCE: [u8; 12] = [0x99; 12];
+
+    fn empty() -> FurlBuffer {
+        FurlBuffer { data: ptr::null_mut(), len: 0 }
+    }
+
+    unsafe fn take(buffer: FurlBuffer) -> Vec<u8> {
+        let bytes = slice::from_raw_parts(buffer.data, buffer.len).to_vec();
+        furl_buffer_free(buffer);
+        bytes
+    }
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(furl_last_error()) }.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn one_shot_and_streaming_containers_agree() {
+        let plaintext: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        unsafe {
+            let mut sealed = empty();
+            assert_eq!(furl_container_encrypt(KEY.as_ptr(), 32, NONCE.as_ptr(), 12, 100, 2, plaintext.as_ptr(), plaintext.len(), &mut sealed), FURL_OK);
+            let sealed = take(sealed);
+
+            let mut writer = ptr::null_mut();
+            assert_eq!(furl_container_writer_new(KEY.as_ptr(), 32, NONCE.as_ptr(), 12, 100, 2, &mut writer), FURL_OK);
+            let mut streamed = Vec::new();
+            for piece in plaintext.chunks(333) {
+                let mut out = empty();
+                assert_eq!(furl_container_writer_update(writer, piece.as_ptr(), piece.len(), &mut out), FURL_OK);
+                streamed.extend(take(out));
+            }
+            let mut out = empty();
+            assert_eq!(furl_container_writer_finish(writer, &mut out), FURL_OK);
+            streamed.extend(take(out));
+            furl_container_writer_free(writer);
+            assert_eq!(streamed, sealed);
+
+            let mut reader = ptr::null_mut();
+            assert_eq!(furl_container_reader_new(KEY.as_ptr(), 32, &mut reader), FURL_OK);
+            let mut opened = Vec::new();
+            for piece in sealed.chunks(50) {
+                let mut out = empty();
+                assert_eq!(furl_container_reader_update(reader, piece.as_ptr(), piece.len(), &mut out), FURL_OK);
+                opened.extend(take(out));
+            }
+            assert_eq!(furl_container_reader_finish(reader), FURL_OK);
+            furl_container_reader_free(reader);
+            assert_eq!(opened, plaintext);
+        }
+    }
+
+    #[test]
+    fn failures_return_error_codes_and_messages() {
+        unsafe {
+            let mut sealed = empty();
+            assert_eq!(furl_container_encrypt(KEY.as_ptr(), 32, NONCE.as_ptr(), 12, 0, 0, b"hello".as_ptr(), 5, &mut sealed), FURL_OK);
+            let mut sealed = take(sealed);
+            *sealed.last_mut().unwrap() ^= 1;
+
+            let mut out = empty();
+            let code = furl_container_decrypt(KEY.as_ptr(), 32, sealed.as_ptr(), sealed.len(), &mut out);
+            assert_eq!(code, furl_core::CryptoErrorCode::AuthenticationFailed as i32);
+            assert!(last_error().contains("Authentication failed"));
+            assert!(out.data.is_null());
+
+            let code = furl_container_encrypt(KEY.as_ptr(), 16, NONCE.as_ptr(), 12, 0, 0, ptr::null(), 0, &mut out);
+            assert_eq!(code, furl_core::CryptoErrorCode::InvalidKeyLength as i32);
+            assert_eq!(furl_container_encrypt(KEY.as_ptr(), 32, NONCE.as_ptr(), 12, 0, 9, ptr::null(), 0, &mut out), furl_core::CryptoErrorCode::Unsupported as i32);
+            assert_eq!(furl_container_decrypt(ptr::null(), 32, ptr::null(), 0, &mut out), furl_core::CryptoErrorCode::InvalidInput as i32);
+            assert_eq!(furl_container_decrypt(KEY.as_ptr(), 32, ptr::null(), 0, ptr::null_mut()), furl_core::CryptoErrorCode::Truncated as i32);
+
+            assert_eq!(furl_hkdf_sha256(KEY.as_ptr(), 32, ptr::null(), 0, ptr::null(), 0, ptr::null_mut(), 0), FURL_OK);
+            assert!(furl_last_error().is_null());
+        }
+    }
+
+    #[test]
+    fn aes_ctr_at_an_offset_matches_one_pass() {
+        let iv = [0x42u8; 16];
+        let mut whole = vec![0u8; 100];
+        let mut tail = vec![0u8; 63];
+        unsafe {
+            assert_eq!(furl_aes_ctr_apply(KEY.as_ptr(), 32, iv.as_ptr(), 16, 0, 0, whole.as_mut_ptr(), whole.len()), FURL_OK);
+            assert_eq!(furl_aes_ctr_apply(KEY.as_ptr(), 32, iv.as_ptr(), 16, 128, 37, tail.as_mut_ptr(), tail.len()), FURL_OK);
+            assert_eq!(furl_aes_ctr_apply(KEY.as_ptr(), 32, iv.as_ptr(), 16, 48, 0, tail.as_mut_ptr(), 0), furl_core::CryptoErrorCode::InvalidInput as i32);
+        }
+        assert_eq!(tail, whole[37..]);
+    }
+
+    #[test]
+    fn kdfs_match_known_answers() {
+        // RFC 5869 test case 3 (no salt, no info) and RFC 7914 section 11
+        let ikm = [0x0bu8; 22];
+        let mut okm = [0u8; 42];
+        let mut derived = [0u8; 64];
+        unsafe {
+            assert_eq!(furl_hkdf_sha256(ikm.as_ptr(), ikm.len(), ptr::null(), 0, ptr::null(), 0, okm.as_mut_ptr(), okm.len()), FURL_OK);
+            assert_eq!(furl_pbkdf2_sha256(b"passwd".as_ptr(), 6, b"salt".as_ptr(), 4, 1, derived.as_mut_ptr(), derived.len()), FURL_OK);
+        }
+        assert_eq!(okm[..8], [0x8d, 0xa4, 0xe7, 0x75, 0xa5, 0x63, 0xc1, 0x8f]);
+        assert_eq!(derived[..8], [0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f]);
+    }
+}
@@ -0,0 +1,353 @@
+//! Kotlin and Swift bindings over furl-core
+//! 
+//! uniffi generates the mobile sources from this crate (`./build.sh mobile`),
+//! so Android and iOS companions parse links, decrypt shares and handle
+//! containers with the code the browser runs, rather than carrying their own
+//! copies of the fragment rules or the CTR counter and seek logic.
+//! 
+//! ```kotlin
+//! val link = parseShareLink(url)
+//! val decryptor = ShareDecryptor(link.key, link.iv)
+//! val plaintext = decryptor.decryptAt(rangeStart, body)
+//! ```
+//! 
+//! Every failure is a `FurlException` (Kotlin) / `FurlError` (Swift) case
+//! named after the `CryptoError` kind the JS module would throw.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use furl_core::container::{Algorithm, ContainerError, ContainerReader, ContainerWriter, DEFAULT_CHUNK_SIZE};
+use furl_core::ctr::AesCtr;
+use furl_core::sharelink::parse_fragment;
+use furl_core::CryptoError;
+
+uniffi::setup_scaffolding!();
+
+/// Failures, one case per `CryptoError` kind
+#[derive(Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum FurlError {
+    InvalidKeyLength(String),
+    InvalidKey(String),
+    InvalidIv(String),
+    InvalidInput(String),
+    InvalidFormat(String),
+    AuthenticationFailed(String),
+    InvalidPadding(String),
+    Truncated(String),
+    Unsupported(String),
+    InvalidState(String),
+    StreamError(String),
+    OutOfMemory(String),
+    Internal(String),
+    Aborted(String),
+    NotFound(String),
+    WrongPassword(String),
+    Expired(String),
+    NotYetValid(String),
+    NetworkError(String),
+}
+
+impl From<CryptoError> for FurlError {
+    fn from(error: CryptoError) -> Self {
+        match error {
+            CryptoError::InvalidKeyLength(message) => FurlError::InvalidKeyLength(message),
+            CryptoError::InvalidKey(message) => FurlError::InvalidKey(message),
+            CryptoError::InvalidIv(message) => FurlError::InvalidIv(message),
+            CryptoError::InvalidInput(message) => FurlError::InvalidInput(message),
+            CryptoError::InvalidFormat(message) => FurlError::InvalidFormat(message),
+            CryptoError::AuthenticationFailed(message) => FurlError::AuthenticationFailed(message),
+            CryptoError::InvalidPadding(message) => FurlError::InvalidPadding(message),
+            CryptoError::Truncated(message) => FurlError::Truncated(message),
+            CryptoError::Unsupported(message) => FurlError::Unsupported(message),
+            CryptoError::InvalidState(message) => FurlError::InvalidState(message),
+            CryptoError::StreamError(message) => FurlError::StreamError(message),
+            CryptoError::OutOfMemory(message) => FurlError::OutOfMemory(message),
+            CryptoError::Internal(message) => FurlError::Internal(message),
+            CryptoError::Aborted(message) => FurlError::Aborted(message),
+            CryptoError::NotFound(message) => FurlError::NotFound(message),
+            CryptoError::WrongPassword(message) => FurlError::WrongPassword(message),
+            CryptoError::Expired(message) => FurlError::Expired(message),
+            CryptoError::NotYetValid(message) => FurlError::NotYetValid(message),
+            CryptoError::NetworkError(message) => FurlError::NetworkError(message),
+        }
+    }
+}
+
+impl From<ContainerError> for FurlError {
+    fn from(error: ContainerError) -> Self {
+        CryptoError::from(error).into()
+    }
+}
+
+impl std::fmt::Display for FurlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FurlError::InvalidKeyLength(message)
+            | FurlError::InvalidKey(message)
+            | FurlError::InvalidIv(message)
+            | FurlError::InvalidInput(message)
+            | FurlError::InvalidFormat(message)
+            | FurlError::AuthenticationFailed(message)
+            | FurlError::InvalidPadding(message)
+            | FurlError::Truncated(message)
+            | FurlError::Unsupported(message)
+            | FurlError::InvalidState(message)
+            | FurlError::StreamError(message)
+            | FurlError::OutOfMemory(message)
+            | FurlError::Internal(message)
+            | FurlError::Aborted(message)
+            | FurlError::NotFound(message)
+            | FurlError::WrongPassword(message)
+            | FurlError::Expired(message)
+            | FurlError::NotYetValid(message)
+            | FurlError::NetworkError(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for FurlError {}
+
+/// Lock an object's state; a panic in another call can't leave it half-updated
+/// in a way that matters, so a poisoned lock is still used
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A parsed furl link fragment
+#[derive(Debug, PartialEq, uniffi::Record)]
+pub struct ShareLink {
+    pub version: u32,
+    /// 16, 24 or 32-byte AES key
+    pub key: Vec<u8>,
+    /// 16-byte AES-CTR IV
+    pub iv: Vec<u8>,
+    /// Original filename, if the link carries one
+    pub name: Option<String>,
+    /// Parameters this version doesn't define
+    pub extra: HashMap<String, String>,
+}
+
+/// Parse a furl link, or just its fragment
+/// 
+/// Accepts the whole URL (everything up to `#` is ignored) or the fragment
+/// with or without its `#`, and applies the same validation as the browser.
+#[uniffi::export]
+pub fn parse_share_link(link: String) -> Result<ShareLink, FurlError> {
+    let fragment = link.split_once('#').map_or(link.as_str(), |(_, fragment)| fragment);
+    let fragment = parse_fragment(fragment)?;
+
+    Ok(ShareLink {
+        version: fragment.version,
+        key: fragment.key,
+        iv: fragment.iv.to_vec(),
+        name: fragment.name,
+        extra: fragment.extra.into_iter().collect(),
+    })
+}
+
+/// AES-CTR decryptor for a share, sequential or at any byte offset
+/// 
+/// `decrypt` continues where the previous call stopped; `decrypt_at` starts
+/// at an absolute offset, e.g. the start of an HTTP `Range` response, and
+/// later `decrypt` calls continue from the end of that piece.
+#[derive(uniffi::Object)]
+pub struct ShareDecryptor {
+    state: Mutex<(AesCtr, u64)>,
+}
+
+#[uniffi::export]
+impl ShareDecryptor {
+    /// Decryptor for a 16, 24 or 32-byte key and 16-byte IV
+    #[uniffi::constructor]
+    pub fn new(key: Vec<u8>, iv: Vec<u8>) -> Result<Self, FurlError> {
+        Ok(ShareDecryptor { state: Mutex::new((AesCtr::new(&key, &iv)?, 0)) })
+    }
+
+    /// Decryptor for the key and IV in a share link
+    #[uniffi::constructor]
+    pub fn from_link(link: String) -> Result<Self, FurlError> {
+        let link = parse_share_link(link)?;
+        ShareDecryptor::new(link.key, link.iv)
+    }
+
+    /// Decrypt the next bytes of the file
+    pub fn decrypt(&self, data: Vec<u8>) -> Result<Vec<u8>, FurlError> {
+        let mut state = lock(&self.state);
+        let offset = state.1;
+        apply_at(&mut state, offset, data)
+    }
+
+    /// Decrypt bytes that start `offset` bytes into the file
+    pub fn decrypt_at(&self, offset: u64, data: Vec<u8>) -> Result<Vec<u8>, FurlError> {
+        apply_at(&mut lock(&self.state), offset, data)
+    }
+
+    /// Offset the next `decrypt` call starts at
+    pub fn position(&self) -> u64 {
+        lock(&self.state).1
+    }
+}
+
+fn apply_at(state: &mut (AesCtr, u64), offset: u64, mut data: Vec<u8>) -> Result<Vec<u8>, FurlError> {
+    let end = offset
+        .checked_add(data.len() as u64)
+        .ok_or_else(|| CryptoError::InvalidInput("Offset is beyond the end of the keystream".into()))?;
+    let (cipher, position) = state;
+    cipher.seek(offset)?;
+    cipher.apply_keystream(&mut data)?;
+    *position = end;
+    Ok(data)
+}
+
+/// AEAD used for container chunks
+#[derive(Clone, Copy, Debug, PartialEq, uniffi::Enum)]
+pub enum ContainerAlgorithm {
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl From<ContainerAlgorithm> for Algorithm {
+    fn from(algorithm: ContainerAlgorithm) -> Self {
+        match algorithm {
+            ContainerAlgorithm::Aes256Gcm => Algorithm::Aes256Gcm,
+            ContainerAlgorithm::ChaCha20Poly1305 => Algorithm::ChaCha20Poly1305,
+        }
+    }
+}
+
+fn chunk_size(size: u32) -> u32 {
+    if size == 0 { DEFAULT_CHUNK_SIZE } else { size }
+}
+
+/// Encrypt data into the chunked container format in one call
+/// 
+/// `chunk_size` 0 picks the default of 64 KiB.
+#[uniffi::export]
+pub fn encrypt_container(key: Vec<u8>, base_nonce: Vec<u8>, data: Vec<u8>, chunk_size: u32, algorithm: ContainerAlgorithm) -> Result<Vec<u8>, FurlError> {
+    let mut writer = ContainerWriter::new(&key, &base_nonce, self::chunk_size(chunk_size), algorithm.into())?;
+    let mut container = writer.update(&data)?;
+    container.extend_from_slice(&writer.finish()?);
+    Ok(container)
+}
+
+/// Decrypt and verify a complete container in one call
+#[uniffi::export]
+pub fn decrypt_container(key: Vec<u8>, container: Vec<u8>) -> Result<Vec<u8>, FurlError> {
+    let mut reader = ContainerReader::new(&key)?;
+    let plaintext = reader.update(&container)?;
+    reader.finish()?;
+    Ok(plaintext)
+}
+
+/// Streaming container encryptor
+#[derive(uniffi::Object)]
+pub struct ContainerEncryptor {
+    writer: Mutex<ContainerWriter>,
+}
+
+#[uniffi::export]
+impl ContainerEncryptor {
+    /// `chunk_size` 0 picks the default of 64 KiB; never reuse `base_nonce` with the same key
+    #[uniffi::constructor]
+    pub fn new(key: Vec<u8>, base_nonce: Vec<u8>, chunk_size: u32, algorithm: ContainerAlgorithm) -> Result<Self, FurlError> {
+        let writer = ContainerWriter::new(&key, &base_nonce, self::chunk_size(chunk_size), algorithm.into())?;
+        Ok(ContainerEncryptor { writer: Mutex::new(writer) })
+    }
+
+    /// Add plaintext; returns container bytes that are ready to upload
+    pub fn update(&self, data: Vec<u8>) -> Result<Vec<u8>, FurlError> {
+        Ok(lock(&self.writer).update(&data)?)
+    }
+
+    /// Seal the final chunk and return the last container bytes
+    pub fn finish(&self) -> Result<Vec<u8>, FurlError> {
+        Ok(lock(&self.writer).finish()?)
+    }
+}
+
+/// Streaming container decryptor
+/// 
+/// Returns plaintext only for chunks that are complete and authenticated;
+/// `finish` fails if the final chunk never arrived.
+#[derive(uniffi::Object)]
+pub struct ContainerDecryptor {
+    reader: Mutex<ContainerReader>,
+}
+
+#[uniffi::export]
+impl ContainerDecryptor {
+    /// Decryptor for a 32-byte key
+    #[uniffi::constructor]
+    pub fn new(key: Vec<u8>) -> Result<Self, FurlError> {
+        Ok(ContainerDecryptor { reader: Mutex::new(ContainerReader::new(&key)?) })
+    }
+
+    /// Add container bytes; returns newly authenticated plaintext
+    pub fn update(&self, data: Vec<u8>) -> Result<Vec<u8>, FurlError> {
+        Ok(lock(&self.reader).update(&data)?)
+    }
+
+    /// Finish the stream, failing if it was truncated
+    pub fn finish(&self) -> Result<(), FurlError> {
+        Ok(lock(&self.reader).finish()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK: &str = "https://furl.example/furl.html?atSign=@alice#v=1&key=AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8&iv=-_-_-_-_-_-_-_-_-_-_-w&name=a%20b.txt&thumb=1";
+
+    #[test]
+    fn links_parse_from_a_full_url() {
+        let link = parse_share_link(LINK.into()).unwrap();
+        assert_eq!(link.key, (0..32).collect::<Vec<u8>>());
+        assert_eq!(link.name.as_deref(), Some("a b.txt"));
+        assert_eq!(link.extra.get("thumb").map(String::as_str), Some("1"));
+
+        assert!(matches!(parse_share_link("#v=2&key=AAAA".into()), Err(FurlError::Unsupported(_))));
+        assert!(matches!(parse_share_link("https://furl.example/".into()), Err(FurlError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn ranged_and_sequential_decryption_agree() {
+        let link = parse_share_link(LINK.into()).unwrap();
+        let mut expected = vec![0u8; 1000];
+        AesCtr::new(&link.key, &link.iv).unwrap().apply_keystream(&mut expected).unwrap();
+
+        let decryptor = ShareDecryptor::from_link(LINK.into()).unwrap();
+        let mut sequential = decryptor.decrypt(vec![0; 333]).unwrap();
+        sequential.extend(decryptor.decrypt(vec![0; 667]).unwrap());
+        assert_eq!(sequential, expected);
+
+        assert_eq!(decryptor.decrypt_at(517, vec![0; 100]).unwrap(), expected[517..617]);
+        assert_eq!(decryptor.position(), 617);
+        assert_eq!(decryptor.decrypt(vec![0; 50]).unwrap(), expected[617..667]);
+        assert!(matches!(decryptor.decrypt_at(u64::MAX, vec![0; 2]), Err(FurlError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn containers_round_trip_and_report_kinds() {
+        let (key, nonce) = (vec![0x24; 32], vec![0x99; 12]);
+        let data: Vec<u8> = (0..500u32).map(|i| i as u8).collect();
+        let sealed = encrypt_container(key.clone(), nonce.clone(), data.clone(), 64, ContainerAlgorithm::ChaCha20Poly1305).unwrap();
+
+        let encryptor = ContainerEncryptor::new(key.clone(), nonce, 64, ContainerAlgorithm::ChaCha20Poly1305).unwrap();
+        let mut streamed = encryptor.update(data.clone()).unwrap();
+        streamed.extend(encryptor.finish().unwrap());
+        assert_eq!(streamed, sealed);
+
+        let decryptor = ContainerDecryptor::new(key.clone()).unwrap();
+        assert_eq!(decryptor.update(sealed[..200].to_vec()).unwrap(), data[..128]);
+        assert!(matches!(decryptor.finish(), Err(FurlError::Truncated(_))));
+        assert_eq!(decrypt_container(key.clone(), sealed.clone()).unwrap(), data);
+
+        let mut tampered = sealed;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(decrypt_container(key, tampered), Err(FurlError::AuthenticationFailed(_))));
+        assert!(matches!(ContainerDecryptor::new(vec![0; 16]), Err(FurlError::InvalidKeyLength(_))));
+    }
+}
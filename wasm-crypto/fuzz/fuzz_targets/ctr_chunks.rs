@@ -0,0 +1,31 @@
+//! Chunked and seeked AES-CTR must agree for any key, IV, chunk size and offset
+//! 
+//! Run with `cargo +nightly fuzz run ctr_chunks` from `wasm-crypto/`.
+//! 
+//! Input layout: key size selector (1) | key (16, 24 or 32) | IV (16) |
+//! chunk size (u16 LE) | start offset (u64 LE) | data
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use furl_crypto::fuzzing::check_ctr_chunks;
+
+fuzz_target!(|input: &[u8]| {
+    let Some((&selector, rest)) = input.split_first() else {
+        return;
+    };
+    let key_len = [16, 24, 32][selector as usize % 3];
+    if rest.len() < key_len + 16 + 2 + 8 {
+        return;
+    }
+
+    let (key, rest) = rest.split_at(key_len);
+    let (iv, rest) = rest.split_at(16);
+    let (chunk_size, rest) = rest.split_at(2);
+    let (start_offset, data) = rest.split_at(8);
+
+    let chunk_size = u16::from_le_bytes([chunk_size[0], chunk_size[1]]) as usize;
+    let start_offset = u64::from_le_bytes(start_offset.try_into().unwrap());
+
+    check_ctr_chunks(key, iv, data, chunk_size, start_offset);
+});
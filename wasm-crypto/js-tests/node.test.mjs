@@ -0,0 +1,482 @@
+// Node.js integration tests for the nodejs build
+// Build first with `./build.sh node`, then run `node --test js-tests/`.
+
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { createRequire } from 'node:module';
+import { readFileSync } from 'node:fs';
+import { createCipheriv, createHash, generateKeyPairSync, randomBytes, sign } from 'node:crypto';
+
+import { decryptAesCtrPartitioned, planPartitions } from '../../web/wasm-crypto-pool.js';
+
+const require = createRequire(import.meta.url);
+const furl = require('../pkg-node/furl_crypto.js');
+
+const key = randomBytes(32);
+const iv = randomBytes(16);
+
+test('Buffers are accepted wherever a Uint8Array is', () => {
+    const plaintext = Buffer.from('furl on node, '.repeat(1000));
+    const ciphertext = furl.encrypt_aes_ctr(key, iv, plaintext);
+    assert.equal(ciphertext.length, plaintext.length);
+    assert.deepEqual(Buffer.from(furl.decrypt_aes_ctr(key, iv, ciphertext)), plaintext);
+
+    // Small Buffers share Node's pool, so they are views at a non-zero offset
+    const pooled = Buffer.from('pooled buffer with an offset');
+    assert.notEqual(pooled.byteOffset, 0);
+    const sealed = furl.encrypt_aes_ctr(key, iv, pooled);
+    assert.deepEqual(Buffer.from(furl.decrypt_aes_ctr_buffer(key, iv, Buffer.from(sealed))), pooled);
+});
+
+test('output matches Node crypto', () => {
+    const plaintext = randomBytes(100_000);
+    const cipher = require('node:crypto').createCipheriv('aes-256-ctr', key, iv);
+    const expected = Buffer.concat([cipher.update(plaintext), cipher.final()]);
+    assert.deepEqual(Buffer.from(furl.encrypt_aes_ctr(key, iv, plaintext)), expected);
+});
+
+test('errors carry their kind and code', () => {
+    assert.throws(() => furl.decrypt_aes_ctr(Buffer.alloc(5), iv, Buffer.alloc(1)), (error) => {
+        return error.name === 'InvalidKeyLength' && error.code === furl.CryptoErrorCode.InvalidKeyLength;
+    });
+});
+
+test('chunk sizes of 0 and u32::MAX are handled rather than aborting', () => {
+    const data = randomBytes(1000);
+    const chunkCallback = () => {};
+    const expected = furl.decrypt_aes_ctr(key, iv, data);
+    assert.throws(() => furl.decrypt_aes_ctr_chunked(key, iv, data, 0), { name: 'InvalidInput' });
+    assert.throws(() => furl.encrypt_aes_ctr_chunked(key, iv, data, 0), { name: 'InvalidInput' });
+    assert.throws(() => furl.decrypt_aes_ctr_parallel(key, iv, data, 0), { name: 'InvalidInput' });
+    assert.throws(() => furl.decrypt_aes_ctr_chunks(key, iv, data, 0n, 0, chunkCallback), { name: 'InvalidInput' });
+    assert.throws(() => furl.decrypt_chacha20_chunked(key, iv.subarray(0, 12), data, 0), { name: 'InvalidInput' });
+    assert.throws(() => furl.decrypt_chacha20poly1305_chunked(key, iv.subarray(0, 12), data, 0), { name: 'InvalidInput' });
+    assert.deepEqual(furl.decrypt_aes_ctr_chunked(key, iv, data, 1), expected);
+
+    const max = 0xffffffff;
+    assert.deepEqual(furl.decrypt_aes_ctr_chunked(key, iv, data, max), expected);
+    assert.deepEqual(furl.encrypt_aes_ctr_chunked(key, iv, data, max), expected);
+    assert.deepEqual(furl.decrypt_aes_ctr_parallel(key, iv, data, max), expected);
+    assert.equal(furl.decrypt_aes_ctr_chunks(key, iv, data, 0n, max, chunkCallback), 1000n);
+    assert.equal(furl.decrypt_chacha20_chunked(key, iv.subarray(0, 12), data, max).length, 1000);
+    assert.throws(() => furl.decrypt_chacha20poly1305_chunked(key, iv.subarray(0, 12), data, max), { name: 'InvalidInput' });
+});
+
+test('log messages can be routed to a handler instead of the console', () => {
+    const messages = [];
+    furl.set_log_handler((level, message) => messages.push([level, message]));
+    furl.set_log_level(furl.LogLevel.Debug);
+    try {
+        furl.decrypt_aes_ctr(key, iv, Buffer.alloc(16));
+    } finally {
+        furl.set_log_level(furl.LogLevel.Warn);
+        furl.set_log_handler(null);
+    }
+
+    assert.ok(messages.length > 0);
+    assert.ok(messages.every(([level]) => level === furl.LogLevel.Debug));
+    assert.match(messages[0][1], /16 bytes/);
+});
+
+test('Blob-based shares round trip', async () => {
+    const contents = randomBytes(300_000);
+    const share = await furl.prepare_share(new Blob([contents]), { name: 'data.bin', chunk_size: 65536 });
+    assert.equal(share.parts.length, 5);
+
+    // The fragment's base64 is URL-safe, so URLSearchParams reads it unchanged
+    const shareKey = furl.key_from_base64(new URLSearchParams(share.fragment).get('key'));
+    const manifest = new furl.Manifest(Buffer.from(share.manifest).toString());
+    const plaintext = manifest.decrypt_entry(shareKey, 'data.bin', Buffer.concat(share.parts));
+    assert.deepEqual(Buffer.from(plaintext), contents);
+    assert.equal(JSON.parse(Buffer.from(share.manifest)).files[0].sha256, createHash('sha256').update(contents).digest('hex'));
+});
+
+test('a manifest opened with the uploader key decrypts nothing until verified', async () => {
+    const contents = randomBytes(1000);
+    const share = await furl.prepare_share(new Blob([contents]), { name: 'data.bin' });
+    const shareKey = furl.key_from_base64(new URLSearchParams(share.fragment).get('key'));
+    const { publicKey, privateKey } = generateKeyPairSync('ed25519');
+    const uploaderKey = Buffer.from(publicKey.export({ format: 'jwk' }).x, 'base64url');
+    const signature = sign(null, share.manifest, privateKey);
+
+    const manifest = new furl.Manifest(Buffer.from(share.manifest).toString(), uploaderKey);
+    assert.throws(() => manifest.decrypt_entry(shareKey, 'data.bin', Buffer.concat(share.parts)), { name: 'AuthenticationFailed' });
+    assert.throws(() => manifest.verify(randomBytes(32), signature), { name: 'AuthenticationFailed' });
+    manifest.verify(uploaderKey, signature);
+    assert.deepEqual(Buffer.from(manifest.decrypt_entry(shareKey, 'data.bin', Buffer.concat(share.parts))), contents);
+    assert.throws(() => new furl.Manifest(Buffer.from(share.manifest).toString(), uploaderKey.subarray(1)), { name: 'InvalidKeyLength' });
+});
+
+test('containers match the vectors shared with furl-core and furl-cli', () => {
+    const { vectors } = JSON.parse(readFileSync(new URL('../test-vectors/container.json', import.meta.url)));
+    for (const vector of vectors) {
+        const [key, nonce, plaintext] = [vector.key, vector.base_nonce, vector.plaintext].map((hex) => Buffer.from(hex, 'hex'));
+        const container = furl.encrypt_container(key, nonce, plaintext, vector.chunk_size, vector.algorithm);
+        assert.equal(Buffer.from(container).toString('hex'), vector.container, vector.description);
+        assert.deepEqual(Buffer.from(furl.decrypt_container(key, Buffer.from(vector.container, 'hex'))), plaintext);
+    }
+});
+
+test('benchmark reports throughput for each cipher', () => {
+    for (const cipher of ['aes-256-ctr', 'aes-256-gcm', 'chacha20-poly1305', 'blake3']) {
+        const report = furl.benchmark(cipher, 1 << 20, 3);
+        assert.equal(report.cipher, cipher);
+        assert.equal(report.iterations, 3);
+        assert.ok(report.min_ms <= report.mean_ms && report.total_ms > 0);
+        assert.ok(report.bytes_per_second > 0);
+    }
+    assert.throws(() => furl.benchmark('rot13', 16, 1), { name: 'Unsupported' });
+    assert.throws(() => furl.benchmark('aes-256-ctr', 0, 1), { name: 'InvalidInput' });
+});
+
+test('decrypt metrics count bytes, chunks and time', () => {
+    furl.reset_metrics();
+    const data = randomBytes(300_000);
+    furl.decrypt_aes_ctr(key, iv, data);
+    furl.decrypt_aes_ctr_chunked(key, iv, data, 100_000);
+
+    const metrics = furl.get_metrics();
+    assert.equal(metrics.operations, 2);
+    assert.equal(metrics.bytes_processed, 600_000n);
+    assert.equal(metrics.chunks_processed, 4);
+    assert.ok(metrics.cipher_ms > 0 && metrics.copy_ms > 0);
+    assert.ok(metrics.cipher_bytes_per_second > 0);
+
+    furl.reset_metrics();
+    assert.equal(furl.get_metrics().bytes_processed, 0n);
+    assert.equal(furl.get_metrics().cipher_bytes_per_second, null);
+});
+
+test('a memory budget flushes plaintext to on_flush', () => {
+    const data = randomBytes(250_000);
+    const expected = furl.decrypt_aes_ctr(key, iv, data);
+    const parts = [];
+    const on_flush = (plaintext, offset) => parts.push([Buffer.from(plaintext), offset]);
+
+    const tail = furl.decrypt_aes_ctr_chunked(key, iv, data, 40_000, null, { memory_budget: 100_000, on_flush });
+    assert.deepEqual(parts.map(([part, offset]) => [part.length, offset]), [[80_000, 0n], [80_000, 80_000n]]);
+    assert.deepEqual(Buffer.concat([...parts.map(([part]) => part), tail]), Buffer.from(expected));
+
+    assert.throws(() => furl.decrypt_aes_ctr_chunked(key, iv, data, 40_000, null, { memory_budget: 100_000 }), { name: 'OutOfMemory' });
+    assert.throws(() => furl.decrypt_aes_ctr_chunked(key, iv, data, 40_000, null, { on_flush }), { name: 'InvalidInput' });
+});
+
+test('a CipherContext matches the one-shot calls', () => {
+    const context = new furl.CipherContext(key);
+    assert.equal(context.key_length, 32);
+    for (let i = 0; i < 3; i++) {
+        const chunkIv = randomBytes(16);
+        const data = randomBytes(1000);
+        assert.deepEqual(context.decrypt_aes_ctr(chunkIv, data), furl.decrypt_aes_ctr(key, chunkIv, data));
+        assert.deepEqual(context.decrypt_aes_ctr_range(chunkIv, data.subarray(100), 100n), furl.decrypt_aes_ctr(key, chunkIv, data).subarray(100));
+    }
+
+    const nonce = randomBytes(12);
+    const gcm = createCipheriv('aes-256-gcm', key, nonce);
+    const sealed = Buffer.concat([gcm.update('thumbnail'), gcm.final(), gcm.getAuthTag()]);
+    assert.equal(Buffer.from(context.decrypt_aes_gcm(nonce, sealed)).toString(), 'thumbnail');
+    sealed[0] ^= 1;
+    assert.throws(() => context.decrypt_aes_gcm(nonce, sealed), { name: 'AuthenticationFailed' });
+    context.free();
+
+    const short = new furl.CipherContext(key.subarray(0, 16));
+    assert.throws(() => short.decrypt_aes_gcm(nonce, sealed), { name: 'InvalidKeyLength' });
+    short.free();
+});
+
+test('decrypt_batch decrypts every item in order', () => {
+    const items = [10, 0, 500].map((size) => ({ iv: randomBytes(16), data: randomBytes(size) }));
+    const plaintexts = furl.decrypt_batch(key, items);
+    assert.equal(plaintexts.length, 3);
+    items.forEach(({ iv, data }, i) => assert.deepEqual(plaintexts[i], furl.decrypt_aes_ctr(key, iv, data)));
+
+    const context = new furl.CipherContext(key);
+    assert.deepEqual(context.decrypt_batch(items), plaintexts);
+    context.free();
+
+    assert.deepEqual(furl.decrypt_batch(key, []), []);
+    assert.throws(() => furl.decrypt_batch(key, [items[0], { iv: items[1].iv }]), { name: 'InvalidInput', message: /batch item 1/ });
+});
+
+test('partitioned AES-CTR decryption reassembles out-of-order results', async () => {
+    assert.deepEqual(planPartitions(100, 4, 16), [{ start: 0, end: 16 }, { start: 16, end: 48 }, { start: 48, end: 80 }, { start: 80, end: 100 }]);
+    assert.equal(planPartitions(1000, 8).length, 1);
+
+    const data = randomBytes(100_003);
+    // Stands in for a worker pool: later partitions finish first
+    let delay = 40;
+    const run = (op, args) => new Promise((resolve) => setTimeout(() => resolve(furl[op](...args)), (delay -= 10)));
+    const progress = [];
+    const plaintext = await decryptAesCtrPartitioned(run, key, iv, data, {
+        partitions: 4,
+        minPartitionSize: 16_384,
+        onProgress: ({ bytes_done }) => progress.push(bytes_done),
+    });
+    assert.deepEqual(plaintext, furl.decrypt_aes_ctr(key, iv, data));
+    assert.equal(progress.length, 4);
+    assert.equal(progress.at(-1), data.length);
+});
+
+test('SharedArrayBuffer views are read and written in place', async () => {
+    assert.equal(furl.get_capabilities().shared_array_buffer, true);
+    const data = randomBytes(200_000);
+    const expected = furl.decrypt_aes_ctr(key, iv, data);
+
+    const shared = new Uint8Array(new SharedArrayBuffer(data.length));
+    shared.set(data);
+    assert.deepEqual(furl.decrypt_aes_ctr(key, iv, shared), expected);
+    assert.deepEqual(furl.decrypt_aes_ctr_buffer(key, iv, shared.buffer), expected);
+
+    const output = new Uint8Array(new SharedArrayBuffer(data.length));
+    assert.equal(furl.decrypt_aes_ctr_range_into(key, iv, shared.subarray(50_000), output.subarray(50_000), 50_000n), 150_000);
+    assert.deepEqual(output.subarray(50_000), expected.subarray(50_000));
+
+    const run = async (op, args) => furl[op](...args);
+    const plaintext = await decryptAesCtrPartitioned(run, key, iv, shared, { minPartitionSize: 16_384 });
+    assert.ok(plaintext.buffer instanceof SharedArrayBuffer);
+    assert.deepEqual(plaintext, expected);
+});
+
+test('view results alias the staging buffer until the next call', () => {
+    const first = randomBytes(4096);
+    const second = randomBytes(4096);
+    const expected = furl.decrypt_aes_ctr(key, iv, first);
+    const expectedTail = furl.decrypt_aes_ctr(key, iv, second).subarray(16);
+
+    const view = furl.decrypt_aes_ctr_view(key, iv, first);
+    assert.deepEqual(view, expected);
+    const kept = view.slice();
+
+    // Compared before any other call, which would overwrite it
+    assert.deepEqual(furl.decrypt_aes_ctr_range_view(key, iv, second.subarray(16), 16n), expectedTail);
+    assert.notDeepEqual(view.subarray(0, 16), expected.subarray(0, 16));
+    assert.deepEqual(kept, expected);
+
+    furl.release_views();
+    assert.deepEqual(furl.decrypt_aes_ctr(key, iv, first), expected);
+});
+
+test('copying calls zero the staging buffer before returning', () => {
+    const data = randomBytes(4096);
+    const view = furl.decrypt_aes_ctr_view(key, iv, data);
+    const expected = view.slice();
+
+    assert.deepEqual(furl.decrypt_aes_ctr(key, iv, data), expected);
+    assert.equal(view.length, data.length);
+    assert.ok(view.every((byte) => byte === 0));
+    furl.release_views();
+});
+
+test('init reserves memory up front and fails gracefully', () => {
+    const { memory_bytes } = furl.init({ working_set: 32 << 20 });
+    assert.ok(memory_bytes >= 32 << 20);
+    furl.decrypt_aes_ctr_chunked(key, iv, new Uint8Array(12 << 20));
+    assert.equal(furl.init().memory_bytes, memory_bytes);
+
+    assert.throws(() => furl.init({ working_set: 4e9 }), { name: 'OutOfMemory' });
+    assert.throws(() => furl.init({ working_set: -1 }), { name: 'InvalidInput' });
+    assert.equal(furl.decrypt_aes_ctr(key, iv, new Uint8Array(16)).length, 16);
+});
+
+test('AES-GCM-SIV round trips and rejects tampering', () => {
+    const nonce = randomBytes(12);
+    for (const size of [16, 32]) {
+        const sealed = furl.encrypt_aes_gcm_siv(key.subarray(0, size), nonce, Buffer.from('chunk 7'));
+        assert.equal(sealed.length, 7 + 16);
+        assert.equal(Buffer.from(furl.decrypt_aes_gcm_siv(key.subarray(0, size), nonce, sealed)).toString(), 'chunk 7');
+        sealed[3] ^= 1;
+        assert.throws(() => furl.decrypt_aes_gcm_siv(key.subarray(0, size), nonce, sealed), { name: 'AuthenticationFailed' });
+    }
+});
+
+test('AES-SIV is deterministic and binds associated data', () => {
+    const sivKey = randomBytes(64);
+    const fileId = Buffer.from('file-42');
+    const first = furl.encrypt_aes_siv(sivKey, Buffer.from('report.pdf'), fileId);
+    assert.deepEqual(furl.encrypt_aes_siv(sivKey, Buffer.from('report.pdf'), fileId), first);
+    assert.notDeepEqual(furl.encrypt_aes_siv(sivKey, Buffer.from('report.pdf')), first);
+
+    assert.equal(Buffer.from(furl.decrypt_aes_siv(sivKey, first, fileId)).toString(), 'report.pdf');
+    assert.throws(() => furl.decrypt_aes_siv(sivKey, first, Buffer.from('file-43')), { name: 'AuthenticationFailed' });
+    assert.throws(() => furl.encrypt_aes_siv(key.subarray(0, 16), fileId), { name: 'InvalidKeyLength' });
+});
+
+test('XTS-AES-256 decrypts the IEEE 1619 vectors', () => {
+    const vectors = JSON.parse(readFileSync(new URL('../test-vectors/xts-aes-256.json', import.meta.url)));
+    const xtsKey = Buffer.from(vectors.key, 'hex');
+    for (const vector of vectors.vectors) {
+        const plain = furl.decrypt_xts_aes_256(xtsKey, Buffer.from(vector.ciphertext, 'hex'), BigInt('0x' + vector.sector_number), vector.sector_size);
+        assert.equal(Buffer.from(plain).toString('hex'), vector.plaintext, vector.description);
+    }
+    assert.throws(() => furl.decrypt_xts_aes_256(xtsKey, new Uint8Array(520), 0n), { name: 'InvalidInput' });
+    assert.throws(() => furl.decrypt_xts_aes_256(key, new Uint8Array(512), 0n), { name: 'InvalidKeyLength' });
+});
+
+test('AEAD calls bind associated data into the tag', () => {
+    const nonce = randomBytes(12);
+    const plaintext = Buffer.from('chunk 3 of file 42, '.repeat(100));
+    const aad = Buffer.from('manifest:file-42:3');
+    const seal = (algorithm) => {
+        const cipher = createCipheriv(algorithm, key, nonce, { authTagLength: 16 });
+        cipher.setAAD(aad);
+        return Buffer.concat([cipher.update(plaintext), cipher.final(), cipher.getAuthTag()]);
+    };
+
+    const gcm = seal('aes-256-gcm');
+    assert.deepEqual(Buffer.from(furl.decrypt_aes_gcm(key, nonce, gcm, aad)), plaintext);
+    assert.deepEqual(Buffer.from(new furl.CipherContext(key).decrypt_aes_gcm(nonce, gcm, aad)), plaintext);
+    assert.throws(() => furl.decrypt_aes_gcm(key, nonce, gcm, Buffer.from('manifest:file-43:3')), { name: 'AuthenticationFailed' });
+    assert.throws(() => furl.decrypt_aes_gcm(key, nonce, gcm), { name: 'AuthenticationFailed' });
+
+    const chacha = seal('chacha20-poly1305');
+    assert.deepEqual(Buffer.from(furl.decrypt_chacha20poly1305(key, nonce, chacha, aad)), plaintext);
+    assert.deepEqual(Buffer.from(furl.decrypt_chacha20poly1305_chunked(key, nonce, chacha, 100, undefined, aad)), plaintext);
+    assert.throws(() => furl.decrypt_chacha20poly1305_chunked(key, nonce, chacha, 100), { name: 'AuthenticationFailed' });
+
+    const xnonce = randomBytes(24);
+    const sealed = furl.encrypt_xchacha20poly1305(key, xnonce, plaintext, aad);
+    assert.deepEqual(Buffer.from(furl.decrypt_xchacha20poly1305(key, xnonce, sealed, aad)), plaintext);
+    assert.throws(() => furl.decrypt_xchacha20poly1305(key, xnonce, sealed), { name: 'AuthenticationFailed' });
+
+    const siv = furl.encrypt_aes_gcm_siv(key, nonce, plaintext, aad);
+    assert.deepEqual(Buffer.from(furl.decrypt_aes_gcm_siv(key, nonce, siv, aad)), plaintext);
+    assert.throws(() => furl.decrypt_aes_gcm_siv(key, nonce, siv, new Uint8Array(0)), { name: 'AuthenticationFailed' });
+});
+
+test('STREAM segments must arrive in order and end with the last one', () => {
+    const prefix = randomBytes(7);
+    const encryptor = new furl.AeadStreamEncryptor(key, prefix, 'chacha20-poly1305', 2);
+    const segments = ['one', 'two', 'three', 'four', 'five'].map((word) => Buffer.from(word));
+    const sealed = segments.slice(0, -1).map((segment) => encryptor.encrypt_next(segment));
+    sealed.push(encryptor.encrypt_last(segments.at(-1)));
+    assert.throws(() => encryptor.encrypt_next(Buffer.from('six')), { name: 'InvalidState' });
+
+    const open = () => new furl.AeadStreamDecryptor(key, prefix, 'chacha20-poly1305', 2);
+    const decryptor = open();
+    sealed.slice(0, -1).forEach((segment, i) => assert.deepEqual(Buffer.from(decryptor.decrypt_next(segment)), segments[i]));
+    assert.deepEqual(Buffer.from(decryptor.decrypt_last(sealed.at(-1))), segments.at(-1));
+    decryptor.finalize();
+
+    const reordered = open();
+    assert.throws(() => reordered.decrypt_next(sealed[1]), { name: 'AuthenticationFailed' });
+
+    const truncated = open();
+    truncated.decrypt_next(sealed[0]);
+    assert.throws(() => truncated.decrypt_last(sealed[1]), { name: 'AuthenticationFailed' });
+    assert.throws(() => truncated.finalize(), { name: 'Truncated' });
+});
+
+test('Committing AEAD opens only under the key it was sealed with', () => {
+    const nonce = randomBytes(12);
+    for (const algorithm of ['aes-256-gcm', 'chacha20-poly1305']) {
+        const sealed = furl.encrypt_committing(key, nonce, Buffer.from('shared body'), algorithm, Buffer.from('share-7'));
+        assert.equal(sealed.length, 32 + 11 + 16);
+        assert.equal(Buffer.from(furl.decrypt_committing(key, nonce, sealed, algorithm, Buffer.from('share-7'))).toString(), 'shared body');
+        assert.throws(() => furl.decrypt_committing(randomBytes(32), nonce, sealed, algorithm, Buffer.from('share-7')), { name: 'AuthenticationFailed' });
+        assert.throws(() => furl.decrypt_committing(key, nonce, sealed, algorithm), { name: 'AuthenticationFailed' });
+    }
+    assert.throws(() => furl.encrypt_committing(key, nonce, new Uint8Array(1), 'aes-128-gcm'), { name: 'Unsupported' });
+});
+
+test('GMAC and Poly1305 tags match the AEADs they come from', () => {
+    const nonce = randomBytes(12);
+    const data = randomBytes(1000);
+    const cipher = createCipheriv('aes-256-gcm', key, nonce);
+    cipher.setAAD(data);
+    cipher.final();
+    const gcmTag = cipher.getAuthTag();
+    assert.deepEqual(Buffer.from(furl.gmac(key, nonce, data)), gcmTag);
+
+    const stream = new furl.GmacStream(key, nonce);
+    for (let i = 0; i < data.length; i += 77) stream.update(data.subarray(i, i + 77));
+    assert.equal(stream.verify(gcmTag), true);
+    assert.throws(() => stream.update(data), { name: 'InvalidState' });
+
+    const oneTimeKey = randomBytes(32);
+    const tag = furl.poly1305(oneTimeKey, data);
+    assert.equal(furl.poly1305_verify(oneTimeKey, data, tag), true);
+    tag[0] ^= 1;
+    assert.equal(furl.poly1305_verify(oneTimeKey, data, tag), false);
+    assert.throws(() => furl.gmac_verify(key, nonce, data, tag.subarray(0, 12)), { name: 'InvalidInput' });
+});
+
+test('CRC32 and xxHash64 give the reference values in one call or in chunks', () => {
+    const check = Buffer.from('123456789');
+    assert.equal(furl.crc32(check), 0xcbf43926);
+    assert.equal(furl.xxhash64(check), 0x8cb841db40e6ae83n);
+    assert.equal(furl.xxhash64(Buffer.alloc(0)), 0xef46db3751d8e999n);
+
+    const data = randomBytes(1000);
+    const crc = new furl.Crc32();
+    const xxh = new furl.XxHash64(7n);
+    for (let i = 0; i < data.length; i += 33) {
+        crc.update(data.subarray(i, i + 33));
+        xxh.update(data.subarray(i, i + 33));
+    }
+    assert.equal(crc.value(), furl.crc32(data));
+    assert.equal(xxh.value(), furl.xxhash64(data, 7n));
+});
+
+test('verify_chunk catches a corrupted part before decryption', async () => {
+    const contents = randomBytes(10_000);
+    const share = await furl.prepare_share(new Blob([contents]), { name: 'data.bin', chunk_size: 4096 });
+    const manifest = new furl.Manifest(Buffer.from(share.manifest).toString());
+    assert.equal(manifest.chunk_count, 3);
+    assert.equal(manifest.chunk_range_header(2), 'bytes=8192-9999');
+    share.parts.forEach((part, index) => assert.equal(furl.verify_chunk(manifest, index, part), true));
+
+    const corrupted = Uint8Array.from(share.parts[1]);
+    corrupted[100] ^= 1;
+    assert.equal(furl.verify_chunk(manifest, 1, corrupted), false);
+    assert.equal(furl.verify_chunk(manifest, 2, share.parts[1]), false);
+    assert.throws(() => furl.verify_chunk(manifest, 3, share.parts[0]), { name: 'NotFound' });
+});
+
+test('length trailer turns a cut-short CTR stream into Truncated', () => {
+    const key = randomBytes(32);
+    const iv = randomBytes(16);
+    const contents = randomBytes(5000);
+    const ciphertext = furl.encrypt_aes_ctr(key, iv, contents);
+    const stream = Buffer.concat([ciphertext, furl.ctr_length_trailer(key, iv, BigInt(ciphertext.length))]);
+
+    const decrypt = (bytes) => {
+        const decryptor = new furl.Decryptor(key, iv, { length_trailer: true });
+        const parts = [];
+        for (let offset = 0; offset < bytes.length; offset += 1000) parts.push(decryptor.update(bytes.subarray(offset, offset + 1000)));
+        decryptor.finalize();
+        return Buffer.concat(parts);
+    };
+    assert.deepEqual(decrypt(stream), contents);
+    assert.throws(() => decrypt(stream.subarray(0, 4500)), { name: 'Truncated' });
+    assert.throws(() => furl.ctr_length_trailer(key.subarray(0, 5), iv, 0n), { name: 'InvalidKeyLength' });
+});
+
+test('split_key parts rebuild the key from any two of three', () => {
+    const key = randomBytes(32);
+    const parts = furl.split_key(key, 2, 3);
+    assert.equal(parts.length, 3);
+    assert.ok(parts.every(part => /^[A-Za-z0-9_-]+$/.test(part)));
+    for (const pair of [[0, 1], [2, 0], [1, 2]]) {
+        assert.deepEqual(Buffer.from(furl.combine_key(pair.map(i => parts[i]))), key);
+    }
+    assert.throws(() => furl.combine_key([parts[0]]), { name: 'InvalidInput' });
+    assert.throws(() => furl.combine_key([parts[0], furl.split_key(randomBytes(32), 2, 3)[1]]), { name: 'InvalidInput' });
+    assert.throws(() => furl.combine_key([parts[0], furl.split_key(key, 2, 3)[1]]), { name: 'InvalidInput' });
+    const corrupted = Buffer.from(parts[1], 'base64url');
+    corrupted[corrupted.length - 1] ^= 1;
+    assert.throws(() => furl.combine_key([parts[0], corrupted.toString('base64url')]), { name: 'AuthenticationFailed' });
+    assert.throws(() => furl.split_key(key, 4, 3), { name: 'InvalidInput' });
+});
+
+test('keys round trip through BIP39 words', () => {
+    const key = Buffer.from('f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f', 'hex');
+    const words = furl.key_to_mnemonic(key);
+    assert.equal(words, 'void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold');
+    assert.deepEqual(Buffer.from(furl.mnemonic_to_key(words.toUpperCase())), key);
+    assert.equal(furl.key_to_mnemonic(randomBytes(16)).split(' ').length, 12);
+
+    assert.throws(() => furl.mnemonic_to_key(words.replace('camp', 'cmap')), { name: 'InvalidInput', message: /word 5/ });
+    assert.throws(() => furl.mnemonic_to_key(words.replace('void come', 'come void')), { name: 'InvalidFormat' });
+    assert.throws(() => furl.mnemonic_to_key('abandon about'), { name: 'InvalidInput' });
+    assert.throws(() => furl.key_to_mnemonic(randomBytes(20)), { name: 'InvalidKeyLength' });
+});
@@ -0,0 +1,78 @@
+// Cross-runtime smoke test for the ES-module build
+// Build with `./build.sh esm`, then run any of:
+//
+//   node js-tests/runtime-smoke.mjs
+//   deno run --allow-read js-tests/runtime-smoke.mjs
+//   bun js-tests/runtime-smoke.mjs
+//
+// Uses only APIs the three runtimes share, and exits non-zero on failure.
+
+import { loadFurl } from '../js/load.mjs';
+
+const runtime = globalThis.Deno ? 'deno' : globalThis.Bun ? 'bun' : 'node';
+const furl = await loadFurl(new URL('../pkg-esm/furl_crypto.js', import.meta.url));
+
+let failures = 0;
+
+async function check(name, body) {
+    try {
+        await body();
+        console.log(`ok - ${name}`);
+    } catch (error) {
+        failures += 1;
+        console.log(`not ok - ${name}: ${(error && error.stack) || error}`);
+    }
+}
+
+function assert(condition, message) {
+    if (!condition) {
+        throw new Error(message);
+    }
+}
+
+function equalBytes(a, b) {
+    return a.length === b.length && a.every((byte, i) => byte === b[i]);
+}
+
+const key = crypto.getRandomValues(new Uint8Array(32));
+const iv = crypto.getRandomValues(new Uint8Array(16));
+
+await check('module initialises', () => {
+    assert(typeof furl.get_version() === 'string', 'get_version did not return a string');
+    assert(furl.self_test().passed, 'self-test failed');
+});
+
+await check('AES-CTR round trip', () => {
+    const plaintext = new TextEncoder().encode(`hello from ${runtime}`.repeat(100));
+    const ciphertext = furl.encrypt_aes_ctr(key, iv, plaintext);
+    assert(equalBytes(furl.decrypt_aes_ctr(key, iv, ciphertext), plaintext), 'decryption did not round trip');
+});
+
+await check('random keys and metadata use globalThis.crypto', () => {
+    const blob = furl.encrypt_metadata(key, { name: 'edge.txt', size: 5 });
+    assert(furl.decrypt_metadata(key, blob).name === 'edge.txt', 'metadata did not round trip');
+});
+
+await check('Blob shares round trip', async () => {
+    const contents = crypto.getRandomValues(new Uint8Array(50_000));
+    const share = await furl.prepare_share(new Blob([contents]), { name: 'data.bin', chunk_size: 16384 });
+    const manifest = new furl.Manifest(new TextDecoder().decode(share.manifest));
+    const shareKey = furl.key_from_base64(new URLSearchParams(share.fragment).get('key'));
+    const ciphertext = new Uint8Array(await new Blob(share.parts).arrayBuffer());
+    assert(equalBytes(manifest.decrypt_entry(shareKey, 'data.bin', ciphertext), contents), 'share did not round trip');
+});
+
+await check('logging works without a window', () => {
+    const messages = [];
+    furl.set_log_handler((level, message) => messages.push(message));
+    furl.set_log_level(furl.LogLevel.Debug);
+    furl.decrypt_aes_ctr(key, iv, new Uint8Array(16));
+    furl.set_log_level(furl.LogLevel.Warn);
+    furl.set_log_handler(null);
+    assert(messages.length > 0, 'no log messages reached the handler');
+});
+
+console.log(`${runtime}: ${failures ? `${failures} failed` : 'all passed'}`);
+if (failures) {
+    globalThis.Deno ? globalThis.Deno.exit(1) : process.exit(1);
+}
@@ -0,0 +1,48 @@
+// ES-module loader for furl-crypto outside the browser
+// Initialises a `--target web` build (e.g. pkg-esm/ from `./build.sh esm`)
+// in Deno, Bun or Node without relying on `fetch` of file: URLs, which not
+// every runtime supports. The module only touches globals all three share
+// (`globalThis.crypto`, `console`, `setTimeout`, `fetch`, `Blob`), so edge
+// functions can reuse it unchanged.
+//
+//   import { loadFurl } from './js/load.mjs';
+//   const furl = await loadFurl(new URL('../pkg-esm/furl_crypto.js', import.meta.url));
+//   furl.decrypt_aes_ctr(key, iv, data);
+
+/**
+ * Read the bytes behind a URL with whatever the runtime provides
+ * @param {URL} url
+ * @returns {Promise<Uint8Array>}
+ */
+async function readBytes(url) {
+    if (url.protocol !== 'file:') {
+        const response = await fetch(url);
+        if (!response.ok) {
+            throw new Error(`Failed to fetch ${url}: ${response.status}`);
+        }
+        return new Uint8Array(await response.arrayBuffer());
+    }
+
+    if (globalThis.Deno) {
+        return globalThis.Deno.readFile(url);
+    }
+    if (globalThis.Bun) {
+        return new Uint8Array(await globalThis.Bun.file(url).arrayBuffer());
+    }
+    const { readFile } = await import('node:fs/promises');
+    return readFile(url);
+}
+
+/**
+ * Import and initialise a web-target build of furl-crypto
+ * @param {string | URL} moduleUrl - URL of the generated `furl_crypto.js`
+ * @returns {Promise<object>} The module's exports, ready to call
+ */
+export async function loadFurl(moduleUrl) {
+    const url = new URL(moduleUrl, import.meta.url);
+    const furl = await import(url.href);
+    const wasmUrl = new URL(url.pathname.replace(/\.js$/, '_bg.wasm'), url);
+
+    await furl.default({ module_or_path: await readBytes(wasmUrl) });
+    return furl;
+}
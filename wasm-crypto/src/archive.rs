@@ -0,0 +1,138 @@
+//! AES-encrypted ZIP archives (WinZip AE-1/AE-2) for multi-file shares
+//! 
+//! The archive is listed first, without a password, so the viewer can show
+//! its contents; entries are then decrypted and inflated one at a time.
+//! Legacy ZipCrypto entries are refused because that cipher is broken.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+use zip::result::ZipError;
+
+use crate::error::CryptoError;
+
+fn zip_error(error: ZipError) -> CryptoError {
+    match error {
+        ZipError::InvalidPassword => CryptoError::AuthenticationFailed("Incorrect password for ZIP entry".into()),
+        ZipError::FileNotFound => CryptoError::InvalidInput("ZIP entry not found".into()),
+        other => CryptoError::InvalidFormat(format!("Invalid ZIP archive: {}", other)),
+    }
+}
+
+/// Decrypt and inflate one entry, refusing ZipCrypto
+fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, index: usize, password: Option<String>) -> Result<Vec<u8>, CryptoError> {
+    let encrypted = archive.by_index_raw(index).map_err(zip_error)?.encrypted();
+    let aes = archive.get_aes_verification_key_and_salt(index).map_err(zip_error)?.is_some();
+
+    let mut file = match (encrypted, password) {
+        (false, _) => archive.by_index(index),
+        (true, _) if !aes => return Err(CryptoError::Unsupported("Unsupported ZIP encryption: legacy ZipCrypto entries are not accepted".into())),
+        (true, Some(password)) => archive.by_index_decrypt(index, password.as_bytes()),
+        (true, None) => return Err(CryptoError::InvalidInput("ZIP entry is encrypted: a password is required".into())),
+    }
+    .map_err(zip_error)?;
+
+    // Never inflate past the declared size, so a crafted entry can't exhaust memory
+    let size = file.size();
+    let mut contents = Vec::new();
+    usize::try_from(size)
+        .ok()
+        .and_then(|size| contents.try_reserve_exact(size).ok())
+        .ok_or_else(|| CryptoError::OutOfMemory(format!("ZIP entry is too large to extract: {} bytes", size)))?;
+    (&mut file)
+        .take(size + 1)
+        .read_to_end(&mut contents)
+        .map_err(|e| CryptoError::InvalidFormat(format!("ZIP entry is corrupted: {}", e)))?;
+    if contents.len() as u64 != size {
+        return Err(CryptoError::InvalidFormat("ZIP entry is corrupted: size does not match the directory".into()));
+    }
+
+    Ok(contents)
+}
+
+/// Reader over an in-memory ZIP archive
+#[wasm_bindgen]
+pub struct ZipReader {
+    archive: ZipArchive<Cursor<Vec<u8>>>,
+}
+
+#[wasm_bindgen]
+impl ZipReader {
+    /// Open an archive
+    /// 
+    /// # Arguments
+    /// * `data` - Whole ZIP file as Uint8Array
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &Uint8Array) -> Result<ZipReader, JsValue> {
+        let archive = ZipArchive::new(Cursor::new(data.to_vec())).map_err(zip_error)?;
+
+        log!("ZIP: Opened archive with {} entries", archive.len());
+
+        Ok(ZipReader { archive })
+    }
+
+    /// Number of entries in the archive
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> u32 {
+        self.archive.len() as u32
+    }
+
+    /// List the archive without decrypting anything
+    /// 
+    /// # Returns
+    /// Array of objects with `index`, `name`, `size`, `compressed_size`
+    /// (BigInt), `is_dir`, `encrypted` and `aes` (true for AE-1/AE-2 entries)
+    pub fn entries(&mut self) -> Result<js_sys::Array, JsValue> {
+        let entries = js_sys::Array::new();
+
+        for index in 0..self.archive.len() {
+            let aes = self.archive.get_aes_verification_key_and_salt(index).map_err(zip_error)?.is_some();
+            let file = self.archive.by_index_raw(index).map_err(zip_error)?;
+
+            let entry = js_sys::Object::new();
+            js_sys::Reflect::set(&entry, &"index".into(), &(index as u32).into())?;
+            js_sys::Reflect::set(&entry, &"name".into(), &file.name().into())?;
+            js_sys::Reflect::set(&entry, &"size".into(), &file.size().into())?;
+            js_sys::Reflect::set(&entry, &"compressed_size".into(), &file.compressed_size().into())?;
+            js_sys::Reflect::set(&entry, &"is_dir".into(), &file.is_dir().into())?;
+            js_sys::Reflect::set(&entry, &"encrypted".into(), &file.encrypted().into())?;
+            js_sys::Reflect::set(&entry, &"aes".into(), &aes.into())?;
+            entries.push(&entry);
+        }
+
+        Ok(entries)
+    }
+
+    /// Decrypt and decompress one entry
+    /// 
+    /// # Arguments
+    /// * `index` - Entry index from `entries()`
+    /// * `password` - Archive password (required for encrypted entries)
+    /// 
+    /// # Returns
+    /// Entry contents as Uint8Array, or an error if the password is wrong or
+    /// the entry fails its integrity check
+    pub fn extract(&mut self, index: u32, password: Option<String>) -> Result<Uint8Array, JsValue> {
+        let index = index as usize;
+        if index >= self.archive.len() {
+            return Err(CryptoError::InvalidInput(format!("Invalid entry index: archive has {} entries, got {}", self.archive.len(), index)).into());
+        }
+
+        let contents = read_entry(&mut self.archive, index, password)?;
+
+        log!("ZIP: Extracted entry {} ({} bytes)", index, contents.len());
+
+        Ok(Uint8Array::from(contents.as_slice()))
+    }
+
+    /// Decrypt and decompress the entry with the given name
+    pub fn extract_by_name(&mut self, name: &str, password: Option<String>) -> Result<Uint8Array, JsValue> {
+        let index = self
+            .archive
+            .index_for_name(name)
+            .ok_or_else(|| CryptoError::InvalidInput(format!("ZIP entry not found: {}", name)))?;
+
+        self.extract(index as u32, password)
+    }
+}
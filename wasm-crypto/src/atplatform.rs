@@ -0,0 +1,111 @@
+//! Compatibility with AES as used by the atPlatform Dart tooling
+//! 
+//! atPlatform encrypts with the Dart `encrypt` package's `AES(key)`, whose
+//! defaults are SIC mode (CTR with a 128-bit big-endian counter) *and* PKCS#7
+//! padding, so ciphertexts are always a whole number of blocks even though
+//! the mode is a stream cipher. Older clients and self-encryption keys used
+//! `IV.fromLength(16)`, which in `encrypt` before 5.0.2 was all zeros.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::error::CryptoError;
+use crate::AesCtr;
+
+const LEGACY_IV: [u8; 16] = [0u8; 16];
+
+/// SIC-mode decryption followed by strict PKCS#7 unpadding; `None` if the padding is invalid
+pub(crate) fn decrypt_sic_pkcs7(cipher: &mut AesCtr, ciphertext: &[u8]) -> Option<Vec<u8>> {
+    let mut plaintext = ciphertext.to_vec();
+    cipher.apply_keystream(&mut plaintext).ok()?;
+
+    let pad = *plaintext.last()? as usize;
+    if pad == 0 || pad > 16 || pad > plaintext.len() {
+        return None;
+    }
+    if !plaintext[plaintext.len() - pad..].iter().all(|&b| b as usize == pad) {
+        return None;
+    }
+
+    plaintext.truncate(plaintext.len() - pad);
+    Some(plaintext)
+}
+
+/// Decrypt data encrypted by atPlatform's Dart `encrypt`/`AES(key)` defaults
+/// 
+/// # Arguments
+/// * `key` - AES key as Uint8Array (16, 24 or 32 bytes; atPlatform keys are 32 bytes, Base64-encoded)
+/// * `encrypted_data` - Ciphertext as Uint8Array (a non-empty multiple of 16 bytes)
+/// * `iv` - 16-byte IV as Uint8Array; omit for the legacy zero-IV path
+///   (self-encryption keys and values from older clients)
+/// 
+/// # Returns
+/// Decrypted, unpadded data as Uint8Array. Malformed padding is reported as a
+/// JS `Error` whose `name` is `InvalidPadding`.
+#[wasm_bindgen]
+pub fn decrypt_atplatform_aes(
+    key: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    iv: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    let iv = match iv {
+        Some(iv) if iv.length() != 16 => {
+            return Err(CryptoError::InvalidIv(format!("Invalid IV size: expected 16 bytes, got {}", iv.length())).into());
+        }
+        Some(iv) => iv.to_vec(),
+        None => LEGACY_IV.to_vec(),
+    };
+
+    if encrypted_data.length() == 0 || !encrypted_data.length().is_multiple_of(16) {
+        return Err(CryptoError::InvalidInput(format!("Invalid data size: expected a non-empty multiple of 16 bytes, got {}", encrypted_data.length())).into());
+    }
+
+    let mut cipher = AesCtr::new(&key.to_vec(), &iv)?;
+    let plaintext = decrypt_sic_pkcs7(&mut cipher, &encrypted_data.to_vec()).ok_or_else(|| {
+        CryptoError::InvalidPadding("Invalid PKCS#7 padding: wrong key, wrong IV or corrupted ciphertext".into())
+    })?;
+
+    log!("atPlatform: Decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+
+    // Cross-language vectors: AES-256 key 00..1f, SIC mode with PKCS#7, as
+    // produced by Dart `Encrypter(AES(key)).encrypt(...)` (regenerated with
+    // Python `cryptography` AES-CTR over PKCS#7-padded input)
+    const KEY: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+    const LEGACY_CIPHERTEXT: &str = "mvVs2kVp/qTamv0E/VkY8pw5eKBEt5HrqPiVP0bMODM=";
+    const IV: &str = "/////////////////////g==";
+    const IV_CIPHERTEXT: &str = "U9SGMYErfurC1Ji8+qhCItmo1i54kkbta75wGT7rMojCoTKFHnyp55HK+wi+ShLm4E1mvlqpj/W25oshWNImLQ==";
+
+    fn b64(s: &str) -> Vec<u8> {
+        STANDARD.decode(s).unwrap()
+    }
+
+    fn decrypt(iv: &[u8], ciphertext: &str) -> Option<Vec<u8>> {
+        let mut cipher = AesCtr::new(&b64(KEY), iv).unwrap();
+        decrypt_sic_pkcs7(&mut cipher, &b64(ciphertext))
+    }
+
+    #[test]
+    fn decrypts_legacy_zero_iv_values() {
+        assert_eq!(decrypt(&LEGACY_IV, LEGACY_CIPHERTEXT).unwrap(), b"hello atsign world");
+    }
+
+    #[test]
+    fn decrypts_with_explicit_iv_across_counter_wrap() {
+        // Block-aligned input gets a full padding block; the IV's counter wraps past 2^128
+        assert_eq!(decrypt(&b64(IV), IV_CIPHERTEXT).unwrap(), b"0123456789abcdef".repeat(3));
+    }
+
+    #[test]
+    fn rejects_wrong_iv() {
+        assert_eq!(decrypt(&b64(IV), LEGACY_CIPHERTEXT), None);
+    }
+}
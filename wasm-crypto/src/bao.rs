@@ -0,0 +1,402 @@
+//! Bao-style verified streaming
+//! 
+//! A furl link can carry the BLAKE3 hash of the plaintext. Together with a
+//! Bao outboard tree (the BLAKE3 parent nodes, stored next to the file) each
+//! 1KB chunk of decrypted content can be checked against that root as it
+//! arrives, so nothing unverified is handed to the page.
+//! 
+//! The outboard encoding matches the `bao` tool: an 8-byte little-endian
+//! content length followed by the 64-byte parent nodes in pre-order.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use blake3::hazmat::{self, ChainingValue, HasherExt, Mode};
+use std::fmt;
+
+use crate::error::CryptoError;
+
+const CHUNK_LEN: u64 = 1024;
+const HEADER_LEN: usize = 8;
+const PARENT_LEN: usize = 64;
+
+/// Errors produced while verifying content against a Bao tree
+#[derive(Debug, PartialEq)]
+pub(crate) enum BaoError {
+    InvalidRootHash(usize),
+    BadOutboard(&'static str),
+    HashMismatch(u64),
+    TooLong,
+    Truncated,
+    Finished,
+}
+
+impl fmt::Display for BaoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaoError::InvalidRootHash(n) => write!(f, "Invalid root hash size: expected 32 bytes, got {}", n),
+            BaoError::BadOutboard(reason) => write!(f, "Invalid Bao outboard tree: {}", reason),
+            BaoError::HashMismatch(offset) => write!(f, "Verification failed at offset {}: content does not match the root hash", offset),
+            BaoError::TooLong => write!(f, "Content is longer than the length recorded in the tree"),
+            BaoError::Truncated => write!(f, "Content is truncated"),
+            BaoError::Finished => write!(f, "Verifier has already been finalized"),
+        }
+    }
+}
+
+impl From<BaoError> for CryptoError {
+    fn from(error: BaoError) -> CryptoError {
+        let message = error.to_string();
+        match error {
+            BaoError::InvalidRootHash(_) => CryptoError::InvalidInput(message),
+            BaoError::BadOutboard(_) => CryptoError::InvalidFormat(message),
+            BaoError::HashMismatch(_) => CryptoError::AuthenticationFailed(message),
+            BaoError::TooLong => CryptoError::InvalidInput(message),
+            BaoError::Truncated => CryptoError::Truncated(message),
+            BaoError::Finished => CryptoError::InvalidState(message),
+        }
+    }
+}
+
+impl From<BaoError> for JsValue {
+    fn from(error: BaoError) -> JsValue {
+        CryptoError::from(error).into()
+    }
+}
+
+/// Hash a subtree is expected to have
+#[derive(Clone, Copy)]
+enum Expected {
+    Root(blake3::Hash),
+    Node(ChainingValue),
+}
+
+/// Subtree of the content still waiting to be verified
+struct Subtree {
+    start: u64,
+    len: u64,
+    expected: Expected,
+}
+
+/// Number of parent nodes in the tree for `len` content bytes
+fn parent_count(len: u64) -> u64 {
+    len.div_ceil(CHUNK_LEN).max(1) - 1
+}
+
+/// Chaining value of a single chunk starting at `offset`
+fn chunk_cv(chunk: &[u8], offset: u64) -> ChainingValue {
+    let mut hasher = blake3::Hasher::new();
+    hasher.set_input_offset(offset);
+    hasher.update(chunk);
+    hasher.finalize_non_root()
+}
+
+/// Build the outboard tree for `data`
+pub(crate) fn outboard(data: &[u8]) -> Vec<u8> {
+    let len = data.len() as u64;
+    let mut out = Vec::with_capacity(HEADER_LEN + PARENT_LEN * parent_count(len) as usize);
+    out.extend_from_slice(&len.to_le_bytes());
+    if len > CHUNK_LEN {
+        encode_subtree(data, 0, &mut out);
+    }
+    out
+}
+
+fn encode_subtree(data: &[u8], offset: u64, out: &mut Vec<u8>) -> ChainingValue {
+    if data.len() as u64 <= CHUNK_LEN {
+        return chunk_cv(data, offset);
+    }
+
+    // Reserve the parent node so it precedes its children (pre-order)
+    let node = out.len();
+    out.resize(node + PARENT_LEN, 0);
+
+    let left_len = hazmat::left_subtree_len(data.len() as u64);
+    let (left, right) = data.split_at(left_len as usize);
+    let left_cv = encode_subtree(left, offset, out);
+    let right_cv = encode_subtree(right, offset + left_len, out);
+
+    out[node..node + 32].copy_from_slice(&left_cv);
+    out[node + 32..node + PARENT_LEN].copy_from_slice(&right_cv);
+    hazmat::merge_subtrees_non_root(&left_cv, &right_cv, Mode::Hash)
+}
+
+/// Incrementally verifies content against a root hash and outboard tree
+pub(crate) struct Verifier {
+    outboard: Vec<u8>,
+    cursor: usize,
+    stack: Vec<Subtree>,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+impl Verifier {
+    pub(crate) fn new(root_hash: &[u8], outboard: Vec<u8>) -> Result<Self, BaoError> {
+        let root: [u8; 32] = root_hash
+            .try_into()
+            .map_err(|_| BaoError::InvalidRootHash(root_hash.len()))?;
+
+        if outboard.len() < HEADER_LEN {
+            return Err(BaoError::BadOutboard("missing length header"));
+        }
+        let mut header = [0u8; HEADER_LEN];
+        header.copy_from_slice(&outboard[..HEADER_LEN]);
+        let len = u64::from_le_bytes(header);
+
+        let expected_size = parent_count(len)
+            .checked_mul(PARENT_LEN as u64)
+            .and_then(|size| size.checked_add(HEADER_LEN as u64));
+        if expected_size != Some(outboard.len() as u64) {
+            return Err(BaoError::BadOutboard("size does not match content length"));
+        }
+
+        Ok(Verifier {
+            outboard,
+            cursor: HEADER_LEN,
+            stack: vec![Subtree { start: 0, len, expected: Expected::Root(blake3::Hash::from(root)) }],
+            buffer: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Content length recorded in the outboard tree
+    pub(crate) fn content_length(&self) -> u64 {
+        let mut header = [0u8; HEADER_LEN];
+        header.copy_from_slice(&self.outboard[..HEADER_LEN]);
+        u64::from_le_bytes(header)
+    }
+
+    /// Consume content and return every chunk that has been verified so far
+    pub(crate) fn update(&mut self, data: &[u8]) -> Result<Vec<u8>, BaoError> {
+        if self.finished {
+            return Err(BaoError::Finished);
+        }
+
+        self.buffer.extend_from_slice(data);
+        self.drain()
+    }
+
+    /// Verify any remaining chunk and check that the content is complete
+    pub(crate) fn finish(&mut self) -> Result<Vec<u8>, BaoError> {
+        if self.finished {
+            return Err(BaoError::Finished);
+        }
+
+        let verified = self.drain()?;
+        self.finished = true;
+
+        if !self.stack.is_empty() {
+            return Err(BaoError::Truncated);
+        }
+        Ok(verified)
+    }
+
+    fn drain(&mut self) -> Result<Vec<u8>, BaoError> {
+        let mut verified = Vec::new();
+        let mut consumed = 0;
+
+        while let Some(subtree) = self.stack.pop() {
+            if subtree.len > CHUNK_LEN {
+                self.split(subtree)?;
+                continue;
+            }
+
+            let end = consumed + subtree.len as usize;
+            if self.buffer.len() < end {
+                self.stack.push(subtree);
+                break;
+            }
+
+            let chunk = &self.buffer[consumed..end];
+            let valid = match subtree.expected {
+                Expected::Root(root) => blake3::hash(chunk) == root,
+                Expected::Node(cv) => chunk_cv(chunk, subtree.start) == cv,
+            };
+            if !valid {
+                return Err(BaoError::HashMismatch(subtree.start));
+            }
+
+            verified.extend_from_slice(chunk);
+            consumed = end;
+        }
+
+        self.buffer.drain(..consumed);
+        if self.stack.is_empty() && !self.buffer.is_empty() {
+            return Err(BaoError::TooLong);
+        }
+
+        Ok(verified)
+    }
+
+    /// Check the next parent node and replace `subtree` with its children
+    fn split(&mut self, subtree: Subtree) -> Result<(), BaoError> {
+        let node = &self.outboard[self.cursor..self.cursor + PARENT_LEN];
+        let mut left_cv = [0u8; 32];
+        let mut right_cv = [0u8; 32];
+        left_cv.copy_from_slice(&node[..32]);
+        right_cv.copy_from_slice(&node[32..]);
+
+        let valid = match subtree.expected {
+            Expected::Root(root) => hazmat::merge_subtrees_root(&left_cv, &right_cv, Mode::Hash) == root,
+            Expected::Node(cv) => hazmat::merge_subtrees_non_root(&left_cv, &right_cv, Mode::Hash) == cv,
+        };
+        if !valid {
+            return Err(BaoError::HashMismatch(subtree.start));
+        }
+        self.cursor += PARENT_LEN;
+
+        let left_len = hazmat::left_subtree_len(subtree.len);
+        self.stack.push(Subtree {
+            start: subtree.start + left_len,
+            len: subtree.len - left_len,
+            expected: Expected::Node(right_cv),
+        });
+        self.stack.push(Subtree {
+            start: subtree.start,
+            len: left_len,
+            expected: Expected::Node(left_cv),
+        });
+
+        Ok(())
+    }
+}
+
+/// Build the Bao outboard tree for a file
+/// 
+/// The matching root hash is `blake3_hash(data)`.
+/// 
+/// # Arguments
+/// * `data` - Plaintext as Uint8Array
+/// 
+/// # Returns
+/// Outboard tree as Uint8Array (about 6% of the content size)
+#[wasm_bindgen]
+pub fn bao_outboard(data: &Uint8Array) -> Uint8Array {
+    let tree = outboard(&data.to_vec());
+    Uint8Array::from(tree.as_slice())
+}
+
+/// Verify a complete file against its root hash in one call
+/// 
+/// # Arguments
+/// * `root_hash` - 32-byte BLAKE3 root hash as Uint8Array
+/// * `outboard` - Outboard tree from `bao_outboard`
+/// * `data` - Content as Uint8Array
+/// 
+/// # Returns
+/// `true` if the content matches the root hash
+#[wasm_bindgen]
+pub fn bao_verify(root_hash: &Uint8Array, outboard: &Uint8Array, data: &Uint8Array) -> Result<bool, JsValue> {
+    let mut verifier = Verifier::new(&root_hash.to_vec(), outboard.to_vec())?;
+    let verified = verifier.update(&data.to_vec()).and_then(|_| verifier.finish());
+
+    match verified {
+        Ok(_) => Ok(true),
+        Err(BaoError::HashMismatch(_)) | Err(BaoError::TooLong) | Err(BaoError::Truncated) => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Streaming verifier for downloaded content
+/// 
+/// Feed decrypted bytes in any split to `update`; only chunks that have been
+/// checked against the root hash are returned. `finalize` returns the last
+/// verified bytes and fails if the content is incomplete.
+#[wasm_bindgen]
+pub struct BaoVerifier {
+    verifier: Verifier,
+}
+
+#[wasm_bindgen]
+impl BaoVerifier {
+    /// Create a verifier
+    /// 
+    /// # Arguments
+    /// * `root_hash` - 32-byte BLAKE3 root hash from the furl link
+    /// * `outboard` - Outboard tree from `bao_outboard`
+    #[wasm_bindgen(constructor)]
+    pub fn new(root_hash: &Uint8Array, outboard: &Uint8Array) -> Result<BaoVerifier, JsValue> {
+        Ok(BaoVerifier {
+            verifier: Verifier::new(&root_hash.to_vec(), outboard.to_vec())?,
+        })
+    }
+
+    /// Content length recorded in the tree
+    #[wasm_bindgen(getter)]
+    pub fn content_length(&self) -> u64 {
+        self.verifier.content_length()
+    }
+
+    /// Add content; returns newly verified bytes
+    pub fn update(&mut self, data: &Uint8Array) -> Result<Uint8Array, JsValue> {
+        let verified = self.verifier.update(&data.to_vec())?;
+        Ok(Uint8Array::from(verified.as_slice()))
+    }
+
+    /// Finish verification, failing if content is missing
+    pub fn finalize(&mut self) -> Result<Uint8Array, JsValue> {
+        let verified = self.verifier.finish()?;
+        Ok(Uint8Array::from(verified.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn verify_in_pieces(root: &[u8], tree: &[u8], data: &[u8], piece: usize) -> Result<Vec<u8>, BaoError> {
+        let mut verifier = Verifier::new(root, tree.to_vec())?;
+        let mut out = Vec::new();
+        for part in data.chunks(piece) {
+            out.extend(verifier.update(part)?);
+        }
+        out.extend(verifier.finish()?);
+        Ok(out)
+    }
+
+    #[test]
+    fn verifies_content_of_any_length_and_split() {
+        for len in [0, 1, 1023, 1024, 1025, 2048, 3073, 8 * 1024 + 7] {
+            let data = sample(len);
+            let root = blake3::hash(&data);
+            let tree = outboard(&data);
+            assert_eq!(tree.len(), HEADER_LEN + PARENT_LEN * parent_count(len as u64) as usize);
+
+            for piece in [1, 100, 1024, 5000] {
+                assert_eq!(verify_in_pieces(root.as_bytes(), &tree, &data, piece).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_modified_chunk_before_releasing_it() {
+        let data = sample(4096);
+        let root = blake3::hash(&data);
+        let tree = outboard(&data);
+
+        let mut tampered = data.clone();
+        tampered[2500] ^= 1;
+
+        let mut verifier = Verifier::new(root.as_bytes(), tree).unwrap();
+        assert_eq!(verifier.update(&tampered[..2048]).unwrap(), &data[..2048]);
+        assert_eq!(verifier.update(&tampered[2048..]), Err(BaoError::HashMismatch(2048)));
+    }
+
+    #[test]
+    fn rejects_wrong_root_truncation_and_extra_bytes() {
+        let data = sample(3000);
+        let tree = outboard(&data);
+        let root = blake3::hash(&data);
+
+        let wrong = blake3::hash(b"other");
+        assert_eq!(verify_in_pieces(wrong.as_bytes(), &tree, &data, 1024), Err(BaoError::HashMismatch(0)));
+        assert_eq!(verify_in_pieces(root.as_bytes(), &tree, &data[..2999], 1024), Err(BaoError::Truncated));
+
+        let mut longer = data.clone();
+        longer.push(0);
+        assert_eq!(verify_in_pieces(root.as_bytes(), &tree, &longer, 1024), Err(BaoError::TooLong));
+    }
+}
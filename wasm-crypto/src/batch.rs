@@ -0,0 +1,114 @@
+//! Many small AES-CTR decrypts in one call
+//! 
+//! Thumbnails and metadata records are each a few hundred bytes, so calling
+//! `decrypt_aes_ctr` once per record spends more time crossing between JS
+//! and wasm (and expanding the same key) than decrypting. `decrypt_batch`
+//! takes the whole array at once, expands the key a single time and hands
+//! back an array of plaintexts in the same order.
+
+use wasm_bindgen::prelude::*;
+use js_sys::{Array, Uint8Array};
+use furl_core::ctr::{AesKey, CtrOptions};
+
+use crate::counter::ctr_options_from_js;
+use crate::error::CryptoError;
+use crate::{metrics, AesCtr};
+
+/// One `{ iv, data }` entry, copied into wasm memory
+struct BatchItem {
+    iv: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl BatchItem {
+    fn from_js(item: &JsValue, index: u32) -> Result<Self, CryptoError> {
+        let field = |name: &str| -> Result<Vec<u8>, CryptoError> {
+            let value = js_sys::Reflect::get(item, &name.into()).ok().filter(|value| value.is_object());
+            let array = value
+                .and_then(|value| value.dyn_into::<Uint8Array>().ok())
+                .ok_or_else(|| CryptoError::InvalidInput(format!("Invalid batch item {}: `{}` must be a Uint8Array", index, name)))?;
+            Ok(array.to_vec())
+        };
+        Ok(BatchItem { iv: field("iv")?, data: field("data")? })
+    }
+}
+
+/// Decrypt every item in place with one expanded key
+fn decrypt_items(key: &AesKey, items: &mut [BatchItem], options: &CtrOptions) -> Result<(), CryptoError> {
+    for (index, item) in items.iter_mut().enumerate() {
+        let mut cipher = AesCtr::from_key(key, &item.iv, options)
+            .map_err(|error| CryptoError::InvalidIv(format!("Invalid batch item {}: {}", index, error.message())))?;
+        metrics::operation();
+        metrics::cipher(item.data.len(), || cipher.apply_keystream(&mut item.data))?;
+    }
+    Ok(())
+}
+
+/// Read `items`, decrypt them all with `key` and return the plaintexts
+pub(crate) fn decrypt_batch_with_key(key: &AesKey, items: &Array, options: Option<&js_sys::Object>) -> Result<Array, JsValue> {
+    let options = ctr_options_from_js(options)?;
+    let mut batch = metrics::copy(|| items.iter().zip(0..).map(|(item, index)| BatchItem::from_js(&item, index)).collect::<Result<Vec<_>, _>>())?;
+
+    decrypt_items(key, &mut batch, &options)?;
+
+    log!("Batch: Decrypted {} items", batch.len());
+
+    Ok(metrics::copy(|| batch.iter().map(|item| JsValue::from(Uint8Array::from(item.data.as_slice()))).collect()))
+}
+
+/// Decrypt many small AES-CTR ciphertexts under one key in a single call
+/// 
+/// ```javascript
+/// const plaintexts = decrypt_batch(key, records.map(({ iv, ciphertext }) => ({ iv, data: ciphertext })));
+/// ```
+/// 
+/// If any item is malformed the whole call fails, naming the item's index,
+/// and nothing is returned.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `items` - Array of `{ iv: Uint8Array, data: Uint8Array }` objects
+/// * `options` - Optional `{ counter_width, rfc3686 }` object applied to every item
+/// 
+/// # Returns
+/// Array of decrypted Uint8Arrays, in the order of `items`
+#[wasm_bindgen]
+pub fn decrypt_batch(key: &Uint8Array, items: &Array, options: Option<js_sys::Object>) -> Result<Array, JsValue> {
+    let key = AesKey::new(&key.to_vec())?;
+    decrypt_batch_with_key(&key, items, options.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_item_uses_its_own_iv() {
+        let key = [7u8; 16];
+        let plaintexts: Vec<Vec<u8>> = (0..5u8).map(|n| vec![n; 10 + n as usize * 30]).collect();
+        let mut items: Vec<BatchItem> = plaintexts
+            .iter()
+            .zip(0u8..)
+            .map(|(plaintext, n)| {
+                let iv = [n; 16];
+                let mut data = plaintext.clone();
+                AesCtr::new(&key, &iv).unwrap().apply_keystream(&mut data).unwrap();
+                BatchItem { iv: iv.to_vec(), data }
+            })
+            .collect();
+
+        decrypt_items(&AesKey::new(&key).unwrap(), &mut items, &CtrOptions::default()).unwrap();
+        for (item, plaintext) in items.iter().zip(&plaintexts) {
+            assert_eq!(&item.data, plaintext);
+        }
+    }
+
+    #[test]
+    fn a_bad_iv_names_its_item() {
+        let mut items = vec![BatchItem { iv: vec![0; 16], data: vec![1; 4] }, BatchItem { iv: vec![0; 15], data: vec![1; 4] }];
+        match decrypt_items(&AesKey::new(&[0; 32]).unwrap(), &mut items, &CtrOptions::default()) {
+            Err(CryptoError::InvalidIv(message)) => assert!(message.starts_with("Invalid batch item 1:"), "{}", message),
+            other => panic!("expected InvalidIv, got {:?}", other.err()),
+        }
+    }
+}
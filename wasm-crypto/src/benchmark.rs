@@ -0,0 +1,197 @@
+//! Throughput benchmarks run inside the deployed binary
+//! 
+//! Speed varies a lot between devices and between wasm engines, so rather
+//! than guessing, the viewer can time the primitive it's about to use on a
+//! buffer of the size it plans to use, and pick chunk sizes and worker
+//! counts from the result. The same call gives browser test harnesses a
+//! number to track for regressions.
+
+use wasm_bindgen::prelude::*;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use chacha20poly1305::ChaCha20Poly1305;
+use sha2::{Digest, Sha256};
+
+use crate::error::{try_zeroed, CryptoError};
+use crate::metrics::now_ms;
+use crate::AesCtr;
+
+/// Names accepted by `benchmark`, in the order they're documented
+pub(crate) const CIPHERS: &[&str] = &["aes-128-ctr", "aes-256-ctr", "aes-256-gcm", "chacha20-poly1305", "sha-256", "blake3"];
+
+/// One primitive set up with a fixed key, ready to run over a buffer
+enum Workload {
+    AesCtr(Box<AesCtr>),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+    Sha256,
+    Blake3,
+}
+
+impl Workload {
+    fn new(cipher: &str) -> Result<Self, CryptoError> {
+        // Fixed all-zero keys and nonces: only the timing matters here, and
+        // nothing produced is ever kept
+        Ok(match cipher {
+            "aes-128-ctr" => Workload::AesCtr(Box::new(AesCtr::new(&[0; 16], &[0; 16])?)),
+            "aes-256-ctr" => Workload::AesCtr(Box::new(AesCtr::new(&[0; 32], &[0; 16])?)),
+            "aes-256-gcm" => Workload::Aes256Gcm(Box::new(Aes256Gcm::new(&[0; 32].into()))),
+            "chacha20-poly1305" => Workload::ChaCha20Poly1305(Box::new(ChaCha20Poly1305::new(&[0; 32].into()))),
+            "sha-256" => Workload::Sha256,
+            "blake3" => Workload::Blake3,
+            other => return Err(CryptoError::Unsupported(format!("Unknown benchmark cipher: {} (expected one of {})", other, CIPHERS.join(", ")))),
+        })
+    }
+
+    /// Process `data` once, in place for the ciphers
+    fn run(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        let aead_failed = |_| CryptoError::Internal("Benchmark encryption failed".into());
+        match self {
+            Workload::AesCtr(cipher) => cipher.apply_keystream(data)?,
+            Workload::Aes256Gcm(cipher) => {
+                cipher.encrypt_in_place_detached(&[0; 12].into(), b"", data).map_err(aead_failed)?;
+            }
+            Workload::ChaCha20Poly1305(cipher) => {
+                cipher.encrypt_in_place_detached(&[0; 12].into(), b"", data).map_err(aead_failed)?;
+            }
+            Workload::Sha256 => {
+                std::hint::black_box(Sha256::digest(&*data));
+            }
+            Workload::Blake3 => {
+                std::hint::black_box(blake3::hash(data));
+            }
+        }
+        std::hint::black_box(data);
+        Ok(())
+    }
+}
+
+/// Timings for a run, in milliseconds
+#[derive(Debug, PartialEq)]
+struct Timings {
+    total_ms: f64,
+    min_ms: f64,
+}
+
+impl Timings {
+    /// Average throughput, or `None` if the clock didn't advance
+    fn bytes_per_second(&self, size: u32, iterations: u32) -> Option<f64> {
+        (self.total_ms > 0.0).then(|| size as f64 * iterations as f64 * 1000.0 / self.total_ms)
+    }
+}
+
+/// Run `workload` over `data` once untimed to warm up, then `iterations` times timed with `now`
+fn measure(workload: &mut Workload, data: &mut [u8], iterations: u32, mut now: impl FnMut() -> f64) -> Result<Timings, CryptoError> {
+    workload.run(data)?;
+
+    let mut timings = Timings { total_ms: 0.0, min_ms: f64::INFINITY };
+    for _ in 0..iterations {
+        let started = now();
+        workload.run(data)?;
+        let elapsed = (now() - started).max(0.0);
+
+        timings.total_ms += elapsed;
+        timings.min_ms = timings.min_ms.min(elapsed);
+    }
+    Ok(timings)
+}
+
+/// Time one primitive over a buffer of `size` bytes
+/// 
+/// Runs once untimed, then `iterations` times, each over the same buffer in
+/// one call, so the result is what a single decrypt call of that size costs
+/// on this device. Pick the size you intend to use for chunks; per-call
+/// overhead dominates below a few KiB. The AEADs are timed encrypting, which
+/// costs the same as decrypting.
+/// 
+/// ```javascript
+/// const { bytes_per_second } = benchmark('aes-256-ctr', 1 << 20, 20);
+/// const chunkSize = bytes_per_second > 50e6 ? 4 << 20 : 1 << 20;
+/// ```
+/// 
+/// This blocks the calling thread for the whole run; call it from a worker,
+/// or keep `size * iterations` small on the main thread.
+/// 
+/// # Arguments
+/// * `cipher` - One of `aes-128-ctr`, `aes-256-ctr`, `aes-256-gcm`,
+///   `chacha20-poly1305`, `sha-256` or `blake3`
+/// * `size` - Bytes processed per iteration
+/// * `iterations` - Number of timed runs, at least 1
+/// 
+/// # Returns
+/// Object with:
+/// * `cipher`, `size`, `iterations` - The arguments
+/// * `total_ms` - Time across all timed runs
+/// * `mean_ms` / `min_ms` - Average and fastest single run
+/// * `bytes_per_second` - Average throughput, or `null` if the run was too
+///   quick for the clock to measure (use a larger size or more iterations)
+#[wasm_bindgen]
+pub fn benchmark(cipher: &str, size: u32, iterations: u32) -> Result<js_sys::Object, JsValue> {
+    if size == 0 || iterations == 0 {
+        return Err(CryptoError::InvalidInput("Benchmark size and iterations must be at least 1".into()).into());
+    }
+
+    let mut workload = Workload::new(cipher)?;
+    let mut data = try_zeroed(size as usize)?;
+    let timings = measure(&mut workload, &mut data, iterations, now_ms)?;
+    let bytes_per_second = timings.bytes_per_second(size, iterations);
+
+    log!("Benchmark: {} x {} bytes of {} in {:.1} ms", iterations, size, cipher, timings.total_ms);
+
+    let report = js_sys::Object::new();
+    js_sys::Reflect::set(&report, &"cipher".into(), &JsValue::from_str(cipher))?;
+    js_sys::Reflect::set(&report, &"size".into(), &size.into())?;
+    js_sys::Reflect::set(&report, &"iterations".into(), &iterations.into())?;
+    js_sys::Reflect::set(&report, &"total_ms".into(), &timings.total_ms.into())?;
+    js_sys::Reflect::set(&report, &"mean_ms".into(), &(timings.total_ms / iterations as f64).into())?;
+    js_sys::Reflect::set(&report, &"min_ms".into(), &timings.min_ms.into())?;
+    js_sys::Reflect::set(&report, &"bytes_per_second".into(), &bytes_per_second.map_or(JsValue::NULL, JsValue::from))?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clock that advances `step` ms per reading
+    fn ticking(step: f64) -> impl FnMut() -> f64 {
+        let mut time = 0.0;
+        move || {
+            time += step;
+            time
+        }
+    }
+
+    #[test]
+    fn every_cipher_runs() {
+        for cipher in CIPHERS {
+            let mut workload = Workload::new(cipher).unwrap();
+            let timings = measure(&mut workload, &mut [0u8; 1000], 3, ticking(2.0)).unwrap();
+            assert_eq!(timings, Timings { total_ms: 6.0, min_ms: 2.0 }, "{}", cipher);
+        }
+    }
+
+    #[test]
+    fn ciphers_change_the_buffer_and_hashes_leave_it() {
+        let mut data = [0u8; 64];
+        Workload::new("aes-256-ctr").unwrap().run(&mut data).unwrap();
+        assert_ne!(data, [0u8; 64]);
+
+        let mut data = [0u8; 64];
+        Workload::new("blake3").unwrap().run(&mut data).unwrap();
+        assert_eq!(data, [0u8; 64]);
+    }
+
+    #[test]
+    fn throughput_is_bytes_over_time() {
+        let timings = Timings { total_ms: 500.0, min_ms: 100.0 };
+        assert_eq!(timings.bytes_per_second(1_000_000, 5), Some(10_000_000.0));
+        assert_eq!(Timings { total_ms: 0.0, min_ms: 0.0 }.bytes_per_second(16, 1), None);
+    }
+
+    #[test]
+    fn unknown_ciphers_are_unsupported() {
+        assert!(matches!(Workload::new("rot13"), Err(CryptoError::Unsupported(_))));
+    }
+}
@@ -0,0 +1,208 @@
+//! Memory budget for plaintext accumulated by chunked decryption
+//! 
+//! Functions that return the whole plaintext build it up in wasm memory,
+//! which on mobile Safari can get the tab killed long before an allocation
+//! fails. Their `options` object takes:
+//! 
+//! * `memory_budget` - Most plaintext bytes to hold at once
+//! * `on_flush` - Called with `(plaintext: Uint8Array, offset: BigInt)`
+//!   whenever holding the next chunk would go over the budget; the buffered
+//!   plaintext is handed over and dropped
+//! 
+//! With a budget the return value holds only the plaintext that wasn't
+//! flushed, so the caller reassembles the flushed parts followed by the
+//! result. Without `on_flush`, going over the budget fails with
+//! `OutOfMemory` before anything more is allocated.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::error::CryptoError;
+
+/// `memory_budget` and `on_flush` read from a JS options object
+#[derive(Default)]
+pub(crate) struct MemoryBudget {
+    limit: Option<usize>,
+    on_flush: Option<js_sys::Function>,
+}
+
+impl MemoryBudget {
+    /// Read the budget from an optional JS `options` object; unlimited if absent
+    pub(crate) fn from_js(options: Option<&js_sys::Object>) -> Result<Self, CryptoError> {
+        let Some(options) = options else {
+            return Ok(MemoryBudget::default());
+        };
+
+        let limit = js_sys::Reflect::get(options, &"memory_budget".into())
+            .map_err(|_| CryptoError::InvalidInput("Invalid options: memory_budget could not be read".into()))?;
+        let limit = if limit.is_undefined() || limit.is_null() {
+            None
+        } else {
+            let bytes = limit
+                .as_f64()
+                .ok_or_else(|| CryptoError::InvalidInput("Invalid memory_budget: expected a number of bytes".into()))?;
+            Some(parse_limit(bytes)?)
+        };
+
+        let on_flush = js_sys::Reflect::get(options, &"on_flush".into())
+            .map_err(|_| CryptoError::InvalidInput("Invalid options: on_flush could not be read".into()))?;
+        let on_flush = if on_flush.is_undefined() || on_flush.is_null() {
+            None
+        } else {
+            Some(on_flush.dyn_into::<js_sys::Function>().map_err(|_| CryptoError::InvalidInput("Invalid on_flush: expected a function".into()))?)
+        };
+
+        if on_flush.is_some() && limit.is_none() {
+            return Err(CryptoError::InvalidInput("Invalid options: on_flush needs a memory_budget".into()));
+        }
+
+        Ok(MemoryBudget { limit, on_flush })
+    }
+
+    /// An empty buffer for up to `total` bytes of plaintext, flushing to `on_flush`
+    pub(crate) fn buffer(&self, total: Option<usize>) -> PlaintextBuffer<impl FnMut(&[u8], u64) -> Result<(), JsValue> + '_> {
+        let flush = self.on_flush.as_ref().map(|on_flush| {
+            move |data: &[u8], offset: u64| on_flush.call2(&JsValue::NULL, &Uint8Array::from(data), &JsValue::from(offset)).map(|_| ())
+        });
+        PlaintextBuffer::new(self.limit, total, flush)
+    }
+}
+
+/// Check a `memory_budget` value, which must be a whole number of bytes
+fn parse_limit(bytes: f64) -> Result<usize, CryptoError> {
+    if !(bytes >= 1.0 && bytes.fract() == 0.0 && bytes <= usize::MAX as f64) {
+        return Err(CryptoError::InvalidInput(format!("Invalid memory_budget {}: expected a whole number of bytes, at least 1", bytes)));
+    }
+    Ok(bytes as usize)
+}
+
+/// Plaintext collected chunk by chunk, held to an optional budget
+pub(crate) struct PlaintextBuffer<F> {
+    data: Vec<u8>,
+    limit: Option<usize>,
+    flush: Option<F>,
+    /// Bytes already handed to `flush`, i.e. the offset of `data[0]`
+    flushed: u64,
+}
+
+impl<F, E> PlaintextBuffer<F>
+where
+    F: FnMut(&[u8], u64) -> Result<(), E>,
+    E: From<CryptoError>,
+{
+    /// `total` is the expected plaintext length, used to reserve space up front
+    fn new(limit: Option<usize>, total: Option<usize>, flush: Option<F>) -> Self {
+        let capacity = match (limit, total) {
+            (Some(limit), Some(total)) => limit.min(total),
+            (None, Some(total)) => total,
+            (_, None) => 0,
+        };
+        // If this much can't be had now, `push` reports it when it's needed
+        let mut data = Vec::new();
+        let _ = data.try_reserve_exact(capacity);
+        PlaintextBuffer { data, limit, flush, flushed: 0 }
+    }
+
+    /// Append a chunk, failing with `OutOfMemory` rather than trapping
+    fn extend(&mut self, chunk: &[u8]) -> Result<(), CryptoError> {
+        self.data
+            .try_reserve(chunk.len())
+            .map_err(|_| CryptoError::OutOfMemory(format!("Failed to allocate {} bytes", self.data.len() + chunk.len())))?;
+        self.data.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    /// Add a chunk, first flushing what's held if the chunk wouldn't fit
+    /// 
+    /// A chunk larger than the whole budget is already in memory, so it's
+    /// flushed straight away rather than copied.
+    pub(crate) fn push(&mut self, chunk: &[u8]) -> Result<(), E> {
+        let Some(limit) = self.limit else {
+            return Ok(self.extend(chunk)?);
+        };
+        if self.data.len() + chunk.len() <= limit {
+            return Ok(self.extend(chunk)?);
+        }
+
+        let Some(flush) = self.flush.as_mut() else {
+            return Err(CryptoError::OutOfMemory(format!(
+                "Plaintext would exceed the memory budget of {} bytes; pass on_flush to receive it in parts",
+                limit
+            ))
+            .into());
+        };
+
+        if !self.data.is_empty() {
+            flush(&self.data, self.flushed)?;
+            self.flushed += self.data.len() as u64;
+            self.data.clear();
+        }
+        if chunk.len() > limit {
+            flush(chunk, self.flushed)?;
+            self.flushed += chunk.len() as u64;
+        } else {
+            self.extend(chunk)?;
+        }
+        Ok(())
+    }
+
+    /// Bytes pushed so far, flushed or not
+    pub(crate) fn total(&self) -> u64 {
+        self.flushed + self.data.len() as u64
+    }
+
+    /// The plaintext that hasn't been flushed
+    pub(crate) fn into_inner(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Flushed = Vec<(Vec<u8>, u64)>;
+
+    fn buffer(limit: Option<usize>, flushed: &mut Flushed) -> PlaintextBuffer<impl FnMut(&[u8], u64) -> Result<(), CryptoError> + '_> {
+        PlaintextBuffer::new(limit, Some(100), Some(move |data: &[u8], offset| {
+            flushed.push((data.to_vec(), offset));
+            Ok(())
+        }))
+    }
+
+    #[test]
+    fn flushes_before_going_over_budget() {
+        let mut flushed = Flushed::new();
+        let mut plaintext = buffer(Some(10), &mut flushed);
+        for chunk in [&b"abcd"[..], b"efgh", b"ijkl", b"0123456789abcdef", b"xy"] {
+            plaintext.push(chunk).unwrap();
+        }
+        assert_eq!(plaintext.total(), 30);
+        assert_eq!(plaintext.into_inner(), b"xy");
+        assert_eq!(flushed, vec![(b"abcdefgh".to_vec(), 0), (b"ijkl".to_vec(), 8), (b"0123456789abcdef".to_vec(), 12)]);
+    }
+
+    #[test]
+    fn unlimited_buffers_never_flush() {
+        let mut flushed = Flushed::new();
+        let mut plaintext = buffer(None, &mut flushed);
+        plaintext.push(&[7; 1000]).unwrap();
+        assert_eq!(plaintext.into_inner().len(), 1000);
+        assert!(flushed.is_empty());
+    }
+
+    #[test]
+    fn going_over_budget_without_on_flush_fails() {
+        let mut plaintext = PlaintextBuffer::<fn(&[u8], u64) -> Result<(), CryptoError>>::new(Some(8), None, None);
+        plaintext.push(b"12345678").unwrap();
+        assert!(matches!(plaintext.push(b"9"), Err(CryptoError::OutOfMemory(_))));
+    }
+
+    #[test]
+    fn budgets_are_whole_positive_byte_counts() {
+        assert_eq!(parse_limit(64.0 * 1024.0 * 1024.0).unwrap(), 64 << 20);
+        for bad in [0.0, -1.0, 1.5, f64::NAN, f64::INFINITY] {
+            assert!(parse_limit(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Fast non-cryptographic checksums: CRC32 and xxHash64
+//! 
+//! For spotting a chunk damaged in transit before paying for decryption,
+//! when the transport (TLS, a signed manifest) already provides
+//! authenticity. Anyone can forge a matching checksum, so these never
+//! replace a MAC or an AEAD tag.
+//! 
+//! CRC32 is the IEEE polynomial used by zip, gzip and PNG. xxHash64 is
+//! implemented here rather than pulled in as a dependency; it is a few
+//! dozen lines and is checked against the reference values below.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+const P1: u64 = 0x9e37_79b1_85eb_ca87;
+const P2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const P3: u64 = 0x1656_67b1_9e37_79f9;
+const P4: u64 = 0x85eb_ca77_c2b2_ae63;
+const P5: u64 = 0x27d4_eb2f_1656_67c5;
+
+/// Bytes per stripe: four 8-byte lanes
+const STRIPE_LEN: usize = 32;
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+fn round(acc: u64, lane: u64) -> u64 {
+    acc.wrapping_add(lane.wrapping_mul(P2)).rotate_left(31).wrapping_mul(P1)
+}
+
+fn merge_round(hash: u64, acc: u64) -> u64 {
+    (hash ^ round(0, acc)).wrapping_mul(P1).wrapping_add(P4)
+}
+
+/// Streaming XXH64 state
+struct Xxh64 {
+    seed: u64,
+    acc: [u64; 4],
+    /// Input not yet consumed because it doesn't fill a stripe
+    stripe: [u8; STRIPE_LEN],
+    buffered: usize,
+    total_len: u64,
+}
+
+impl Xxh64 {
+    fn new(seed: u64) -> Self {
+        Xxh64 {
+            seed,
+            acc: [seed.wrapping_add(P1).wrapping_add(P2), seed.wrapping_add(P2), seed, seed.wrapping_sub(P1)],
+            stripe: [0; STRIPE_LEN],
+            buffered: 0,
+            total_len: 0,
+        }
+    }
+
+    fn consume(acc: &mut [u64; 4], stripe: &[u8]) {
+        for (lane, acc) in stripe.chunks_exact(8).zip(acc.iter_mut()) {
+            *acc = round(*acc, read_u64(lane));
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+
+        if self.buffered > 0 {
+            let take = (STRIPE_LEN - self.buffered).min(data.len());
+            self.stripe[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+            self.buffered += take;
+            data = &data[take..];
+            if self.buffered < STRIPE_LEN {
+                return;
+            }
+            Self::consume(&mut self.acc, &self.stripe);
+            self.buffered = 0;
+        }
+
+        let mut stripes = data.chunks_exact(STRIPE_LEN);
+        for stripe in &mut stripes {
+            Self::consume(&mut self.acc, stripe);
+        }
+        let rest = stripes.remainder();
+        self.stripe[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    fn digest(&self) -> u64 {
+        let mut hash = if self.total_len >= STRIPE_LEN as u64 {
+            let [a, b, c, d] = self.acc;
+            let hash = a.rotate_left(1).wrapping_add(b.rotate_left(7)).wrapping_add(c.rotate_left(12)).wrapping_add(d.rotate_left(18));
+            self.acc.iter().fold(hash, |hash, &acc| merge_round(hash, acc))
+        } else {
+            self.seed.wrapping_add(P5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut rest = &self.stripe[..self.buffered];
+        while rest.len() >= 8 {
+            hash ^= round(0, read_u64(rest));
+            hash = hash.rotate_left(27).wrapping_mul(P1).wrapping_add(P4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^= (u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64).wrapping_mul(P1);
+            hash = hash.rotate_left(23).wrapping_mul(P2).wrapping_add(P3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash ^= (byte as u64).wrapping_mul(P5);
+            hash = hash.rotate_left(11).wrapping_mul(P1);
+        }
+
+        // Avalanche
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(P2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(P3);
+        hash ^ (hash >> 32)
+    }
+}
+
+/// CRC32 (IEEE) of `data`
+/// 
+/// # Arguments
+/// * `data` - Data as Uint8Array
+/// 
+/// # Returns
+/// The checksum as an unsigned 32-bit number
+#[wasm_bindgen]
+pub fn crc32(data: &Uint8Array) -> u32 {
+    crc32fast::hash(&data.to_vec())
+}
+
+/// xxHash64 of `data`
+/// 
+/// # Arguments
+/// * `data` - Data as Uint8Array
+/// * `seed` - Optional 64-bit seed as BigInt (default: 0)
+/// 
+/// # Returns
+/// The hash as a BigInt; `.toString(16).padStart(16, '0')` gives the usual hex form
+#[wasm_bindgen]
+pub fn xxhash64(data: &Uint8Array, seed: Option<u64>) -> u64 {
+    let mut state = Xxh64::new(seed.unwrap_or(0));
+    state.update(&data.to_vec());
+    state.digest()
+}
+
+/// Incremental CRC32 for data arriving in chunks
+/// 
+/// ```javascript
+/// const crc = new Crc32();
+/// for await (const chunk of chunks) crc.update(chunk);
+/// if (crc.value() !== expected) refetch();
+/// ```
+#[wasm_bindgen]
+pub struct Crc32 {
+    state: crc32fast::Hasher,
+}
+
+#[wasm_bindgen]
+impl Crc32 {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Crc32 {
+        Crc32 { state: crc32fast::Hasher::new() }
+    }
+
+    /// Feed the next chunk of data into the checksum
+    pub fn update(&mut self, data: &Uint8Array) {
+        self.state.update(&data.to_vec());
+    }
+
+    /// Checksum of everything fed in so far; more data may follow
+    pub fn value(&self) -> u32 {
+        self.state.clone().finalize()
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Crc32::new()
+    }
+}
+
+/// Incremental xxHash64 for data arriving in chunks
+#[wasm_bindgen]
+pub struct XxHash64 {
+    state: Xxh64,
+}
+
+#[wasm_bindgen]
+impl XxHash64 {
+    /// Start a hash with an optional 64-bit seed (default: 0)
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: Option<u64>) -> XxHash64 {
+        XxHash64 { state: Xxh64::new(seed.unwrap_or(0)) }
+    }
+
+    /// Feed the next chunk of data into the hash
+    pub fn update(&mut self, data: &Uint8Array) {
+        self.state.update(&data.to_vec());
+    }
+
+    /// Hash of everything fed in so far, as a BigInt; more data may follow
+    pub fn value(&self) -> u64 {
+        self.state.digest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xxh64(data: &[u8], seed: u64) -> u64 {
+        let mut state = Xxh64::new(seed);
+        state.update(data);
+        state.digest()
+    }
+
+    #[test]
+    fn xxhash64_matches_reference_values() {
+        let counting: Vec<u8> = (0..100).collect();
+        for (data, seed, expected) in [
+            (&b""[..], 0, 0xef46_db37_51d8_e999),
+            (b"a", 0, 0xd24e_c4f1_a98c_6e5b),
+            (b"abc", 0, 0x44bc_2cf5_ad77_0999),
+            (b"123456789", 0, 0x8cb8_41db_40e6_ae83),
+            (&counting, 0, 0x6ac1_e580_3216_6597),
+            (&counting, 0x9e37_79b9_7f4a_7c15, 0x3b97_d91e_ba03_e785),
+        ] {
+            assert_eq!(xxh64(data, seed), expected, "{} bytes, seed {:x}", data.len(), seed);
+        }
+    }
+
+    #[test]
+    fn xxhash64_is_independent_of_chunking() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        let whole = xxh64(&data, 42);
+        for step in [1, 3, 31, 32, 33, 100] {
+            let mut state = Xxh64::new(42);
+            data.chunks(step).for_each(|chunk| state.update(chunk));
+            assert_eq!(state.digest(), whole, "chunks of {}", step);
+        }
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32fast::hash(b"123456789"), 0xcbf4_3926);
+    }
+}
@@ -0,0 +1,209 @@
+//! Key-committing AES-GCM and ChaCha20-Poly1305
+//! 
+//! Neither AEAD commits to its key: an attacker who picks two keys can build
+//! one ciphertext whose tag verifies under both, so two recipients of a
+//! multi-recipient share could be shown different plaintexts (the
+//! "invisible salamanders" attack). The committing variants derive the
+//! encryption key and a 32-byte commitment from the key and nonce with HKDF
+//! and prepend the commitment:
+//! 
+//! ```text
+//! enc_key || commitment = HKDF-SHA256(ikm = key, info = "furl key commitment" || nonce, 64 bytes)
+//! sealed                = commitment || AEAD(enc_key, nonce, plaintext, aad)
+//! ```
+//! 
+//! Decryption checks the commitment before opening, so a ciphertext opens
+//! under at most one key (up to a SHA-256 collision). The output is 32
+//! bytes longer than the plain AEAD and can't be opened by it.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use furl_core::container::Algorithm;
+use furl_core::kdf::hkdf_sha256;
+use zeroize::Zeroize;
+
+use crate::aad_bytes;
+use crate::error::CryptoError;
+use crate::mac::bytes_equal;
+
+const COMMITMENT_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const INFO: &[u8] = b"furl key commitment";
+
+/// Encryption key and commitment for one key and nonce
+struct Committed {
+    enc_key: [u8; 32],
+    commitment: [u8; COMMITMENT_LEN],
+}
+
+impl Committed {
+    fn derive(key: &[u8], nonce: &[u8]) -> Result<Self, CryptoError> {
+        if key.len() != 32 {
+            return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.len())));
+        }
+        if nonce.len() != 12 {
+            return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 12 bytes, got {}", nonce.len())));
+        }
+
+        let mut info = INFO.to_vec();
+        info.extend_from_slice(nonce);
+        let mut okm = [0u8; 64];
+        hkdf_sha256(key, None, &info, &mut okm)?;
+
+        let mut committed = Committed { enc_key: [0; 32], commitment: [0; COMMITMENT_LEN] };
+        committed.enc_key.copy_from_slice(&okm[..32]);
+        committed.commitment.copy_from_slice(&okm[32..]);
+        okm.zeroize();
+        Ok(committed)
+    }
+
+    fn seal(&self, algorithm: Algorithm, nonce: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let payload = Payload { msg: plaintext, aad };
+        let ciphertext = match algorithm {
+            Algorithm::Aes256Gcm => Aes256Gcm::new(&self.enc_key.into()).encrypt(nonce.into(), payload),
+            Algorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new(&self.enc_key.into()).encrypt(nonce.into(), payload),
+        }
+        .map_err(|_| CryptoError::Internal("Encryption failed".into()))?;
+
+        let mut sealed = self.commitment.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    fn open(&self, algorithm: Algorithm, nonce: &[u8], sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if sealed.len() < COMMITMENT_LEN + TAG_LEN {
+            return Err(CryptoError::InvalidInput(format!(
+                "Invalid data size: expected at least a 32-byte commitment and a 16-byte tag, got {} bytes",
+                sealed.len()
+            )));
+        }
+
+        let (commitment, ciphertext) = sealed.split_at(COMMITMENT_LEN);
+        if !bytes_equal(commitment, &self.commitment) {
+            return Err(CryptoError::AuthenticationFailed("Authentication failed: the ciphertext is not committed to this key".into()));
+        }
+
+        let payload = Payload { msg: ciphertext, aad };
+        match algorithm {
+            Algorithm::Aes256Gcm => Aes256Gcm::new(&self.enc_key.into()).decrypt(nonce.into(), payload),
+            Algorithm::ChaCha20Poly1305 => ChaCha20Poly1305::new(&self.enc_key.into()).decrypt(nonce.into(), payload),
+        }
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))
+    }
+}
+
+impl Drop for Committed {
+    fn drop(&mut self) {
+        self.enc_key.zeroize();
+    }
+}
+
+/// Encrypt with a key-committing AES-256-GCM or ChaCha20-Poly1305
+/// 
+/// Use for data that several recipients decrypt with different keys, or
+/// whenever a ciphertext must not be openable under a second key.
+/// 
+/// # Arguments
+/// * `key` - 32-byte key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `algorithm` - `"aes-256-gcm"` (default) or `"chacha20-poly1305"`
+/// * `aad` - Optional associated data to authenticate but not encrypt
+/// 
+/// # Returns
+/// 32-byte commitment, ciphertext and 16-byte tag as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_committing(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    data: &Uint8Array,
+    algorithm: Option<String>,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    let algorithm = Algorithm::from_name(algorithm.as_deref())?;
+    let nonce_bytes = nonce.to_vec();
+    let committed = Committed::derive(&key.to_vec(), &nonce_bytes)?;
+
+    log!("Committing AEAD: Encrypting {} bytes", data.length());
+
+    let sealed = committed.seal(algorithm, &nonce_bytes, &data.to_vec(), &aad_bytes(aad.as_ref()))?;
+
+    Ok(Uint8Array::from(sealed.as_slice()))
+}
+
+/// Decrypt data sealed by `encrypt_committing`
+/// 
+/// # Arguments
+/// * `key` - 32-byte key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `encrypted_data` - Commitment, ciphertext and tag as Uint8Array
+/// * `algorithm` - `"aes-256-gcm"` (default) or `"chacha20-poly1305"`
+/// * `aad` - Optional associated data the data was sealed with
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or `AuthenticationFailed` if the data isn't
+/// committed to this key or the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_committing(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    algorithm: Option<String>,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    let algorithm = Algorithm::from_name(algorithm.as_deref())?;
+    let nonce_bytes = nonce.to_vec();
+    let committed = Committed::derive(&key.to_vec(), &nonce_bytes)?;
+
+    log!("Committing AEAD: Decrypting {} bytes", encrypted_data.length().saturating_sub((COMMITMENT_LEN + TAG_LEN) as u32));
+
+    let plaintext = committed.open(algorithm, &nonce_bytes, &encrypted_data.to_vec(), &aad_bytes(aad.as_ref()))?;
+
+    log!("Committing AEAD: Successfully decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NONCE: [u8; 12] = [3; 12];
+
+    #[test]
+    fn round_trips_for_both_algorithms() {
+        let committed = Committed::derive(&[1; 32], &NONCE).unwrap();
+        for algorithm in [Algorithm::Aes256Gcm, Algorithm::ChaCha20Poly1305] {
+            let sealed = committed.seal(algorithm, &NONCE, b"share body", b"recipient 1").unwrap();
+            assert_eq!(sealed.len(), COMMITMENT_LEN + 10 + TAG_LEN);
+            assert_eq!(committed.open(algorithm, &NONCE, &sealed, b"recipient 1").unwrap(), b"share body");
+            assert!(matches!(committed.open(algorithm, &NONCE, &sealed, b"recipient 2"), Err(CryptoError::AuthenticationFailed(_))));
+        }
+    }
+
+    #[test]
+    fn matches_the_hkdf_derivation() {
+        let key = [0x5a; 32];
+        let mut okm = [0u8; 64];
+        hkdf_sha256(&key, None, &[INFO, &NONCE[..]].concat(), &mut okm).unwrap();
+
+        let sealed = Committed::derive(&key, &NONCE).unwrap().seal(Algorithm::Aes256Gcm, &NONCE, b"data", b"").unwrap();
+        assert_eq!(sealed[..32], okm[32..]);
+        let plain = Aes256Gcm::new_from_slice(&okm[..32]).unwrap().encrypt((&NONCE).into(), &b"data"[..]).unwrap();
+        assert_eq!(sealed[32..], plain);
+    }
+
+    #[test]
+    fn another_key_is_rejected_by_the_commitment() {
+        let sealed = Committed::derive(&[1; 32], &NONCE).unwrap().seal(Algorithm::Aes256Gcm, &NONCE, b"data", b"").unwrap();
+        match Committed::derive(&[2; 32], &NONCE).unwrap().open(Algorithm::Aes256Gcm, &NONCE, &sealed, b"") {
+            Err(CryptoError::AuthenticationFailed(message)) => assert!(message.contains("not committed"), "{}", message),
+            other => panic!("expected AuthenticationFailed, got {:?}", other),
+        }
+        assert!(matches!(Committed::derive(&[1; 16], &NONCE), Err(CryptoError::InvalidKeyLength(_))));
+        assert!(matches!(Committed::derive(&[1; 32], &NONCE).unwrap().open(Algorithm::Aes256Gcm, &NONCE, &[0; 47], b""), Err(CryptoError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,141 @@
+//! Chunked authenticated container format
+//! 
+//! Splits a file into independently sealed AEAD chunks so a download can be
+//! verified and decrypted chunk by chunk while still detecting truncation,
+//! reordering and chunks spliced in from another file.
+//! 
+//! The layout and the reader and writer live in `furl_core::container`; this
+//! module wraps them for JS.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use furl_core::container::{Algorithm, ContainerReader, ContainerWriter, DEFAULT_CHUNK_SIZE};
+
+/// Encrypt data into the chunked container format in one call
+/// 
+/// # Arguments
+/// * `key` - 32-byte key as Uint8Array
+/// * `base_nonce` - 12-byte random nonce as Uint8Array (never reuse with the same key)
+/// * `data` - Plaintext as Uint8Array
+/// * `chunk_size` - Plaintext bytes per chunk (default: 64KB, max 16MB)
+/// * `algorithm` - `"aes-256-gcm"` (default) or `"chacha20-poly1305"`
+/// 
+/// # Returns
+/// Container bytes as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_container(
+    key: &Uint8Array,
+    base_nonce: &Uint8Array,
+    data: &Uint8Array,
+    chunk_size: Option<u32>,
+    algorithm: Option<String>
+) -> Result<Uint8Array, JsValue> {
+    let algorithm = Algorithm::from_name(algorithm.as_deref())?;
+    let mut writer = ContainerWriter::new(&key.to_vec(), &base_nonce.to_vec(), chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE), algorithm)?;
+
+    let mut out = writer.update(&data.to_vec())?;
+    out.extend_from_slice(&writer.finish()?);
+
+    log!("Container: Encrypted {} bytes into {} container bytes", data.length(), out.len());
+
+    Ok(Uint8Array::from(out.as_slice()))
+}
+
+/// Decrypt and verify a complete container in one call
+/// 
+/// # Arguments
+/// * `key` - 32-byte key as Uint8Array
+/// * `container` - Container bytes as Uint8Array
+/// 
+/// # Returns
+/// Plaintext as Uint8Array, or an error if any chunk fails authentication
+/// or the container is truncated
+#[wasm_bindgen]
+pub fn decrypt_container(key: &Uint8Array, container: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let mut reader = ContainerReader::new(&key.to_vec())?;
+
+    let plaintext = reader.update(&container.to_vec())?;
+    reader.finish()?;
+
+    log!("Container: Decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// Streaming container encryptor for uploads
+/// 
+/// `update` returns container bytes (header first) for each completed chunk;
+/// `finalize` seals whatever is left as the final chunk.
+#[wasm_bindgen]
+pub struct ContainerEncryptor {
+    writer: ContainerWriter,
+}
+
+#[wasm_bindgen]
+impl ContainerEncryptor {
+    /// Create an encryptor
+    /// 
+    /// # Arguments
+    /// * `key` - 32-byte key as Uint8Array
+    /// * `base_nonce` - 12-byte random nonce as Uint8Array (never reuse with the same key)
+    /// * `chunk_size` - Plaintext bytes per chunk (default: 64KB, max 16MB)
+    /// * `algorithm` - `"aes-256-gcm"` (default) or `"chacha20-poly1305"`
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        key: &Uint8Array,
+        base_nonce: &Uint8Array,
+        chunk_size: Option<u32>,
+        algorithm: Option<String>
+    ) -> Result<ContainerEncryptor, JsValue> {
+        let algorithm = Algorithm::from_name(algorithm.as_deref())?;
+        let writer = ContainerWriter::new(&key.to_vec(), &base_nonce.to_vec(), chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE), algorithm)?;
+
+        Ok(ContainerEncryptor { writer })
+    }
+
+    /// Add plaintext; returns container bytes that are ready to upload
+    pub fn update(&mut self, data: &Uint8Array) -> Result<Uint8Array, JsValue> {
+        let out = self.writer.update(&data.to_vec())?;
+        Ok(Uint8Array::from(out.as_slice()))
+    }
+
+    /// Seal the final chunk and return the last container bytes
+    pub fn finalize(&mut self) -> Result<Uint8Array, JsValue> {
+        let out = self.writer.finish()?;
+        Ok(Uint8Array::from(out.as_slice()))
+    }
+}
+
+/// Streaming container decryptor for downloads
+/// 
+/// Feed container bytes in any split to `update`; plaintext is returned only
+/// for chunks that have been fully received and authenticated. `finalize`
+/// fails if the final chunk never arrived.
+#[wasm_bindgen]
+pub struct ContainerDecryptor {
+    reader: ContainerReader,
+}
+
+#[wasm_bindgen]
+impl ContainerDecryptor {
+    /// Create a decryptor from a 32-byte key
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array) -> Result<ContainerDecryptor, JsValue> {
+        Ok(ContainerDecryptor {
+            reader: ContainerReader::new(&key.to_vec())?,
+        })
+    }
+
+    /// Add container bytes; returns newly authenticated plaintext
+    pub fn update(&mut self, data: &Uint8Array) -> Result<Uint8Array, JsValue> {
+        let out = self.reader.update(&data.to_vec())?;
+        Ok(Uint8Array::from(out.as_slice()))
+    }
+
+    /// Finish the stream, failing if it was truncated
+    pub fn finalize(&mut self) -> Result<(), JsValue> {
+        self.reader.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+//! Expanded keys reused across many small decrypts
+//! 
+//! Each `decrypt_aes_ctr` or `decrypt_aes_gcm` call runs the AES key
+//! schedule again, which for hundreds of thumbnail-sized chunks under one key
+//! costs more than the decryption itself. A `CipherContext` expands the key
+//! once (and sets up GHASH for 32-byte keys); each call then copies the
+//! expanded schedule instead of deriving it.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use furl_core::ctr::AesKey;
+
+use crate::batch::decrypt_batch_with_key;
+use crate::counter::ctr_options_from_js;
+use crate::error::CryptoError;
+use crate::keyhandle::{check_nonce, KeyHandle};
+use crate::{aad_bytes, metrics, staging, AesCtr};
+
+/// An AES key expanded once for repeated AES-CTR and AES-GCM calls
+/// 
+/// ```javascript
+/// const context = new CipherContext(key);
+/// const thumbnails = chunks.map(({ iv, data }) => context.decrypt_aes_ctr(iv, data));
+/// context.free();
+/// ```
+/// 
+/// Call `free()` when done; the key schedule is zeroed as it is released.
+#[wasm_bindgen]
+pub struct CipherContext {
+    key: AesKey,
+    /// Only for 32-byte keys
+    gcm: Option<Box<Aes256Gcm>>,
+}
+
+#[wasm_bindgen]
+impl CipherContext {
+    /// Expand a 16, 24 or 32-byte AES key
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array) -> Result<CipherContext, JsValue> {
+        Ok(CipherContext::from_bytes(&key.to_vec())?)
+    }
+
+    /// Expand the key held in a KeyHandle
+    pub fn from_handle(key: &KeyHandle) -> Result<CipherContext, JsValue> {
+        Ok(CipherContext::from_bytes(key.bytes())?)
+    }
+
+    /// Key size in bytes
+    #[wasm_bindgen(getter)]
+    pub fn key_length(&self) -> u32 {
+        self.key.key_length() as u32
+    }
+
+    /// `decrypt_aes_ctr` with this context's key
+    /// 
+    /// # Arguments
+    /// * `iv` - 16-byte initialization vector as Uint8Array
+    /// * `encrypted_data` - Encrypted data as Uint8Array
+    /// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+    /// 
+    /// # Returns
+    /// Decrypted data as Uint8Array
+    pub fn decrypt_aes_ctr(&self, iv: &Uint8Array, encrypted_data: &Uint8Array, options: Option<js_sys::Object>) -> Result<Uint8Array, JsValue> {
+        self.decrypt_aes_ctr_range(iv, encrypted_data, 0, options)
+    }
+
+    /// `decrypt_aes_ctr_range` with this context's key
+    /// 
+    /// # Arguments
+    /// * `iv` - 16-byte initialization vector of the whole file as Uint8Array
+    /// * `ciphertext_slice` - Ciphertext bytes starting at `start_offset`
+    /// * `start_offset` - Byte offset of the slice within the whole ciphertext (BigInt)
+    /// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+    /// 
+    /// # Returns
+    /// Decrypted slice as Uint8Array
+    pub fn decrypt_aes_ctr_range(
+        &self,
+        iv: &Uint8Array,
+        ciphertext_slice: &Uint8Array,
+        start_offset: u64,
+        options: Option<js_sys::Object>
+    ) -> Result<Uint8Array, JsValue> {
+        let mut cipher = AesCtr::from_key(&self.key, &iv.to_vec(), &ctr_options_from_js(options.as_ref())?)?;
+        cipher.seek(start_offset)?;
+
+        metrics::operation();
+
+        let plaintext = staging::with_staged(ciphertext_slice, |data| {
+            metrics::cipher(data.len(), || cipher.apply_keystream(data)).map(|()| metrics::copy(|| Uint8Array::from(&*data)))
+        })??;
+
+        Ok(plaintext)
+    }
+
+    /// `decrypt_batch` with this context's key
+    /// 
+    /// # Arguments
+    /// * `items` - Array of `{ iv: Uint8Array, data: Uint8Array }` objects
+    /// * `options` - Optional `{ counter_width, rfc3686 }` object applied to every item
+    /// 
+    /// # Returns
+    /// Array of decrypted Uint8Arrays, in the order of `items`
+    pub fn decrypt_batch(&self, items: &js_sys::Array, options: Option<js_sys::Object>) -> Result<js_sys::Array, JsValue> {
+        decrypt_batch_with_key(&self.key, items, options.as_ref())
+    }
+
+    /// `decrypt_aes_gcm` with this context's key, which must be 32 bytes
+    /// 
+    /// # Arguments
+    /// * `nonce` - 12-byte GCM nonce as Uint8Array
+    /// * `encrypted_data` - Ciphertext followed by the 16-byte authentication tag
+    /// * `aad` - Optional associated data the tag was computed over
+    /// 
+    /// # Returns
+    /// Decrypted data as Uint8Array, or an error if the tag does not verify
+    pub fn decrypt_aes_gcm(&self, nonce: &Uint8Array, encrypted_data: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+        let cipher = self.gcm.as_ref().ok_or_else(|| {
+            CryptoError::InvalidKeyLength(format!("Invalid key size: AES-GCM needs a 32-byte key, this context holds {} bytes", self.key.key_length()))
+        })?;
+        let nonce = check_nonce(nonce, 12, "nonce")?;
+        if encrypted_data.length() < 16 {
+            return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", encrypted_data.length())).into());
+        }
+
+        let plaintext = cipher
+            .decrypt(aes_gcm::Nonce::from_slice(&nonce), Payload { msg: &encrypted_data.to_vec(), aad: &aad_bytes(aad.as_ref()) })
+            .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))?;
+
+        Ok(Uint8Array::from(plaintext.as_slice()))
+    }
+}
+
+impl CipherContext {
+    fn from_bytes(key: &[u8]) -> Result<CipherContext, CryptoError> {
+        let expanded = AesKey::new(key)?;
+        let gcm = match key.len() {
+            32 => Some(Box::new(Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?)),
+            _ => None,
+        };
+
+        log!("CipherContext: Expanded {}-byte key", key.len());
+
+        Ok(CipherContext { key: expanded, gcm })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::aead::AeadInPlace;
+
+    #[test]
+    fn one_context_serves_many_ivs() {
+        let key = [0x42u8; 32];
+        let context = CipherContext::from_bytes(&key).unwrap();
+
+        for seed in 0..4u8 {
+            let iv = [seed; 16];
+            let mut expected = vec![seed; 100];
+            AesCtr::new(&key, &iv).unwrap().apply_keystream(&mut expected).unwrap();
+
+            let mut actual = vec![seed; 100];
+            AesCtr::from_key(&context.key, &iv, &Default::default()).unwrap().apply_keystream(&mut actual).unwrap();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn gcm_is_set_up_only_for_256_bit_keys() {
+        let context = CipherContext::from_bytes(&[0u8; 32]).unwrap();
+        let mut data = vec![0u8; 16];
+        let tag = context.gcm.as_ref().unwrap().encrypt_in_place_detached(&[0u8; 12].into(), b"", &mut data).unwrap();
+        // McGrew & Viega test case 14
+        assert_eq!(tag[..4], [0xd0, 0xd1, 0xc8, 0xa7]);
+
+        assert!(CipherContext::from_bytes(&[0u8; 16]).unwrap().gcm.is_none());
+        assert!(matches!(CipherContext::from_bytes(&[0u8; 15]), Err(CryptoError::InvalidKeyLength(_))));
+    }
+}
@@ -0,0 +1,44 @@
+//! AES-CTR counter block options from JS
+//! 
+//! Counter widths and the RFC 3686 layout are explained in `furl_core::ctr`.
+//! AES-CTR functions that take an `options` object read:
+//! 
+//! * `counter_width` - 32, 64 or 128 bits (default 128), see `CounterWidth`
+//! * `rfc3686` - `true` to pass `nonce || IV` (12 bytes) as the IV and use
+//!   the RFC 3686 counter block; implies a 32-bit counter
+
+pub use furl_core::ctr::CounterWidth;
+pub(crate) use furl_core::ctr::CtrOptions;
+
+use crate::error::CryptoError;
+
+/// Parse AES-CTR settings from an optional JS `options` object
+pub(crate) fn ctr_options_from_js(options: Option<&js_sys::Object>) -> Result<CtrOptions, CryptoError> {
+    let mut parsed = CtrOptions::default();
+    let Some(options) = options else {
+        return Ok(parsed);
+    };
+
+    let width = js_sys::Reflect::get(options, &"counter_width".into())
+        .map_err(|_| CryptoError::InvalidInput("Invalid options: counter_width could not be read".into()))?;
+    let width = if width.is_undefined() {
+        None
+    } else {
+        let bits = width
+            .as_f64()
+            .ok_or_else(|| CryptoError::InvalidInput("Invalid counter_width: expected a number".into()))?;
+        Some(CounterWidth::from_bits(bits)?)
+    };
+
+    let rfc3686 = js_sys::Reflect::get(options, &"rfc3686".into())
+        .map_err(|_| CryptoError::InvalidInput("Invalid options: rfc3686 could not be read".into()))?;
+    parsed.rfc3686 = rfc3686.is_truthy();
+
+    parsed.counter_width = match (parsed.rfc3686, width) {
+        (true, None | Some(CounterWidth::Bits32)) => CounterWidth::Bits32,
+        (true, Some(_)) => return Err(CryptoError::InvalidInput("Invalid counter_width: RFC 3686 uses a 32-bit counter".into())),
+        (false, width) => width.unwrap_or(CounterWidth::Bits128),
+    };
+
+    Ok(parsed)
+}
@@ -0,0 +1,252 @@
+//! Streaming decompression of decrypted output
+//! 
+//! The uploader may compress a file before encrypting it. The streaming
+//! decrypt functions take `options.decompress` and inflate the plaintext
+//! chunk by chunk as it is decrypted, so JS only ever sees the original file
+//! and the compressed form is never buffered whole. Format names follow the
+//! Web `CompressionStream` API:
+//! 
+//! * `"gzip"` - RFC 1952, including several concatenated members
+//! * `"deflate"` - zlib-wrapped DEFLATE (RFC 1950)
+//! * `"deflate-raw"` - bare DEFLATE (RFC 1951)
+//! * `"br"` - Brotli (RFC 7932), as in `Content-Encoding: br`; much smaller
+//!   than gzip for text-heavy shares such as JSON or CSV dumps
+
+use std::io::Write;
+use flate2::{Decompress, FlushDecompress, Status};
+use flate2::write::MultiGzDecoder;
+use brotli_decompressor::DecompressorWriter;
+
+use crate::error::CryptoError;
+
+/// Compression applied to the plaintext before it was encrypted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Compression {
+    Gzip,
+    Deflate,
+    DeflateRaw,
+    Brotli,
+}
+
+impl Compression {
+    fn from_name(name: &str) -> Result<Self, CryptoError> {
+        match name {
+            "gzip" => Ok(Compression::Gzip),
+            "deflate" => Ok(Compression::Deflate),
+            "deflate-raw" => Ok(Compression::DeflateRaw),
+            "br" => Ok(Compression::Brotli),
+            other => Err(CryptoError::Unsupported(format!("Unsupported decompress format {:?}: expected \"gzip\", \"deflate\", \"deflate-raw\" or \"br\"", other))),
+        }
+    }
+
+    /// Read `decompress` from an optional JS `options` object; `None` if absent
+    pub(crate) fn from_js(options: Option<&js_sys::Object>) -> Result<Option<Self>, CryptoError> {
+        let Some(options) = options else {
+            return Ok(None);
+        };
+
+        let format = js_sys::Reflect::get(options, &"decompress".into())
+            .map_err(|_| CryptoError::InvalidInput("Invalid options: decompress could not be read".into()))?;
+        if format.is_undefined() || format.is_null() {
+            return Ok(None);
+        }
+
+        let name = format
+            .as_string()
+            .ok_or_else(|| CryptoError::InvalidInput("Invalid decompress: expected a format name".into()))?;
+        Compression::from_name(&name).map(Some)
+    }
+}
+
+/// zlib or raw DEFLATE, tracking where the stream ends
+/// 
+/// flate2's `write` decoders for these formats accept a stream that stops
+/// part-way through, so a truncated download would pass unnoticed.
+struct Inflater {
+    state: Decompress,
+    output: Vec<u8>,
+    ended: bool,
+}
+
+impl Inflater {
+    fn write(&mut self, mut data: &[u8]) -> Result<(), CryptoError> {
+        while !data.is_empty() {
+            if self.ended {
+                return Err(CryptoError::InvalidFormat("Unexpected data after the end of the compressed stream".into()));
+            }
+
+            self.output.reserve(OUTPUT_RESERVE);
+            let consumed_before = self.state.total_in();
+            let status = self
+                .state
+                .decompress_vec(data, &mut self.output, FlushDecompress::None)
+                .map_err(|e| CryptoError::InvalidFormat(format!("Decompression failed: {}", e)))?;
+            data = &data[(self.state.total_in() - consumed_before) as usize..];
+
+            self.ended = status == Status::StreamEnd;
+        }
+        Ok(())
+    }
+}
+
+/// Spare output capacity offered to each inflate call
+const OUTPUT_RESERVE: usize = 32 * 1024;
+
+/// Size of the Brotli decoder's internal output buffer
+const BROTLI_BUFFER_SIZE: usize = 32 * 1024;
+
+enum Decoder {
+    /// No `decompress` option: chunks pass through unchanged
+    Identity,
+    Gzip(MultiGzDecoder<Vec<u8>>),
+    Inflate(Inflater),
+    Brotli(Box<DecompressorWriter<Vec<u8>>>),
+}
+
+/// Push-based decompressor: feed plaintext chunks in, take inflated bytes out
+pub(crate) struct Decompressor {
+    decoder: Decoder,
+    bytes_in: u64,
+    bytes_out: u64,
+}
+
+impl Decompressor {
+    /// Decompressor for `format`, or a pass-through one for `None`
+    pub(crate) fn new(format: Option<Compression>) -> Self {
+        let inflater = |zlib_header| Decoder::Inflate(Inflater { state: Decompress::new(zlib_header), output: Vec::new(), ended: false });
+        let decoder = match format {
+            None => Decoder::Identity,
+            Some(Compression::Gzip) => Decoder::Gzip(MultiGzDecoder::new(Vec::new())),
+            Some(Compression::Deflate) => inflater(true),
+            Some(Compression::DeflateRaw) => inflater(false),
+            Some(Compression::Brotli) => Decoder::Brotli(Box::new(DecompressorWriter::new(Vec::new(), BROTLI_BUFFER_SIZE))),
+        };
+
+        Decompressor { decoder, bytes_in: 0, bytes_out: 0 }
+    }
+
+    /// Inflate the next chunk, returning whatever output it completes (possibly nothing)
+    pub(crate) fn update(&mut self, data: Vec<u8>) -> Result<Vec<u8>, CryptoError> {
+        match &mut self.decoder {
+            Decoder::Identity => return Ok(data),
+            Decoder::Gzip(decoder) => decoder
+                .write_all(&data)
+                .map_err(|e| CryptoError::InvalidFormat(format!("Decompression failed after {} compressed bytes: {}", self.bytes_in, e)))?,
+            Decoder::Inflate(inflater) => inflater.write(&data)?,
+            Decoder::Brotli(decoder) => decoder
+                .write_all(&data)
+                .map_err(|e| CryptoError::InvalidFormat(format!("Brotli decompression failed after {} compressed bytes: {}", self.bytes_in, e)))?,
+        }
+        self.bytes_in += data.len() as u64;
+
+        Ok(self.take_output())
+    }
+
+    /// Flush the end of the stream, failing if it was cut short
+    pub(crate) fn finish(mut self) -> Result<Vec<u8>, CryptoError> {
+        let complete = match &mut self.decoder {
+            Decoder::Identity => return Ok(Vec::new()),
+            Decoder::Gzip(decoder) => decoder.try_finish().is_ok(),
+            Decoder::Inflate(inflater) => inflater.ended,
+            Decoder::Brotli(decoder) => decoder.close().is_ok(),
+        };
+        if !complete {
+            return Err(CryptoError::Truncated(format!("Compressed stream ended early after {} bytes", self.bytes_in)));
+        }
+
+        let output = self.take_output();
+        log!("Decompress: Inflated {} bytes to {}", self.bytes_in, self.bytes_out);
+
+        Ok(output)
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        let output = std::mem::take(match &mut self.decoder {
+            Decoder::Identity => return Vec::new(),
+            Decoder::Gzip(decoder) => decoder.get_mut(),
+            Decoder::Inflate(inflater) => &mut inflater.output,
+            Decoder::Brotli(decoder) => decoder.get_mut(),
+        });
+        self.bytes_out += output.len() as u64;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+
+    fn compress(format: Compression, data: &[u8]) -> Vec<u8> {
+        let level = flate2::Compression::default();
+        match format {
+            Compression::Gzip => { let mut encoder = GzEncoder::new(Vec::new(), level); encoder.write_all(data).unwrap(); encoder.finish().unwrap() }
+            Compression::Deflate => { let mut encoder = ZlibEncoder::new(Vec::new(), level); encoder.write_all(data).unwrap(); encoder.finish().unwrap() }
+            Compression::DeflateRaw => { let mut encoder = DeflateEncoder::new(Vec::new(), level); encoder.write_all(data).unwrap(); encoder.finish().unwrap() }
+            Compression::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 9, 22);
+                encoder.write_all(data).unwrap();
+                encoder.into_inner()
+            }
+        }
+    }
+
+    fn inflate_in_chunks(format: Compression, data: &[u8], chunk_size: usize) -> Result<Vec<u8>, CryptoError> {
+        let mut decompressor = Decompressor::new(Some(format));
+        let mut output = Vec::new();
+        for chunk in data.chunks(chunk_size) {
+            output.extend(decompressor.update(chunk.to_vec())?);
+        }
+        output.extend(decompressor.finish()?);
+        Ok(output)
+    }
+
+    #[test]
+    fn every_format_round_trips_at_any_chunk_size() {
+        let original: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8 ^ (i / 1000) as u8).collect();
+
+        for format in [Compression::Gzip, Compression::Deflate, Compression::DeflateRaw, Compression::Brotli] {
+            let compressed = compress(format, &original);
+            for chunk_size in [1, 7, 1000, compressed.len()] {
+                assert_eq!(inflate_in_chunks(format, &compressed, chunk_size).unwrap(), original, "{:?} in {} byte chunks", format, chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn concatenated_gzip_members_are_joined() {
+        let mut compressed = compress(Compression::Gzip, b"first member, ");
+        compressed.extend(compress(Compression::Gzip, b"second member"));
+
+        assert_eq!(inflate_in_chunks(Compression::Gzip, &compressed, 5).unwrap(), b"first member, second member");
+    }
+
+    #[test]
+    fn truncated_and_corrupted_streams_are_rejected() {
+        let original = vec![b'x'; 10_000];
+
+        for format in [Compression::Gzip, Compression::Deflate, Compression::DeflateRaw, Compression::Brotli] {
+            let compressed = compress(format, &original);
+            let truncated = inflate_in_chunks(format, &compressed[..compressed.len() - 3], 64).unwrap_err();
+            assert_eq!(truncated.name(), "Truncated", "{:?}", format);
+        }
+
+        let mut corrupted = compress(Compression::Gzip, &original);
+        corrupted[0] ^= 0xff;
+        assert_eq!(inflate_in_chunks(Compression::Gzip, &corrupted, 64).unwrap_err().name(), "InvalidFormat");
+
+        let mut trailing = compress(Compression::Deflate, &original);
+        trailing.push(0);
+        assert_eq!(inflate_in_chunks(Compression::Deflate, &trailing, 64).unwrap_err().name(), "InvalidFormat");
+
+        let mut trailing = compress(Compression::Brotli, &original);
+        trailing.push(0);
+        assert_eq!(inflate_in_chunks(Compression::Brotli, &trailing, 64).unwrap_err().name(), "InvalidFormat");
+
+        assert_eq!(Compression::from_name("zstd").unwrap_err().name(), "Unsupported");
+
+        let mut identity = Decompressor::new(None);
+        assert_eq!(identity.update(original.clone()).unwrap(), original);
+        assert!(identity.finish().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Decoding keys and IVs from text
+//! 
+//! The key and IV in a furl link fragment are base64 text. These helpers take
+//! standard or URL-safe base64 (padded or not) and hex, and check the decoded
+//! length, so pages don't need their own `atob` and length checks.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+
+use crate::error::CryptoError;
+
+pub(crate) use furl_core::encoding::{decode_base64, decode_hex};
+
+/// Lowercase hex, as manifests carry their hashes
+pub(crate) fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Reject decoded key material of the wrong length
+fn check_length(bytes: Vec<u8>, expected_length: Option<u32>) -> Result<Vec<u8>, CryptoError> {
+    match expected_length {
+        Some(expected) if bytes.len() != expected as usize => Err(CryptoError::InvalidKeyLength(format!(
+            "Invalid key material size: expected {} bytes, got {}",
+            expected,
+            bytes.len()
+        ))),
+        _ => Ok(bytes),
+    }
+}
+
+/// Decode a base64 key or IV, e.g. from a link fragment
+/// 
+/// # Arguments
+/// * `text` - Standard or URL-safe base64, padding optional
+/// * `expected_length` - Optional decoded size in bytes to enforce (e.g. 32 for an AES-256 key)
+/// 
+/// # Returns
+/// Decoded bytes as Uint8Array
+#[wasm_bindgen]
+pub fn key_from_base64(text: &str, expected_length: Option<u32>) -> Result<Uint8Array, JsValue> {
+    let bytes = check_length(decode_base64(text)?, expected_length)?;
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+/// Decode a hex key or IV
+/// 
+/// # Arguments
+/// * `text` - Hex digits in either case
+/// * `expected_length` - Optional decoded size in bytes to enforce
+/// 
+/// # Returns
+/// Decoded bytes as Uint8Array
+#[wasm_bindgen]
+pub fn key_from_hex(text: &str, expected_length: Option<u32>) -> Result<Uint8Array, JsValue> {
+    let bytes = check_length(decode_hex(text)?, expected_length)?;
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+/// Encode bytes as base64 for building links
+/// 
+/// # Arguments
+/// * `data` - Bytes to encode as Uint8Array
+/// * `url_safe` - Use the URL-safe alphabet without padding (default false)
+/// 
+/// # Returns
+/// Base64 text
+#[wasm_bindgen]
+pub fn key_to_base64(data: &Uint8Array, url_safe: Option<bool>) -> String {
+    if url_safe.unwrap_or(false) {
+        URL_SAFE_NO_PAD.encode(data.to_vec())
+    } else {
+        STANDARD.encode(data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_accepts_both_alphabets_with_or_without_padding() {
+        let bytes = vec![0xfb, 0xff, 0xbf, 0x00];
+        for text in ["+/+/AA==", "+/+/AA", "-_-_AA", "-_-_AA==", "  +/+/AA==\n"] {
+            assert_eq!(decode_base64(text).unwrap(), bytes, "{:?}", text);
+        }
+        assert!(decode_base64("+/-_").is_err());
+        assert!(decode_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn hex_decodes_either_case_and_rejects_bad_digits() {
+        assert_eq!(decode_hex("00ff7Fa0").unwrap(), vec![0x00, 0xff, 0x7f, 0xa0]);
+        assert_eq!(decode_hex("").unwrap(), Vec::<u8>::new());
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("zz").is_err());
+        assert_eq!(encode_hex(&[0x00, 0xff, 0x7f, 0xa0]), "00ff7fa0");
+    }
+
+    #[test]
+    fn expected_length_is_enforced() {
+        assert!(check_length(vec![0; 32], Some(32)).is_ok());
+        assert!(check_length(vec![0; 32], None).is_ok());
+        assert_eq!(check_length(vec![0; 31], Some(32)).unwrap_err().name(), "InvalidKeyLength");
+    }
+}
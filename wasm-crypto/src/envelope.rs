@@ -0,0 +1,271 @@
+//! Multi-recipient envelopes
+//! 
+//! A file shared with several people carries its file key wrapped once per
+//! recipient. The envelope header comes before the body:
+//! 
+//! ```text
+//! magic      4 bytes   "FENV"
+//! version    1 byte    1
+//! count      1 byte    number of recipient stanzas (at least 1)
+//! 
+//! stanza (repeated `count` times)
+//!   type     1 byte    1 = RSA-OAEP-SHA256, 2 = X25519 sealed box
+//!   key_id   8 bytes   first 8 bytes of SHA-256 of the recipient public key
+//!                      (SubjectPublicKeyInfo DER for RSA, raw 32 bytes for X25519)
+//!   length   2 bytes   big-endian body length
+//!   body     `length` bytes   wrapped file key
+//! ```
+//! 
+//! The reader computes the key id of the supplied private key, so only the
+//! stanzas addressed to it are tried.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use rsa::{Oaep, RsaPrivateKey};
+use rsa::pkcs8::EncodePublicKey;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::error::CryptoError;
+use crate::keywrap::{parse_private_key, rsa_oaep_decrypt};
+use crate::x25519::unseal;
+
+const MAGIC: &[u8; 4] = b"FENV";
+const VERSION: u8 = 1;
+const PREAMBLE_LEN: usize = 6;
+const STANZA_HEADER_LEN: usize = 11;
+
+const TYPE_RSA_OAEP_SHA256: u8 = 1;
+const TYPE_X25519: u8 = 2;
+
+/// Errors produced while opening an envelope
+#[derive(Debug, PartialEq)]
+pub(crate) enum EnvelopeError {
+    BadHeader(&'static str),
+    NoMatchingRecipient,
+    UnwrapFailed,
+}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnvelopeError::BadHeader(reason) => write!(f, "Invalid envelope header: {}", reason),
+            EnvelopeError::NoMatchingRecipient => write!(f, "No recipient stanza matches this private key"),
+            EnvelopeError::UnwrapFailed => write!(f, "Key unwrapping failed: corrupted recipient stanza"),
+        }
+    }
+}
+
+impl From<EnvelopeError> for CryptoError {
+    fn from(error: EnvelopeError) -> CryptoError {
+        let message = error.to_string();
+        match error {
+            EnvelopeError::BadHeader(_) => CryptoError::InvalidFormat(message),
+            EnvelopeError::NoMatchingRecipient => CryptoError::InvalidKey(message),
+            EnvelopeError::UnwrapFailed => CryptoError::AuthenticationFailed(message),
+        }
+    }
+}
+
+impl From<EnvelopeError> for JsValue {
+    fn from(error: EnvelopeError) -> JsValue {
+        CryptoError::from(error).into()
+    }
+}
+
+/// One recipient entry in the header
+struct Stanza<'a> {
+    kind: u8,
+    key_id: &'a [u8],
+    body: &'a [u8],
+}
+
+/// Private key of the recipient opening the envelope
+pub(crate) enum RecipientKey {
+    Rsa(Box<RsaPrivateKey>),
+    X25519([u8; 32]),
+}
+
+impl RecipientKey {
+    /// 32 bytes is an X25519 secret key; anything else is parsed as RSA DER
+    fn from_bytes(private_key: &[u8]) -> Result<Self, CryptoError> {
+        match <[u8; 32]>::try_from(private_key) {
+            Ok(secret) => Ok(RecipientKey::X25519(secret)),
+            Err(_) => Ok(RecipientKey::Rsa(Box::new(parse_private_key(private_key)?))),
+        }
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            RecipientKey::Rsa(_) => TYPE_RSA_OAEP_SHA256,
+            RecipientKey::X25519(_) => TYPE_X25519,
+        }
+    }
+
+    fn key_id(&self) -> Result<[u8; 8], EnvelopeError> {
+        let digest = match self {
+            RecipientKey::Rsa(key) => {
+                let spki = key
+                    .to_public_key()
+                    .to_public_key_der()
+                    .map_err(|_| EnvelopeError::NoMatchingRecipient)?;
+                Sha256::digest(spki.as_bytes())
+            }
+            RecipientKey::X25519(secret) => {
+                let public = x25519_dalek::PublicKey::from(&x25519_dalek::StaticSecret::from(*secret));
+                Sha256::digest(public.as_bytes())
+            }
+        };
+
+        let mut key_id = [0u8; 8];
+        key_id.copy_from_slice(&digest[..8]);
+        Ok(key_id)
+    }
+
+    fn unwrap(&self, body: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            RecipientKey::Rsa(key) => rsa_oaep_decrypt(key, Oaep::new::<Sha256>(), body),
+            RecipientKey::X25519(secret) => unseal(*secret, body),
+        }
+    }
+}
+
+/// Split the header into stanzas, returning them with the header length
+fn parse_stanzas(header: &[u8]) -> Result<(Vec<Stanza<'_>>, usize), EnvelopeError> {
+    if header.len() < PREAMBLE_LEN {
+        return Err(EnvelopeError::BadHeader("too short"));
+    }
+    if &header[..4] != MAGIC {
+        return Err(EnvelopeError::BadHeader("not a furl envelope"));
+    }
+    if header[4] != VERSION {
+        return Err(EnvelopeError::BadHeader("unsupported version"));
+    }
+
+    let count = header[5] as usize;
+    if count == 0 {
+        return Err(EnvelopeError::BadHeader("no recipients"));
+    }
+
+    let mut stanzas = Vec::with_capacity(count);
+    let mut offset = PREAMBLE_LEN;
+    for _ in 0..count {
+        let fixed = header
+            .get(offset..offset + STANZA_HEADER_LEN)
+            .ok_or(EnvelopeError::BadHeader("truncated stanza"))?;
+        let length = u16::from_be_bytes([fixed[9], fixed[10]]) as usize;
+
+        let body_start = offset + STANZA_HEADER_LEN;
+        let body = header
+            .get(body_start..body_start + length)
+            .ok_or(EnvelopeError::BadHeader("truncated stanza"))?;
+
+        stanzas.push(Stanza { kind: fixed[0], key_id: &fixed[1..9], body });
+        offset = body_start + length;
+    }
+
+    Ok((stanzas, offset))
+}
+
+/// Find the stanza addressed to `key` and unwrap the file key from it
+pub(crate) fn open(header: &[u8], key: &RecipientKey) -> Result<(Vec<u8>, usize), EnvelopeError> {
+    let (stanzas, header_length) = parse_stanzas(header)?;
+    let key_id = key.key_id()?;
+
+    let mut matched = false;
+    for stanza in stanzas.iter().filter(|s| s.kind == key.kind() && s.key_id == key_id) {
+        matched = true;
+        if let Some(file_key) = key.unwrap(stanza.body) {
+            return Ok((file_key, header_length));
+        }
+    }
+
+    Err(if matched { EnvelopeError::UnwrapFailed } else { EnvelopeError::NoMatchingRecipient })
+}
+
+/// Unwrap the file key from a multi-recipient envelope header
+/// 
+/// # Arguments
+/// * `header` - Envelope bytes as Uint8Array (may include the body that follows)
+/// * `private_key` - 32-byte X25519 secret key, or an RSA private key as PKCS#8 or PKCS#1 DER
+/// 
+/// # Returns
+/// Object with:
+/// * `file_key` - Unwrapped content key as Uint8Array
+/// * `header_length` - Envelope length in bytes; the body starts here
+#[wasm_bindgen]
+pub fn open_envelope(header: &Uint8Array, private_key: &Uint8Array) -> Result<js_sys::Object, JsValue> {
+    let key = RecipientKey::from_bytes(&private_key.to_vec())?;
+    let (file_key, header_length) = open(&header.to_vec(), &key).map_err(CryptoError::from)?;
+
+    log!("Envelope: Unwrapped {}-byte file key from {}-byte header", file_key.len(), header_length);
+
+    let result = js_sys::Object::new();
+    js_sys::Reflect::set(&result, &"file_key".into(), &Uint8Array::from(file_key.as_slice()))?;
+    js_sys::Reflect::set(&result, &"header_length".into(), &(header_length as u32).into())?;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::OsRng;
+
+    fn stanza(kind: u8, key_id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = vec![kind];
+        out.extend_from_slice(key_id);
+        out.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn x25519_stanza(secret: [u8; 32], file_key: &[u8]) -> Vec<u8> {
+        let recipient = crypto_box::SecretKey::from(secret).public_key();
+        let body = recipient.seal(&mut OsRng, file_key).unwrap();
+        let key_id = RecipientKey::X25519(secret).key_id().unwrap();
+        stanza(TYPE_X25519, &key_id, &body)
+    }
+
+    fn envelope(stanzas: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(VERSION);
+        out.push(stanzas.len() as u8);
+        stanzas.iter().for_each(|s| out.extend_from_slice(s));
+        out
+    }
+
+    #[test]
+    fn finds_the_stanza_for_each_recipient() {
+        let file_key = [0x42u8; 32];
+        let (alice, bob) = ([1u8; 32], [2u8; 32]);
+        let rsa_decoy = stanza(TYPE_RSA_OAEP_SHA256, &[9; 8], &[0; 256]);
+
+        let mut header = envelope(&[rsa_decoy, x25519_stanza(alice, &file_key), x25519_stanza(bob, &file_key)]);
+        let header_length = header.len();
+        header.extend_from_slice(b"body follows");
+
+        for secret in [alice, bob] {
+            let (key, length) = open(&header, &RecipientKey::X25519(secret)).unwrap();
+            assert_eq!(key, file_key);
+            assert_eq!(length, header_length);
+        }
+
+        let stranger = RecipientKey::X25519([3u8; 32]);
+        assert_eq!(open(&header, &stranger), Err(EnvelopeError::NoMatchingRecipient));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let alice = RecipientKey::X25519([1u8; 32]);
+        let good = envelope(&[x25519_stanza([1u8; 32], &[0x42; 32])]);
+
+        assert_eq!(open(&good[..good.len() - 1], &alice), Err(EnvelopeError::BadHeader("truncated stanza")));
+        assert_eq!(open(&envelope(&[]), &alice), Err(EnvelopeError::BadHeader("no recipients")));
+        assert_eq!(open(b"NOPE\x01\x01", &alice), Err(EnvelopeError::BadHeader("not a furl envelope")));
+
+        let mut tampered = good.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(open(&tampered, &alice), Err(EnvelopeError::UnwrapFailed));
+    }
+}
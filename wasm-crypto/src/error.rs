@@ -0,0 +1,17 @@
+//! Typed errors crossing the JS boundary
+//! 
+//! Every failure is thrown as a JS `Error` whose `name` is the error kind
+//! (for example `"AuthenticationFailed"`) and whose numeric `code` matches
+//! the exported `CryptoErrorCode` enum, so callers can branch without
+//! parsing messages:
+//! 
+//! ```javascript
+//! try { decrypt_aes_gcm(key, nonce, data); }
+//! catch (e) { if (e.code === CryptoErrorCode.AuthenticationFailed) { ... } }
+//! ```
+//! 
+//! The kinds themselves live in `furl_core::error`, whose `js` feature
+//! provides the conversion to a JS `Error`.
+
+pub use furl_core::error::{CryptoError, CryptoErrorCode};
+pub(crate) use furl_core::error::try_zeroed;
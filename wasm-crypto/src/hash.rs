@@ -0,0 +1,156 @@
+//! Cryptographic hashing for checksum verification while streaming
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::error::CryptoError;
+
+/// Hash state for each supported algorithm
+enum HashState {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    // Boxed: the BLAKE3 chunk stack is much larger than the SHA-2 states
+    Blake3(Box<blake3::Hasher>),
+}
+
+/// Incremental hasher for checksumming data as it streams in
+/// 
+/// ```javascript
+/// const hasher = new Hasher("sha256");
+/// for await (const chunk of chunks) hasher.update(chunk);
+/// const digest = hasher.finalize();
+/// ```
+#[wasm_bindgen]
+pub struct Hasher {
+    state: Option<HashState>,
+    algorithm: String,
+}
+
+#[wasm_bindgen]
+impl Hasher {
+    /// Create a hasher for `"sha256"`, `"sha512"` or `"blake3"`
+    #[wasm_bindgen(constructor)]
+    pub fn new(algorithm: &str) -> Result<Hasher, JsValue> {
+        let state = match algorithm.to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => HashState::Sha256(Sha256::new()),
+            "sha512" | "sha-512" => HashState::Sha512(Sha512::new()),
+            "blake3" => HashState::Blake3(Box::new(blake3::Hasher::new())),
+            other => return Err(CryptoError::Unsupported(format!("Unsupported hash algorithm: {}", other)).into()),
+        };
+
+        Ok(Hasher {
+            state: Some(state),
+            algorithm: algorithm.to_ascii_lowercase(),
+        })
+    }
+
+    /// Create a keyed BLAKE3 hasher, producing a MAC over the streamed data
+    /// 
+    /// # Arguments
+    /// * `key` - 32-byte BLAKE3 key as Uint8Array
+    pub fn blake3_keyed(key: &Uint8Array) -> Result<Hasher, JsValue> {
+        let key = blake3_key(key)?;
+
+        Ok(Hasher {
+            state: Some(HashState::Blake3(Box::new(blake3::Hasher::new_keyed(&key)))),
+            algorithm: "blake3-keyed".to_string(),
+        })
+    }
+
+    /// Feed the next chunk of data into the hash
+    pub fn update(&mut self, data: &Uint8Array) -> Result<(), JsValue> {
+        let data = data.to_vec();
+
+        match self.state.as_mut() {
+            Some(HashState::Sha256(hasher)) => hasher.update(&data),
+            Some(HashState::Sha512(hasher)) => hasher.update(&data),
+            Some(HashState::Blake3(hasher)) => {
+                hasher.update(&data);
+            }
+            None => return Err(CryptoError::InvalidState("Hasher has already been finalized".into()).into()),
+        }
+
+        Ok(())
+    }
+
+    /// Finish hashing and return the digest (32 bytes for SHA-256 and BLAKE3, 64 for SHA-512)
+    pub fn finalize(&mut self) -> Result<Uint8Array, JsValue> {
+        let digest = match self.state.take() {
+            Some(HashState::Sha256(hasher)) => hasher.finalize().to_vec(),
+            Some(HashState::Sha512(hasher)) => hasher.finalize().to_vec(),
+            Some(HashState::Blake3(hasher)) => hasher.finalize().as_bytes().to_vec(),
+            None => return Err(CryptoError::InvalidState("Hasher has already been finalized".into()).into()),
+        };
+
+        Ok(Uint8Array::from(digest.as_slice()))
+    }
+
+    /// Name of the algorithm this hasher was created with
+    #[wasm_bindgen(getter)]
+    pub fn algorithm(&self) -> String {
+        self.algorithm.clone()
+    }
+}
+
+/// Compute the SHA-256 digest of `data` in one call
+#[wasm_bindgen]
+pub fn sha256(data: &Uint8Array) -> Uint8Array {
+    Uint8Array::from(&Sha256::digest(data.to_vec())[..])
+}
+
+/// Compute the SHA-512 digest of `data` in one call
+#[wasm_bindgen]
+pub fn sha512(data: &Uint8Array) -> Uint8Array {
+    Uint8Array::from(&Sha512::digest(data.to_vec())[..])
+}
+
+/// Compute the 32-byte BLAKE3 digest of `data` in one call
+#[wasm_bindgen]
+pub fn blake3_hash(data: &Uint8Array) -> Uint8Array {
+    Uint8Array::from(&blake3::hash(&data.to_vec()).as_bytes()[..])
+}
+
+/// Compute a keyed BLAKE3 hash (a MAC) of `data`
+/// 
+/// # Arguments
+/// * `key` - 32-byte BLAKE3 key as Uint8Array
+/// * `data` - Data to authenticate as Uint8Array
+/// 
+/// # Returns
+/// 32-byte tag as Uint8Array
+#[wasm_bindgen]
+pub fn blake3_keyed_hash(key: &Uint8Array, data: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let key = blake3_key(key)?;
+
+    Ok(Uint8Array::from(&blake3::keyed_hash(&key, &data.to_vec()).as_bytes()[..]))
+}
+
+/// Verify a keyed BLAKE3 tag in constant time
+/// 
+/// # Arguments
+/// * `key` - 32-byte BLAKE3 key as Uint8Array
+/// * `data` - Authenticated data as Uint8Array
+/// * `tag` - Expected 32-byte tag as Uint8Array
+/// 
+/// # Returns
+/// `true` if the tag is valid for `data`
+#[wasm_bindgen]
+pub fn blake3_keyed_verify(key: &Uint8Array, data: &Uint8Array, tag: &Uint8Array) -> Result<bool, JsValue> {
+    let key = blake3_key(key)?;
+
+    let expected: [u8; 32] = tag
+        .to_vec()
+        .try_into()
+        .map_err(|_| CryptoError::InvalidInput(format!("Invalid tag size: expected 32 bytes, got {}", tag.length())))?;
+
+    // blake3::Hash equality is constant-time
+    Ok(blake3::keyed_hash(&key, &data.to_vec()) == blake3::Hash::from(expected))
+}
+
+/// Validate and copy a 32-byte BLAKE3 key
+fn blake3_key(key: &Uint8Array) -> Result<[u8; 32], CryptoError> {
+    key.to_vec()
+        .try_into()
+        .map_err(|_| CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())))
+}
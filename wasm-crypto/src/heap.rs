@@ -0,0 +1,133 @@
+//! Reserving wasm linear memory up front
+//! 
+//! Linear memory starts small and the allocator grows it a few pages at a
+//! time as buffers get bigger, so the first large decrypt pauses on a
+//! series of `memory.grow` calls, each of which may copy the whole heap.
+//! `init({ working_set })` grows memory once to the size the viewer expects
+//! to need; the allocator keeps the reserved space and serves later
+//! buffers from it.
+//! 
+//! When memory can't grow (the browser refuses, or the module's maximum is
+//! reached) a Rust allocation aborts the module with an `unreachable` trap.
+//! `init` itself, and the full-size input copies and plaintext buffers of the
+//! chunked AES-CTR calls, check first and throw `OutOfMemory` instead,
+//! leaving the module usable.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::error::CryptoError;
+
+/// Size of one wasm memory page
+#[cfg(target_arch = "wasm32")]
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// Bytes of linear memory currently allocated to the module, if it runs as wasm
+fn memory_bytes() -> Option<usize> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        Some(core::arch::wasm32::memory_size(0) * PAGE_SIZE)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// Make `bytes` of heap available without growing memory again
+/// 
+/// Allocates and frees one block of that size; the allocator keeps the
+/// freed block, so later allocations that fit are served from it.
+fn reserve(bytes: usize) -> Result<(), CryptoError> {
+    let mut block = Vec::<u8>::new();
+    block.try_reserve_exact(bytes).map_err(|_| {
+        CryptoError::OutOfMemory(format!("Could not reserve {} bytes of wasm memory: the runtime refused to grow it", bytes))
+    })
+}
+
+/// Copy a JS array into a new Vec, failing with `OutOfMemory` instead of trapping
+pub(crate) fn try_to_vec(array: &Uint8Array) -> Result<Vec<u8>, CryptoError> {
+    let len = array.length() as usize;
+    let mut data = Vec::new();
+    data.try_reserve_exact(len).map_err(|_| CryptoError::OutOfMemory(format!("Failed to allocate {} bytes", len)))?;
+    array.copy_to_uninit(&mut data.spare_capacity_mut()[..len]);
+    // SAFETY: `copy_to_uninit` initialised the first `len` bytes
+    unsafe { data.set_len(len) };
+    Ok(data)
+}
+
+/// Read `working_set`, the bytes to reserve, from an optional options object
+fn working_set_from_js(options: Option<&js_sys::Object>) -> Result<usize, CryptoError> {
+    let Some(options) = options else {
+        return Ok(0);
+    };
+    let value = js_sys::Reflect::get(options, &"working_set".into())
+        .map_err(|_| CryptoError::InvalidInput("Invalid options: working_set could not be read".into()))?;
+    if value.is_undefined() || value.is_null() {
+        return Ok(0);
+    }
+    let bytes = value.as_f64().ok_or_else(|| CryptoError::InvalidInput("Invalid working_set: expected a number of bytes".into()))?;
+    parse_working_set(bytes)
+}
+
+/// Check a `working_set` value, which must be a whole number of bytes
+fn parse_working_set(bytes: f64) -> Result<usize, CryptoError> {
+    if !(bytes >= 0.0 && bytes.fract() == 0.0 && bytes <= usize::MAX as f64) {
+        return Err(CryptoError::InvalidInput(format!("Invalid working_set {}: expected a whole number of bytes", bytes)));
+    }
+    Ok(bytes as usize)
+}
+
+/// Reserve wasm memory for the expected working set before decrypting
+/// 
+/// Call once after loading the module, with roughly the most memory one
+/// operation will hold at a time: for a one-shot decrypt about twice the
+/// file size (ciphertext and plaintext), for chunked calls with a
+/// `memory_budget` the budget plus a few chunks. Calling again with a
+/// larger size reserves more; a smaller one changes nothing.
+/// 
+/// ```javascript
+/// await initWasm();
+/// init({ working_set: 2 * file.size });
+/// ```
+/// 
+/// # Arguments
+/// * `options` - Optional `{ working_set }` object; `working_set` is in bytes (default: 0)
+/// 
+/// # Returns
+/// Object with `reserved` (bytes reserved by this call) and `memory_bytes`
+/// (linear memory now allocated to the module). Throws `OutOfMemory` if
+/// memory can't grow that far; the module stays usable with what it has.
+#[wasm_bindgen]
+pub fn init(options: Option<js_sys::Object>) -> Result<js_sys::Object, JsValue> {
+    let working_set = working_set_from_js(options.as_ref())?;
+    reserve(working_set)?;
+
+    log!("Memory: Reserved {} bytes, linear memory is {:?} bytes", working_set, memory_bytes());
+
+    let report = js_sys::Object::new();
+    js_sys::Reflect::set(&report, &"reserved".into(), &(working_set as f64).into())?;
+    js_sys::Reflect::set(&report, &"memory_bytes".into(), &memory_bytes().map_or(JsValue::NULL, |bytes| (bytes as f64).into()))?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn impossible_reservations_fail_gracefully() {
+        reserve(1 << 20).unwrap();
+        assert!(matches!(reserve(usize::MAX), Err(CryptoError::OutOfMemory(_))));
+    }
+
+    #[test]
+    fn working_sets_are_whole_byte_counts() {
+        assert_eq!(parse_working_set(0.0).unwrap(), 0);
+        assert_eq!(parse_working_set(256.0 * 1024.0 * 1024.0).unwrap(), 256 << 20);
+        for bad in [-1.0, 0.5, f64::NAN, f64::INFINITY] {
+            assert!(parse_working_set(bad).is_err(), "{}", bad);
+        }
+    }
+}
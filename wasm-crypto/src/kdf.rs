@@ -0,0 +1,140 @@
+//! Key derivation functions for passphrase-protected links and for
+//! expanding one link secret into independent per-purpose keys
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::error::CryptoError;
+
+pub(crate) use furl_core::kdf::{hkdf_sha256, pbkdf2_sha256};
+
+/// Largest derived key `derive_key_pbkdf2` will produce
+const MAX_DERIVED_KEY_LEN: usize = 1024;
+
+/// HKDF-SHA256 can expand to at most 255 hash blocks
+const MAX_HKDF_LEN: usize = 255 * 32;
+
+/// HKDF `info` labels for the keys derived by `derive_share_keys`
+const CONTENT_KEY_INFO: &[u8] = b"furl v1 content encryption";
+const FILENAME_KEY_INFO: &[u8] = b"furl v1 filename encryption";
+const MAC_KEY_INFO: &[u8] = b"furl v1 mac";
+
+/// Derive a key from a passphrase using PBKDF2-HMAC-SHA256
+/// 
+/// # Arguments
+/// * `passphrase` - Passphrase string (UTF-8 encoded before hashing)
+/// * `salt` - Salt as Uint8Array (at least 8 bytes; 16 random bytes recommended)
+/// * `iterations` - Iteration count (must be at least 1)
+/// * `dklen` - Length of the derived key in bytes (1 to 1024)
+/// * `progress_callback` - Optional callback receiving percent complete, for high iteration counts
+/// 
+/// # Returns
+/// Derived key as Uint8Array
+#[wasm_bindgen]
+pub fn derive_key_pbkdf2(
+    passphrase: &str,
+    salt: &Uint8Array,
+    iterations: u32,
+    dklen: usize,
+    progress_callback: Option<js_sys::Function>
+) -> Result<Uint8Array, JsValue> {
+    // Validate inputs
+    if salt.length() < 8 {
+        return Err(CryptoError::InvalidInput(format!("Invalid salt size: expected at least 8 bytes, got {}", salt.length())).into());
+    }
+
+    if iterations == 0 {
+        return Err(CryptoError::InvalidInput("Invalid iteration count: must be at least 1".into()).into());
+    }
+
+    if dklen == 0 || dklen > MAX_DERIVED_KEY_LEN {
+        return Err(CryptoError::InvalidInput(format!("Invalid derived key length: expected 1 to {} bytes, got {}", MAX_DERIVED_KEY_LEN, dklen)).into());
+    }
+
+    let salt_bytes = salt.to_vec();
+    let mut derived = vec![0u8; dklen];
+
+    log!("PBKDF2: Deriving {} bytes with {} iterations", dklen, iterations);
+
+    pbkdf2_sha256(passphrase.as_bytes(), &salt_bytes, iterations, &mut derived, |progress| {
+        if let Some(ref callback) = progress_callback {
+            let _ = callback.call1(&JsValue::NULL, &JsValue::from(progress));
+        }
+    });
+
+    Ok(Uint8Array::from(derived.as_slice()))
+}
+
+/// Derive key material with HKDF-SHA256 (RFC 5869)
+/// 
+/// # Arguments
+/// * `ikm` - Input key material (e.g. the secret from the link fragment) as Uint8Array
+/// * `salt` - Optional salt as Uint8Array (defaults to 32 zero bytes per the RFC)
+/// * `info` - Context label binding the output to one purpose, e.g. `"furl v1 mac"`
+/// * `length` - Number of bytes to derive (1 to 8160)
+/// 
+/// # Returns
+/// Derived key as Uint8Array
+#[wasm_bindgen]
+pub fn derive_key_hkdf(
+    ikm: &Uint8Array,
+    salt: Option<Uint8Array>,
+    info: &str,
+    length: usize
+) -> Result<Uint8Array, JsValue> {
+    if ikm.length() == 0 {
+        return Err(CryptoError::InvalidInput("Invalid input key material: must not be empty".into()).into());
+    }
+
+    if length == 0 || length > MAX_HKDF_LEN {
+        return Err(CryptoError::InvalidInput(format!("Invalid derived key length: expected 1 to {} bytes, got {}", MAX_HKDF_LEN, length)).into());
+    }
+
+    let ikm_bytes = ikm.to_vec();
+    let salt_bytes = salt.map(|salt| salt.to_vec());
+    let mut okm = vec![0u8; length];
+
+    hkdf_sha256(&ikm_bytes, salt_bytes.as_deref(), info.as_bytes(), &mut okm)?;
+
+    Ok(Uint8Array::from(okm.as_slice()))
+}
+
+/// Expand a share secret into separate 32-byte keys for each purpose
+/// 
+/// Using one raw key for content, filename and MAC would let a weakness in
+/// one use leak into the others; HKDF with distinct `info` labels gives
+/// independent keys from the same link secret.
+/// 
+/// # Arguments
+/// * `secret` - Share secret from the link fragment as Uint8Array (at least 16 bytes)
+/// * `salt` - Optional salt as Uint8Array (e.g. a per-file random value)
+/// 
+/// # Returns
+/// Object with `content_key`, `filename_key` and `mac_key` Uint8Arrays
+#[wasm_bindgen]
+pub fn derive_share_keys(
+    secret: &Uint8Array,
+    salt: Option<Uint8Array>
+) -> Result<js_sys::Object, JsValue> {
+    if secret.length() < 16 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid secret size: expected at least 16 bytes, got {}", secret.length())).into());
+    }
+
+    let secret_bytes = secret.to_vec();
+    let salt_bytes = salt.map(|salt| salt.to_vec());
+    let keys = js_sys::Object::new();
+
+    for (name, info) in [
+        ("content_key", CONTENT_KEY_INFO),
+        ("filename_key", FILENAME_KEY_INFO),
+        ("mac_key", MAC_KEY_INFO),
+    ] {
+        let mut key = [0u8; 32];
+        hkdf_sha256(&secret_bytes, salt_bytes.as_deref(), info, &mut key)?;
+        js_sys::Reflect::set(&keys, &name.into(), &Uint8Array::from(&key[..]))?;
+    }
+
+    log!("HKDF: Derived content, filename and MAC keys");
+
+    Ok(keys)
+}
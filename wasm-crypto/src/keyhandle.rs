@@ -0,0 +1,307 @@
+//! Keys held in wasm memory behind an opaque handle
+//! 
+//! `import_key` copies a key into wasm memory once and zeroes the caller's
+//! array, so the raw key no longer sits in the garbage-collected JS heap
+//! (where it can't be wiped and may be copied around by the GC). The
+//! `*_with_handle` functions take the resulting `KeyHandle` in place of a key
+//! array; `Decryptor.from_handle` does the same for streaming.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+
+use crate::error::CryptoError;
+use crate::{aad_bytes, AesCtr};
+use crate::counter::ctr_options_from_js;
+
+/// A key stored in wasm memory, usable without exposing its bytes to JS
+/// 
+/// There is no way to read the key back out. Call `free()` once the key is
+/// no longer needed; the allocator zeroes its memory as it is released.
+#[wasm_bindgen]
+pub struct KeyHandle {
+    key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl KeyHandle {
+    /// Key size in bytes
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> u32 {
+        self.key.len() as u32
+    }
+}
+
+impl KeyHandle {
+    pub(crate) fn from_bytes(key: Vec<u8>) -> Self {
+        KeyHandle { key }
+    }
+
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// The key, checked to be exactly 32 bytes for the AEAD ciphers
+    fn key_256(&self) -> Result<&[u8], CryptoError> {
+        match self.key.len() {
+            32 => Ok(&self.key),
+            n => Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", n))),
+        }
+    }
+}
+
+/// Reject an IV or nonce of the wrong size
+pub(crate) fn check_nonce(nonce: &Uint8Array, expected: u32, name: &str) -> Result<Vec<u8>, CryptoError> {
+    if nonce.length() != expected {
+        return Err(CryptoError::InvalidIv(format!("Invalid {} size: expected {} bytes, got {}", name, expected, nonce.length())));
+    }
+    Ok(nonce.to_vec())
+}
+
+/// Reject AEAD input too short to hold the 16-byte tag
+fn check_tagged(encrypted_data: &Uint8Array) -> Result<Vec<u8>, CryptoError> {
+    if encrypted_data.length() < 16 {
+        return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", encrypted_data.length())));
+    }
+    Ok(encrypted_data.to_vec())
+}
+
+/// Move a key into wasm memory and zero the source array
+/// 
+/// # Arguments
+/// * `bytes` - Key as Uint8Array; overwritten with zeros once copied
+/// 
+/// # Returns
+/// KeyHandle to pass to the `*_with_handle` functions
+#[wasm_bindgen]
+pub fn import_key(bytes: &Uint8Array) -> Result<KeyHandle, JsValue> {
+    if bytes.length() == 0 {
+        return Err(CryptoError::InvalidKeyLength("Invalid key size: key is empty".into()).into());
+    }
+
+    let key = bytes.to_vec();
+    bytes.fill(0, 0, bytes.length());
+
+    log!("KeyHandle: Imported {}-byte key", key.len());
+
+    Ok(KeyHandle { key })
+}
+
+/// Decode a base64 key (e.g. from a link fragment) straight into a KeyHandle
+/// 
+/// The decoded bytes never exist as a JS array. The string itself can't be
+/// wiped, so drop references to it (and clear `location.hash`) afterwards.
+/// 
+/// # Arguments
+/// * `text` - Standard or URL-safe base64, padding optional
+/// * `expected_length` - Optional key size in bytes to enforce
+/// 
+/// # Returns
+/// KeyHandle to pass to the `*_with_handle` functions
+#[wasm_bindgen]
+pub fn import_key_base64(text: &str, expected_length: Option<u32>) -> Result<KeyHandle, JsValue> {
+    let key = crate::encoding::decode_base64(text)?;
+
+    if let Some(expected) = expected_length.filter(|&expected| key.len() != expected as usize) {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected {} bytes, got {}", expected, key.len())).into());
+    }
+    if key.is_empty() {
+        return Err(CryptoError::InvalidKeyLength("Invalid key size: key is empty".into()).into());
+    }
+
+    Ok(KeyHandle { key })
+}
+
+/// `decrypt_aes_ctr` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 16, 24 or 32-byte AES key
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - Encrypted data as Uint8Array
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_with_handle(key: &KeyHandle, iv: &Uint8Array, encrypted_data: &Uint8Array, options: Option<js_sys::Object>) -> Result<Uint8Array, JsValue> {
+    decrypt_aes_ctr_range_with_handle(key, iv, encrypted_data, 0, options)
+}
+
+/// `encrypt_aes_ctr` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 16, 24 or 32-byte AES key
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Encrypted data as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_aes_ctr_with_handle(key: &KeyHandle, iv: &Uint8Array, data: &Uint8Array, options: Option<js_sys::Object>) -> Result<Uint8Array, JsValue> {
+    // CTR encryption = decryption
+    decrypt_aes_ctr_range_with_handle(key, iv, data, 0, options)
+}
+
+/// `decrypt_aes_ctr_range` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 16, 24 or 32-byte AES key
+/// * `iv` - 16-byte initialization vector of the whole file as Uint8Array
+/// * `ciphertext_slice` - Ciphertext bytes starting at `start_offset`
+/// * `start_offset` - Byte offset of the slice within the whole ciphertext (BigInt)
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Decrypted slice as Uint8Array
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_range_with_handle(
+    key: &KeyHandle,
+    iv: &Uint8Array,
+    ciphertext_slice: &Uint8Array,
+    start_offset: u64,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    let iv_bytes = iv.to_vec();
+    let mut data = ciphertext_slice.to_vec();
+
+    log!("KeyHandle: AES-{}-CTR over {} bytes at offset {}", key.bytes().len() * 8, data.len(), start_offset);
+
+    let mut cipher = AesCtr::with_options(key.bytes(), &iv_bytes, &ctr_options_from_js(options.as_ref())?)?;
+    cipher.seek(start_offset)?;
+    cipher.apply_keystream(&mut data)?;
+
+    Ok(Uint8Array::from(data.as_slice()))
+}
+
+/// `decrypt_aes_ctr_chunked` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 16, 24 or 32-byte AES key
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - Encrypted data as Uint8Array
+/// * `chunk_size` - Size of chunks to process (default: 2MB)
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk
+/// * `options` - Optional `{ counter_width, rfc3686, memory_budget, on_flush }` object (default: 128-bit counter, 16-byte IV, no budget)
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array (with `memory_budget`, only the part not passed to `on_flush`)
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_chunked_with_handle(
+    key: &KeyHandle,
+    iv: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    chunk_size: Option<usize>,
+    progress_callback: Option<js_sys::Function>,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    let iv_bytes = iv.to_vec();
+    let chunk_size = chunk_size.unwrap_or(2 * 1024 * 1024);
+    let cipher = AesCtr::with_options(key.bytes(), &iv_bytes, &ctr_options_from_js(options.as_ref())?)?;
+
+    let budget = crate::budget::MemoryBudget::from_js(options.as_ref())?;
+    let result = crate::apply_aes_ctr_chunked(cipher, &crate::heap::try_to_vec(encrypted_data)?, chunk_size, progress_callback.as_ref(), &budget)?;
+
+    Ok(Uint8Array::from(result.as_slice()))
+}
+
+/// `decrypt_aes_gcm` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 32-byte AES key
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte GCM tag
+/// * `aad` - Optional associated data the tag was computed over
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_aes_gcm_with_handle(key: &KeyHandle, nonce: &Uint8Array, encrypted_data: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+    let nonce_bytes = check_nonce(nonce, 12, "nonce")?;
+    let data = check_tagged(encrypted_data)?;
+
+    let cipher = Aes256Gcm::new_from_slice(key.key_256()?)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    let plaintext = cipher
+        .decrypt(aes_gcm::Nonce::from_slice(&nonce_bytes), Payload { msg: &data, aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))?;
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// `decrypt_chacha20poly1305` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 32-byte key
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte Poly1305 tag
+/// * `aad` - Optional associated data the tag was computed over
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_chacha20poly1305_with_handle(key: &KeyHandle, nonce: &Uint8Array, encrypted_data: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+    let nonce_bytes = check_nonce(nonce, 12, "nonce")?;
+    let data = check_tagged(encrypted_data)?;
+
+    let cipher = ChaCha20Poly1305::new_from_slice(key.key_256()?)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    let plaintext = cipher
+        .decrypt(chacha20poly1305::Nonce::from_slice(&nonce_bytes), Payload { msg: &data, aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))?;
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// `encrypt_xchacha20poly1305` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 32-byte key
+/// * `nonce` - 24-byte nonce as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `aad` - Optional associated data to authenticate but not encrypt
+/// 
+/// # Returns
+/// Ciphertext followed by the 16-byte Poly1305 tag as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_xchacha20poly1305_with_handle(key: &KeyHandle, nonce: &Uint8Array, data: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+    let nonce_bytes = check_nonce(nonce, 24, "nonce")?;
+
+    let cipher = XChaCha20Poly1305::new_from_slice(key.key_256()?)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    let ciphertext = cipher
+        .encrypt(chacha20poly1305::XNonce::from_slice(&nonce_bytes), Payload { msg: &data.to_vec(), aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::Internal("Encryption failed".into()))?;
+
+    Ok(Uint8Array::from(ciphertext.as_slice()))
+}
+
+/// `decrypt_xchacha20poly1305` with the key held in a KeyHandle
+/// 
+/// # Arguments
+/// * `key` - KeyHandle for a 32-byte key
+/// * `nonce` - 24-byte nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte Poly1305 tag
+/// * `aad` - Optional associated data the tag was computed over
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_xchacha20poly1305_with_handle(key: &KeyHandle, nonce: &Uint8Array, encrypted_data: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+    let nonce_bytes = check_nonce(nonce, 24, "nonce")?;
+    let data = check_tagged(encrypted_data)?;
+
+    let cipher = XChaCha20Poly1305::new_from_slice(key.key_256()?)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    let plaintext = cipher
+        .decrypt(chacha20poly1305::XNonce::from_slice(&nonce_bytes), Payload { msg: &data, aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))?;
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
@@ -0,0 +1,179 @@
+//! Wrapping and unwrapping of file keys
+//! 
+//! In the sharing flow the AES file key is encrypted with the recipient's
+//! RSA-2048 public key (atPlatform style) and shipped in the ciphertext
+//! header; the recipient unwraps it here with their private key.
+//! 
+//! AES Key Wrap (RFC 3394) protects a data-encryption key under a
+//! key-encryption key, so rotating the KEK only rewrites the header and not
+//! the file body.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use rand_core::OsRng;
+use rsa::{Oaep, RsaPrivateKey};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::traits::PublicKeyParts;
+use sha1::Sha1;
+use sha2::Sha256;
+
+use crate::error::CryptoError;
+
+const MIN_MODULUS_BITS: usize = 2048;
+
+/// Parse a DER private key in PKCS#8 or PKCS#1 form and check its size
+pub(crate) fn parse_private_key(der: &[u8]) -> Result<RsaPrivateKey, CryptoError> {
+    let key = RsaPrivateKey::from_pkcs8_der(der)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_der(der))
+        .map_err(|_| CryptoError::InvalidKey("Invalid private key: expected RSA PKCS#8 or PKCS#1 DER".into()))?;
+
+    let bits = key.n().bits();
+    if bits < MIN_MODULUS_BITS {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid private key size: expected at least {} bits, got {}", MIN_MODULUS_BITS, bits)));
+    }
+
+    Ok(key)
+}
+
+/// Blinded RSA-OAEP decryption, returning `None` on any failure
+pub(crate) fn rsa_oaep_decrypt(key: &RsaPrivateKey, padding: Oaep, wrapped_key: &[u8]) -> Option<Vec<u8>> {
+    key.decrypt_blinded(&mut OsRng, padding, wrapped_key).ok()
+}
+
+/// Unwrap a session key with RSA-OAEP
+/// 
+/// Decryption uses blinding, so timing doesn't depend on the private
+/// exponent. All failures from a well-formed key produce the same error,
+/// to avoid acting as a padding oracle.
+/// 
+/// # Arguments
+/// * `private_key` - RSA private key (2048 bits or more) as PKCS#8 or PKCS#1 DER
+/// * `wrapped_key` - OAEP ciphertext as Uint8Array (the modulus size, 256 bytes for RSA-2048)
+/// * `hash` - OAEP hash and MGF1 digest: `"sha-256"` (default) or `"sha-1"`
+/// 
+/// # Returns
+/// Unwrapped session key as Uint8Array
+#[wasm_bindgen]
+pub fn rsa_oaep_unwrap_key(
+    private_key: &Uint8Array,
+    wrapped_key: &Uint8Array,
+    hash: Option<String>
+) -> Result<Uint8Array, JsValue> {
+    let padding = match hash.as_deref().map(str::to_ascii_lowercase).as_deref() {
+        None | Some("sha-256") | Some("sha256") => Oaep::new::<Sha256>(),
+        Some("sha-1") | Some("sha1") => Oaep::new::<Sha1>(),
+        Some(other) => return Err(CryptoError::Unsupported(format!("Unsupported OAEP hash: {}", other)).into()),
+    };
+
+    let key = parse_private_key(&private_key.to_vec())?;
+
+    if wrapped_key.length() as usize != key.size() {
+        return Err(CryptoError::InvalidInput(format!("Invalid wrapped key size: expected {} bytes, got {}", key.size(), wrapped_key.length())).into());
+    }
+
+    let session_key = rsa_oaep_decrypt(&key, padding, &wrapped_key.to_vec())
+        .ok_or_else(|| CryptoError::AuthenticationFailed("Key unwrapping failed: wrong private key or corrupted header".into()))?;
+
+    log!("RSA-OAEP: Unwrapped {}-byte session key", session_key.len());
+
+    Ok(Uint8Array::from(session_key.as_slice()))
+}
+
+/// Key-encryption key for RFC 3394, in any AES key size
+enum Kek {
+    Aes128(KekAes128),
+    Aes192(KekAes192),
+    Aes256(KekAes256),
+}
+
+impl Kek {
+    /// Returns `None` unless the KEK is 16, 24 or 32 bytes
+    fn new(kek: &[u8]) -> Option<Self> {
+        match kek.len() {
+            16 => KekAes128::try_from(kek).ok().map(Kek::Aes128),
+            24 => KekAes192::try_from(kek).ok().map(Kek::Aes192),
+            32 => KekAes256::try_from(kek).ok().map(Kek::Aes256),
+            _ => None,
+        }
+    }
+
+    fn from_js(kek: &Uint8Array) -> Result<Self, CryptoError> {
+        Kek::new(&kek.to_vec())
+            .ok_or_else(|| CryptoError::InvalidKeyLength(format!("Invalid key size: expected 16, 24 or 32 bytes, got {}", kek.length())))
+    }
+
+    fn wrap(&self, data: &[u8]) -> Result<Vec<u8>, aes_kw::Error> {
+        match self {
+            Kek::Aes128(kek) => kek.wrap_vec(data),
+            Kek::Aes192(kek) => kek.wrap_vec(data),
+            Kek::Aes256(kek) => kek.wrap_vec(data),
+        }
+    }
+
+    fn unwrap(&self, data: &[u8]) -> Result<Vec<u8>, aes_kw::Error> {
+        match self {
+            Kek::Aes128(kek) => kek.unwrap_vec(data),
+            Kek::Aes192(kek) => kek.unwrap_vec(data),
+            Kek::Aes256(kek) => kek.unwrap_vec(data),
+        }
+    }
+}
+
+/// RFC 3394 wrap, returning `None` for a bad KEK or key size
+pub(crate) fn aes_kw_wrap(kek: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+    Kek::new(kek)?.wrap(key).ok()
+}
+
+/// RFC 3394 unwrap, returning `None` for a bad KEK size or failed integrity check
+pub(crate) fn aes_kw_unwrap(kek: &[u8], wrapped_key: &[u8]) -> Option<Vec<u8>> {
+    Kek::new(kek)?.unwrap(wrapped_key).ok()
+}
+
+/// Wrap a key with AES Key Wrap (RFC 3394)
+/// 
+/// # Arguments
+/// * `kek` - Key-encryption key as Uint8Array (16, 24 or 32 bytes)
+/// * `key` - Key to wrap as Uint8Array (a multiple of 8 bytes, at least 16)
+/// 
+/// # Returns
+/// Wrapped key as Uint8Array (8 bytes longer than `key`)
+#[wasm_bindgen]
+pub fn aes_key_wrap(kek: &Uint8Array, key: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let length = key.length() as usize;
+    if length < 16 || !length.is_multiple_of(8) {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected a multiple of 8 bytes, at least 16, got {}", length)).into());
+    }
+
+    let wrapped = Kek::from_js(kek)?
+        .wrap(&key.to_vec())
+        .map_err(|_| CryptoError::Internal("Key wrapping failed".into()))?;
+
+    Ok(Uint8Array::from(wrapped.as_slice()))
+}
+
+/// Unwrap a key wrapped with AES Key Wrap (RFC 3394)
+/// 
+/// # Arguments
+/// * `kek` - Key-encryption key as Uint8Array (16, 24 or 32 bytes)
+/// * `wrapped_key` - Wrapped key as Uint8Array (a multiple of 8 bytes, at least 24)
+/// 
+/// # Returns
+/// Unwrapped key as Uint8Array, or an error if the integrity check fails
+/// (wrong KEK or corrupted header)
+#[wasm_bindgen]
+pub fn aes_key_unwrap(kek: &Uint8Array, wrapped_key: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let length = wrapped_key.length() as usize;
+    if length < 24 || !length.is_multiple_of(8) {
+        return Err(CryptoError::InvalidInput(format!("Invalid wrapped key size: expected a multiple of 8 bytes, at least 24, got {}", length)).into());
+    }
+
+    let key = Kek::from_js(kek)?
+        .unwrap(&wrapped_key.to_vec())
+        .map_err(|_| CryptoError::AuthenticationFailed("Key unwrapping failed: wrong key-encryption key or corrupted header".into()))?;
+
+    log!("AES-KW: Unwrapped {}-byte key", key.len());
+
+    Ok(Uint8Array::from(key.as_slice()))
+}
@@ -1,41 +1,206 @@
 use wasm_bindgen::prelude::*;
 use aes::Aes256;
 use ctr::Ctr128BE;
-use ctr::cipher::{KeyIvInit, StreamCipher};
+use ctr::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use cbc::cipher::BlockDecryptMut;
+use cbc::cipher::block_padding::Pkcs7;
 use chacha20::{ChaCha20, Key, Nonce};
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use poly1305::Poly1305;
+use poly1305::universal_hash::UniversalHash;
 use js_sys::Uint8Array;
-use web_sys::console;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use std::cell::RefCell;
+use std::rc::Rc;
 
-// Use wee_alloc for smaller binary size
+use counter::{ctr_options_from_js, CtrOptions};
+use furl_core::ctr::AesCtr;
+use budget::MemoryBudget;
+use decompress::{Compression, Decompressor};
+use progress::Progress;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// Start the rayon worker pool from JS: `await init_thread_pool(navigator.hardwareConcurrency)`
+#[cfg(all(feature = "parallel", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+// The standard allocator (dlmalloc on wasm) by default; wee_alloc with the
+// `small-binary` feature, trading speed for a few KB. Either way freed memory
+// is zeroed, so keys and plaintext don't outlive the buffers that held them.
+#[cfg(not(feature = "small-binary"))]
+#[global_allocator]
+static ALLOC: memory::ZeroizeOnFree<std::alloc::System> = memory::ZeroizeOnFree(std::alloc::System);
+
+#[cfg(feature = "small-binary")]
 #[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+static ALLOC: memory::ZeroizeOnFree<memory::AlignFallback<wee_alloc::WeeAlloc>> = memory::ZeroizeOnFree(memory::AlignFallback(wee_alloc::WeeAlloc::INIT));
+
+#[cfg(all(feature = "small-binary", feature = "parallel"))]
+compile_error!("wee_alloc is not thread-safe: the `small-binary` and `parallel` features can't be combined");
 
-// Type alias for AES-256-CTR
+// AES-256-CTR with the full 128-bit counter, for OpenSSL's `enc -aes-256-ctr`
 type Aes256Ctr = Ctr128BE<Aes256>;
 
-#[wasm_bindgen]
-extern "C" {
-    fn alert(s: &str);
+// Slice size used when reading Blob and File inputs
+const BLOB_SLICE_SIZE: u32 = 1024 * 1024;
+
+// Chunk size and default time budget for `decrypt_aes_ctr_async`
+const ASYNC_CHUNK_SIZE: usize = 64 * 1024;
+const ASYNC_SLICE_MS: f64 = 8.0;
+
+// Type alias for AES-256-CBC decryption
+type Aes256CbcDec = cbc::Decryptor<Aes256>;
+
+// Macro for logging to console at a given level. Messages above the level
+// set with `set_log_level` are skipped before formatting; the `silent`
+// feature and native builds (e.g. unit tests) compile them out entirely.
+macro_rules! log_at {
+    ( $level:expr, $( $t:tt )* ) => {
+        #[cfg(all(target_arch = "wasm32", not(feature = "silent")))]
+        if $crate::logging::enabled($level) {
+            $crate::logging::write($level, &format!( $( $t )* ));
+        }
+        #[cfg(any(not(target_arch = "wasm32"), feature = "silent"))]
+        let _ = ($level, format_args!( $( $t )* ));
+    }
 }
 
-// Macro for logging to console
+// Diagnostic logging (sizes, byte counts, progress) at `LogLevel::Debug`
 macro_rules! log {
     ( $( $t:tt )* ) => {
-        console::log_1(&format!( $( $t )* ).into());
+        log_at!($crate::logging::LogLevel::Debug, $( $t )*)
     }
 }
 
+mod archive;
+mod atplatform;
+mod bao;
+mod batch;
+mod benchmark;
+mod budget;
+mod checksum;
+mod commit;
+mod container;
+mod context;
+mod counter;
+mod decompress;
+mod encoding;
+mod envelope;
+mod error;
+mod hash;
+mod heap;
+mod jwe;
+mod kdf;
+mod keyhandle;
+mod keywrap;
+mod logging;
+mod mac;
+mod manifest;
+mod media;
+mod memory;
+mod metrics;
+mod metadata;
+mod mime;
+mod mnemonic;
+mod openssl;
+mod password;
+mod progress;
+mod resume;
+mod secretstream;
+mod selftest;
+mod serviceworker;
+mod shamir;
+mod share;
+mod sharelink;
+mod sign;
+mod siv;
+mod staging;
+mod stream;
+mod tar;
+mod trailer;
+mod upload;
+mod vault;
+mod x25519;
+mod xts;
+
+pub use archive::*;
+pub use atplatform::*;
+pub use bao::*;
+pub use batch::*;
+pub use benchmark::*;
+pub use checksum::*;
+pub use commit::*;
+pub use container::*;
+pub use context::*;
+pub use counter::*;
+pub use encoding::*;
+pub use envelope::*;
+pub use error::*;
+pub use hash::*;
+pub use heap::*;
+pub use jwe::*;
+pub use kdf::*;
+pub use keyhandle::*;
+pub use keywrap::*;
+pub use logging::*;
+pub use mac::*;
+pub use manifest::*;
+pub use media::*;
+pub use memory::*;
+pub use metrics::*;
+pub use metadata::*;
+pub use mime::*;
+pub use mnemonic::*;
+pub use openssl::*;
+pub use password::*;
+pub use resume::*;
+pub use secretstream::*;
+pub use selftest::*;
+pub use serviceworker::*;
+pub use shamir::*;
+pub use share::*;
+pub use sharelink::*;
+pub use sign::*;
+pub use siv::*;
+pub use staging::*;
+pub use stream::*;
+pub use tar::*;
+pub use trailer::*;
+pub use upload::*;
+pub use vault::*;
+pub use x25519::*;
+pub use xts::*;
+
+/// Check that an AES key is 16, 24 or 32 bytes long
+fn validate_aes_key(key: &Uint8Array) -> Result<(), JsValue> {
+    match key.length() {
+        16 | 24 | 32 => Ok(()),
+        n => Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 16, 24 or 32 bytes, got {}", n)).into()),
+    }
+}
+
+/// Copy optional AEAD associated data into wasm memory; none is the same as empty
+pub(crate) fn aad_bytes(aad: Option<&Uint8Array>) -> Vec<u8> {
+    aad.map(Uint8Array::to_vec).unwrap_or_default()
+}
+
 #[wasm_bindgen]
 pub fn init_panic_hook() {
     console_error_panic_hook::set_once();
 }
 
-/// Decrypt data using AES-256-CTR mode
+/// Decrypt data using AES-CTR mode (AES-128, AES-192 or AES-256 by key length)
 /// 
 /// # Arguments
-/// * `key` - 32-byte AES key as Uint8Array
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
 /// * `iv` - 16-byte initialization vector as Uint8Array  
 /// * `encrypted_data` - Encrypted data as Uint8Array
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
 /// 
 /// # Returns
 /// Decrypted data as Uint8Array
@@ -43,128 +208,1313 @@ pub fn init_panic_hook() {
 pub fn decrypt_aes_ctr(
     key: &Uint8Array,
     iv: &Uint8Array, 
-    encrypted_data: &Uint8Array
+    encrypted_data: &Uint8Array,
+    options: Option<js_sys::Object>
 ) -> Result<Uint8Array, JsValue> {
+    let plaintext = decrypt_aes_ctr_staged(key, iv, encrypted_data, 0, options.as_ref(), false, |plaintext| {
+        metrics::copy(|| Uint8Array::from(plaintext))
+    })?;
+
+    log!("WASM: Successfully decrypted {} bytes", plaintext.length());
+
+    Ok(plaintext)
+}
+
+/// Decrypt data using AES-CTR mode, returning a view instead of a copy
+/// 
+/// Same as `decrypt_aes_ctr`, but the plaintext is left in a module-owned
+/// buffer and the result is a view of it: it is overwritten by the next
+/// call that stages data and detached if wasm memory grows. Use it
+/// straight away or `.slice()` it; see the `staging` module for the rules.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - Encrypted data as Uint8Array
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Uint8Array view of the decrypted data in wasm memory
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_view(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    decrypt_aes_ctr_staged(key, iv, encrypted_data, 0, options.as_ref(), true, staging::view)
+}
+
+/// Decrypt AES-CTR data in the staging buffer and pass the plaintext to `finish`
+/// 
+/// `finish` either copies the plaintext out, after which the buffer is
+/// zeroed, or (with `view`) returns a view of it as the final step.
+fn decrypt_aes_ctr_staged<T>(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    start_offset: u64,
+    options: Option<&js_sys::Object>,
+    view: bool,
+    finish: impl FnOnce(&[u8]) -> T
+) -> Result<T, JsValue> {
     // Validate input sizes
-    if key.length() != 32 {
-        return Err(JsValue::from_str(&format!("Invalid key size: expected 32 bytes, got {}", key.length())));
-    }
-    
-    if iv.length() != 16 {
-        return Err(JsValue::from_str(&format!("Invalid IV size: expected 16 bytes, got {}", iv.length())));
-    }
+    validate_aes_key(key)?;
 
-    // Convert JS Uint8Arrays to Rust Vec<u8>
-    let key_bytes: Vec<u8> = key.to_vec();
-    let iv_bytes: Vec<u8> = iv.to_vec();
-    let mut data_bytes: Vec<u8> = encrypted_data.to_vec();
-
-    log!("WASM: Decrypting {} bytes with AES-256-CTR", data_bytes.len());
-    log!("WASM: Key size: {} bytes", key_bytes.len());
-    log!("WASM: IV size: {} bytes", iv_bytes.len());
-
-    // Create cipher
-    let mut cipher = match Aes256Ctr::new(
-        (&key_bytes[..]).try_into().map_err(|_| JsValue::from_str("Invalid key format"))?,
-        (&iv_bytes[..]).try_into().map_err(|_| JsValue::from_str("Invalid IV format"))?
-    ) {
-        cipher => cipher,
-    };
+    log!("WASM: Decrypting {} bytes at offset {} with AES-{}-CTR", encrypted_data.length(), start_offset, key.length() * 8);
+    log!("WASM: IV size: {} bytes", iv.length());
 
-    // Decrypt in place (CTR mode encryption = decryption)
-    cipher.apply_keystream(&mut data_bytes);
+    let mut cipher = AesCtr::with_options(&key.to_vec(), &iv.to_vec(), &ctr_options_from_js(options)?)?;
+    cipher.seek(start_offset)?;
+    metrics::operation();
 
-    log!("WASM: Successfully decrypted {} bytes", data_bytes.len());
+    // Decrypt in place (CTR mode encryption = decryption)
+    let decrypt_in_place = |data: &mut [u8]| metrics::cipher(data.len(), || cipher.apply_keystream(data)).map(|()| finish(data));
+    let plaintext = if view {
+        staging::with_staged_view(encrypted_data, decrypt_in_place)
+    } else {
+        staging::with_staged(encrypted_data, decrypt_in_place)
+    }??;
 
-    // Convert back to Uint8Array
-    Ok(Uint8Array::from(&data_bytes[..]))
+    Ok(plaintext)
 }
 
 /// Decrypt data in chunks to handle large files efficiently
 /// 
 /// # Arguments
-/// * `key` - 32-byte AES key as Uint8Array
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
 /// * `iv` - 16-byte initialization vector as Uint8Array  
 /// * `encrypted_data` - Encrypted data as Uint8Array
 /// * `chunk_size` - Size of chunks to process (default: 2MB)
-/// * `progress_callback` - Optional callback for progress updates
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk (`bytes_done`, `percent`, `bytes_per_second`, `remaining_ms`, ...)
+/// * `options` - Optional `{ counter_width, rfc3686, memory_budget, on_flush }` object (default: 128-bit counter, 16-byte IV, no budget); see the `budget` module
 /// 
 /// # Returns
-/// Decrypted data as Uint8Array
+/// Decrypted data as Uint8Array (with `memory_budget`, only the part not passed to `on_flush`)
 #[wasm_bindgen]
 pub fn decrypt_aes_ctr_chunked(
     key: &Uint8Array,
     iv: &Uint8Array,
     encrypted_data: &Uint8Array,
     chunk_size: Option<usize>,
-    progress_callback: Option<js_sys::Function>
+    progress_callback: Option<js_sys::Function>,
+    options: Option<js_sys::Object>
 ) -> Result<Uint8Array, JsValue> {
     // Validate input sizes
-    if key.length() != 32 {
-        return Err(JsValue::from_str(&format!("Invalid key size: expected 32 bytes, got {}", key.length())));
-    }
+    validate_aes_key(key)?;
     
+    let key_bytes: Vec<u8> = key.to_vec();
+    let iv_bytes: Vec<u8> = iv.to_vec();
+    let data_bytes: Vec<u8> = metrics::copy(|| heap::try_to_vec(encrypted_data))?;
+    let chunk_size = chunk_size.unwrap_or(2 * 1024 * 1024); // Default 2MB chunks
+    metrics::operation();
+
+    log!("WASM: Chunked decryption of {} bytes in {} byte chunks", data_bytes.len(), chunk_size);
+
+    let cipher = AesCtr::with_options(&key_bytes, &iv_bytes, &ctr_options_from_js(options.as_ref())?)?;
+    let budget = MemoryBudget::from_js(options.as_ref())?;
+    let result = apply_aes_ctr_chunked(cipher, &data_bytes, chunk_size, progress_callback.as_ref(), &budget)?;
+
+    log!("WASM: Successfully decrypted {} bytes", data_bytes.len());
+
+    Ok(metrics::copy(|| Uint8Array::from(&result[..])))
+}
+
+/// Decrypt a slice of AES-CTR ciphertext that starts at an arbitrary byte offset
+/// 
+/// Used with HTTP Range requests: the keystream is positioned at
+/// `start_offset` (counter block and offset within the block), so only the
+/// fetched bytes need to be decrypted.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector of the whole file as Uint8Array
+/// * `ciphertext_slice` - Ciphertext bytes starting at `start_offset`
+/// * `start_offset` - Byte offset of the slice within the whole ciphertext (BigInt)
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Decrypted slice as Uint8Array
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_range(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    ciphertext_slice: &Uint8Array,
+    start_offset: u64,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    decrypt_aes_ctr_staged(key, iv, ciphertext_slice, start_offset, options.as_ref(), false, |plaintext| {
+        metrics::copy(|| Uint8Array::from(plaintext))
+    })
+}
+
+/// Decrypt a slice of AES-CTR ciphertext, returning a view instead of a copy
+/// 
+/// `decrypt_aes_ctr_range` with the view ownership rules of
+/// `decrypt_aes_ctr_view`.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector of the whole file as Uint8Array
+/// * `ciphertext_slice` - Ciphertext bytes starting at `start_offset`
+/// * `start_offset` - Byte offset of the slice within the whole ciphertext (BigInt)
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Uint8Array view of the decrypted slice in wasm memory
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_range_view(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    ciphertext_slice: &Uint8Array,
+    start_offset: u64,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    decrypt_aes_ctr_staged(key, iv, ciphertext_slice, start_offset, options.as_ref(), true, staging::view)
+}
+
+/// Decrypt AES-CTR data into a caller-provided output buffer
+/// 
+/// The ciphertext is streamed through a small scratch buffer in wasm memory
+/// and the plaintext written straight into `output`, so no full-size copy of
+/// the data is ever allocated inside wasm. `output` may be the same array as
+/// `input` to decrypt in place.
+/// 
+/// Both arrays may be views over a `SharedArrayBuffer` (available when the
+/// page is cross-origin isolated), so a worker can decrypt data the main
+/// thread shares with it and the plaintext is seen there without being
+/// posted back.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `input` - Encrypted data as Uint8Array
+/// * `output` - Preallocated Uint8Array of at least `input.length` bytes
+/// 
+/// # Returns
+/// Number of bytes written to `output`
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_into(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    input: &Uint8Array,
+    output: &Uint8Array
+) -> Result<u32, JsValue> {
+    decrypt_aes_ctr_range_into(key, iv, input, output, 0, None)
+}
+
+/// Decrypt part of an AES-CTR ciphertext into a caller-provided output buffer
+/// 
+/// `decrypt_aes_ctr_into` for a slice starting `start_offset` bytes into the
+/// ciphertext, like `decrypt_aes_ctr_range`. Workers given disjoint
+/// subarrays of one shared input and output can each decrypt their part in
+/// place with no copies through `postMessage`.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector of the whole file as Uint8Array
+/// * `input` - Ciphertext bytes starting at `start_offset`
+/// * `output` - Preallocated Uint8Array of at least `input.length` bytes
+/// * `start_offset` - Byte offset of `input` within the whole ciphertext (BigInt)
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Number of bytes written to `output`
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_range_into(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    input: &Uint8Array,
+    output: &Uint8Array,
+    start_offset: u64,
+    options: Option<js_sys::Object>
+) -> Result<u32, JsValue> {
+    // Validate input sizes
+    validate_aes_key(key)?;
+
+    if output.length() < input.length() {
+        return Err(CryptoError::InvalidInput(format!("Output buffer too small: need {} bytes, got {}", input.length(), output.length())).into());
+    }
+
+    const SCRATCH_SIZE: u32 = 64 * 1024;
+
+    let mut cipher = AesCtr::with_options(&key.to_vec(), &iv.to_vec(), &ctr_options_from_js(options.as_ref())?)?;
+    cipher.seek(start_offset)?;
+    let mut scratch = vec![0u8; SCRATCH_SIZE.min(input.length()) as usize];
+    let total_len = input.length();
+    let mut offset = 0u32;
+    metrics::operation();
+
+    log!("WASM: Decrypting {} bytes at offset {} into caller buffer with AES-{}-CTR", total_len, start_offset, key.length() * 8);
+
+    while offset < total_len {
+        let end = (offset + SCRATCH_SIZE).min(total_len);
+        let window = &mut scratch[..(end - offset) as usize];
+
+        metrics::copy(|| input.subarray(offset, end).copy_to(window));
+        metrics::cipher(window.len(), || cipher.apply_keystream(window))?;
+        metrics::copy(|| output.subarray(offset, end).copy_from(window));
+
+        offset = end;
+    }
+
+    Ok(total_len)
+}
+
+/// Buffer allocated in wasm linear memory for zero-copy in-place decryption
+/// 
+/// JS writes ciphertext directly into `view()` (one copy, e.g. from a fetch
+/// chunk), calls `decrypt_aes_ctr_in_place`, then reads the plaintext back
+/// out of `view()` without any further copies.
+/// 
+/// The array returned by `view()` aliases wasm memory and is detached
+/// whenever that memory grows, so fetch a fresh view after any other call
+/// into the module instead of holding on to it.
+#[wasm_bindgen]
+pub struct WasmBuffer {
+    data: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl WasmBuffer {
+    /// Allocate a zero-filled buffer of `length` bytes inside wasm memory
+    /// 
+    /// Throws `OutOfMemory` if wasm memory can't grow that far.
+    #[wasm_bindgen(constructor)]
+    pub fn new(length: usize) -> Result<WasmBuffer, JsValue> {
+        Ok(WasmBuffer {
+            data: try_zeroed(length)?,
+        })
+    }
+
+    /// Size of the buffer in bytes
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Uint8Array view over the buffer's wasm memory (no copy)
+    pub fn view(&self) -> Uint8Array {
+        // SAFETY: the view is only valid until wasm memory is next resized;
+        // callers are told to re-fetch it rather than cache it
+        unsafe { Uint8Array::view(&self.data) }
+    }
+
+    /// Decrypt the buffer contents in place with AES-CTR
+    /// 
+    /// # Arguments
+    /// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+    /// * `iv` - 16-byte initialization vector as Uint8Array
+    /// * `start_offset` - Optional keystream offset of the buffer within the file (BigInt)
+    pub fn decrypt_aes_ctr_in_place(
+        &mut self,
+        key: &Uint8Array,
+        iv: &Uint8Array,
+        start_offset: Option<u64>
+    ) -> Result<(), JsValue> {
+        validate_aes_key(key)?;
+
+        if iv.length() != 16 {
+            return Err(CryptoError::InvalidIv(format!("Invalid IV size: expected 16 bytes, got {}", iv.length())).into());
+        }
+
+        let mut cipher = AesCtr::new(&key.to_vec(), &iv.to_vec())?;
+        cipher.seek(start_offset.unwrap_or(0))?;
+        metrics::operation();
+        metrics::cipher(self.data.len(), || cipher.apply_keystream(&mut self.data))?;
+
+        log!("WASM: Decrypted {} bytes in place", self.data.len());
+
+        Ok(())
+    }
+}
+
+/// Encrypt data using AES-CTR mode (AES-128, AES-192 or AES-256 by key length)
+/// 
+/// CTR mode is symmetric, so the output decrypts with `decrypt_aes_ctr`
+/// using the same key and IV. Never reuse a key/IV pair for two files.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Encrypted data as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_aes_ctr(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    data: &Uint8Array,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    validate_aes_key(key)?;
+
+    let key_bytes: Vec<u8> = key.to_vec();
+    let iv_bytes: Vec<u8> = iv.to_vec();
+    let mut data_bytes: Vec<u8> = data.to_vec();
+
+    log!("WASM: Encrypting {} bytes with AES-{}-CTR", data_bytes.len(), key_bytes.len() * 8);
+
+    let mut cipher = AesCtr::with_options(&key_bytes, &iv_bytes, &ctr_options_from_js(options.as_ref())?)?;
+    cipher.apply_keystream(&mut data_bytes)?;
+
+    log!("WASM: Successfully encrypted {} bytes", data_bytes.len());
+
+    Ok(Uint8Array::from(&data_bytes[..]))
+}
+
+/// Encrypt data in chunks to handle large files efficiently
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `chunk_size` - Size of chunks to process (default: 2MB)
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk (`bytes_done`, `percent`, `bytes_per_second`, `remaining_ms`, ...)
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Encrypted data as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_aes_ctr_chunked(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    data: &Uint8Array,
+    chunk_size: Option<usize>,
+    progress_callback: Option<js_sys::Function>,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    validate_aes_key(key)?;
+
+    let key_bytes: Vec<u8> = key.to_vec();
+    let iv_bytes: Vec<u8> = iv.to_vec();
+    let data_bytes: Vec<u8> = data.to_vec();
+    let chunk_size = chunk_size.unwrap_or(2 * 1024 * 1024); // Default 2MB chunks
+
+    log!("WASM: Chunked encryption of {} bytes in {} byte chunks", data_bytes.len(), chunk_size);
+
+    let cipher = AesCtr::with_options(&key_bytes, &iv_bytes, &ctr_options_from_js(options.as_ref())?)?;
+    let result = apply_aes_ctr_chunked(cipher, &data_bytes, chunk_size, progress_callback.as_ref(), &MemoryBudget::default())?;
+
+    log!("WASM: Successfully encrypted {} bytes", result.len());
+
+    Ok(Uint8Array::from(&result[..]))
+}
+
+/// Decrypt data with AES-CTR, splitting the work across threads when available
+/// 
+/// With the `parallel` feature (the threaded build from `build.sh threads`,
+/// which needs SharedArrayBuffer and therefore COOP/COEP headers) chunks are
+/// decrypted concurrently on the pool started by `init_thread_pool`. Other
+/// builds process the same chunks on the calling thread, so this is always
+/// safe to call.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - Encrypted data as Uint8Array
+/// * `chunk_size` - Size of the unit of work per thread (default: 2MB)
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_parallel(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    chunk_size: Option<usize>
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    validate_aes_key(key)?;
+
     if iv.length() != 16 {
-        return Err(JsValue::from_str(&format!("Invalid IV size: expected 16 bytes, got {}", iv.length())));
+        return Err(CryptoError::InvalidIv(format!("Invalid IV size: expected 16 bytes, got {}", iv.length())).into());
     }
 
     let key_bytes: Vec<u8> = key.to_vec();
     let iv_bytes: Vec<u8> = iv.to_vec();
-    let data_bytes: Vec<u8> = encrypted_data.to_vec();
+    let mut data_bytes: Vec<u8> = metrics::copy(|| heap::try_to_vec(encrypted_data))?;
     let chunk_size = chunk_size.unwrap_or(2 * 1024 * 1024); // Default 2MB chunks
+    metrics::operation();
 
-    log!("WASM: Chunked decryption of {} bytes in {} byte chunks", data_bytes.len(), chunk_size);
+    log!("WASM: Parallel decryption of {} bytes in {} byte chunks (threads: {})", data_bytes.len(), chunk_size, cfg!(feature = "parallel"));
+
+    apply_aes_ctr_parallel(&AesCtr::new(&key_bytes, &iv_bytes)?, &mut data_bytes, chunk_size)?;
+
+    Ok(metrics::copy(|| Uint8Array::from(&data_bytes[..])))
+}
+
+/// Refuse a chunk size of 0, which would never advance through the data
+fn check_chunk_size(chunk_size: usize) -> Result<(), CryptoError> {
+    if chunk_size == 0 {
+        return Err(CryptoError::InvalidInput("Invalid chunk size: must be at least 1 byte".into()));
+    }
+    Ok(())
+}
+
+/// Apply the AES-CTR keystream in place, one independently seeked cipher per chunk
+fn apply_aes_ctr_parallel(
+    base: &AesCtr,
+    data_bytes: &mut [u8],
+    chunk_size: usize
+) -> Result<(), CryptoError> {
+    check_chunk_size(chunk_size)?;
+
+    let decrypt_chunk = |(chunk_idx, chunk): (usize, &mut [u8])| {
+        let mut cipher = base.clone();
+        cipher.seek((chunk_idx * chunk_size) as u64)?;
+        metrics::cipher(chunk.len(), || cipher.apply_keystream(chunk))
+    };
+
+    #[cfg(feature = "parallel")]
+    return data_bytes.par_chunks_mut(chunk_size).enumerate().try_for_each(decrypt_chunk);
+
+    #[cfg(not(feature = "parallel"))]
+    return data_bytes.chunks_mut(chunk_size).enumerate().try_for_each(decrypt_chunk);
+}
+
+/// Decrypt data chunk by chunk, handing each plaintext chunk to a callback
+/// 
+/// Unlike `decrypt_aes_ctr_chunked` nothing is concatenated, so peak wasm
+/// memory stays at one chunk. Files larger than 4 GB can be processed by
+/// passing successive slices (e.g. from `Blob.slice()`) with their position
+/// in the file as `start_offset`.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector of the whole file as Uint8Array
+/// * `encrypted_data` - Ciphertext slice as Uint8Array
+/// * `start_offset` - Byte offset of the slice within the whole ciphertext (BigInt)
+/// * `chunk_size` - Size of chunks to process (default: 2MB)
+/// * `chunk_callback` - Called with `(plaintext: Uint8Array, offset: BigInt)` for each chunk
+/// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+/// 
+/// # Returns
+/// Total number of bytes decrypted as BigInt
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_chunks(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    start_offset: u64,
+    chunk_size: Option<usize>,
+    chunk_callback: &js_sys::Function,
+    options: Option<js_sys::Object>
+) -> Result<u64, JsValue> {
+    // Validate input sizes
+    validate_aes_key(key)?;
+
+    let key_bytes: Vec<u8> = key.to_vec();
+    let iv_bytes: Vec<u8> = iv.to_vec();
+    let data_bytes: Vec<u8> = metrics::copy(|| heap::try_to_vec(encrypted_data))?;
+    let chunk_size = chunk_size.unwrap_or(2 * 1024 * 1024); // Default 2MB chunks
+    metrics::operation();
+
+    log!("WASM: Streaming decryption of {} bytes at offset {} in {} byte chunks", data_bytes.len(), start_offset, chunk_size);
+
+    let cipher = AesCtr::with_options(&key_bytes, &iv_bytes, &ctr_options_from_js(options.as_ref())?)?;
+
+    process_aes_ctr_chunks(cipher, &data_bytes, chunk_size, start_offset, |_, offset, chunk| {
+        chunk_callback
+            .call2(&JsValue::NULL, &metrics::copy(|| Uint8Array::from(chunk)), &JsValue::from(offset))
+            .map(|_| ())
+    })?;
+
+    Ok(data_bytes.len() as u64)
+}
+
+/// Apply the AES-CTR keystream chunk by chunk (CTR encryption = decryption)
+/// 
+/// Returns the output `budget` didn't flush, which is all of it without one.
+fn apply_aes_ctr_chunked(
+    cipher: AesCtr,
+    data_bytes: &[u8],
+    chunk_size: usize,
+    progress_callback: Option<&js_sys::Function>,
+    budget: &MemoryBudget
+) -> Result<Vec<u8>, JsValue> {
+    let mut result = budget.buffer(Some(data_bytes.len()));
+    let progress = Progress::new(progress_callback, Some(data_bytes.len() as u64), Some(chunk_size as u64));
+
+    process_aes_ctr_chunks::<_, JsValue>(cipher, data_bytes, chunk_size, 0, |chunk_idx, _, chunk| {
+        result.push(chunk)?;
+        progress.report(result.total(), chunk_idx as u64);
 
-    let mut result = Vec::with_capacity(data_bytes.len());
-    let total_chunks = (data_bytes.len() + chunk_size - 1) / chunk_size;
+        Ok(())
+    })?;
+
+    Ok(result.into_inner())
+}
+
+/// Apply the AES-CTR keystream to `data_bytes` one chunk at a time
+/// 
+/// `cipher` must be at keystream position 0; `start_offset` is the position
+/// of the first byte. Each processed
+/// chunk is passed to `emit` together with its index and absolute offset; the
+/// chunk buffer is reused, so `emit` must copy anything it wants to keep.
+fn process_aes_ctr_chunks<F, E>(
+    mut cipher: AesCtr,
+    data_bytes: &[u8],
+    chunk_size: usize,
+    start_offset: u64,
+    mut emit: F
+) -> Result<(), E>
+where
+    F: FnMut(usize, u64, &[u8]) -> Result<(), E>,
+    E: From<CryptoError>,
+{
+    check_chunk_size(chunk_size)?;
+    let total_chunks = data_bytes.len().div_ceil(chunk_size);
+    let mut chunk_data = Vec::with_capacity(chunk_size.min(data_bytes.len()));
+
+    // One cipher for the whole run: the keystream position carries over from
+    // chunk to chunk, so chunk sizes need not be block-aligned
+    cipher.seek(start_offset)?;
 
     for (chunk_idx, chunk) in data_bytes.chunks(chunk_size).enumerate() {
-        // Calculate the counter offset for this chunk
-        let blocks_processed = (chunk_idx * chunk_size) / 16;
-        let mut chunk_iv = iv_bytes.clone();
-        
-        // Increment the counter by blocks_processed
-        increment_counter(&mut chunk_iv, blocks_processed);
+        let chunk_offset = start_offset + (chunk_idx * chunk_size) as u64;
+
+        // Process this chunk
+        chunk_data.clear();
+        chunk_data.extend_from_slice(chunk);
+        metrics::cipher(chunk_data.len(), || cipher.apply_keystream(&mut chunk_data))?;
+        emit(chunk_idx, chunk_offset, &chunk_data)?;
+
+        log!("WASM: Processed chunk {}/{} ({} bytes)", chunk_idx + 1, total_chunks, chunk.len());
+    }
+
+    Ok(())
+}
+
+/// Streaming AES-CTR decryptor for data that arrives incrementally
+/// 
+/// Feed ciphertext chunks of any size to `update` in order as they arrive
+/// (e.g. from a `fetch` body reader) and call `finalize` once the stream
+/// ends. The keystream position is tracked internally, so chunk boundaries
+/// do not need to line up with AES blocks.
+/// 
+/// With `{ length_trailer: true }` the stream must end with the trailer from
+/// `ctr_length_trailer`: the last 40 bytes given to `update` are held back
+/// rather than decrypted, and `finalize` throws `Truncated` if the stream is
+/// shorter than the trailer says (see the `trailer` module).
+#[wasm_bindgen]
+pub struct Decryptor {
+    cipher: AesCtr,
+    trailer: Option<TrailerCheck>,
+    bytes_processed: u64,
+    finalized: bool,
+    cancelled: bool,
+    state_check: [u8; 8],
+}
+
+#[wasm_bindgen]
+impl Decryptor {
+    /// Create a decryptor from a 16, 24 or 32-byte AES key and 16-byte IV
+    /// 
+    /// `options` is an optional `{ counter_width, length_trailer }` object,
+    /// see `CounterWidth` and the `trailer` module.
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array, iv: &Uint8Array, options: Option<js_sys::Object>) -> Result<Decryptor, JsValue> {
+        validate_aes_key(key)?;
+
+        let trailer = length_trailer_from_js(options.as_ref())?;
+        Ok(Decryptor::from_bytes(&key.to_vec(), &iv.to_vec(), &ctr_options_from_js(options.as_ref())?, trailer)?)
+    }
+
+    /// Create a decryptor from a KeyHandle and 16-byte IV
+    pub fn from_handle(key: &KeyHandle, iv: &Uint8Array, options: Option<js_sys::Object>) -> Result<Decryptor, JsValue> {
+        let trailer = length_trailer_from_js(options.as_ref())?;
+        Ok(Decryptor::from_bytes(key.bytes(), &iv.to_vec(), &ctr_options_from_js(options.as_ref())?, trailer)?)
+    }
+
+    /// Recreate a decryptor from `export_state()` to resume an interrupted stream
+    /// 
+    /// The key and IV are not part of the state and must be supplied again;
+    /// a state exported under a different key or IV is rejected.
+    /// 
+    /// # Arguments
+    /// * `key` - The same 16, 24 or 32-byte AES key as Uint8Array
+    /// * `iv` - The same 16-byte initialization vector as Uint8Array
+    /// * `state` - Bytes returned by `export_state()`
+    /// * `options` - The same `{ counter_width }` options the stream was started with
+    pub fn import_state(key: &Uint8Array, iv: &Uint8Array, state: &Uint8Array, options: Option<js_sys::Object>) -> Result<Decryptor, JsValue> {
+        let decryptor = Decryptor::new(key, iv, options)?;
+        let (position, check) = decode_decryptor_state(&state.to_vec())?;
+
+        if check != decryptor.state_check {
+            return Err(CryptoError::InvalidKey("Decryptor state was exported with a different key or IV".into()).into());
+        }
+
+        let decryptor = decryptor.starting_at(position)?;
+
+        log!("Decryptor: Resumed at byte {}", position);
+
+        Ok(decryptor)
+    }
+
+    /// Decrypt the next chunk of ciphertext and return its plaintext
+    pub fn update(&mut self, chunk: &Uint8Array) -> Result<Uint8Array, JsValue> {
+        let mut data = metrics::copy(|| chunk.to_vec());
+        self.decrypt(&mut data)?;
+
+        Ok(metrics::copy(|| Uint8Array::from(data.as_slice())))
+    }
+
+    /// Finish the stream. Returns any remaining plaintext (always empty for
+    /// CTR mode); further calls to `update` are rejected.
+    /// 
+    /// With `length_trailer`, throws `Truncated` if the trailer is missing or
+    /// commits to more bytes than were decrypted.
+    pub fn finalize(&mut self) -> Result<Uint8Array, JsValue> {
+        if self.cancelled {
+            return Err(CryptoError::Aborted("Decryptor was cancelled".into()).into());
+        }
+        if self.finalized {
+            return Err(CryptoError::InvalidState("Decryptor has already been finalized".into()).into());
+        }
+
+        self.finalized = true;
+        if let Some(trailer) = &self.trailer {
+            trailer.check(self.bytes_processed)?;
+        }
+
+        log!("Decryptor: Finalized after {} bytes", self.bytes_processed);
+
+        Ok(Uint8Array::new_with_length(0))
+    }
+
+    /// Total number of ciphertext bytes decrypted so far (BigInt)
+    #[wasm_bindgen(getter)]
+    pub fn bytes_processed(&self) -> u64 {
+        self.bytes_processed
+    }
+
+    /// Snapshot the stream position so decryption can resume after a reload
+    /// 
+    /// The state is 17 bytes: a version, the number of bytes processed and a
+    /// short check value derived from the key and IV. It contains no key
+    /// material, so it can be kept in IndexedDB or `localStorage` (via
+    /// `key_to_base64`) next to the partial download. Pass it to
+    /// `Decryptor.import_state` with the same key and IV, then continue with
+    /// the ciphertext from `bytes_processed` onwards.
+    pub fn export_state(&self) -> Result<Uint8Array, JsValue> {
+        if self.cancelled || self.finalized {
+            return Err(CryptoError::InvalidState("Decryptor has already been finalized or cancelled".into()).into());
+        }
+
+        Ok(Uint8Array::from(&encode_decryptor_state(self.bytes_processed, &self.state_check)[..]))
+    }
+
+    /// Stop the stream early, e.g. when the user cancels a download
+    /// 
+    /// Later `update` and `finalize` calls throw an `Aborted` error. Call
+    /// `free()` afterwards to release the key schedule immediately rather
+    /// than waiting for garbage collection.
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+
+        log!("Decryptor: Cancelled after {} bytes", self.bytes_processed);
+    }
+}
+
+impl Decryptor {
+    /// Create a decryptor from raw key and IV bytes
+    fn from_bytes(key_bytes: &[u8], iv_bytes: &[u8], options: &CtrOptions, length_trailer: bool) -> Result<Decryptor, CryptoError> {
+        let cipher = AesCtr::with_options(key_bytes, iv_bytes, options)?;
+        let trailer = length_trailer.then(|| TrailerCheck::new(key_bytes, iv_bytes)).transpose()?;
+
+        log!("Decryptor: Created AES-{}-CTR stream", key_bytes.len() * 8);
+        metrics::operation();
+
+        Ok(Decryptor {
+            cipher,
+            trailer,
+            bytes_processed: 0,
+            finalized: false,
+            cancelled: false,
+            state_check: decryptor_state_check(key_bytes, iv_bytes),
+        })
+    }
+
+    /// Move to `offset` in the keystream, for ciphertext starting partway through a file
+    fn starting_at(mut self, offset: u64) -> Result<Decryptor, CryptoError> {
+        self.cipher.seek(offset)?;
+        self.bytes_processed = offset;
+        Ok(self)
+    }
+
+    /// Decrypt the next chunk of a stream, holding back a trailing length
+    /// trailer so `data` may come back shorter
+    fn decrypt(&mut self, data: &mut Vec<u8>) -> Result<(), JsValue> {
+        if let Some(trailer) = &mut self.trailer {
+            trailer.hold_back(data);
+        }
+        self.apply(data)
+    }
+
+    /// Decrypt the next chunk in place
+    fn apply(&mut self, data: &mut [u8]) -> Result<(), JsValue> {
+        if self.cancelled {
+            return Err(CryptoError::Aborted("Decryptor was cancelled".into()).into());
+        }
+        if self.finalized {
+            return Err(CryptoError::InvalidState("Decryptor has already been finalized".into()).into());
+        }
+
+        metrics::cipher(data.len(), || self.cipher.apply_keystream(data))?;
+        self.bytes_processed += data.len() as u64;
+
+        Ok(())
+    }
+}
+
+const DECRYPTOR_STATE_VERSION: u8 = 1;
+const DECRYPTOR_STATE_LEN: usize = 17;
+
+/// Check value tying an exported state to its key and IV, without revealing either
+fn decryptor_state_check(key: &[u8], iv: &[u8]) -> [u8; 8] {
+    let mut hasher = blake3::Hasher::new_derive_key("furl v1 decryptor state check");
+    hasher.update(key).update(iv);
+
+    let mut check = [0u8; 8];
+    check.copy_from_slice(&hasher.finalize().as_bytes()[..8]);
+    check
+}
+
+/// Serialize a decryptor position: version | bytes processed (u64 BE) | check
+fn encode_decryptor_state(position: u64, check: &[u8; 8]) -> [u8; DECRYPTOR_STATE_LEN] {
+    let mut state = [0u8; DECRYPTOR_STATE_LEN];
+    state[0] = DECRYPTOR_STATE_VERSION;
+    state[1..9].copy_from_slice(&position.to_be_bytes());
+    state[9..].copy_from_slice(check);
+    state
+}
+
+fn decode_decryptor_state(state: &[u8]) -> Result<(u64, [u8; 8]), CryptoError> {
+    let state: &[u8; DECRYPTOR_STATE_LEN] = state
+        .try_into()
+        .map_err(|_| CryptoError::InvalidFormat(format!("Invalid decryptor state: expected {} bytes, got {}", DECRYPTOR_STATE_LEN, state.len())))?;
+
+    if state[0] != DECRYPTOR_STATE_VERSION {
+        return Err(CryptoError::Unsupported(format!("Unsupported decryptor state version: {}", state[0])));
+    }
+
+    let mut position = [0u8; 8];
+    position.copy_from_slice(&state[1..9]);
+    let mut check = [0u8; 8];
+    check.copy_from_slice(&state[9..]);
+
+    Ok((u64::from_be_bytes(position), check))
+}
+
+/// Create a Web Streams `TransformStream` that decrypts AES-CTR ciphertext
+/// 
+/// Each `Uint8Array` chunk written to the stream is decrypted with a
+/// `Decryptor` and enqueued as plaintext, so callers can simply do
+/// `fetch(url).then(r => r.body.pipeThrough(create_decrypt_stream(key, iv)).pipeTo(sink))`.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `options` - Optional `{ counter_width, rfc3686, decompress, length_trailer }` object; `decompress` is `"gzip"`, `"deflate"`, `"deflate-raw"` or `"br"` to inflate the plaintext as it streams, and `length_trailer` checks a trailer from `ctr_length_trailer` at the end
+/// 
+/// # Returns
+/// A TransformStream from ciphertext to plaintext Uint8Array chunks
+#[wasm_bindgen]
+pub fn create_decrypt_stream(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    options: Option<js_sys::Object>
+) -> Result<web_sys::TransformStream, JsValue> {
+    let decompressor = Decompressor::new(Compression::from_js(options.as_ref())?);
+
+    decrypt_transform(Decryptor::new(key, iv, options)?, decompressor)
+}
+
+/// Wrap a decryptor (and optional decompressor) in a `TransformStream`
+pub(crate) fn decrypt_transform(decryptor: Decryptor, decompressor: Decompressor) -> Result<web_sys::TransformStream, JsValue> {
+    // Shared between the transform and flush callbacks; flush drops the
+    // decryptor so the key schedule is released when the stream closes
+    let decryptor = Rc::new(RefCell::new(Some((decryptor, decompressor))));
+
+    let transform_state = decryptor.clone();
+    let transform = Closure::<dyn FnMut(JsValue, web_sys::TransformStreamDefaultController) -> Result<(), JsValue>>::new(
+        move |chunk: JsValue, controller: web_sys::TransformStreamDefaultController| {
+            let chunk = chunk
+                .dyn_into::<Uint8Array>()
+                .map_err(|_| CryptoError::StreamError("Decrypt stream chunks must be Uint8Array".into()))?;
+            let mut state = transform_state.borrow_mut();
+            let (decryptor, decompressor) = state
+                .as_mut()
+                .ok_or_else(|| CryptoError::InvalidState("Decrypt stream has already been closed".into()))?;
+            let mut data = metrics::copy(|| chunk.to_vec());
+            decryptor.decrypt(&mut data)?;
+            let plaintext = decompressor.update(data)?;
+            if plaintext.is_empty() {
+                return Ok(());
+            }
+            controller.enqueue_with_chunk(&metrics::copy(|| Uint8Array::from(plaintext.as_slice())))
+        },
+    );
+
+    let flush = Closure::<dyn FnMut(web_sys::TransformStreamDefaultController) -> Result<(), JsValue>>::new(
+        move |controller: web_sys::TransformStreamDefaultController| {
+            if let Some((mut decryptor, decompressor)) = decryptor.borrow_mut().take() {
+                decryptor.finalize()?;
+                let remaining = decompressor.finish()?;
+                if !remaining.is_empty() {
+                    controller.enqueue_with_chunk(&Uint8Array::from(remaining.as_slice()))?;
+                }
+            }
+            Ok(())
+        },
+    );
+
+    let transformer = web_sys::Transformer::new();
+    transformer.set_transform(transform.into_js_value().unchecked_ref());
+    transformer.set_flush(flush.into_js_value().unchecked_ref());
+
+    web_sys::TransformStream::new_with_transformer(&transformer)
+}
+
+/// Decrypt AES-CTR ciphertext pulled directly from a `ReadableStream`
+/// 
+/// Reads the stream (e.g. `response.body` from `fetch`) to completion inside
+/// wasm, decrypting each chunk as it arrives.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `stream` - ReadableStream yielding Uint8Array ciphertext chunks
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk (`bytes_done`, `bytes_per_second`, ...; totals are null for streams)
+/// * `signal` - Optional AbortSignal; aborting it cancels the stream and rejects with the signal's reason (pass the same signal to `fetch` so a stalled read stops too)
+/// * `options` - Optional `{ counter_width, rfc3686, decompress, memory_budget, on_flush, length_trailer }` object; `decompress` is `"gzip"`, `"deflate"`, `"deflate-raw"` or `"br"` to inflate the plaintext as it streams, `memory_budget` / `on_flush` cap the plaintext held (see the `budget` module), and `length_trailer` checks a trailer from `ctr_length_trailer` at the end
+/// 
+/// # Returns
+/// Promise resolving to the decrypted data as Uint8Array (with `memory_budget`, only the part not passed to `on_flush`)
+#[wasm_bindgen]
+pub async fn decrypt_from_stream(
+    key: Uint8Array,
+    iv: Uint8Array,
+    stream: web_sys::ReadableStream,
+    progress_callback: Option<js_sys::Function>,
+    signal: Option<web_sys::AbortSignal>,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    let mut decompressor = Decompressor::new(Compression::from_js(options.as_ref())?);
+    let budget = MemoryBudget::from_js(options.as_ref())?;
+    let mut decryptor = Decryptor::new(&key, &iv, options)?;
+    let reader = web_sys::ReadableStreamDefaultReader::new(&stream)?;
+    let mut result = budget.buffer(None);
+    let progress = Progress::new(progress_callback.as_ref(), None, None);
+
+    log!("WASM: Decrypting from ReadableStream");
+
+    let mut chunk_index = 0;
+    while let Some(mut data) = read_stream_chunk(&reader).await? {
+        if let Err(error) = check_aborted(signal.as_ref()) {
+            let _ = reader.cancel_with_reason(&error);
+            return Err(error);
+        }
+        decryptor.decrypt(&mut data)?;
+        if let Err(error) = result.push(&decompressor.update(data)?) {
+            let _ = reader.cancel_with_reason(&error);
+            return Err(error);
+        }
+
+        progress.report(decryptor.bytes_processed(), chunk_index);
+        chunk_index += 1;
+    }
+
+    reader.release_lock();
+    decryptor.finalize()?;
+    result.push(&decompressor.finish()?)?;
+
+    log!("WASM: Successfully decrypted {} bytes from stream", result.total());
+
+    let result = result.into_inner();
+    Ok(metrics::copy(|| Uint8Array::from(result.as_slice())))
+}
+
+/// Decrypt a ReadableStream of AES-CTR ciphertext into a `WritableStream`
+/// 
+/// Plaintext is written chunk by chunk and never accumulated in wasm memory,
+/// so this works for files far larger than the tab could hold. Any
+/// `WritableStream` is accepted, including the `FileSystemWritableFileStream`
+/// returned by `showSaveFilePicker()` for writing straight to disk. The sink
+/// is closed on success and aborted if decryption fails.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `stream` - ReadableStream yielding Uint8Array ciphertext chunks
+/// * `sink` - WritableStream receiving Uint8Array plaintext chunks
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk (`bytes_done`, `bytes_per_second`, ...; totals are null for streams)
+/// * `signal` - Optional AbortSignal; aborting it cancels the stream, aborts the sink and rejects with the signal's reason
+/// * `options` - Optional `{ counter_width, rfc3686, decompress, length_trailer }` object; `decompress` is `"gzip"`, `"deflate"`, `"deflate-raw"` or `"br"` to inflate the plaintext as it streams, and `length_trailer` checks a trailer from `ctr_length_trailer` at the end
+/// 
+/// # Returns
+/// Promise resolving to the total number of bytes written (BigInt)
+#[wasm_bindgen]
+pub async fn decrypt_stream_to_sink(
+    key: Uint8Array,
+    iv: Uint8Array,
+    stream: web_sys::ReadableStream,
+    sink: web_sys::WritableStream,
+    progress_callback: Option<js_sys::Function>,
+    signal: Option<web_sys::AbortSignal>,
+    options: Option<js_sys::Object>
+) -> Result<u64, JsValue> {
+    let mut decompressor = Decompressor::new(Compression::from_js(options.as_ref())?);
+    let mut decryptor = Decryptor::new(&key, &iv, options)?;
+    let reader = web_sys::ReadableStreamDefaultReader::new(&stream)?;
+    let writer = sink.get_writer()?;
+
+    log!("WASM: Decrypting from ReadableStream into WritableStream");
+
+    let pumped = async {
+        let written = pump_stream_to_writer(&reader, &writer, &mut decryptor, &mut decompressor, progress_callback.as_ref(), signal.as_ref()).await?;
+        decryptor.finalize()?;
+        Ok::<_, JsValue>(written + finish_sink(&writer, decompressor).await?)
+    }
+    .await;
+
+    let written = match pumped {
+        Ok(written) => written,
+        Err(error) => {
+            let _ = writer.abort_with_reason(&error);
+            let _ = reader.cancel_with_reason(&error);
+            return Err(error);
+        }
+    };
+
+    reader.release_lock();
+    JsFuture::from(writer.close()).await?;
+
+    log!("WASM: Successfully wrote {} decrypted bytes to sink", written);
+
+    Ok(written)
+}
+
+/// Write one plaintext chunk to `writer` once it has room
+async fn write_to_sink(writer: &web_sys::WritableStreamDefaultWriter, data: &[u8]) -> Result<(), JsValue> {
+    // Wait for the sink to drain before queueing more plaintext
+    JsFuture::from(writer.ready()).await?;
+    JsFuture::from(writer.write_with_chunk(&metrics::copy(|| Uint8Array::from(data)))).await?;
+    Ok(())
+}
+
+/// Write out whatever the decompressor still holds, returning its length
+async fn finish_sink(writer: &web_sys::WritableStreamDefaultWriter, decompressor: Decompressor) -> Result<u64, JsValue> {
+    let tail = decompressor.finish()?;
+    if !tail.is_empty() {
+        write_to_sink(writer, &tail).await?;
+    }
+    Ok(tail.len() as u64)
+}
 
-        // Create cipher for this chunk
-        let mut cipher = Aes256Ctr::new(
-            (&key_bytes[..]).try_into().map_err(|_| JsValue::from_str("Invalid key format"))?,
-            (&chunk_iv[..]).try_into().map_err(|_| JsValue::from_str("Invalid IV format"))?
-        );
+/// Decrypt every chunk from `reader` and write it to `writer`, honouring
+/// backpressure; returns the number of bytes written
+async fn pump_stream_to_writer(
+    reader: &web_sys::ReadableStreamDefaultReader,
+    writer: &web_sys::WritableStreamDefaultWriter,
+    decryptor: &mut Decryptor,
+    decompressor: &mut Decompressor,
+    progress_callback: Option<&js_sys::Function>,
+    signal: Option<&web_sys::AbortSignal>
+) -> Result<u64, JsValue> {
+    let progress = Progress::new(progress_callback, None, None);
+    let mut written = 0u64;
 
-        // Decrypt this chunk
-        let mut chunk_data = chunk.to_vec();
-        cipher.apply_keystream(&mut chunk_data);
-        result.extend_from_slice(&chunk_data);
+    let mut chunk_index = 0;
+    while let Some(mut data) = read_stream_chunk(reader).await? {
+        check_aborted(signal)?;
+        decryptor.decrypt(&mut data)?;
 
-        // Call progress callback if provided
-        if let Some(ref callback) = progress_callback {
-            let progress = ((chunk_idx + 1) as f64 / total_chunks as f64 * 100.0) as u32;
-            let _ = callback.call1(&JsValue::NULL, &JsValue::from(progress));
+        let plaintext = decompressor.update(data)?;
+        if !plaintext.is_empty() {
+            write_to_sink(writer, &plaintext).await?;
+            written += plaintext.len() as u64;
         }
 
-        log!("WASM: Processed chunk {}/{} ({} bytes)", chunk_idx + 1, total_chunks, chunk.len());
-    }
+        progress.report(decryptor.bytes_processed(), chunk_index);
+        chunk_index += 1;
+    }
+
+    Ok(written)
+}
+
+/// Fail with the signal's abort reason once it has been aborted
+fn check_aborted(signal: Option<&web_sys::AbortSignal>) -> Result<(), JsValue> {
+    match signal {
+        Some(signal) if signal.aborted() => {
+            let reason = signal.reason();
+            if reason.is_undefined() {
+                Err(CryptoError::Aborted("Decryption was aborted".into()).into())
+            } else {
+                Err(reason)
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Read the next chunk from a stream reader, or `None` once the stream is done
+async fn read_stream_chunk(reader: &web_sys::ReadableStreamDefaultReader) -> Result<Option<Vec<u8>>, JsValue> {
+    let chunk: web_sys::ReadableStreamReadResult = JsFuture::from(reader.read()).await?.unchecked_into();
+
+    if chunk.get_done().unwrap_or(false) {
+        return Ok(None);
+    }
+
+    match chunk.get_value().dyn_into::<Uint8Array>() {
+        Ok(chunk) => Ok(Some(metrics::copy(|| chunk.to_vec()))),
+        Err(_) => {
+            let _ = reader.cancel();
+            Err(CryptoError::StreamError("Stream chunks must be Uint8Array".into()).into())
+        }
+    }
+}
+
+/// Decrypt AES-CTR data without blocking the page
+/// 
+/// Works through the data in 64KB chunks and hands control back to the event
+/// loop (via `setTimeout`) roughly every `slice_ms` milliseconds, so rendering
+/// and input stay responsive during a large decrypt on the main thread.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - Encrypted data as Uint8Array
+/// * `slice_ms` - Optional time budget per slice in milliseconds (default 8)
+/// * `progress_callback` - Optional callback receiving a progress report after each slice (`bytes_done`, `percent`, `bytes_per_second`, `remaining_ms`, ...)
+/// * `signal` - Optional AbortSignal; aborting it stops the chunk loop and rejects with the signal's reason
+/// 
+/// # Returns
+/// Promise resolving to the decrypted data as Uint8Array
+#[wasm_bindgen]
+pub async fn decrypt_aes_ctr_async(
+    key: Uint8Array,
+    iv: Uint8Array,
+    encrypted_data: Uint8Array,
+    slice_ms: Option<f64>,
+    progress_callback: Option<js_sys::Function>,
+    signal: Option<web_sys::AbortSignal>
+) -> Result<Uint8Array, JsValue> {
+    let mut decryptor = Decryptor::new(&key, &iv, None)?;
+    let mut data_bytes = metrics::copy(|| heap::try_to_vec(&encrypted_data))?;
+    let slice_ms = slice_ms.unwrap_or(ASYNC_SLICE_MS);
+
+    log!("WASM: Async decryption of {} bytes in {}ms slices", data_bytes.len(), slice_ms);
+
+    let total_len = data_bytes.len() as u64;
+    let progress = Progress::new(progress_callback.as_ref(), Some(total_len), Some(ASYNC_CHUNK_SIZE as u64));
+
+    let mut slice_start = js_sys::Date::now();
+    for (chunk_index, chunk) in data_bytes.chunks_mut(ASYNC_CHUNK_SIZE).enumerate() {
+        check_aborted(signal.as_ref())?;
+        decryptor.apply(chunk)?;
+
+        if decryptor.bytes_processed() == total_len {
+            progress.report(total_len, chunk_index as u64);
+        } else if js_sys::Date::now() - slice_start >= slice_ms {
+            progress.report(decryptor.bytes_processed(), chunk_index as u64);
+            yield_to_event_loop().await?;
+            slice_start = js_sys::Date::now();
+        }
+    }
+
+    decryptor.finalize()?;
+
+    log!("WASM: Successfully decrypted {} bytes", data_bytes.len());
+
+    Ok(metrics::copy(|| Uint8Array::from(data_bytes.as_slice())))
+}
+
+/// Resolve on the next macrotask, letting the browser render and handle input
+/// 
+/// Uses the global `setTimeout`, so it works on the main thread and in workers.
+async fn yield_to_event_loop() -> Result<(), JsValue> {
+    let set_timeout: js_sys::Function = js_sys::Reflect::get(&js_sys::global(), &"setTimeout".into())?
+        .dyn_into()
+        .map_err(|_| CryptoError::Unsupported("setTimeout is not available in this environment".into()))?;
+
+    let tick = js_sys::Promise::new(&mut |resolve, _reject| {
+        let _ = set_timeout.call2(&JsValue::NULL, &resolve, &JsValue::from(0));
+    });
+    JsFuture::from(tick).await?;
+
+    Ok(())
+}
+
+/// Copy an ArrayBuffer, SharedArrayBuffer, any typed array or a DataView into wasm memory
+pub(crate) fn bytes_from_js(value: &JsValue) -> Result<Vec<u8>, CryptoError> {
+    if let Some(array) = value.dyn_ref::<Uint8Array>() {
+        return Ok(array.to_vec());
+    }
+    if value.is_instance_of::<js_sys::ArrayBuffer>() || value.is_instance_of::<js_sys::SharedArrayBuffer>() {
+        return Ok(Uint8Array::new(value).to_vec());
+    }
+    if js_sys::ArrayBuffer::is_view(value) {
+        // Typed arrays and DataView share the buffer/byteOffset/byteLength getters
+        let view = value.unchecked_ref::<js_sys::DataView>();
+        return Ok(Uint8Array::new_with_byte_offset_and_length(&view.buffer(), view.byte_offset() as u32, view.byte_length() as u32).to_vec());
+    }
+
+    Err(CryptoError::InvalidInput("Invalid data: expected an ArrayBuffer, typed array or DataView".into()))
+}
+
+/// Decrypt AES-CTR ciphertext held in an ArrayBuffer or any ArrayBuffer view
+/// 
+/// Same as `decrypt_aes_ctr`, but accepts the `ArrayBuffer` returned by
+/// `response.arrayBuffer()` or `FileReader` without wrapping it first. A
+/// `SharedArrayBuffer` or a view over one is accepted too.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - ArrayBuffer, SharedArrayBuffer, typed array or DataView
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array
+#[wasm_bindgen]
+pub fn decrypt_aes_ctr_buffer(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    encrypted_data: &JsValue
+) -> Result<Uint8Array, JsValue> {
+    validate_aes_key(key)?;
+
+    if iv.length() != 16 {
+        return Err(CryptoError::InvalidIv(format!("Invalid IV size: expected 16 bytes, got {}", iv.length())).into());
+    }
+
+    let mut data_bytes = metrics::copy(|| bytes_from_js(encrypted_data))?;
+    let mut cipher = AesCtr::new(&key.to_vec(), &iv.to_vec())?;
+    metrics::operation();
+    metrics::cipher(data_bytes.len(), || cipher.apply_keystream(&mut data_bytes))?;
+
+    log!("WASM: Decrypted {} bytes from ArrayBuffer", data_bytes.len());
+
+    Ok(metrics::copy(|| Uint8Array::from(data_bytes.as_slice())))
+}
+
+/// Decrypt an AES-CTR encrypted `Blob` or `File`
+/// 
+/// The blob is read one slice at a time with `Blob.arrayBuffer()`, so a `File`
+/// from `<input type="file">` or drag-and-drop can be passed straight in and
+/// only one slice of ciphertext is held in memory alongside the plaintext.
+/// For output larger than memory, pass `blob.stream()` to
+/// `decrypt_stream_to_sink` instead.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `blob` - Blob or File holding the ciphertext
+/// * `chunk_size` - Optional slice size in bytes (default 1MB)
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk (`bytes_done`, `percent`, `bytes_per_second`, `remaining_ms`, ...)
+/// * `signal` - Optional AbortSignal; aborting it stops the slice loop and rejects with the signal's reason
+/// 
+/// # Returns
+/// Promise resolving to the decrypted data as Uint8Array
+#[wasm_bindgen]
+pub async fn decrypt_aes_ctr_blob(
+    key: Uint8Array,
+    iv: Uint8Array,
+    blob: web_sys::Blob,
+    chunk_size: Option<u32>,
+    progress_callback: Option<js_sys::Function>,
+    signal: Option<web_sys::AbortSignal>
+) -> Result<Uint8Array, JsValue> {
+    let chunk_size = chunk_size.unwrap_or(BLOB_SLICE_SIZE) as u64;
+    if chunk_size == 0 {
+        return Err(CryptoError::InvalidInput("Invalid chunk size: must be at least 1 byte".into()).into());
+    }
+
+    let mut decryptor = Decryptor::new(&key, &iv, None)?;
+    let total_len = blob.size() as u64;
+
+    // Reserve the whole plaintext up front so the slices are copied in exactly once
+    let mut result = Vec::new();
+    usize::try_from(total_len)
+        .ok()
+        .and_then(|len| result.try_reserve_exact(len).ok())
+        .ok_or_else(|| CryptoError::OutOfMemory(format!("Failed to allocate {} bytes", total_len)))?;
+
+    log!("WASM: Decrypting {} byte Blob in {} byte slices", total_len, chunk_size);
+
+    let progress = Progress::new(progress_callback.as_ref(), Some(total_len), Some(chunk_size));
+    let mut offset = 0u64;
+    while offset < total_len {
+        check_aborted(signal.as_ref())?;
+        let end = (offset + chunk_size).min(total_len);
+        let slice = blob.slice_with_f64_and_f64(offset as f64, end as f64)?;
+        let buffer = Uint8Array::new(&JsFuture::from(slice.array_buffer()).await?);
+
+        let start = result.len();
+        result.resize(start + buffer.length() as usize, 0);
+        metrics::copy(|| buffer.copy_to(&mut result[start..]));
+        decryptor.apply(&mut result[start..])?;
+        progress.report(end, offset / chunk_size);
+        offset = end;
+    }
+
+    decryptor.finalize()?;
+
+    log!("WASM: Successfully decrypted {} bytes from Blob", result.len());
+
+    Ok(metrics::copy(|| Uint8Array::from(result.as_slice())))
+}
+
+/// Decrypt data using AES-256-CBC and strip PKCS#7 padding
+/// 
+/// # Arguments
+/// * `key` - 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - Encrypted data as Uint8Array (a non-empty multiple of 16 bytes)
+/// 
+/// # Returns
+/// Decrypted, unpadded data as Uint8Array. Malformed padding is reported as a
+/// JS `Error` whose `name` is `InvalidPadding`.
+#[wasm_bindgen]
+pub fn decrypt_aes_cbc(
+    key: &Uint8Array,
+    iv: &Uint8Array,
+    encrypted_data: &Uint8Array
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    if key.length() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
+    }
+
+    if iv.length() != 16 {
+        return Err(CryptoError::InvalidIv(format!("Invalid IV size: expected 16 bytes, got {}", iv.length())).into());
+    }
+
+    if encrypted_data.length() == 0 || !encrypted_data.length().is_multiple_of(16) {
+        return Err(CryptoError::InvalidInput(format!("Invalid data size: expected a non-empty multiple of 16 bytes, got {}", encrypted_data.length())).into());
+    }
+
+    let key_bytes = key.to_vec();
+    let iv_bytes = iv.to_vec();
+    let data = encrypted_data.to_vec();
+
+    log!("AES-CBC: Decrypting {} bytes", data.len());
+
+    let cipher = Aes256CbcDec::new_from_slices(&key_bytes, &iv_bytes)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key or IV format".into()))?;
 
-    log!("WASM: Successfully decrypted {} bytes in {} chunks", result.len(), total_chunks);
+    let plaintext = cipher.decrypt_padded_vec_mut::<Pkcs7>(&data).map_err(|_| {
+        CryptoError::InvalidPadding("Invalid PKCS#7 padding: wrong key or corrupted ciphertext".into())
+    })?;
 
-    Ok(Uint8Array::from(&result[..]))
-}
+    log!("AES-CBC: Successfully decrypted {} bytes", plaintext.len());
 
-/// Increment a 16-byte counter by the specified number of blocks
-fn increment_counter(counter: &mut [u8], blocks: usize) {
-    let mut carry = blocks;
-    
-    // Work from right to left (big-endian)
-    for i in (0..counter.len()).rev() {
-        if carry == 0 {
-            break;
-        }
-        
-        let sum = counter[i] as usize + (carry & 0xFF);
-        counter[i] = (sum & 0xFF) as u8;
-        carry >>= 8;
-    }
+    Ok(Uint8Array::from(plaintext.as_slice()))
 }
 
 /// Decrypt data using ChaCha20 stream cipher
@@ -184,11 +1534,11 @@ pub fn decrypt_chacha20(
 ) -> Result<Uint8Array, JsValue> {
     // Validate input sizes
     if key.length() != 32 {
-        return Err(JsValue::from_str(&format!("Invalid key size: expected 32 bytes, got {}", key.length())));
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
     }
     
     if nonce.length() != 8 && nonce.length() != 12 {
-        return Err(JsValue::from_str(&format!("Invalid nonce size: expected 8 or 12 bytes, got {}", nonce.length())));
+        return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 8 or 12 bytes, got {}", nonce.length())).into());
     }
 
     // Convert JS arrays to Rust vectors
@@ -200,12 +1550,12 @@ pub fn decrypt_chacha20(
 
     // Create ChaCha20 cipher
     let key_array: [u8; 32] = key_bytes.try_into()
-        .map_err(|_| JsValue::from_str("Failed to convert key to fixed array"))?;
+        .map_err(|_| CryptoError::InvalidKey("Failed to convert key to fixed array".into()))?;
 
     let mut cipher = if nonce_bytes.len() == 12 {
         // Standard 12-byte nonce
         let nonce_array: [u8; 12] = nonce_bytes.try_into()
-            .map_err(|_| JsValue::from_str("Failed to convert 12-byte nonce to fixed array"))?;
+            .map_err(|_| CryptoError::InvalidIv("Failed to convert 12-byte nonce to fixed array".into()))?;
         ChaCha20::new(
             Key::from_slice(&key_array),
             Nonce::from_slice(&nonce_array)
@@ -236,7 +1586,7 @@ pub fn decrypt_chacha20(
 /// * `nonce` - 8 or 12-byte ChaCha20 nonce as Uint8Array
 /// * `encrypted_data` - Encrypted data as Uint8Array
 /// * `chunk_size` - Size of chunks to process (for memory management)
-/// * `progress_callback` - Optional JavaScript function to call with progress updates
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk (`bytes_done`, `percent`, `bytes_per_second`, `remaining_ms`, ...)
 /// 
 /// # Returns
 /// Decrypted data as Uint8Array
@@ -250,12 +1600,13 @@ pub fn decrypt_chacha20_chunked(
 ) -> Result<Uint8Array, JsValue> {
     // Validate inputs
     if key.length() != 32 {
-        return Err(JsValue::from_str(&format!("Invalid key size: expected 32 bytes, got {}", key.length())));
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
     }
     
     if nonce.length() != 8 && nonce.length() != 12 {
-        return Err(JsValue::from_str(&format!("Invalid nonce size: expected 8 or 12 bytes, got {}", nonce.length())));
+        return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 8 or 12 bytes, got {}", nonce.length())).into());
     }
+    check_chunk_size(chunk_size)?;
 
     let key_bytes = key.to_vec();
     let nonce_bytes = nonce.to_vec();
@@ -266,12 +1617,12 @@ pub fn decrypt_chacha20_chunked(
 
     // Create ChaCha20 cipher
     let key_array: [u8; 32] = key_bytes.try_into()
-        .map_err(|_| JsValue::from_str("Failed to convert key to fixed array"))?;
+        .map_err(|_| CryptoError::InvalidKey("Failed to convert key to fixed array".into()))?;
 
     let mut cipher = if nonce_bytes.len() == 12 {
         // Standard 12-byte nonce
         let nonce_array: [u8; 12] = nonce_bytes.try_into()
-            .map_err(|_| JsValue::from_str("Failed to convert 12-byte nonce to fixed array"))?;
+            .map_err(|_| CryptoError::InvalidIv("Failed to convert 12-byte nonce to fixed array".into()))?;
         ChaCha20::new(
             Key::from_slice(&key_array),
             Nonce::from_slice(&nonce_array)
@@ -287,18 +1638,15 @@ pub fn decrypt_chacha20_chunked(
     };
 
     // Process data in chunks for better memory management and progress reporting
-    let num_chunks = (total_len + chunk_size - 1) / chunk_size;
+    let progress = Progress::new(progress_callback.as_ref(), Some(total_len as u64), Some(chunk_size as u64));
+    let mut bytes_done = 0;
     
     for (chunk_idx, chunk) in data.chunks_mut(chunk_size).enumerate() {
         // Decrypt this chunk in-place
         cipher.apply_keystream(chunk);
         
-        // Report progress if callback provided
-        if let Some(callback) = &progress_callback {
-            let progress = ((chunk_idx + 1) as f64 / num_chunks as f64 * 100.0) as u32;
-            let progress_value = JsValue::from(progress);
-            let _ = callback.call1(&JsValue::NULL, &progress_value);
-        }
+        bytes_done += chunk.len() as u64;
+        progress.report(bytes_done, chunk_idx as u64);
     }
 
     log!("ChaCha20 Chunked: Successfully decrypted {} bytes", total_len);
@@ -307,6 +1655,331 @@ pub fn decrypt_chacha20_chunked(
     Ok(Uint8Array::from(data.as_slice()))
 }
 
+/// Decrypt and authenticate data using AES-256-GCM
+/// 
+/// # Arguments
+/// * `key` - 32-byte AES key as Uint8Array
+/// * `nonce` - 12-byte GCM nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte authentication tag
+///   (the layout produced by WebCrypto and most GCM implementations)
+/// * `aad` - Optional associated data the tag was computed over, such as a
+///   chunk index or file id; decryption fails unless it matches exactly
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_aes_gcm(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    if key.length() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
+    }
+
+    if nonce.length() != 12 {
+        return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 12 bytes, got {}", nonce.length())).into());
+    }
+
+    if encrypted_data.length() < 16 {
+        return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", encrypted_data.length())).into());
+    }
+
+    let key_bytes = key.to_vec();
+    let nonce_bytes = nonce.to_vec();
+    let data = encrypted_data.to_vec();
+
+    log!("AES-GCM: Decrypting {} bytes", data.len() - 16);
+
+    let cipher = Aes256Gcm::new_from_slice(&key_bytes)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    // Decrypt and verify the trailing tag in one step
+    let plaintext = cipher
+        .decrypt(aes_gcm::Nonce::from_slice(&nonce_bytes), Payload { msg: &data, aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))?;
+
+    log!("AES-GCM: Successfully decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// Decrypt and authenticate data using ChaCha20-Poly1305 (RFC 8439)
+/// 
+/// # Arguments
+/// * `key` - 32-byte ChaCha20-Poly1305 key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte Poly1305 tag
+/// * `aad` - Optional associated data the tag was computed over, such as a
+///   chunk index or file id; decryption fails unless it matches exactly
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_chacha20poly1305(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    if key.length() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
+    }
+
+    if nonce.length() != 12 {
+        return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 12 bytes, got {}", nonce.length())).into());
+    }
+
+    if encrypted_data.length() < 16 {
+        return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", encrypted_data.length())).into());
+    }
+
+    let key_bytes = key.to_vec();
+    let nonce_bytes = nonce.to_vec();
+    let data = encrypted_data.to_vec();
+
+    log!("ChaCha20-Poly1305: Decrypting {} bytes", data.len() - 16);
+
+    let cipher = ChaCha20Poly1305::new_from_slice(&key_bytes)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    let plaintext = cipher
+        .decrypt(chacha20poly1305::Nonce::from_slice(&nonce_bytes), Payload { msg: &data, aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))?;
+
+    log!("ChaCha20-Poly1305: Successfully decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// Decrypt large ChaCha20-Poly1305 data with chunked processing and progress callback
+/// 
+/// The Poly1305 tag is accumulated over the ciphertext while each chunk is
+/// decrypted, and the plaintext is only returned once the tag has verified.
+/// 
+/// # Arguments
+/// * `key` - 32-byte ChaCha20-Poly1305 key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte Poly1305 tag
+/// * `chunk_size` - Size of chunks to process, at least 1 (rounded up to a multiple of 16)
+/// * `progress_callback` - Optional callback receiving a progress report after each chunk (`bytes_done`, `percent`, `bytes_per_second`, `remaining_ms`, ...)
+/// * `aad` - Optional associated data the tag was computed over; decryption
+///   fails unless it matches exactly
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_chacha20poly1305_chunked(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    chunk_size: usize,
+    progress_callback: Option<js_sys::Function>,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    // Validate inputs
+    if key.length() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
+    }
+
+    if nonce.length() != 12 {
+        return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 12 bytes, got {}", nonce.length())).into());
+    }
+
+    if encrypted_data.length() < 16 {
+        return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", encrypted_data.length())).into());
+    }
+    check_chunk_size(chunk_size)?;
+
+    // Poly1305 blocks are 16 bytes; keep every chunk but the last block-aligned
+    let chunk_size = chunk_size
+        .checked_next_multiple_of(16)
+        .ok_or_else(|| CryptoError::InvalidInput(format!("Invalid chunk size: too large to round up to a multiple of 16, got {}", chunk_size)))?;
+
+    let key_bytes = key.to_vec();
+    let nonce_bytes = nonce.to_vec();
+    let mut data = encrypted_data.to_vec();
+    let tag = data.split_off(data.len() - 16);
+    let total_len = data.len();
+
+    log!("ChaCha20-Poly1305 Chunked: Decrypting {} bytes in chunks of {}", total_len, chunk_size);
+
+    let mut cipher = ChaCha20::new_from_slices(&key_bytes, &nonce_bytes)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key or nonce format".into()))?;
+
+    // The one-time Poly1305 key is the first 32 bytes of keystream block 0;
+    // the payload is encrypted starting at block 1
+    let mut mac_key = [0u8; 32];
+    cipher.apply_keystream(&mut mac_key);
+    cipher.seek(64u64);
+    let mut mac = Poly1305::new(poly1305::Key::from_slice(&mac_key));
+    let aad = aad_bytes(aad.as_ref());
+    mac.update_padded(&aad);
+
+    let progress = Progress::new(progress_callback.as_ref(), Some(total_len as u64), Some(chunk_size as u64));
+    let mut bytes_done = 0;
+
+    for (chunk_idx, chunk) in data.chunks_mut(chunk_size).enumerate() {
+        // Authenticate the ciphertext before decrypting it in-place
+        mac.update_padded(chunk);
+        cipher.apply_keystream(chunk);
+
+        bytes_done += chunk.len() as u64;
+        progress.report(bytes_done, chunk_idx as u64);
+    }
+
+    // Length block: 64-bit little-endian AAD length then ciphertext length
+    let mut lengths = poly1305::Block::default();
+    lengths[..8].copy_from_slice(&(aad.len() as u64).to_le_bytes());
+    lengths[8..].copy_from_slice(&(total_len as u64).to_le_bytes());
+    mac.update(&[lengths]);
+
+    if mac.verify(poly1305::Block::from_slice(&tag)).is_err() {
+        // Don't leave unauthenticated plaintext lying around in wasm memory
+        data.iter_mut().for_each(|b| *b = 0);
+        return Err(CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()).into());
+    }
+
+    log!("ChaCha20-Poly1305 Chunked: Successfully decrypted {} bytes", total_len);
+
+    Ok(Uint8Array::from(data.as_slice()))
+}
+
+/// Encrypt data using XChaCha20-Poly1305 (extended 24-byte nonce)
+/// 
+/// The 192-bit nonce is large enough to be generated at random per file
+/// without worrying about collisions.
+/// 
+/// # Arguments
+/// * `key` - 32-byte XChaCha20-Poly1305 key as Uint8Array
+/// * `nonce` - 24-byte nonce as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `aad` - Optional associated data to authenticate but not encrypt, such
+///   as a chunk index or file id; the same bytes must be given to decrypt
+/// 
+/// # Returns
+/// Ciphertext followed by the 16-byte Poly1305 tag as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_xchacha20poly1305(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    if key.length() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
+    }
+
+    if nonce.length() != 24 {
+        return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 24 bytes, got {}", nonce.length())).into());
+    }
+
+    let key_bytes = key.to_vec();
+    let nonce_bytes = nonce.to_vec();
+    let plaintext = data.to_vec();
+
+    log!("XChaCha20-Poly1305: Encrypting {} bytes", plaintext.len());
+
+    let cipher = XChaCha20Poly1305::new_from_slice(&key_bytes)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    let ciphertext = cipher
+        .encrypt(chacha20poly1305::XNonce::from_slice(&nonce_bytes), Payload { msg: &plaintext, aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::Internal("Encryption failed".into()))?;
+
+    log!("XChaCha20-Poly1305: Successfully encrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(ciphertext.as_slice()))
+}
+
+/// Decrypt and authenticate data using XChaCha20-Poly1305 (extended 24-byte nonce)
+/// 
+/// # Arguments
+/// * `key` - 32-byte XChaCha20-Poly1305 key as Uint8Array
+/// * `nonce` - 24-byte nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte Poly1305 tag
+/// * `aad` - Optional associated data the tag was computed over, such as a
+///   chunk index or file id; decryption fails unless it matches exactly
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_xchacha20poly1305(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    // Validate input sizes
+    if key.length() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())).into());
+    }
+
+    if nonce.length() != 24 {
+        return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 24 bytes, got {}", nonce.length())).into());
+    }
+
+    if encrypted_data.length() < 16 {
+        return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", encrypted_data.length())).into());
+    }
+
+    let key_bytes = key.to_vec();
+    let nonce_bytes = nonce.to_vec();
+    let data = encrypted_data.to_vec();
+
+    log!("XChaCha20-Poly1305: Decrypting {} bytes", data.len() - 16);
+
+    let cipher = XChaCha20Poly1305::new_from_slice(&key_bytes)
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+
+    let plaintext = cipher
+        .decrypt(chacha20poly1305::XNonce::from_slice(&nonce_bytes), Payload { msg: &data, aad: &aad_bytes(aad.as_ref()) })
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))?;
+
+    log!("XChaCha20-Poly1305: Successfully decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// Report how this build of the module was compiled
+/// 
+/// # Returns
+/// Object with:
+/// * `version` - crate version
+/// * `simd128` - whether the binary was compiled with WebAssembly SIMD
+///   enabled (see `build.sh simd`). This only allows LLVM auto-vectorisation;
+///   no code path uses SIMD explicitly
+/// * `aes_backend` - AES implementation in use. wasm has no AES instructions,
+///   so this is the constant-time bitsliced "fixslice" software backend in
+///   every wasm build, with or without `simd128`
+/// * `allocator` - `"dlmalloc"`, or `"wee_alloc"` for a `small-binary` build
+/// * `shared_array_buffer` - whether `SharedArrayBuffer` views can be passed
+///   in, which browsers only allow on cross-origin isolated pages
+#[wasm_bindgen]
+pub fn get_capabilities() -> Result<js_sys::Object, JsValue> {
+    let aes_backend = if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+        "aes-ni (runtime detected, soft fallback)"
+    } else if cfg!(target_pointer_width = "32") {
+        "soft-fixslice32"
+    } else {
+        "soft-fixslice64"
+    };
+
+    let capabilities = js_sys::Object::new();
+    js_sys::Reflect::set(&capabilities, &"version".into(), &get_version().into())?;
+    js_sys::Reflect::set(&capabilities, &"simd128".into(), &cfg!(target_feature = "simd128").into())?;
+    js_sys::Reflect::set(&capabilities, &"aes_backend".into(), &aes_backend.into())?;
+    js_sys::Reflect::set(&capabilities, &"allocator".into(), &(if cfg!(feature = "small-binary") { "wee_alloc" } else { "dlmalloc" }).into())?;
+    let shared_array_buffer = js_sys::Reflect::has(&js_sys::global(), &"SharedArrayBuffer".into()).unwrap_or(false);
+    js_sys::Reflect::set(&capabilities, &"shared_array_buffer".into(), &shared_array_buffer.into())?;
+
+    Ok(capabilities)
+}
+
 /// Get version information
 #[wasm_bindgen]
 pub fn get_version() -> String {
@@ -318,3 +1991,417 @@ pub fn get_version() -> String {
 pub fn test_wasm() -> String {
     "WASM module loaded successfully!".to_string()
 }
+
+/// Entry points for the cargo-fuzz targets in `fuzz/`
+#[cfg(fuzzing)]
+#[doc(hidden)]
+pub mod fuzzing {
+    use super::*;
+
+    /// Decrypt `data` at `start_offset` three ways and panic unless they agree:
+    /// in `chunk_size` chunks, in one pass from the nearest block boundary, and
+    /// (from offset 0) split across the parallel chunk path
+    pub fn check_ctr_chunks(key: &[u8], iv: &[u8], data: &[u8], chunk_size: usize, start_offset: u64) {
+        let Ok(mut cipher) = AesCtr::new(key, iv) else {
+            return;
+        };
+        if chunk_size == 0 {
+            let error = process_aes_ctr_chunks::<_, CryptoError>(cipher.clone(), data, 0, start_offset, |_, _, _| Ok(())).unwrap_err();
+            assert_eq!(error.name(), "InvalidInput");
+            let error = apply_aes_ctr_parallel(&cipher, &mut data.to_vec(), 0).unwrap_err();
+            assert_eq!(error.name(), "InvalidInput");
+            return;
+        }
+        if data.is_empty() || start_offset.checked_add(data.len() as u64).is_none() {
+            return;
+        }
+
+        let mut chunked = Vec::with_capacity(data.len());
+        process_aes_ctr_chunks::<_, CryptoError>(cipher.clone(), data, chunk_size, start_offset, |_, offset, chunk| {
+            assert_eq!(offset, start_offset + chunked.len() as u64, "chunk offsets are not contiguous");
+            chunked.extend_from_slice(chunk);
+            Ok(())
+        })
+        .unwrap();
+
+        // Seek to the start of the block and discard the bytes before `start_offset`
+        let skip = (start_offset % 16) as usize;
+        let mut aligned = vec![0u8; skip];
+        aligned.extend_from_slice(data);
+        let base = cipher.clone();
+        cipher.seek(start_offset - skip as u64).unwrap();
+        cipher.apply_keystream(&mut aligned).unwrap();
+        assert_eq!(chunked, &aligned[skip..], "chunked output differs from one pass");
+
+        if start_offset == 0 {
+            let mut parallel = data.to_vec();
+            apply_aes_ctr_parallel(&base, &mut parallel, chunk_size).unwrap();
+            assert_eq!(parallel, chunked, "parallel output differs from chunked");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_128: [u8; 16] = [0x2b; 16];
+    const KEY_256: [u8; 32] = [0x60; 32];
+
+    fn ctr_one_shot(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut out = data.to_vec();
+        AesCtr::new(key, iv).unwrap().apply_keystream(&mut out).unwrap();
+        out
+    }
+
+    fn sample_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 + 7) as u8).collect()
+    }
+
+    #[test]
+    fn chunked_matches_one_shot_for_odd_chunk_sizes() {
+        let iv = [0x11u8; 16];
+        let data = sample_data(1000);
+
+        for key in [&KEY_128[..], &KEY_256[..]] {
+            let expected = ctr_one_shot(key, &iv, &data);
+            for chunk_size in [1, 3, 7, 15, 17, 31, 33, 100, 999, 1000, 4096] {
+                let actual = apply_aes_ctr_chunked(AesCtr::new(key, &iv).unwrap(), &data, chunk_size, None, &MemoryBudget::default()).unwrap();
+                assert_eq!(actual, expected, "chunk_size {}", chunk_size);
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_matches_one_shot_across_counter_carry() {
+        // Low bytes of the counter are about to overflow into higher bytes
+        let mut iv = [0u8; 16];
+        iv[13..].copy_from_slice(&[0xff, 0xff, 0xfe]);
+        let data = sample_data(200);
+
+        let expected = ctr_one_shot(&KEY_256, &iv, &data);
+        for chunk_size in [5, 16, 24, 48] {
+            let actual = apply_aes_ctr_chunked(AesCtr::new(&KEY_256, &iv).unwrap(), &data, chunk_size, None, &MemoryBudget::default()).unwrap();
+            assert_eq!(actual, expected, "chunk_size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn parallel_matches_one_shot() {
+        let iv = [0xa5u8; 16];
+        let data = sample_data(1000);
+        let expected = ctr_one_shot(&KEY_256, &iv, &data);
+
+        for chunk_size in [1, 16, 17, 333, 2048] {
+            let mut actual = data.clone();
+            apply_aes_ctr_parallel(&AesCtr::new(&KEY_256, &iv).unwrap(), &mut actual, chunk_size).unwrap();
+            assert_eq!(actual, expected, "chunk_size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn narrow_counters_wrap_without_carrying() {
+        // Low 32 bits one block from wrapping, and the low 64 bits too
+        let mut iv = [0x33u8; 16];
+        iv[8..].copy_from_slice(&[0xff; 8]);
+        let data = sample_data(48);
+
+        let mut wide = data.clone();
+        AesCtr::new(&KEY_128, &iv).unwrap().apply_keystream(&mut wide).unwrap();
+
+        for width in [CounterWidth::Bits32, CounterWidth::Bits64] {
+            let mut narrow = data.clone();
+            AesCtr::with_options(&KEY_128, &iv, &CtrOptions { counter_width: width, rfc3686: false }).unwrap().apply_keystream(&mut narrow).unwrap();
+            assert_eq!(narrow[..16], wide[..16], "{:?} first block", width);
+            assert_ne!(narrow[16..], wide[16..], "{:?} carried into the nonce", width);
+
+            // Second block is the IV with the counter part wrapped to zero
+            let counter_bytes = width as usize / 8;
+            let mut expected_block = iv;
+            expected_block[16 - counter_bytes..].fill(0);
+            assert_eq!(narrow[16..32], xor(&data[16..32], &reference_keystream(&KEY_128, &expected_block, 0, 16))[..]);
+        }
+    }
+
+    #[test]
+    fn rfc3686_test_vectors() {
+        let rfc3686 = CtrOptions { counter_width: CounterWidth::Bits32, rfc3686: true };
+        let hex = |text: &str| crate::encoding::decode_hex(text).unwrap();
+
+        // Test vectors #1 and #2: nonce || IV, plaintext, ciphertext
+        for (key, nonce_iv, plaintext, ciphertext) in [
+            ("ae6852f8121067cc4bf7a5765577f39e", "00000030 0000000000000000", "53696e676c6520626c6f636b206d7367", "e4095d4fb7a7b3792d6175a3261311b8"),
+            (
+                "7e24067817fae0d743d6ce1f32539163",
+                "006cb6db c0543b59da48d90b",
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "5104a106168a72d9790d41ee8edad388eb2e1efc46da57c8fce630df9141be28",
+            ),
+        ] {
+            let (key, nonce_iv) = (hex(key), hex(&nonce_iv.replace(' ', "")));
+            let mut data = hex(plaintext);
+            AesCtr::with_options(&key, &nonce_iv, &rfc3686).unwrap().apply_keystream(&mut data).unwrap();
+            assert_eq!(data, hex(ciphertext));
+        }
+    }
+
+    #[test]
+    fn chunks_at_start_offset_match_one_shot_slice() {
+        let iv = [0x42u8; 16];
+        let data = sample_data(500);
+        let expected = ctr_one_shot(&KEY_128, &iv, &data);
+
+        for start in [0usize, 1, 15, 16, 17, 250] {
+            let mut actual = Vec::new();
+            process_aes_ctr_chunks::<_, CryptoError>(AesCtr::new(&KEY_128, &iv).unwrap(), &data[start..], 13, start as u64, |_, offset, chunk| {
+                assert_eq!(offset as usize, start + actual.len());
+                actual.extend_from_slice(chunk);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(actual, &expected[start..], "start {}", start);
+        }
+    }
+
+    /// Keystream computed block by block with a plain 128-bit big-endian counter
+    fn reference_keystream(key: &[u8], iv: &[u8; 16], offset: u64, len: usize) -> Vec<u8> {
+        reference_keystream_with_width(key, iv, CounterWidth::Bits128, offset, len)
+    }
+
+    /// Keystream where only the low `width` bits of the counter block are
+    /// incremented, wrapping without carrying into the bits above
+    fn reference_keystream_with_width(key: &[u8], iv: &[u8; 16], width: CounterWidth, offset: u64, len: usize) -> Vec<u8> {
+        use aes::{Aes128, Aes192};
+        use aes::cipher::BlockEncrypt;
+
+        let encrypt: Box<dyn Fn(&mut aes::Block)> = match key.len() {
+            16 => { let cipher = Aes128::new_from_slice(key).unwrap(); Box::new(move |block| cipher.encrypt_block(block)) }
+            24 => { let cipher = Aes192::new_from_slice(key).unwrap(); Box::new(move |block| cipher.encrypt_block(block)) }
+            _ => { let cipher = Aes256::new_from_slice(key).unwrap(); Box::new(move |block| cipher.encrypt_block(block)) }
+        };
+
+        let iv = u128::from_be_bytes(*iv);
+        let mask = match width {
+            CounterWidth::Bits128 => u128::MAX,
+            narrow => (1u128 << narrow as u32) - 1,
+        };
+        let skip = (offset % 16) as usize;
+        let mut stream = Vec::new();
+        for block_idx in 0..(skip + len).div_ceil(16) {
+            let counter = iv.wrapping_add((offset / 16) as u128 + block_idx as u128);
+            let mut block = aes::Block::from(((iv & !mask) | (counter & mask)).to_be_bytes());
+            encrypt(&mut block);
+            stream.extend_from_slice(&block);
+        }
+        stream.drain(..skip);
+        stream.truncate(len);
+        stream
+    }
+
+    fn xor(data: &[u8], keystream: &[u8]) -> Vec<u8> {
+        data.iter().zip(keystream).map(|(byte, key)| byte ^ key).collect()
+    }
+
+    fn aes_key() -> impl proptest::strategy::Strategy<Value = Vec<u8>> {
+        use proptest::prelude::*;
+        prop::sample::select(vec![16usize, 24, 32]).prop_flat_map(|len| prop::collection::vec(any::<u8>(), len))
+    }
+
+    /// Random IVs, and IVs whose low 32, 64, 96 or 128 bits are a few blocks from wrapping
+    fn counter_iv() -> impl proptest::strategy::Strategy<Value = [u8; 16]> {
+        use proptest::prelude::*;
+        prop_oneof![
+            any::<[u8; 16]>(),
+            (any::<u128>(), prop::sample::select(vec![32u32, 64, 96, 128]), 0u128..8).prop_map(|(high, width, distance)| {
+                let low = if width == 128 { u128::MAX } else { (1u128 << width) - 1 };
+                ((high & !low) | (low - distance)).to_be_bytes()
+            }),
+        ]
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn chunked_and_parallel_match_reference(
+            key in aes_key(),
+            iv in counter_iv(),
+            data in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..1500),
+            chunk_size in 1usize..300
+        ) {
+            let expected = xor(&data, &reference_keystream(&key, &iv, 0, data.len()));
+            proptest::prop_assert_eq!(&apply_aes_ctr_chunked(AesCtr::new(&key, &iv).unwrap(), &data, chunk_size, None, &MemoryBudget::default()).unwrap(), &expected);
+
+            let mut parallel = data.clone();
+            apply_aes_ctr_parallel(&AesCtr::new(&key, &iv).unwrap(), &mut parallel, chunk_size).unwrap();
+            proptest::prop_assert_eq!(&parallel, &expected);
+        }
+
+        #[test]
+        fn seek_matches_reference_at_any_offset(
+            key in aes_key(),
+            iv in counter_iv(),
+            offset in proptest::prop_oneof![0u64..4096, proptest::prelude::any::<u64>()],
+            len in 0usize..100
+        ) {
+            let mut keystream = vec![0u8; len];
+            let mut cipher = AesCtr::new(&key, &iv).unwrap();
+            cipher.seek(offset).unwrap();
+            cipher.apply_keystream(&mut keystream).unwrap();
+            proptest::prop_assert_eq!(keystream, reference_keystream(&key, &iv, offset, len));
+        }
+
+        #[test]
+        fn narrow_counters_match_reference(
+            key in aes_key(),
+            iv in counter_iv(),
+            width in proptest::sample::select(vec![CounterWidth::Bits32, CounterWidth::Bits64, CounterWidth::Bits128]),
+            offset in 0u64..(1 << 20),
+            data in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..300),
+            chunk_size in 1usize..100
+        ) {
+            let options = CtrOptions { counter_width: width, rfc3686: false };
+            let expected = xor(&data, &reference_keystream_with_width(&key, &iv, width, offset, data.len()));
+
+            let mut cipher = AesCtr::with_options(&key, &iv, &options).unwrap();
+            cipher.seek(offset).unwrap();
+            let mut one_pass = data.clone();
+            cipher.apply_keystream(&mut one_pass).unwrap();
+            proptest::prop_assert_eq!(&one_pass, &expected);
+
+            let mut chunked = Vec::new();
+            process_aes_ctr_chunks::<_, CryptoError>(AesCtr::with_options(&key, &iv, &options).unwrap(), &data, chunk_size, offset, |_, _, chunk| {
+                chunked.extend_from_slice(chunk);
+                Ok(())
+            })
+            .unwrap();
+            proptest::prop_assert_eq!(&chunked, &expected);
+        }
+
+        #[test]
+        fn chunks_from_any_start_offset_are_contiguous(
+            key in aes_key(),
+            iv in counter_iv(),
+            start_offset in 0u64..(1 << 40),
+            data in proptest::collection::vec(proptest::prelude::any::<u8>(), 0..600),
+            chunk_size in 1usize..100
+        ) {
+            let mut actual = Vec::new();
+            process_aes_ctr_chunks::<_, CryptoError>(AesCtr::new(&key, &iv).unwrap(), &data, chunk_size, start_offset, |chunk_idx, offset, chunk| {
+                assert_eq!(offset, start_offset + (chunk_idx * chunk_size) as u64);
+                assert_eq!(offset, start_offset + actual.len() as u64);
+                actual.extend_from_slice(chunk);
+                Ok(())
+            })
+            .unwrap();
+            proptest::prop_assert_eq!(actual, xor(&data, &reference_keystream(&key, &iv, start_offset, data.len())));
+        }
+    }
+
+    /// Deterministic xorshift bytes, so failures reproduce
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    /// Every truncation and single-byte corruption of `sample`, plus random garbage
+    fn adversarial(sample: &[u8]) -> Vec<Vec<u8>> {
+        let mut inputs: Vec<Vec<u8>> = (0..sample.len()).map(|len| sample[..len].to_vec()).collect();
+        for position in 0..sample.len() {
+            let mut corrupted = sample.to_vec();
+            corrupted[position] ^= 0x80;
+            inputs.push(corrupted);
+        }
+        for (seed, len) in [(1u64, 0usize), (2, 1), (3, 17), (4, 64), (5, 1000)] {
+            inputs.push(noise(seed, len));
+        }
+        inputs
+    }
+
+    #[test]
+    fn decryptor_state_round_trips_and_resumes_keystream() {
+        let iv = [0x42u8; 16];
+        let data = sample_data(300);
+        let expected = ctr_one_shot(&KEY_128, &iv, &data);
+
+        let check = decryptor_state_check(&KEY_128, &iv);
+        let state = encode_decryptor_state(123, &check);
+        assert_eq!(decode_decryptor_state(&state), Ok((123, check)));
+        assert_ne!(decryptor_state_check(&KEY_256, &iv), check);
+
+        let (position, _) = decode_decryptor_state(&state).unwrap();
+        let mut cipher = AesCtr::new(&KEY_128, &iv).unwrap();
+        cipher.seek(position).unwrap();
+        let mut resumed = data[123..].to_vec();
+        cipher.apply_keystream(&mut resumed).unwrap();
+        assert_eq!(resumed, &expected[123..]);
+
+        assert!(decode_decryptor_state(&state[..16]).is_err());
+        let mut future = state;
+        future[0] = 2;
+        assert_eq!(decode_decryptor_state(&future).unwrap_err().name(), "Unsupported");
+    }
+
+    #[test]
+    fn adversarial_inputs_are_rejected_without_panicking() {
+        use furl_core::container::{Algorithm, ContainerReader, ContainerWriter};
+
+        let key = [0x11u8; 32];
+        let plaintext = noise(42, 100);
+
+        let mut writer = ContainerWriter::new(&key, &[0x22; 12], 32, Algorithm::ChaCha20Poly1305).unwrap();
+        let mut container = writer.update(&plaintext).unwrap();
+        container.extend(writer.finish().unwrap());
+        for input in adversarial(&container) {
+            let mut reader = ContainerReader::new(&key).unwrap();
+            let complete = reader.update(&input).and_then(|_| reader.finish());
+            assert!(complete.is_err(), "container accepted {} corrupted bytes", input.len());
+        }
+
+        let outboard = crate::bao::outboard(&plaintext);
+        let root = blake3::hash(&plaintext);
+        for input in adversarial(&outboard) {
+            if let Ok(mut verifier) = crate::bao::Verifier::new(root.as_bytes(), input) {
+                let _ = verifier.update(&plaintext).and_then(|_| verifier.finish());
+            }
+        }
+        for input in adversarial(&plaintext) {
+            let mut verifier = crate::bao::Verifier::new(root.as_bytes(), outboard.clone()).unwrap();
+            assert!(verifier.update(&input).and_then(|_| verifier.finish()).is_err());
+        }
+
+        let mut push = crate::secretstream::SecretStream::new(&key, &[0x33; 24]);
+        let message = push.push(&plaintext, b"", crate::secretstream::TAG_FINAL);
+        for input in adversarial(&message) {
+            let mut pull = crate::secretstream::SecretStream::new(&key, &[0x33; 24]);
+            assert!(pull.pull(&input, b"").is_none());
+        }
+
+        for input in adversarial(b"Salted__\x01\x02\x03\x04\x05\x06\x07\x08 sixteen bytes!") {
+            let _ = crate::openssl::decrypt(&input, b"pw", crate::openssl::CipherMode::Cbc, None, crate::openssl::KdfDigest::Md5);
+            let _ = crate::openssl::decrypt(&input, b"pw", crate::openssl::CipherMode::Ctr, Some(1), crate::openssl::KdfDigest::Sha256);
+            let _ = crate::openssl::decrypt_cryptojs_blob(&String::from_utf8_lossy(&input), b"pw");
+            let _ = crate::envelope::open(&input, &crate::envelope::RecipientKey::X25519(key));
+            let _ = crate::jwe::decrypt(&String::from_utf8_lossy(&input), &key);
+            let _ = crate::keywrap::aes_kw_unwrap(&key, &input);
+            let _ = crate::keywrap::parse_private_key(&input);
+            let _ = crate::x25519::unseal(key, &input);
+            let _ = crate::atplatform::decrypt_sic_pkcs7(&mut AesCtr::new(&key, &[0u8; 16]).unwrap(), &input);
+            let _ = zip::ZipArchive::new(std::io::Cursor::new(input));
+        }
+
+        let cipher = AesCtr::new(&key, &[0u8; 16]).unwrap();
+        let chunks = process_aes_ctr_chunks::<_, CryptoError>(cipher.clone(), &plaintext, 0, 0, |_, _, _| Ok(()));
+        assert_eq!(chunks.unwrap_err().name(), "InvalidInput");
+        assert_eq!(apply_aes_ctr_parallel(&cipher, &mut plaintext.clone(), 0).unwrap_err().name(), "InvalidInput");
+
+        for token in ["....", "e30.AA.AAAAAAAAAAAAAAAA.AA.AAAAAAAAAAAAAAAAAAAAAA", "eyJhbGciOiJkaXIiLCJlbmMiOiJBMjU2R0NNIn0....", "a.b.c.d.e.f"] {
+            assert!(crate::jwe::decrypt(token, &key).is_err());
+        }
+    }
+}
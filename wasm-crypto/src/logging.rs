@@ -0,0 +1,110 @@
+//! Console logging with a runtime level
+//! 
+//! Diagnostic messages (sizes, byte counts, chunk progress) are `Debug` and
+//! stay quiet unless the page opts in with `set_log_level(LogLevel.Debug)`.
+//! Building with the `silent` feature removes every log call from the
+//! binary, whatever the level.
+//! 
+//! Messages go to `console` unless a handler is installed with
+//! `set_log_handler`, e.g. to feed a Node.js logger. Building without the
+//! default `console` feature drops the console path, so nothing is written
+//! anywhere until a handler is set.
+
+use wasm_bindgen::prelude::*;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Verbosity levels, exported to JS as `LogLevel`
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+// Atomic so threaded builds can read it from worker threads
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Warn as u8);
+
+thread_local! {
+    // JS functions can't cross threads; each wasm thread has its own handler
+    static HANDLER: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+/// Set the most verbose level written to the console (default `Warn`)
+/// 
+/// # Arguments
+/// * `level` - `LogLevel.Off` silences the module entirely
+#[wasm_bindgen]
+pub fn set_log_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Send log messages to `handler(level, message)` instead of the console
+/// 
+/// # Arguments
+/// * `handler` - Function receiving the `LogLevel` and the message text, or
+///   `null` to go back to the console
+#[wasm_bindgen]
+pub fn set_log_handler(handler: Option<js_sys::Function>) {
+    HANDLER.with(|current| *current.borrow_mut() = handler);
+}
+
+/// Current log level
+#[wasm_bindgen]
+pub fn get_log_level() -> LogLevel {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Off,
+        1 => LogLevel::Error,
+        2 => LogLevel::Warn,
+        3 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// Whether messages at `level` are currently written
+#[cfg_attr(any(not(target_arch = "wasm32"), feature = "silent"), allow(dead_code))]
+pub(crate) fn enabled(level: LogLevel) -> bool {
+    level != LogLevel::Off && level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Hand one message to the log handler, or else the matching console method
+#[cfg(all(target_arch = "wasm32", not(feature = "silent")))]
+pub(crate) fn write(level: LogLevel, message: &str) {
+    let message = JsValue::from_str(message);
+    if let Some(handler) = HANDLER.with(|handler| handler.borrow().clone()) {
+        let _ = handler.call2(&JsValue::NULL, &(level as u8).into(), &message);
+    } else {
+        #[cfg(feature = "console")]
+        match level {
+            LogLevel::Off => {}
+            LogLevel::Error => web_sys::console::error_1(&message),
+            LogLevel::Warn => web_sys::console::warn_1(&message),
+            LogLevel::Info => web_sys::console::info_1(&message),
+            LogLevel::Debug => web_sys::console::debug_1(&message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_filters_more_verbose_messages() {
+        assert_eq!(get_log_level(), LogLevel::Warn);
+        assert!(enabled(LogLevel::Error) && enabled(LogLevel::Warn));
+        assert!(!enabled(LogLevel::Debug));
+
+        set_log_level(LogLevel::Debug);
+        assert!(enabled(LogLevel::Debug));
+
+        set_log_level(LogLevel::Off);
+        assert!(!enabled(LogLevel::Error));
+        assert!(!enabled(LogLevel::Off));
+
+        set_log_level(LogLevel::Warn);
+    }
+}
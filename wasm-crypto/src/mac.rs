@@ -0,0 +1,432 @@
+//! Message authentication codes for verifying ciphertext integrity
+//! 
+//! Besides HMAC-SHA256 this has the two universal-hash MACs that AEADs are
+//! built from, for formats that encrypt-then-MAC with them directly:
+//! Poly1305 (RFC 8439), whose 32-byte key must only ever authenticate one
+//! message, and GMAC, the AES-GCM tag over data with no ciphertext, which
+//! needs a unique 12-byte nonce per message instead. Both come as one-shot
+//! calls and as streaming classes that take the data in chunks of any size.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use aes::{Aes128, Aes256};
+use aes::cipher::BlockEncrypt;
+use ghash::GHash;
+use hmac::{Hmac, Mac};
+use poly1305::Poly1305;
+use poly1305::universal_hash::UniversalHash;
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use crate::error::CryptoError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Compute an HMAC-SHA256 tag
+/// 
+/// # Arguments
+/// * `key` - MAC key as Uint8Array (32 bytes recommended, must not be empty)
+/// * `data` - Data to authenticate as Uint8Array
+/// 
+/// # Returns
+/// 32-byte tag as Uint8Array
+#[wasm_bindgen]
+pub fn hmac_sha256(key: &Uint8Array, data: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    if key.length() == 0 {
+        return Err(CryptoError::InvalidKeyLength("Invalid key size: HMAC key must not be empty".into()).into());
+    }
+
+    let mut mac = HmacSha256::new_from_slice(&key.to_vec())
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+    mac.update(&data.to_vec());
+
+    Ok(Uint8Array::from(&mac.finalize().into_bytes()[..]))
+}
+
+/// Compare two byte strings in time independent of their contents
+/// 
+/// Only the lengths can be learned from timing; inputs of different lengths
+/// are unequal.
+pub(crate) fn bytes_equal(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// Compare two byte arrays in constant time
+/// 
+/// Use this instead of comparing in JS (`===` on strings, or a byte loop
+/// that exits at the first difference) when checking tags, password
+/// verifiers or key fingerprints, where the time taken would reveal how
+/// many leading bytes matched.
+/// 
+/// # Arguments
+/// * `a` - First value as Uint8Array
+/// * `b` - Second value as Uint8Array
+/// 
+/// # Returns
+/// `true` if both arrays have the same length and contents
+#[wasm_bindgen]
+pub fn constant_time_eq(a: &Uint8Array, b: &Uint8Array) -> bool {
+    bytes_equal(&a.to_vec(), &b.to_vec())
+}
+
+/// Verify an HMAC-SHA256 tag in constant time
+/// 
+/// Always check tags with this rather than comparing bytes in JS, which
+/// leaks how many leading bytes matched through timing.
+/// 
+/// # Arguments
+/// * `key` - MAC key as Uint8Array
+/// * `data` - Authenticated data as Uint8Array
+/// * `tag` - Expected 32-byte tag as Uint8Array
+/// 
+/// # Returns
+/// `true` if the tag is valid for `data`
+#[wasm_bindgen]
+pub fn hmac_verify(key: &Uint8Array, data: &Uint8Array, tag: &Uint8Array) -> Result<bool, JsValue> {
+    if key.length() == 0 {
+        return Err(CryptoError::InvalidKeyLength("Invalid key size: HMAC key must not be empty".into()).into());
+    }
+
+    if tag.length() != 32 {
+        return Err(CryptoError::InvalidInput(format!("Invalid tag size: expected 32 bytes, got {}", tag.length())).into());
+    }
+
+    let mut mac = HmacSha256::new_from_slice(&key.to_vec())
+        .map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))?;
+    mac.update(&data.to_vec());
+
+    let valid = mac.verify_slice(&tag.to_vec()).is_ok();
+
+    log!("HMAC: Tag verification {}", if valid { "succeeded" } else { "failed" });
+
+    Ok(valid)
+}
+
+/// Poly1305 and GMAC tags are one 16-byte block
+const BLOCK_LEN: usize = 16;
+
+/// Bytes held back from a universal hash until they fill a block
+/// 
+/// The hashes only take whole blocks (a partial one is padded, which must
+/// only happen at the very end), so chunked input is hashed a block at a
+/// time and any remainder carried over to the next chunk.
+#[derive(Default)]
+struct PendingBlock(Vec<u8>);
+
+impl PendingBlock {
+    fn absorb(&mut self, hash: &mut impl UniversalHash, mut data: &[u8]) {
+        if !self.0.is_empty() {
+            let take = (BLOCK_LEN - self.0.len()).min(data.len());
+            self.0.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.0.len() < BLOCK_LEN {
+                return;
+            }
+            hash.update_padded(&self.0);
+            self.0.clear();
+        }
+
+        let whole = data.len() - data.len() % BLOCK_LEN;
+        hash.update_padded(&data[..whole]);
+        self.0.extend_from_slice(&data[whole..]);
+    }
+}
+
+/// Check a tag against the computed one, in constant time
+fn tag_matches(expected: &Uint8Array, computed: &[u8; BLOCK_LEN]) -> Result<bool, CryptoError> {
+    if expected.length() != BLOCK_LEN as u32 {
+        return Err(CryptoError::InvalidInput(format!("Invalid tag size: expected 16 bytes, got {}", expected.length())));
+    }
+    Ok(bytes_equal(&expected.to_vec(), computed))
+}
+
+fn finalized(name: &str) -> CryptoError {
+    CryptoError::InvalidState(format!("{} has already been finalized", name))
+}
+
+/// Incremental Poly1305 (RFC 8439) for data arriving in chunks
+/// 
+/// The key is a one-time key: never authenticate two messages with it.
+/// 
+/// ```javascript
+/// const mac = new Poly1305Stream(oneTimeKey);
+/// for await (const chunk of chunks) mac.update(chunk);
+/// if (!mac.verify(tag)) throw new Error('corrupted');
+/// ```
+#[wasm_bindgen]
+pub struct Poly1305Stream {
+    mac: Option<Poly1305>,
+    pending: PendingBlock,
+}
+
+#[wasm_bindgen]
+impl Poly1305Stream {
+    /// Start a MAC with a 32-byte one-time key
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array) -> Result<Poly1305Stream, JsValue> {
+        Ok(Poly1305Stream::from_key(&key.to_vec())?)
+    }
+
+    /// Feed the next chunk of data into the MAC
+    pub fn update(&mut self, data: &Uint8Array) -> Result<(), JsValue> {
+        Ok(self.absorb(&data.to_vec())?)
+    }
+
+    /// Finish and return the 16-byte tag
+    pub fn finalize(&mut self) -> Result<Uint8Array, JsValue> {
+        Ok(Uint8Array::from(&self.tag()?[..]))
+    }
+
+    /// Finish and check `tag` in constant time
+    /// 
+    /// # Returns
+    /// `true` if `tag` is the 16-byte tag of the data fed in
+    pub fn verify(&mut self, tag: &Uint8Array) -> Result<bool, JsValue> {
+        Ok(tag_matches(tag, &self.tag()?)?)
+    }
+}
+
+impl Poly1305Stream {
+    fn from_key(key: &[u8]) -> Result<Self, CryptoError> {
+        // Scoped here: with `KeyInit` in scope `HmacSha256::new_from_slice` is ambiguous
+        use poly1305::universal_hash::KeyInit;
+
+        if key.len() != 32 {
+            return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.len())));
+        }
+        Ok(Poly1305Stream { mac: Some(Poly1305::new(key.into())), pending: PendingBlock::default() })
+    }
+
+    fn absorb(&mut self, data: &[u8]) -> Result<(), CryptoError> {
+        let mac = self.mac.as_mut().ok_or_else(|| finalized("Poly1305Stream"))?;
+        self.pending.absorb(mac, data);
+        Ok(())
+    }
+
+    fn tag(&mut self) -> Result<[u8; BLOCK_LEN], CryptoError> {
+        let mac = self.mac.take().ok_or_else(|| finalized("Poly1305Stream"))?;
+        // The final partial block is padded with a 1 byte, not zeroes
+        Ok(mac.compute_unpadded(&self.pending.0).into())
+    }
+}
+
+/// Incremental GMAC (the AES-GCM tag of data with no ciphertext)
+/// 
+/// ```javascript
+/// const mac = new GmacStream(key, nonce);
+/// for await (const chunk of chunks) mac.update(chunk);
+/// const tag = mac.finalize();
+/// ```
+#[wasm_bindgen]
+pub struct GmacStream {
+    ghash: Option<GHash>,
+    /// AES_K(nonce || 1), XORed into the hash to form the tag
+    mask: [u8; BLOCK_LEN],
+    pending: PendingBlock,
+    len: u64,
+}
+
+#[wasm_bindgen]
+impl GmacStream {
+    /// Start a MAC
+    /// 
+    /// # Arguments
+    /// * `key` - 16 or 32-byte AES key as Uint8Array
+    /// * `nonce` - 12-byte nonce as Uint8Array, unique per message under this key
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array, nonce: &Uint8Array) -> Result<GmacStream, JsValue> {
+        Ok(GmacStream::from_key(&key.to_vec(), &nonce.to_vec())?)
+    }
+
+    /// Feed the next chunk of data into the MAC
+    pub fn update(&mut self, data: &Uint8Array) -> Result<(), JsValue> {
+        Ok(self.absorb(&data.to_vec())?)
+    }
+
+    /// Finish and return the 16-byte tag
+    pub fn finalize(&mut self) -> Result<Uint8Array, JsValue> {
+        Ok(Uint8Array::from(&self.tag()?[..]))
+    }
+
+    /// Finish and check `tag` in constant time
+    /// 
+    /// # Returns
+    /// `true` if `tag` is the 16-byte tag of the data fed in
+    pub fn verify(&mut self, tag: &Uint8Array) -> Result<bool, JsValue> {
+        Ok(tag_matches(tag, &self.tag()?)?)
+    }
+}
+
+impl GmacStream {
+    fn from_key(key: &[u8], nonce: &[u8]) -> Result<Self, CryptoError> {
+        use aes::cipher::KeyInit;
+
+        if nonce.len() != 12 {
+            return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 12 bytes, got {}", nonce.len())));
+        }
+
+        // The hash key is AES_K(0); the mask is AES_K of the first counter block
+        let mut blocks = [aes::Block::default(); 2];
+        blocks[1][..12].copy_from_slice(nonce);
+        blocks[1][15] = 1;
+        match key.len() {
+            16 => Aes128::new(key.into()).encrypt_blocks(&mut blocks),
+            32 => Aes256::new(key.into()).encrypt_blocks(&mut blocks),
+            n => return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 16 or 32 bytes, got {}", n))),
+        }
+
+        Ok(GmacStream {
+            ghash: Some(GHash::new(&blocks[0])),
+            mask: blocks[1].into(),
+            pending: PendingBlock::default(),
+            len: 0,
+        })
+    }
+
+    fn absorb(&mut self, data: &[u8]) -> Result<(), CryptoError> {
+        let ghash = self.ghash.as_mut().ok_or_else(|| finalized("GmacStream"))?;
+        self.pending.absorb(ghash, data);
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    fn tag(&mut self) -> Result<[u8; BLOCK_LEN], CryptoError> {
+        let mut ghash = self.ghash.take().ok_or_else(|| finalized("GmacStream"))?;
+        ghash.update_padded(&self.pending.0);
+
+        // Length block: bit lengths of the associated data and the (empty) ciphertext
+        let mut lengths = ghash::Block::default();
+        lengths[..8].copy_from_slice(&(self.len * 8).to_be_bytes());
+        ghash.update(&[lengths]);
+
+        let mut tag: [u8; BLOCK_LEN] = ghash.finalize().into();
+        tag.iter_mut().zip(self.mask).for_each(|(t, m)| *t ^= m);
+        Ok(tag)
+    }
+}
+
+/// Compute a Poly1305 tag (RFC 8439)
+/// 
+/// # Arguments
+/// * `key` - 32-byte one-time key as Uint8Array; never reuse it for another message
+/// * `data` - Data to authenticate as Uint8Array
+/// 
+/// # Returns
+/// 16-byte tag as Uint8Array
+#[wasm_bindgen]
+pub fn poly1305(key: &Uint8Array, data: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let mut mac = Poly1305Stream::from_key(&key.to_vec())?;
+    mac.absorb(&data.to_vec())?;
+    Ok(Uint8Array::from(&mac.tag()?[..]))
+}
+
+/// Verify a Poly1305 tag in constant time
+/// 
+/// # Arguments
+/// * `key` - 32-byte one-time key as Uint8Array
+/// * `data` - Authenticated data as Uint8Array
+/// * `tag` - Expected 16-byte tag as Uint8Array
+/// 
+/// # Returns
+/// `true` if the tag is valid for `data`
+#[wasm_bindgen]
+pub fn poly1305_verify(key: &Uint8Array, data: &Uint8Array, tag: &Uint8Array) -> Result<bool, JsValue> {
+    let mut mac = Poly1305Stream::from_key(&key.to_vec())?;
+    mac.absorb(&data.to_vec())?;
+    Ok(tag_matches(tag, &mac.tag()?)?)
+}
+
+/// Compute a GMAC tag (AES-GCM with `data` as associated data and no plaintext)
+/// 
+/// # Arguments
+/// * `key` - 16 or 32-byte AES key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array, unique per message under this key
+/// * `data` - Data to authenticate as Uint8Array
+/// 
+/// # Returns
+/// 16-byte tag as Uint8Array
+#[wasm_bindgen]
+pub fn gmac(key: &Uint8Array, nonce: &Uint8Array, data: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let mut mac = GmacStream::from_key(&key.to_vec(), &nonce.to_vec())?;
+    mac.absorb(&data.to_vec())?;
+    Ok(Uint8Array::from(&mac.tag()?[..]))
+}
+
+/// Verify a GMAC tag in constant time
+/// 
+/// # Arguments
+/// * `key` - 16 or 32-byte AES key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `data` - Authenticated data as Uint8Array
+/// * `tag` - Expected 16-byte tag as Uint8Array
+/// 
+/// # Returns
+/// `true` if the tag is valid for `data`
+#[wasm_bindgen]
+pub fn gmac_verify(key: &Uint8Array, nonce: &Uint8Array, data: &Uint8Array, tag: &Uint8Array) -> Result<bool, JsValue> {
+    let mut mac = GmacStream::from_key(&key.to_vec(), &nonce.to_vec())?;
+    mac.absorb(&data.to_vec())?;
+    Ok(tag_matches(tag, &mac.tag()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_hex;
+
+    #[test]
+    fn bytes_equal_compares_length_and_contents() {
+        assert!(bytes_equal(b"furl tag", b"furl tag"));
+        assert!(bytes_equal(b"", b""));
+        assert!(!bytes_equal(b"furl tag", b"furl tah"));
+        assert!(!bytes_equal(b"furl tag", b"furl ta"));
+    }
+
+    #[test]
+    fn poly1305_matches_rfc_8439_in_any_split() {
+        // Section 2.5.2
+        let key = decode_hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b").unwrap();
+        let message = b"Cryptographic Forum Research Group";
+        let expected = decode_hex("a8061dc1305136c6c22b8baf0c0127a9").unwrap();
+
+        for split in [0, 1, 15, 16, 17, 33, message.len()] {
+            let mut mac = Poly1305Stream::from_key(&key).unwrap();
+            mac.absorb(&message[..split]).unwrap();
+            mac.absorb(&message[split..]).unwrap();
+            assert_eq!(mac.tag().unwrap()[..], expected, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn gmac_matches_aes_gcm_in_any_split() {
+        // Tags from Python `cryptography`'s AESGCM with the data as associated
+        // data and an empty plaintext
+        let nonce = decode_hex("cafebabefacedbaddecaf888").unwrap();
+        let key128 = decode_hex("feffe9928665731c6d6a8f9467308308").unwrap();
+        let key256 = decode_hex("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308").unwrap();
+        let data: Vec<u8> = (0..40).collect();
+
+        for (key, len, expected) in [
+            (&key128, 0, "3247184b3c4f69a44dbcd22887bbb418"),
+            (&key128, 40, "bf0899ffeafd55070906bb8a167f2f43"),
+            (&key256, 40, "387eb23b84586ac02257c0f040ee817a"),
+        ] {
+            for split in [0, 7, 16, len] {
+                let split = split.min(len);
+                let mut mac = GmacStream::from_key(key, &nonce).unwrap();
+                mac.absorb(&data[..split]).unwrap();
+                mac.absorb(&data[split..len]).unwrap();
+                assert_eq!(mac.tag().unwrap()[..], decode_hex(expected).unwrap(), "{} bytes split at {}", len, split);
+            }
+        }
+    }
+
+    #[test]
+    fn streams_finish_once() {
+        let mut mac = GmacStream::from_key(&[0; 16], &[0; 12]).unwrap();
+        mac.tag().unwrap();
+        assert!(matches!(mac.absorb(b"more"), Err(CryptoError::InvalidState(_))));
+        assert!(matches!(Poly1305Stream::from_key(&[0; 16]), Err(CryptoError::InvalidKeyLength(_))));
+        assert!(matches!(GmacStream::from_key(&[0; 24], &[0; 12]), Err(CryptoError::InvalidKeyLength(_))));
+    }
+}
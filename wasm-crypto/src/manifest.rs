@@ -0,0 +1,669 @@
+//! Multi-file share manifests
+//! 
+//! A folder share uploads every file's ciphertext back to back as one blob,
+//! plus a JSON manifest saying where each file lives in it. Each file is
+//! encrypted with AES-CTR under the share key and its own IV, so any one of
+//! them can be fetched with a Range request and decrypted on its own:
+//! 
+//! ```json
+//! {
+//!   "version": 1,
+//!   "files": [
+//!     { "name": "photos/cat.jpg", "offset": 0, "length": 48213,
+//!       "iv": "<base64, 16 bytes>", "sha256": "<hex of the plaintext>",
+//!       "content_type": "image/jpeg" }
+//!   ]
+//! }
+//! ```
+//! 
+//! `content_type` is optional; unknown fields are ignored so newer uploaders
+//! can add more, while a higher `version` is refused.
+//! 
+//! A file may also list the parts it was uploaded in under `chunks`, each
+//! with its `offset` and `length` within the file and the SHA-256 of its
+//! ciphertext (`ciphertext_sha256`), as `prepare_share` writes them.
+//! `verify_chunk` checks a range fetched on its own against that hash
+//! before it is decrypted, so a corrupted range can be fetched again rather
+//! than failing the file's hash once it has all been downloaded. Chunks are
+//! numbered across all files in manifest order.
+//! 
+//! A manifest may also carry top-level `not_before` and `expires_at` times
+//! (Unix seconds). `decrypt_entry` refuses to decrypt outside that window
+//! with `NotYetValid` or `Expired`, even if the ciphertext is still being
+//! served.
+//! 
+//! A storage host could just as well swap a file and its hash, or edit those
+//! times, so the uploader signs the exact manifest bytes with Ed25519 and
+//! publishes the detached signature beside them; it covers every file's
+//! offset, length, IV and hash as well as the validity window. When the share
+//! link names the uploader's key (its `pk` parameter), `Manifest.signed` checks
+//! the signature while parsing, and a manifest opened with
+//! `new Manifest(json, public_key)` throws `AuthenticationFailed` from
+//! `decrypt_entry` until `verify` has succeeded with that same key, so a host
+//! can't serve an unsigned or re-signed copy in its place.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use sha2::{Digest, Sha256};
+
+use crate::encoding::{decode_base64, decode_hex};
+use crate::error::CryptoError;
+use crate::mac::bytes_equal;
+use crate::sign::ed25519_verify;
+use crate::AesCtr;
+
+pub(crate) const MANIFEST_VERSION: u64 = 1;
+
+/// One uploaded part of a file, as listed under its `chunks`
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ManifestChunk {
+    /// Position within the file
+    offset: u64,
+    length: u64,
+    /// Missing from manifests written before ciphertext hashes were added
+    ciphertext_sha256: Option<[u8; 32]>,
+}
+
+/// One file in the manifest
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ManifestEntry {
+    name: String,
+    offset: u64,
+    length: u64,
+    iv: [u8; 16],
+    sha256: [u8; 32],
+    content_type: Option<String>,
+    chunks: Vec<ManifestChunk>,
+}
+
+impl ManifestEntry {
+    fn from_json(index: usize, file: &serde_json::Value) -> Result<Self, CryptoError> {
+        let invalid = |reason: &str| CryptoError::InvalidFormat(format!("Invalid manifest: file {} {}", index, reason));
+        let text = |name: &str| file.get(name).and_then(|value| value.as_str());
+        let number = |name: &str| file.get(name).and_then(|value| value.as_u64());
+
+        let name = text("name").filter(|name| !name.is_empty()).ok_or_else(|| invalid("has no name"))?;
+        let offset = number("offset").ok_or_else(|| invalid("has no valid offset"))?;
+        let length = number("length").ok_or_else(|| invalid("has no valid length"))?;
+        if offset.checked_add(length).is_none() {
+            return Err(invalid("ends past the largest supported offset"));
+        }
+
+        let iv = text("iv")
+            .map(decode_base64)
+            .transpose()?
+            .and_then(|iv| <[u8; 16]>::try_from(iv).ok())
+            .ok_or_else(|| invalid("needs a 16-byte base64 iv"))?;
+        let sha256 = text("sha256")
+            .map(decode_hex)
+            .transpose()?
+            .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+            .ok_or_else(|| invalid("needs a 32-byte hex sha256"))?;
+
+        let chunks = match file.get("chunks") {
+            None | Some(serde_json::Value::Null) => Vec::new(),
+            Some(chunks) => chunks
+                .as_array()
+                .ok_or_else(|| invalid("has a chunks field that isn't an array"))?
+                .iter()
+                .enumerate()
+                .map(|(chunk_index, chunk)| ManifestChunk::from_json(chunk, length).map_err(|reason| invalid(&format!("chunk {} {}", chunk_index, reason))))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        Ok(ManifestEntry {
+            name: name.to_string(),
+            offset,
+            length,
+            iv,
+            sha256,
+            content_type: text("content_type").map(str::to_string),
+            chunks,
+        })
+    }
+
+    fn end(&self) -> u64 {
+        self.offset + self.length
+    }
+}
+
+impl ManifestChunk {
+    /// Parse one chunk of a file `file_length` bytes long; errors are the reason only
+    fn from_json(chunk: &serde_json::Value, file_length: u64) -> Result<Self, String> {
+        let number = |name: &str| chunk.get(name).and_then(|value| value.as_u64());
+
+        let offset = number("offset").ok_or("has no valid offset")?;
+        let length = number("length").ok_or("has no valid length")?;
+        if offset.checked_add(length).is_none_or(|end| end > file_length) {
+            return Err("ends past the end of the file".into());
+        }
+
+        let ciphertext_sha256 = match chunk.get("ciphertext_sha256").and_then(|hash| hash.as_str()) {
+            None => None,
+            Some(hash) => Some(
+                decode_hex(hash)
+                    .ok()
+                    .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                    .ok_or("needs a 32-byte hex ciphertext_sha256")?,
+            ),
+        };
+
+        Ok(ManifestChunk { offset, length, ciphertext_sha256 })
+    }
+
+    /// Whether `bytes` is exactly this chunk's ciphertext as uploaded
+    fn verify(&self, bytes: &[u8]) -> Result<bool, CryptoError> {
+        let expected = self
+            .ciphertext_sha256
+            .ok_or_else(|| CryptoError::Unsupported("This manifest has no ciphertext hashes for its chunks".into()))?;
+        Ok(bytes.len() as u64 == self.length && bytes_equal(&Sha256::digest(bytes), &expected))
+    }
+}
+
+/// When a manifest may be used, in Unix seconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Validity {
+    not_before: Option<u64>,
+    expires_at: Option<u64>,
+}
+
+impl Validity {
+    fn from_json(manifest: &serde_json::Value) -> Result<Self, CryptoError> {
+        let time = |name: &str| match manifest.get(name) {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(value) => value
+                .as_u64()
+                .map(Some)
+                .ok_or_else(|| CryptoError::InvalidFormat(format!("Invalid manifest: {} must be a time in Unix seconds", name))),
+        };
+
+        let validity = Validity { not_before: time("not_before")?, expires_at: time("expires_at")? };
+        if let (Some(not_before), Some(expires_at)) = (validity.not_before, validity.expires_at) {
+            if expires_at <= not_before {
+                return Err(CryptoError::InvalidFormat("Invalid manifest: expires_at must be after not_before".into()));
+            }
+        }
+        Ok(validity)
+    }
+
+    /// Refuse use outside the window, given the current time in Unix seconds
+    fn check(&self, now: u64) -> Result<(), CryptoError> {
+        if let Some(not_before) = self.not_before.filter(|&not_before| now < not_before) {
+            return Err(CryptoError::NotYetValid(format!("This share can't be opened until {} (Unix time)", not_before)));
+        }
+        if let Some(expires_at) = self.expires_at.filter(|&expires_at| now >= expires_at) {
+            return Err(CryptoError::Expired(format!("This share expired at {} (Unix time)", expires_at)));
+        }
+        Ok(())
+    }
+}
+
+/// Everything a manifest document describes
+#[derive(Debug)]
+struct ManifestDocument {
+    entries: Vec<ManifestEntry>,
+    validity: Validity,
+}
+
+/// Parse and validate a manifest document
+fn parse_manifest(json: &[u8]) -> Result<ManifestDocument, CryptoError> {
+    let manifest: serde_json::Value = serde_json::from_slice(json)
+        .map_err(|e| CryptoError::InvalidFormat(format!("Invalid manifest: not JSON ({})", e)))?;
+
+    match manifest.get("version").and_then(|version| version.as_u64()) {
+        Some(MANIFEST_VERSION) => {}
+        Some(version) => return Err(CryptoError::Unsupported(format!("Unsupported manifest version {}", version))),
+        None => return Err(CryptoError::InvalidFormat("Invalid manifest: missing version".into())),
+    }
+
+    let files = manifest
+        .get("files")
+        .and_then(|files| files.as_array())
+        .ok_or_else(|| CryptoError::InvalidFormat("Invalid manifest: missing files array".into()))?;
+
+    let entries = files.iter().enumerate().map(|(index, file)| ManifestEntry::from_json(index, file)).collect::<Result<Vec<_>, _>>()?;
+
+    // Duplicate names would make `decrypt_entry` ambiguous
+    let mut names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    names.sort_unstable();
+    if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(CryptoError::InvalidFormat(format!("Invalid manifest: duplicate file name {:?}", pair[0])));
+    }
+
+    Ok(ManifestDocument { entries, validity: Validity::from_json(&manifest)? })
+}
+
+/// Check the uploader's detached signature over the exact manifest bytes
+fn verify_manifest(json: &[u8], signature: &[u8], public_key: &[u8]) -> Result<(), CryptoError> {
+    if !ed25519_verify(public_key, json, signature)? {
+        return Err(CryptoError::AuthenticationFailed("Manifest signature is not valid for this public key".into()));
+    }
+    Ok(())
+}
+
+/// Verify the signature first, then parse
+fn parse_signed_manifest(json: &[u8], signature: &[u8], public_key: &[u8]) -> Result<ManifestDocument, CryptoError> {
+    verify_manifest(json, signature, public_key)?;
+    parse_manifest(json)
+}
+
+/// Decrypt one entry out of `data`, which holds the blob from byte `data_offset` on
+fn decrypt_entry_bytes(entry: &ManifestEntry, key: &[u8], data: &[u8], data_offset: u64) -> Result<Vec<u8>, CryptoError> {
+    let data_end = data_offset + data.len() as u64;
+    if entry.offset < data_offset || entry.end() > data_end {
+        return Err(CryptoError::Truncated(format!(
+            "Ciphertext for {:?} is bytes {}-{} of the blob, but only {}-{} were supplied",
+            entry.name, entry.offset, entry.end(), data_offset, data_end
+        )));
+    }
+
+    let start = (entry.offset - data_offset) as usize;
+    let mut plaintext = data[start..start + entry.length as usize].to_vec();
+    AesCtr::new(key, &entry.iv)?.apply_keystream(&mut plaintext)?;
+
+    if !bytes_equal(&Sha256::digest(&plaintext), &entry.sha256) {
+        return Err(CryptoError::AuthenticationFailed(format!("{:?} does not match its manifest hash: wrong key or corrupted download", entry.name)));
+    }
+
+    Ok(plaintext)
+}
+
+/// Parsed multi-file share manifest
+#[wasm_bindgen]
+pub struct Manifest {
+    /// The document as parsed, kept for `verify`
+    json: Vec<u8>,
+    entries: Vec<ManifestEntry>,
+    validity: Validity,
+    /// Uploader key the share link names; nothing is decrypted until a
+    /// signature by it has verified
+    signer: Option<Vec<u8>>,
+    signed: bool,
+}
+
+#[wasm_bindgen]
+impl Manifest {
+    /// Parse a manifest
+    /// 
+    /// # Arguments
+    /// * `json` - Manifest document as a string
+    /// * `public_key` - The uploader's 32-byte Ed25519 public key from the
+    ///   share link (`ShareLink.public_key`), if it names one. `decrypt_entry`
+    ///   then throws `AuthenticationFailed` until `verify` has succeeded.
+    #[wasm_bindgen(constructor)]
+    pub fn new(json: &str, public_key: Option<Uint8Array>) -> Result<Manifest, JsValue> {
+        let signer = public_key.map(|key| key.to_vec());
+        if let Some(key) = signer.as_ref().filter(|key| key.len() != 32) {
+            return Err(CryptoError::InvalidKeyLength(format!("Invalid public key size: expected 32 bytes, got {}", key.len())).into());
+        }
+        let document = parse_manifest(json.as_bytes())?;
+
+        log!("Manifest: Parsed {} files", document.entries.len());
+
+        Ok(Manifest::from_document(json.as_bytes().to_vec(), document, signer, false))
+    }
+
+    /// Parse a manifest after checking the uploader's Ed25519 signature over it
+    /// 
+    /// # Arguments
+    /// * `json` - Manifest bytes exactly as signed, as Uint8Array
+    /// * `signature` - 64-byte Ed25519 signature as Uint8Array
+    /// * `public_key` - Uploader's 32-byte Ed25519 public key as Uint8Array
+    /// 
+    /// # Returns
+    /// Manifest; throws `AuthenticationFailed` if the signature doesn't verify
+    pub fn signed(json: &Uint8Array, signature: &Uint8Array, public_key: &Uint8Array) -> Result<Manifest, JsValue> {
+        let json = json.to_vec();
+        let document = parse_signed_manifest(&json, &signature.to_vec(), &public_key.to_vec())?;
+
+        log!("Manifest: Verified signature, parsed {} files", document.entries.len());
+
+        Ok(Manifest::from_document(json, document, Some(public_key.to_vec()), true))
+    }
+
+    /// Check the uploader's detached Ed25519 signature over this manifest
+    /// 
+    /// `decrypt_entry` refuses to run before this succeeds on a manifest
+    /// opened with the share link's public key, and it fails for any other
+    /// key. The manifest must have been parsed from exactly the text that was
+    /// signed (e.g. `response.text()` of the manifest file).
+    /// 
+    /// # Arguments
+    /// * `public_key` - Uploader's 32-byte Ed25519 public key as Uint8Array
+    /// * `signature` - 64-byte detached signature as Uint8Array
+    /// 
+    /// # Returns
+    /// Nothing; throws `AuthenticationFailed` if the signature doesn't verify,
+    /// in which case nothing in the manifest should be trusted
+    pub fn verify(&mut self, public_key: &Uint8Array, signature: &Uint8Array) -> Result<(), JsValue> {
+        let public_key = public_key.to_vec();
+        if self.signer.as_ref().is_some_and(|signer| *signer != public_key) {
+            return Err(CryptoError::AuthenticationFailed("Manifest signature is by a different key than the share link names".into()).into());
+        }
+        verify_manifest(&self.json, &signature.to_vec(), &public_key)?;
+        self.signer = Some(public_key);
+        self.signed = true;
+
+        log!("Manifest: Verified signature over {} bytes", self.json.len());
+
+        Ok(())
+    }
+
+    /// Whether the manifest's signature has been verified
+    #[wasm_bindgen(getter)]
+    pub fn is_signed(&self) -> bool {
+        self.signed
+    }
+
+    /// Earliest time the share may be opened, in Unix seconds (BigInt), if limited
+    #[wasm_bindgen(getter)]
+    pub fn not_before(&self) -> Option<u64> {
+        self.validity.not_before
+    }
+
+    /// Time the share expires, in Unix seconds (BigInt), if limited
+    #[wasm_bindgen(getter)]
+    pub fn expires_at(&self) -> Option<u64> {
+        self.validity.expires_at
+    }
+
+    /// Number of files in the manifest
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> u32 {
+        self.entries.len() as u32
+    }
+
+    /// List the files
+    /// 
+    /// # Returns
+    /// Array of objects with `index`, `name`, `offset` and `length` (BigInt),
+    /// and `content_type` (or null)
+    pub fn entries(&self) -> Result<js_sys::Array, JsValue> {
+        let entries = js_sys::Array::new();
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &"index".into(), &(index as u32).into())?;
+            js_sys::Reflect::set(&object, &"name".into(), &entry.name.as_str().into())?;
+            js_sys::Reflect::set(&object, &"offset".into(), &entry.offset.into())?;
+            js_sys::Reflect::set(&object, &"length".into(), &entry.length.into())?;
+            js_sys::Reflect::set(&object, &"content_type".into(), &entry.content_type.as_deref().map_or(JsValue::NULL, JsValue::from))?;
+            entries.push(&object);
+        }
+
+        Ok(entries)
+    }
+
+    /// HTTP `Range` header value that fetches just one file's ciphertext
+    /// 
+    /// # Returns
+    /// e.g. `"bytes=1024-4095"`, or `null` for an empty file
+    pub fn range_header(&self, name: &str) -> Result<Option<String>, JsValue> {
+        let entry = self.entry(name)?;
+        Ok((entry.length > 0).then(|| format!("bytes={}-{}", entry.offset, entry.end() - 1)))
+    }
+
+    /// Number of chunks listed across all files
+    #[wasm_bindgen(getter)]
+    pub fn chunk_count(&self) -> u32 {
+        self.entries.iter().map(|entry| entry.chunks.len() as u32).sum()
+    }
+
+    /// HTTP `Range` header value that fetches one chunk's ciphertext
+    /// 
+    /// # Returns
+    /// e.g. `"bytes=4194304-8388607"`, or `null` for an empty chunk
+    pub fn chunk_range_header(&self, index: u32) -> Result<Option<String>, JsValue> {
+        let (entry, chunk) = self.chunk(index)?;
+        let start = entry.offset + chunk.offset;
+        Ok((chunk.length > 0).then(|| format!("bytes={}-{}", start, start + chunk.length - 1)))
+    }
+
+    /// Decrypt one file and check it against its manifest hash
+    /// 
+    /// # Arguments
+    /// * `key` - 16, 24 or 32-byte share key as Uint8Array
+    /// * `name` - File name from `entries()`
+    /// * `ciphertext` - The whole blob, or any part of it containing the file
+    /// * `ciphertext_offset` - Position of `ciphertext` within the blob (BigInt, default 0); pass the file's `offset` when only its range was fetched
+    /// 
+    /// # Returns
+    /// Decrypted file as Uint8Array. Throws `AuthenticationFailed` if the
+    /// hash doesn't match, `Truncated` if `ciphertext` doesn't cover the file,
+    /// `Expired` or `NotYetValid` outside the manifest's validity window, and
+    /// `AuthenticationFailed` before a signature the share link asks for has
+    /// been verified.
+    pub fn decrypt_entry(&self, key: &Uint8Array, name: &str, ciphertext: &Uint8Array, ciphertext_offset: Option<u64>) -> Result<Uint8Array, JsValue> {
+        self.check_usable((js_sys::Date::now() / 1000.0) as u64)?;
+        let entry = self.entry(name)?;
+        let plaintext = decrypt_entry_bytes(entry, &key.to_vec(), &ciphertext.to_vec(), ciphertext_offset.unwrap_or(0))?;
+
+        log!("Manifest: Decrypted {:?} ({} bytes)", entry.name, plaintext.len());
+
+        Ok(Uint8Array::from(plaintext.as_slice()))
+    }
+}
+
+impl Manifest {
+    fn from_document(json: Vec<u8>, document: ManifestDocument, signer: Option<Vec<u8>>, signed: bool) -> Manifest {
+        Manifest { json, entries: document.entries, validity: document.validity, signer, signed }
+    }
+
+    /// Refuse to decrypt before the signature the link asks for has verified,
+    /// or outside the validity window, given the current time in Unix seconds
+    fn check_usable(&self, now: u64) -> Result<(), CryptoError> {
+        if self.signer.is_some() && !self.signed {
+            return Err(CryptoError::AuthenticationFailed(
+                "Manifest signature has not been verified: the share link names an uploader key, so call verify first".into(),
+            ));
+        }
+        self.validity.check(now)
+    }
+
+    /// The file holding chunk `index`, counting across files, and the chunk itself
+    fn chunk(&self, index: u32) -> Result<(&ManifestEntry, &ManifestChunk), CryptoError> {
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.chunks.iter().map(move |chunk| (entry, chunk)))
+            .nth(index as usize)
+            .ok_or_else(|| CryptoError::NotFound(format!("No chunk {} in the manifest, which lists {}", index, self.chunk_count())))
+    }
+
+    fn entry(&self, name: &str) -> Result<&ManifestEntry, CryptoError> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| CryptoError::NotFound(format!("No file named {:?} in the manifest", name)))
+    }
+}
+
+/// Check a downloaded chunk's ciphertext against the manifest before decrypting it
+/// 
+/// Fetch each range with `Manifest.chunk_range_header`, check it, and fetch
+/// it again if it doesn't match.
+/// 
+/// # Arguments
+/// * `manifest` - The share's manifest, verified first if it is signed
+/// * `index` - Chunk number, counting across all files from 0
+/// * `bytes` - The chunk's ciphertext as Uint8Array
+/// 
+/// # Returns
+/// `true` if the chunk matches its hash, `false` if it is corrupted or the
+/// wrong length. Throws `NotFound` for a chunk the manifest doesn't list and
+/// `Unsupported` if the manifest has no ciphertext hashes.
+#[wasm_bindgen]
+pub fn verify_chunk(manifest: &Manifest, index: u32, bytes: &Uint8Array) -> Result<bool, JsValue> {
+    let (entry, chunk) = manifest.chunk(index)?;
+    let matches = chunk.verify(&bytes.to_vec())?;
+
+    if !matches {
+        log!("Manifest: Chunk {} of {:?} does not match its hash", index, entry.name);
+    }
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    const KEY: [u8; 32] = [0x42; 32];
+
+    /// Encrypt `files` into one blob and describe them in a manifest
+    fn share(files: &[(&str, &[u8], [u8; 16])]) -> (String, Vec<u8>) {
+        let mut blob = Vec::new();
+        let mut entries = Vec::new();
+        for (name, contents, iv) in files {
+            let mut ciphertext = contents.to_vec();
+            AesCtr::new(&KEY, iv).unwrap().apply_keystream(&mut ciphertext).unwrap();
+            entries.push(serde_json::json!({
+                "name": name,
+                "offset": blob.len(),
+                "length": ciphertext.len(),
+                "iv": base64::engine::general_purpose::STANDARD.encode(iv),
+                "sha256": Sha256::digest(contents).iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+                "extra": "ignored",
+            }));
+            blob.extend_from_slice(&ciphertext);
+        }
+        (serde_json::json!({ "version": 1, "files": entries }).to_string(), blob)
+    }
+
+    #[test]
+    fn entries_decrypt_from_whole_blob_or_their_own_range() {
+        let (json, blob) = share(&[("a.txt", b"first file", [1; 16]), ("dir/b.bin", &[9u8; 300], [2; 16]), ("empty", b"", [3; 16])]);
+        let entries = parse_manifest(json.as_bytes()).unwrap().entries;
+        assert_eq!(entries.len(), 3);
+        assert_eq!((entries[1].offset, entries[1].length), (10, 300));
+
+        assert_eq!(decrypt_entry_bytes(&entries[0], &KEY, &blob, 0).unwrap(), b"first file");
+        assert_eq!(decrypt_entry_bytes(&entries[1], &KEY, &blob[10..310], 10).unwrap(), [9u8; 300]);
+        assert!(decrypt_entry_bytes(&entries[2], &KEY, &[], 310).unwrap().is_empty());
+
+        assert_eq!(decrypt_entry_bytes(&entries[1], &KEY, &blob[10..300], 10).unwrap_err().name(), "Truncated");
+        assert_eq!(decrypt_entry_bytes(&entries[1], &KEY, &blob[11..], 11).unwrap_err().name(), "Truncated");
+        assert_eq!(decrypt_entry_bytes(&entries[0], &[0x24; 32], &blob, 0).unwrap_err().name(), "AuthenticationFailed");
+    }
+
+    #[test]
+    fn malformed_manifests_are_rejected() {
+        let (json, _) = share(&[("a.txt", b"x", [1; 16])]);
+        let mut manifest: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let with = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut copy = manifest.clone();
+            edit(&mut copy);
+            parse_manifest(copy.to_string().as_bytes()).unwrap_err().name()
+        };
+        assert_eq!(with(&|m| m["version"] = 2.into()), "Unsupported");
+        assert_eq!(with(&|m| m["files"][0]["iv"] = "AAAA".into()), "InvalidFormat");
+        assert_eq!(with(&|m| m["files"][0]["offset"] = (-1).into()), "InvalidFormat");
+        assert_eq!(with(&|m| { m["files"][0]["offset"] = 1.into(); m["files"][0]["length"] = u64::MAX.into() }), "InvalidFormat");
+        assert_eq!(with(&|m| m["files"][0].as_object_mut().unwrap().remove("sha256").map(drop).unwrap()), "InvalidFormat");
+
+        let duplicate = manifest["files"][0].clone();
+        manifest["files"].as_array_mut().unwrap().push(duplicate);
+        assert_eq!(parse_manifest(manifest.to_string().as_bytes()).unwrap_err().name(), "InvalidFormat");
+        assert_eq!(parse_manifest(b"not json").unwrap_err().name(), "InvalidFormat");
+    }
+
+    #[test]
+    fn validity_window_is_enforced() {
+        let (json, _) = share(&[("a.txt", b"x", [1; 16])]);
+        let mut manifest: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parse_manifest(json.as_bytes()).unwrap().validity, Validity::default());
+
+        manifest["not_before"] = 1_000.into();
+        manifest["expires_at"] = 2_000.into();
+        let validity = parse_manifest(manifest.to_string().as_bytes()).unwrap().validity;
+        assert_eq!(validity.check(999).unwrap_err().name(), "NotYetValid");
+        assert!(validity.check(1_000).is_ok());
+        assert!(validity.check(1_999).is_ok());
+        assert_eq!(validity.check(2_000).unwrap_err().name(), "Expired");
+
+        manifest["expires_at"] = 1_000.into();
+        assert_eq!(parse_manifest(manifest.to_string().as_bytes()).unwrap_err().name(), "InvalidFormat");
+        manifest["expires_at"] = "tomorrow".into();
+        assert_eq!(parse_manifest(manifest.to_string().as_bytes()).unwrap_err().name(), "InvalidFormat");
+    }
+
+    #[test]
+    fn chunks_are_checked_against_their_ciphertext_hashes() {
+        let (json, blob) = share(&[("a.bin", &[7u8; 250], [1; 16])]);
+        let mut manifest: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let hex = |bytes: &[u8]| Sha256::digest(bytes).iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+        manifest["files"][0]["chunks"] = serde_json::json!([
+            { "offset": 0, "length": 128, "sha256": "ignored", "ciphertext_sha256": hex(&blob[..128]) },
+            { "offset": 128, "length": 122, "ciphertext_sha256": hex(&blob[128..]) },
+        ]);
+
+        let chunks = &parse_manifest(manifest.to_string().as_bytes()).unwrap().entries[0].chunks;
+        assert!(chunks[0].verify(&blob[..128]).unwrap());
+        assert!(chunks[1].verify(&blob[128..]).unwrap());
+
+        let mut corrupted = blob[..128].to_vec();
+        corrupted[5] ^= 1;
+        assert!(!chunks[0].verify(&corrupted).unwrap());
+        assert!(!chunks[0].verify(&blob[..127]).unwrap());
+        assert!(!chunks[1].verify(&blob[..122]).unwrap());
+
+        let with = |edit: &dyn Fn(&mut serde_json::Value)| {
+            let mut copy = manifest.clone();
+            edit(&mut copy["files"][0]["chunks"]);
+            parse_manifest(copy.to_string().as_bytes()).map(|document| document.entries[0].chunks.clone())
+        };
+        let legacy = with(&|chunks| drop(chunks[1].as_object_mut().unwrap().remove("ciphertext_sha256"))).unwrap();
+        assert_eq!(legacy[1].verify(&blob[128..]).unwrap_err().name(), "Unsupported");
+        assert_eq!(with(&|chunks| chunks[1]["length"] = 123.into()).unwrap_err().name(), "InvalidFormat");
+        assert_eq!(with(&|chunks| chunks[0]["ciphertext_sha256"] = "abcd".into()).unwrap_err().name(), "InvalidFormat");
+        assert_eq!(with(&|chunks| *chunks = "none".into()).unwrap_err().name(), "InvalidFormat");
+    }
+
+    #[test]
+    fn signed_manifests_must_verify_before_parsing() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let signing_key = SigningKey::from_bytes(&[5; 32]);
+        let public_key = signing_key.verifying_key().to_bytes();
+        let (json, _) = share(&[("a.txt", b"x", [1; 16])]);
+        let json = json.replace("\"version\":1", "\"version\":1,\"expires_at\":4102444800");
+        let signature = signing_key.sign(json.as_bytes()).to_bytes();
+
+        let document = parse_signed_manifest(json.as_bytes(), &signature, &public_key).unwrap();
+        assert_eq!(document.validity.expires_at, Some(4_102_444_800));
+
+        // A host pushing the expiry back breaks the signature
+        let extended = json.replace("4102444800", "4102444801");
+        assert_eq!(parse_signed_manifest(extended.as_bytes(), &signature, &public_key).unwrap_err().name(), "AuthenticationFailed");
+        let other_key = SigningKey::from_bytes(&[6; 32]).verifying_key().to_bytes();
+        assert_eq!(parse_signed_manifest(json.as_bytes(), &signature, &other_key).unwrap_err().name(), "AuthenticationFailed");
+        assert_eq!(parse_signed_manifest(json.as_bytes(), &signature[..63], &public_key).unwrap_err().name(), "InvalidInput");
+
+        // Swapping a file means changing its hash, which the signature covers too
+        let swapped = json.replacen(&json[json.find("\"sha256\":\"").unwrap() + 10..][..64], &"0".repeat(64), 1);
+        assert!(parse_manifest(swapped.as_bytes()).is_ok());
+        assert_eq!(verify_manifest(swapped.as_bytes(), &signature, &public_key).unwrap_err().name(), "AuthenticationFailed");
+        assert!(verify_manifest(json.as_bytes(), &signature, &public_key).is_ok());
+    }
+
+    #[test]
+    fn a_link_with_a_public_key_refuses_unsigned_manifests() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let signing_key = SigningKey::from_bytes(&[5; 32]);
+        let public_key = signing_key.verifying_key().to_bytes().to_vec();
+        let (json, _) = share(&[("a.txt", b"x", [1; 16])]);
+        let json = json.replace("\"version\":1", "\"version\":1,\"expires_at\":1000");
+        let signature = signing_key.sign(json.as_bytes()).to_bytes();
+
+        let signed = Manifest::from_document(json.clone().into_bytes(), parse_signed_manifest(json.as_bytes(), &signature, &public_key).unwrap(), Some(public_key.clone()), true);
+        assert_eq!(signed.check_usable(2_000).unwrap_err().name(), "Expired");
+
+        // A host serving the manifest without its expiry and without a signature
+        let stripped = json.replace(",\"expires_at\":1000", "");
+        let open = |signer: Option<Vec<u8>>| Manifest::from_document(stripped.clone().into_bytes(), parse_manifest(stripped.as_bytes()).unwrap(), signer, false);
+        assert_eq!(open(Some(public_key)).check_usable(2_000).unwrap_err().name(), "AuthenticationFailed");
+        assert!(open(None).check_usable(2_000).is_ok());
+    }
+}
@@ -0,0 +1,394 @@
+//! Media Source Extensions playback of encrypted video
+//! 
+//! A player fetches an encrypted video as a series of byte ranges and
+//! appends the plaintext to a `SourceBuffer` so playback starts long before
+//! the download finishes. `MediaDecryptQueue` decrypts each range at its own
+//! keystream position and hands the plaintext back strictly in file order:
+//! ranges fetched in parallel may complete out of order, and MSE needs the
+//! bytes of a progressive MP4 or WebM in sequence.
+//! 
+//! ```javascript
+//! queue.push(offset, new Uint8Array(await response.arrayBuffer()));
+//! for (let bytes; (bytes = queue.pop()); ) {
+//!     await appendBuffer(sourceBuffer, bytes);
+//! }
+//! ```
+//! 
+//! For scrubbing, the uploader publishes a `ChunkIndex` of seek points (the
+//! presentation time and byte offset of each keyframe cluster or fragment):
+//! 
+//! ```json
+//! { "length": 73400320, "chunks": [ { "time": 0, "offset": 0 }, { "time": 4.0, "offset": 1048576 } ] }
+//! ```
+//! 
+//! On a seek the viewer looks up the chunk containing the target time,
+//! calls `MediaDecryptQueue.seek` with its offset and fetches from there;
+//! nothing before it is downloaded or decrypted.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::counter::ctr_options_from_js;
+use crate::error::CryptoError;
+use crate::AesCtr;
+
+/// Reorders decrypted segments into a contiguous stream
+struct SegmentQueue {
+    cipher: AesCtr,
+    /// Position in the file of the next byte to hand out
+    append_offset: u64,
+    /// Decrypted segments that start beyond `append_offset`, keyed by offset
+    pending: BTreeMap<u64, Vec<u8>>,
+    /// Contiguous plaintext waiting to be appended
+    ready: VecDeque<Vec<u8>>,
+}
+
+impl SegmentQueue {
+    fn new(cipher: AesCtr, start_offset: u64) -> Self {
+        SegmentQueue { cipher, append_offset: start_offset, pending: BTreeMap::new(), ready: VecDeque::new() }
+    }
+
+    /// Decrypt the ciphertext found at `offset` and release whatever is now contiguous
+    fn push(&mut self, offset: u64, mut segment: Vec<u8>) -> Result<(), CryptoError> {
+        let end = offset
+            .checked_add(segment.len() as u64)
+            .ok_or_else(|| CryptoError::InvalidInput("Segment ends past the largest supported offset".into()))?;
+        // Already appended (e.g. a retried request): nothing to do
+        if end <= self.append_offset || segment.is_empty() {
+            return Ok(());
+        }
+
+        self.cipher.seek(offset)?;
+        self.cipher.apply_keystream(&mut segment)?;
+
+        // Keep the longer copy if the same range was fetched twice
+        let slot = self.pending.entry(offset).or_default();
+        if segment.len() > slot.len() {
+            *slot = segment;
+        }
+        self.release();
+
+        Ok(())
+    }
+
+    /// Move every pending segment that touches `append_offset` to `ready`
+    fn release(&mut self) {
+        while let Some(entry) = self.pending.first_entry() {
+            let offset = *entry.key();
+            if offset > self.append_offset {
+                break;
+            }
+
+            let segment = entry.remove();
+            let end = offset + segment.len() as u64;
+            if end <= self.append_offset {
+                continue;
+            }
+
+            // Drop the part that overlaps bytes already handed out
+            let fresh = segment[(self.append_offset - offset) as usize..].to_vec();
+            self.append_offset = end;
+            self.ready.push_back(fresh);
+        }
+    }
+
+    /// Restart the stream at `offset`, keeping decrypted segments beyond it
+    fn seek(&mut self, offset: u64) {
+        self.ready.clear();
+        self.pending.retain(|start, segment| *start + segment.len() as u64 > offset);
+        self.append_offset = offset;
+        self.release();
+    }
+
+    fn pending_bytes(&self) -> u64 {
+        self.pending.values().map(|segment| segment.len() as u64).sum()
+    }
+}
+
+/// A seek point: where a chunk starts in time and in the file
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Chunk {
+    time: f64,
+    offset: u64,
+}
+
+/// Parse and validate a chunk index document
+fn parse_chunk_index(json: &[u8]) -> Result<(Vec<Chunk>, u64), CryptoError> {
+    let index: serde_json::Value = serde_json::from_slice(json)
+        .map_err(|e| CryptoError::InvalidFormat(format!("Invalid chunk index: not JSON ({})", e)))?;
+
+    let length = index
+        .get("length")
+        .and_then(|length| length.as_u64())
+        .ok_or_else(|| CryptoError::InvalidFormat("Invalid chunk index: missing length".into()))?;
+    let chunks = index
+        .get("chunks")
+        .and_then(|chunks| chunks.as_array())
+        .filter(|chunks| !chunks.is_empty())
+        .ok_or_else(|| CryptoError::InvalidFormat("Invalid chunk index: missing chunks array".into()))?;
+
+    let chunks = chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let time = chunk.get("time").and_then(|time| time.as_f64()).filter(|time| *time >= 0.0);
+            let offset = chunk.get("offset").and_then(|offset| offset.as_u64());
+            match (time, offset) {
+                (Some(time), Some(offset)) => Ok(Chunk { time, offset }),
+                _ => Err(CryptoError::InvalidFormat(format!("Invalid chunk index: chunk {} needs a time and an offset", i))),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Lookups binary-search on both fields, so both must increase
+    if let Some(i) = chunks.windows(2).position(|pair| pair[1].time <= pair[0].time || pair[1].offset <= pair[0].offset) {
+        return Err(CryptoError::InvalidFormat(format!("Invalid chunk index: chunk {} is not after chunk {}", i + 1, i)));
+    }
+    if chunks[chunks.len() - 1].offset >= length {
+        return Err(CryptoError::InvalidFormat("Invalid chunk index: last chunk starts past the end of the file".into()));
+    }
+
+    Ok((chunks, length))
+}
+
+/// Decrypts fetched byte ranges of an AES-CTR video for a `SourceBuffer`
+#[wasm_bindgen]
+pub struct MediaDecryptQueue {
+    queue: SegmentQueue,
+}
+
+#[wasm_bindgen]
+impl MediaDecryptQueue {
+    /// Create a queue for one encrypted file
+    /// 
+    /// # Arguments
+    /// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+    /// * `iv` - 16-byte initialization vector of the whole file as Uint8Array
+    /// * `options` - Optional `{ counter_width, rfc3686 }` object (default: 128-bit counter, 16-byte IV)
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array, iv: &Uint8Array, options: Option<js_sys::Object>) -> Result<MediaDecryptQueue, JsValue> {
+        let cipher = AesCtr::with_options(&key.to_vec(), &iv.to_vec(), &ctr_options_from_js(options.as_ref())?)?;
+
+        Ok(MediaDecryptQueue { queue: SegmentQueue::new(cipher, 0) })
+    }
+
+    /// Add a fetched range of ciphertext
+    /// 
+    /// Ranges may be pushed in any order and may overlap; plaintext becomes
+    /// available from `pop()` once everything before it has arrived.
+    /// 
+    /// # Arguments
+    /// * `offset` - Byte offset of `segment` within the whole file (BigInt), e.g. the start of its `Range` request
+    /// * `segment` - Ciphertext bytes as Uint8Array
+    pub fn push(&mut self, offset: u64, segment: &Uint8Array) -> Result<(), JsValue> {
+        self.queue.push(offset, segment.to_vec())?;
+
+        log!("MediaDecryptQueue: Received {} bytes at {}, appendable up to {}", segment.length(), offset, self.queue.append_offset);
+
+        Ok(())
+    }
+
+    /// Next plaintext chunk to pass to `SourceBuffer.appendBuffer`, or `undefined` if none is ready
+    pub fn pop(&mut self) -> Option<Uint8Array> {
+        self.queue.ready.pop_front().map(|segment| Uint8Array::from(segment.as_slice()))
+    }
+
+    /// End of the contiguous plaintext released so far (BigInt); the next
+    /// range to fetch starts here
+    #[wasm_bindgen(getter)]
+    pub fn append_offset(&self) -> u64 {
+        self.queue.append_offset
+    }
+
+    /// Bytes decrypted ahead of a gap, waiting for an earlier range to arrive (BigInt)
+    #[wasm_bindgen(getter)]
+    pub fn pending_bytes(&self) -> u64 {
+        self.queue.pending_bytes()
+    }
+
+    /// Continue the stream from another position, e.g. after the user scrubs
+    /// 
+    /// Plaintext not yet taken with `pop()` is discarded; ranges already
+    /// decrypted beyond `offset` are kept. Call `SourceBuffer.abort()` and
+    /// set `timestampOffset` as MSE requires, then push ranges from `offset`.
+    /// 
+    /// # Arguments
+    /// * `offset` - Byte offset to resume from (BigInt), usually a `ChunkIndex` chunk start
+    pub fn seek(&mut self, offset: u64) {
+        self.queue.seek(offset);
+
+        log!("MediaDecryptQueue: Seeked to {}", offset);
+    }
+}
+
+/// Seek points of an encrypted video, mapping playback time to byte ranges
+#[wasm_bindgen]
+pub struct ChunkIndex {
+    chunks: Vec<Chunk>,
+    length: u64,
+}
+
+#[wasm_bindgen]
+impl ChunkIndex {
+    /// Parse a chunk index
+    /// 
+    /// # Arguments
+    /// * `json` - `{ length, chunks: [{ time, offset }] }` with times in seconds, both increasing
+    #[wasm_bindgen(constructor)]
+    pub fn new(json: &str) -> Result<ChunkIndex, JsValue> {
+        let (chunks, length) = parse_chunk_index(json.as_bytes())?;
+
+        log!("ChunkIndex: Parsed {} chunks over {} bytes", chunks.len(), length);
+
+        Ok(ChunkIndex { chunks, length })
+    }
+
+    /// Number of chunks in the index
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> u32 {
+        self.chunks.len() as u32
+    }
+
+    /// Find the chunk to fetch for playback at `time`
+    /// 
+    /// # Arguments
+    /// * `time` - Playback position in seconds; times before the first chunk map to it
+    /// 
+    /// # Returns
+    /// Object with `index`, `time` (the chunk's start time), `start` and `end`
+    /// (BigInt byte offsets, end exclusive) and `range`, a `Range` header
+    /// value covering the chunk
+    pub fn locate(&self, time: f64) -> Result<js_sys::Object, JsValue> {
+        let index = self.chunk_at(time);
+        let (start, end) = self.bounds(index);
+
+        let chunk = js_sys::Object::new();
+        js_sys::Reflect::set(&chunk, &"index".into(), &(index as u32).into())?;
+        js_sys::Reflect::set(&chunk, &"time".into(), &self.chunks[index].time.into())?;
+        js_sys::Reflect::set(&chunk, &"start".into(), &start.into())?;
+        js_sys::Reflect::set(&chunk, &"end".into(), &end.into())?;
+        js_sys::Reflect::set(&chunk, &"range".into(), &format!("bytes={}-{}", start, end - 1).into())?;
+
+        Ok(chunk)
+    }
+}
+
+impl ChunkIndex {
+    /// Index of the last chunk starting at or before `time`
+    fn chunk_at(&self, time: f64) -> usize {
+        self.chunks.partition_point(|chunk| chunk.time <= time).saturating_sub(1)
+    }
+
+    fn bounds(&self, index: usize) -> (u64, u64) {
+        let end = self.chunks.get(index + 1).map_or(self.length, |next| next.offset);
+        (self.chunks[index].offset, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 16] = [0x11; 16];
+    const IV: [u8; 16] = [0xff; 16];
+
+    fn encrypt(plaintext: &[u8]) -> Vec<u8> {
+        let mut ciphertext = plaintext.to_vec();
+        AesCtr::new(&KEY, &IV).unwrap().apply_keystream(&mut ciphertext).unwrap();
+        ciphertext
+    }
+
+    fn drain(queue: &mut SegmentQueue) -> Vec<u8> {
+        queue.ready.drain(..).flatten().collect()
+    }
+
+    #[test]
+    fn out_of_order_segments_come_out_in_file_order() {
+        let video: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        let ciphertext = encrypt(&video);
+        let mut queue = SegmentQueue::new(AesCtr::new(&KEY, &IV).unwrap(), 0);
+
+        queue.push(4_003, ciphertext[4_003..9_000].to_vec()).unwrap();
+        queue.push(1_000, ciphertext[1_000..4_003].to_vec()).unwrap();
+        assert!(drain(&mut queue).is_empty());
+        assert_eq!(queue.pending_bytes(), 8_000);
+
+        queue.push(0, ciphertext[..1_000].to_vec()).unwrap();
+        assert_eq!(queue.append_offset, 9_000);
+        assert_eq!(queue.pending_bytes(), 0);
+        assert_eq!(drain(&mut queue), video[..9_000]);
+
+        queue.push(9_000, ciphertext[9_000..].to_vec()).unwrap();
+        assert_eq!(drain(&mut queue), video[9_000..]);
+    }
+
+    #[test]
+    fn overlapping_and_repeated_segments_are_trimmed() {
+        let video = vec![0x5a; 3_000];
+        let ciphertext = encrypt(&video);
+        let mut queue = SegmentQueue::new(AesCtr::new(&KEY, &IV).unwrap(), 0);
+
+        queue.push(0, ciphertext[..1_500].to_vec()).unwrap();
+        queue.push(0, ciphertext[..1_000].to_vec()).unwrap();
+        queue.push(1_200, ciphertext[1_200..2_000].to_vec()).unwrap();
+        queue.push(1_900, ciphertext[1_900..].to_vec()).unwrap();
+        queue.push(2_500, ciphertext[2_500..].to_vec()).unwrap();
+
+        let segments: Vec<usize> = queue.ready.iter().map(Vec::len).collect();
+        assert_eq!(segments, [1_500, 500, 1_000]);
+        assert_eq!(drain(&mut queue), video);
+        assert_eq!(queue.pending_bytes(), 0);
+
+        assert_eq!(queue.push(u64::MAX, vec![0; 2]).unwrap_err().name(), "InvalidInput");
+    }
+
+    #[test]
+    fn seeking_restarts_the_stream_at_the_new_offset() {
+        let video: Vec<u8> = (0..8_000u32).map(|i| (i % 253) as u8).collect();
+        let ciphertext = encrypt(&video);
+        let mut queue = SegmentQueue::new(AesCtr::new(&KEY, &IV).unwrap(), 0);
+
+        queue.push(0, ciphertext[..1_000].to_vec()).unwrap();
+        queue.push(6_000, ciphertext[6_000..].to_vec()).unwrap();
+        queue.push(3_000, ciphertext[3_000..4_000].to_vec()).unwrap();
+
+        queue.seek(5_000);
+        assert!(queue.ready.is_empty());
+        assert_eq!(queue.pending_bytes(), 2_000);
+
+        queue.push(5_000, ciphertext[5_000..6_000].to_vec()).unwrap();
+        assert_eq!(drain(&mut queue), video[5_000..]);
+
+        // Seeking back: a range starting before the new offset is trimmed
+        queue.seek(3_517);
+        queue.push(3_000, ciphertext[3_000..4_000].to_vec()).unwrap();
+        assert_eq!(drain(&mut queue), video[3_517..4_000]);
+    }
+
+    #[test]
+    fn chunk_index_maps_times_to_byte_ranges() {
+        let json = r#"{ "length": 1000, "chunks": [ { "time": 0, "offset": 0 }, { "time": 2.5, "offset": 300 }, { "time": 5, "offset": 640 } ] }"#;
+        let (chunks, length) = parse_chunk_index(json.as_bytes()).unwrap();
+        let index = ChunkIndex { chunks, length };
+
+        assert_eq!(index.chunk_at(-1.0), 0);
+        assert_eq!(index.chunk_at(2.49), 0);
+        assert_eq!(index.chunk_at(2.5), 1);
+        assert_eq!(index.chunk_at(99.0), 2);
+        assert_eq!(index.bounds(1), (300, 640));
+        assert_eq!(index.bounds(2), (640, 1000));
+
+        for invalid in [
+            r#"{ "length": 1000, "chunks": [] }"#,
+            r#"{ "length": 1000, "chunks": [ { "time": 0, "offset": 0 }, { "time": 0, "offset": 10 } ] }"#,
+            r#"{ "length": 1000, "chunks": [ { "time": 0, "offset": 10 }, { "time": 1, "offset": 10 } ] }"#,
+            r#"{ "length": 1000, "chunks": [ { "time": 0, "offset": 1000 } ] }"#,
+            r#"{ "length": 1000, "chunks": [ { "time": -1, "offset": 0 } ] }"#,
+            r#"{ "chunks": [ { "time": 0, "offset": 0 } ] }"#,
+        ] {
+            assert_eq!(parse_chunk_index(invalid.as_bytes()).unwrap_err().name(), "InvalidFormat", "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Wiping secrets from wasm linear memory
+//! 
+//! Keys, IVs and plaintext pass through many short-lived buffers: copies out
+//! of JS arrays, chunk scratch space, and buffers inside the cipher and
+//! archive crates. Rather than tracking each one, the global allocator zeroes
+//! every block as it is freed. Cipher states additionally zeroize their key
+//! schedules on drop, and `wipe_all` scrubs stack space left behind by
+//! earlier calls.
+
+use wasm_bindgen::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use zeroize::Zeroize;
+
+/// Bytes of stack overwritten by `wipe_all`, comfortably deeper than any call into the module
+const STACK_SCRUB_SIZE: usize = 64 * 1024;
+
+/// Global allocator wrapper that zeroes memory before handing it back
+/// 
+/// `realloc` is left to the default implementation (allocate, copy, free),
+/// so the old block of a growing buffer is wiped too.
+pub struct ZeroizeOnFree<A>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for ZeroizeOnFree<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded unchanged to the wrapped allocator
+        unsafe { self.0.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded unchanged to the wrapped allocator
+        unsafe { self.0.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` was allocated by us with `layout` and is still live,
+        // so it is valid for `layout.size()` bytes until it's released below
+        unsafe {
+            std::slice::from_raw_parts_mut(ptr, layout.size()).zeroize();
+            self.0.dealloc(ptr, layout);
+        }
+    }
+}
+
+/// Largest alignment handed to the wrapped allocator by `AlignFallback`
+const MAX_FORWARDED_ALIGN: usize = 16;
+
+/// Global allocator wrapper that sends over-aligned requests to `System`
+/// 
+/// Only used by `small-binary` builds: wee_alloc fails large allocations aligned to more than 16 bytes, such as
+/// Argon2's 64-byte aligned memory blocks. Those go to the system allocator
+/// (dlmalloc on wasm) instead; `dealloc` routes on the same test, so each
+/// block is always returned to the allocator it came from.
+pub struct AlignFallback<A>(pub A);
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for AlignFallback<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded unchanged to one of the two allocators
+        unsafe {
+            if layout.align() > MAX_FORWARDED_ALIGN { System.alloc(layout) } else { self.0.alloc(layout) }
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwarded unchanged to one of the two allocators
+        unsafe {
+            if layout.align() > MAX_FORWARDED_ALIGN { System.alloc_zeroed(layout) } else { self.0.alloc_zeroed(layout) }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `alloc` picked the allocator from the same `layout`
+        unsafe {
+            if layout.align() > MAX_FORWARDED_ALIGN { System.dealloc(ptr, layout) } else { self.0.dealloc(ptr, layout) }
+        }
+    }
+}
+
+/// Overwrite leftover secrets in wasm memory
+/// 
+/// Freed buffers are already zeroed as they are released; this additionally
+/// releases the buffer behind `*_view` results and scrubs the stack below
+/// the caller, where cipher states and fixed-size key copies from earlier
+/// calls may remain. Call it when the viewer is done with a share, e.g. on
+/// `pagehide`.
+#[wasm_bindgen]
+pub fn wipe_all() {
+    crate::staging::release_views();
+    scrub_stack();
+
+    log!("Memory: Wiped {} bytes of stack", STACK_SCRUB_SIZE);
+}
+
+#[inline(never)]
+fn scrub_stack() {
+    let mut scratch = [0xffu8; STACK_SCRUB_SIZE];
+    scratch.zeroize();
+    std::hint::black_box(&scratch);
+}
+
@@ -0,0 +1,246 @@
+//! Encrypted file metadata
+//! 
+//! The filename, size and content type of a share are sealed into a small
+//! blob kept separately from the body, so a server hosting furl ciphertext
+//! learns neither what is being shared nor (beyond a 256-byte bucket) how
+//! long its name is. The blob is sealed with XChaCha20-Poly1305 under a
+//! 32-byte key, normally the `filename_key` from `derive_share_keys`:
+//! 
+//! ```text
+//! magic      4 bytes    "FMET"
+//! version    1 byte     1
+//! nonce      24 bytes   random
+//! sealed     ciphertext and 16-byte tag of:
+//!   length   2 bytes    big-endian length of the JSON that follows
+//!   json     `{"name": ..., "size": ..., "content_type": ...}`
+//!   padding  zeros up to a multiple of 256 bytes
+//! ```
+//! 
+//! The 5-byte magic and version are the associated data, so the header
+//! can't be swapped without failing authentication.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use rand_core::{OsRng, RngCore};
+
+use crate::error::CryptoError;
+
+const MAGIC: &[u8; 4] = b"FMET";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 5;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// Sealed plaintext is padded to a multiple of this
+const PADDING_BLOCK: usize = 256;
+
+/// Longest JSON that fits the 2-byte length prefix
+const MAX_JSON_LEN: usize = u16::MAX as usize;
+
+/// What the blob describes
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FileMetadata {
+    name: String,
+    size: Option<u64>,
+    content_type: Option<String>,
+}
+
+impl FileMetadata {
+    /// Read `{ name, size, content_type }` from a JS object
+    fn from_js(metadata: &js_sys::Object) -> Result<Self, CryptoError> {
+        let get = |field: &str| {
+            js_sys::Reflect::get(metadata, &field.into())
+                .map(|value| Some(value).filter(|value| !value.is_undefined() && !value.is_null()))
+                .map_err(|_| CryptoError::InvalidInput(format!("Invalid metadata: {} could not be read", field)))
+        };
+
+        let name = get("name")?
+            .and_then(|name| name.as_string())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| CryptoError::InvalidInput("Invalid metadata: name must be a non-empty string".into()))?;
+        let size = get("size")?
+            .map(|size| {
+                size.as_f64()
+                    .filter(|size| size.fract() == 0.0 && *size >= 0.0 && *size <= js_sys::Number::MAX_SAFE_INTEGER)
+                    .map(|size| size as u64)
+                    .ok_or_else(|| CryptoError::InvalidInput("Invalid metadata: size must be a whole number of bytes".into()))
+            })
+            .transpose()?;
+        let content_type = get("content_type")?
+            .map(|content_type| content_type.as_string().ok_or_else(|| CryptoError::InvalidInput("Invalid metadata: content_type must be a string".into())))
+            .transpose()?;
+
+        Ok(FileMetadata { name, size, content_type })
+    }
+
+    fn to_js(&self) -> Result<js_sys::Object, JsValue> {
+        let metadata = js_sys::Object::new();
+        js_sys::Reflect::set(&metadata, &"name".into(), &self.name.as_str().into())?;
+        js_sys::Reflect::set(&metadata, &"size".into(), &self.size.map_or(JsValue::NULL, |size| (size as f64).into()))?;
+        js_sys::Reflect::set(&metadata, &"content_type".into(), &self.content_type.as_deref().map_or(JsValue::NULL, JsValue::from))?;
+        Ok(metadata)
+    }
+
+    fn to_json(&self) -> Vec<u8> {
+        serde_json::json!({ "name": self.name, "size": self.size, "content_type": self.content_type }).to_string().into_bytes()
+    }
+
+    fn from_json(json: &[u8]) -> Result<Self, CryptoError> {
+        let invalid = || CryptoError::InvalidFormat("Invalid metadata: sealed contents are malformed".into());
+        let metadata: serde_json::Value = serde_json::from_slice(json).map_err(|_| invalid())?;
+
+        Ok(FileMetadata {
+            name: metadata.get("name").and_then(|name| name.as_str()).ok_or_else(invalid)?.to_string(),
+            size: metadata.get("size").and_then(|size| size.as_u64()),
+            content_type: metadata.get("content_type").and_then(|content_type| content_type.as_str()).map(str::to_string),
+        })
+    }
+}
+
+fn metadata_cipher(key: &[u8]) -> Result<XChaCha20Poly1305, CryptoError> {
+    if key.len() != 32 {
+        return Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.len())));
+    }
+    XChaCha20Poly1305::new_from_slice(key).map_err(|_| CryptoError::InvalidKey("Invalid key format".into()))
+}
+
+fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0u8; HEADER_LEN];
+    header[..4].copy_from_slice(MAGIC);
+    header[4] = VERSION;
+    header
+}
+
+fn seal_metadata(key: &[u8], metadata: &FileMetadata, nonce: &[u8; NONCE_LEN]) -> Result<Vec<u8>, CryptoError> {
+    let json = metadata.to_json();
+    if json.len() > MAX_JSON_LEN {
+        return Err(CryptoError::InvalidInput(format!("Invalid metadata: at most {} bytes once encoded, got {}", MAX_JSON_LEN, json.len())));
+    }
+
+    let mut plaintext = Vec::with_capacity((2 + json.len()).next_multiple_of(PADDING_BLOCK));
+    plaintext.extend_from_slice(&(json.len() as u16).to_be_bytes());
+    plaintext.extend_from_slice(&json);
+    plaintext.resize(plaintext.len().next_multiple_of(PADDING_BLOCK), 0);
+
+    let header = header();
+    let sealed = metadata_cipher(key)?
+        .encrypt(XNonce::from_slice(nonce), Payload { msg: &plaintext, aad: &header })
+        .map_err(|_| CryptoError::Internal("Metadata encryption failed".into()))?;
+
+    let mut blob = Vec::with_capacity(HEADER_LEN + NONCE_LEN + sealed.len());
+    blob.extend_from_slice(&header);
+    blob.extend_from_slice(nonce);
+    blob.extend_from_slice(&sealed);
+    Ok(blob)
+}
+
+fn open_metadata(key: &[u8], blob: &[u8]) -> Result<FileMetadata, CryptoError> {
+    let cipher = metadata_cipher(key)?;
+    if blob.len() < HEADER_LEN + NONCE_LEN + TAG_LEN {
+        return Err(CryptoError::Truncated(format!("Metadata blob is too short: {} bytes", blob.len())));
+    }
+    if &blob[..4] != MAGIC {
+        return Err(CryptoError::InvalidFormat("Not a furl metadata blob: bad magic".into()));
+    }
+    if blob[4] != VERSION {
+        return Err(CryptoError::Unsupported(format!("Unsupported metadata version {}", blob[4])));
+    }
+
+    let (header, rest) = blob.split_at(HEADER_LEN);
+    let (nonce, sealed) = rest.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: sealed, aad: header })
+        .map_err(|_| CryptoError::AuthenticationFailed("Metadata authentication failed: wrong key or modified blob".into()))?;
+
+    let malformed = || CryptoError::InvalidFormat("Invalid metadata: length exceeds sealed contents".into());
+    let length = plaintext.get(..2).map(|length| u16::from_be_bytes([length[0], length[1]]) as usize).ok_or_else(malformed)?;
+    let json = plaintext.get(2..2 + length).ok_or_else(malformed)?;
+    FileMetadata::from_json(json)
+}
+
+/// Seal a share's filename, size and content type
+/// 
+/// # Arguments
+/// * `key` - 32-byte key as Uint8Array, e.g. `filename_key` from `derive_share_keys`
+/// * `metadata` - `{ name, size?, content_type? }`; `size` is a number of bytes
+/// 
+/// # Returns
+/// Metadata blob as Uint8Array, to store beside the ciphertext
+#[wasm_bindgen]
+pub fn encrypt_metadata(key: &Uint8Array, metadata: &js_sys::Object) -> Result<Uint8Array, JsValue> {
+    let metadata = FileMetadata::from_js(metadata)?;
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+
+    let blob = seal_metadata(&key.to_vec(), &metadata, &nonce)?;
+
+    log!("Metadata: Sealed {} byte blob", blob.len());
+
+    Ok(Uint8Array::from(blob.as_slice()))
+}
+
+/// Open a metadata blob from `encrypt_metadata`
+/// 
+/// # Arguments
+/// * `key` - The 32-byte key it was sealed with
+/// * `blob` - Metadata blob as Uint8Array
+/// 
+/// # Returns
+/// Object with `name`, `size` (number or null) and `content_type` (string or
+/// null); throws `AuthenticationFailed` for a wrong key or modified blob
+#[wasm_bindgen]
+pub fn decrypt_metadata(key: &Uint8Array, blob: &Uint8Array) -> Result<js_sys::Object, JsValue> {
+    let metadata = open_metadata(&key.to_vec(), &blob.to_vec())?;
+
+    log!("Metadata: Opened {} byte blob", blob.length());
+
+    metadata.to_js()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+    const NONCE: [u8; NONCE_LEN] = [9; NONCE_LEN];
+
+    fn metadata(name: &str) -> FileMetadata {
+        FileMetadata { name: name.to_string(), size: Some(1 << 40), content_type: Some("application/pdf".into()) }
+    }
+
+    #[test]
+    fn metadata_round_trips_and_hides_name_length() {
+        let short = seal_metadata(&KEY, &metadata("a.pdf"), &NONCE).unwrap();
+        let long = seal_metadata(&KEY, &metadata("a much longer but still modest name.pdf"), &NONCE).unwrap();
+        assert_eq!(short.len(), HEADER_LEN + NONCE_LEN + PADDING_BLOCK + TAG_LEN);
+        assert_eq!(short.len(), long.len());
+
+        assert_eq!(open_metadata(&KEY, &short).unwrap(), metadata("a.pdf"));
+
+        let bare = FileMetadata { name: "notes".into(), size: None, content_type: None };
+        assert_eq!(open_metadata(&KEY, &seal_metadata(&KEY, &bare, &NONCE).unwrap()).unwrap(), bare);
+
+        let huge = seal_metadata(&KEY, &metadata(&"x".repeat(300)), &NONCE).unwrap();
+        assert_eq!(huge.len(), HEADER_LEN + NONCE_LEN + 2 * PADDING_BLOCK + TAG_LEN);
+    }
+
+    #[test]
+    fn tampered_or_foreign_blobs_are_rejected() {
+        let blob = seal_metadata(&KEY, &metadata("a.pdf"), &NONCE).unwrap();
+
+        assert_eq!(open_metadata(&[8; 32], &blob).unwrap_err().name(), "AuthenticationFailed");
+        assert_eq!(open_metadata(&KEY[..16], &blob).unwrap_err().name(), "InvalidKeyLength");
+
+        let mut flipped = blob.clone();
+        flipped[40] ^= 1;
+        assert_eq!(open_metadata(&KEY, &flipped).unwrap_err().name(), "AuthenticationFailed");
+
+        let mut version = blob.clone();
+        version[4] = 2;
+        assert_eq!(open_metadata(&KEY, &version).unwrap_err().name(), "Unsupported");
+        assert_eq!(open_metadata(&KEY, b"FURL\x01").unwrap_err().name(), "Truncated");
+        assert_eq!(open_metadata(&KEY, &[b'X'; 64]).unwrap_err().name(), "InvalidFormat");
+    }
+}
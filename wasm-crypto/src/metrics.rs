@@ -0,0 +1,194 @@
+//! Runtime counters for the AES-CTR decrypt paths
+//! 
+//! Every AES-CTR decrypt (one-shot, chunked, range, in-place, `Decryptor`
+//! and the stream/Blob helpers built on it) adds to a set of process-wide
+//! counters: operations started, bytes and chunks run through the cipher,
+//! and time spent in the cipher versus copying between JS and wasm memory.
+//! `get_metrics()` reads them, so an integrator can tell a slow CPU (cipher
+//! time dominates) from an expensive boundary (copy time dominates) on a
+//! user's device without a custom build.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use wasm_bindgen::prelude::*;
+
+struct Counters {
+    operations: AtomicU64,
+    bytes: AtomicU64,
+    chunks: AtomicU64,
+    cipher_ns: AtomicU64,
+    copy_ns: AtomicU64,
+}
+
+// Atomics rather than thread-locals so the rayon workers of the threaded
+// build add to the same totals
+static COUNTERS: Counters = Counters {
+    operations: AtomicU64::new(0),
+    bytes: AtomicU64::new(0),
+    chunks: AtomicU64::new(0),
+    cipher_ns: AtomicU64::new(0),
+    copy_ns: AtomicU64::new(0),
+};
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    /// `performance` and its `now` method, looked up once per thread
+    static PERFORMANCE: Option<(JsValue, js_sys::Function)> = {
+        use wasm_bindgen::JsCast;
+        let performance = js_sys::Reflect::get(&js_sys::global(), &"performance".into())
+            .ok()
+            .filter(|performance| performance.is_object());
+        performance.and_then(|performance| {
+            let now = js_sys::Reflect::get(&performance, &"now".into()).ok()?.dyn_into::<js_sys::Function>().ok()?;
+            Some((performance, now))
+        })
+    };
+}
+
+/// Milliseconds on a monotonic clock: `performance.now()` where the runtime
+/// has it, otherwise `Date.now()`
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_ms() -> f64 {
+    PERFORMANCE
+        .with(|performance| {
+            let (performance, now) = performance.as_ref()?;
+            now.call0(performance).ok()?.as_f64()
+        })
+        .unwrap_or_else(js_sys::Date::now)
+}
+
+/// Milliseconds since the first call, for native tests and tools
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_ms() -> f64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+/// Run `body` and add the time it took to `counter`
+fn timed<T>(counter: &AtomicU64, body: impl FnOnce() -> T) -> T {
+    let started = now_ms();
+    let result = body();
+    let elapsed_ns = ((now_ms() - started).max(0.0) * 1_000_000.0) as u64;
+    counter.fetch_add(elapsed_ns, Ordering::Relaxed);
+    result
+}
+
+/// Count the start of one decrypt operation
+pub(crate) fn operation() {
+    COUNTERS.operations.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Run the keystream over one chunk of `len` bytes, timing it as cipher work
+pub(crate) fn cipher<T>(len: usize, body: impl FnOnce() -> T) -> T {
+    let result = timed(&COUNTERS.cipher_ns, body);
+    COUNTERS.bytes.fetch_add(len as u64, Ordering::Relaxed);
+    COUNTERS.chunks.fetch_add(1, Ordering::Relaxed);
+    result
+}
+
+/// Run a copy into or out of wasm memory, timing it as copy work
+pub(crate) fn copy<T>(body: impl FnOnce() -> T) -> T {
+    timed(&COUNTERS.copy_ns, body)
+}
+
+/// Counter values at one point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Snapshot {
+    operations: u64,
+    bytes: u64,
+    chunks: u64,
+    cipher_ms: f64,
+    copy_ms: f64,
+}
+
+impl Snapshot {
+    fn take() -> Self {
+        let ms = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        Snapshot {
+            operations: COUNTERS.operations.load(Ordering::Relaxed),
+            bytes: COUNTERS.bytes.load(Ordering::Relaxed),
+            chunks: COUNTERS.chunks.load(Ordering::Relaxed),
+            cipher_ms: ms(&COUNTERS.cipher_ns),
+            copy_ms: ms(&COUNTERS.copy_ns),
+        }
+    }
+
+    /// Cipher throughput, excluding copies, or `None` before any timed work
+    fn cipher_bytes_per_second(&self) -> Option<f64> {
+        (self.cipher_ms > 0.0).then(|| self.bytes as f64 * 1000.0 / self.cipher_ms)
+    }
+}
+
+/// Read the decrypt counters accumulated since load or the last `reset_metrics`
+/// 
+/// ```javascript
+/// const { cipher_ms, copy_ms, cipher_bytes_per_second } = get_metrics();
+/// if (copy_ms > cipher_ms) console.warn('Boundary copies dominate; try decrypt_aes_ctr_view or decrypt_aes_ctr_into');
+/// ```
+/// 
+/// Times are summed across calls (and across threads in the threaded
+/// build), so they can exceed wall-clock time.
+/// 
+/// # Returns
+/// Object with:
+/// * `operations` - Decrypt calls and `Decryptor`s started
+/// * `bytes_processed` - Bytes run through the cipher (BigInt)
+/// * `chunks_processed` - Keystream applications, one per chunk or `update`
+/// * `cipher_ms` - Time applying the keystream
+/// * `copy_ms` - Time copying data between JS and wasm memory
+/// * `cipher_bytes_per_second` - `bytes_processed` over `cipher_ms`, or `null` if nothing has been timed
+#[wasm_bindgen]
+pub fn get_metrics() -> Result<js_sys::Object, JsValue> {
+    let snapshot = Snapshot::take();
+
+    let metrics = js_sys::Object::new();
+    js_sys::Reflect::set(&metrics, &"operations".into(), &(snapshot.operations as f64).into())?;
+    js_sys::Reflect::set(&metrics, &"bytes_processed".into(), &snapshot.bytes.into())?;
+    js_sys::Reflect::set(&metrics, &"chunks_processed".into(), &(snapshot.chunks as f64).into())?;
+    js_sys::Reflect::set(&metrics, &"cipher_ms".into(), &snapshot.cipher_ms.into())?;
+    js_sys::Reflect::set(&metrics, &"copy_ms".into(), &snapshot.copy_ms.into())?;
+    js_sys::Reflect::set(&metrics, &"cipher_bytes_per_second".into(), &snapshot.cipher_bytes_per_second().map_or(JsValue::NULL, JsValue::from))?;
+
+    Ok(metrics)
+}
+
+/// Set every counter back to zero, e.g. before measuring one download
+#[wasm_bindgen]
+pub fn reset_metrics() {
+    for counter in [&COUNTERS.operations, &COUNTERS.bytes, &COUNTERS.chunks, &COUNTERS.cipher_ns, &COUNTERS.copy_ns] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Other tests decrypt concurrently and share the counters, so these only
+    // check lower bounds
+
+    #[test]
+    fn cipher_work_counts_bytes_chunks_and_time() {
+        let before = Snapshot::take();
+        operation();
+        let result = cipher(1000, || {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            42
+        });
+        copy(|| std::thread::sleep(std::time::Duration::from_millis(1)));
+        let after = Snapshot::take();
+
+        assert_eq!(result, 42);
+        assert!(after.operations > before.operations);
+        assert!(after.bytes >= before.bytes + 1000);
+        assert!(after.chunks > before.chunks);
+        assert!(after.cipher_ms >= before.cipher_ms + 2.0);
+        assert!(after.copy_ms >= before.copy_ms + 1.0);
+    }
+
+    #[test]
+    fn throughput_needs_timed_work() {
+        let snapshot = Snapshot { operations: 1, bytes: 5_000_000, chunks: 5, cipher_ms: 500.0, copy_ms: 0.0 };
+        assert_eq!(snapshot.cipher_bytes_per_second(), Some(10_000_000.0));
+        assert_eq!(Snapshot { cipher_ms: 0.0, ..snapshot }.cipher_bytes_per_second(), None);
+    }
+}
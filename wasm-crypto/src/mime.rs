@@ -0,0 +1,159 @@
+//! MIME type sniffing of decrypted content
+//! 
+//! Shares don't always say what they contain (older links, manifests
+//! without `content_type`), so the viewer looks at the first decrypted bytes
+//! to pick an image, video, audio, PDF or text renderer. Only signatures
+//! that are unambiguous are matched; anything else is `null` and should be
+//! offered as a download.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+/// Number of leading bytes `sniff_mime` looks at; passing more is harmless
+const SNIFF_LENGTH: usize = 512;
+
+/// Fixed signatures: (offset, magic bytes, MIME type)
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (0, b"BM", "image/bmp"),
+    (0, b"\x00\x00\x01\x00", "image/x-icon"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"\x1a\x45\xdf\xa3", "video/webm"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"PK\x05\x06", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (257, b"ustar", "application/x-tar"),
+];
+
+/// RIFF containers: form type at offset 8
+const RIFF_TYPES: &[(&[u8], &str)] = &[(b"WEBP", "image/webp"), (b"WAVE", "audio/wav"), (b"AVI ", "video/x-msvideo")];
+
+/// ISO base media (`ftyp`) major brands
+const FTYP_BRANDS: &[(&[u8], &str)] = &[
+    (b"avif", "image/avif"),
+    (b"avis", "image/avif"),
+    (b"heic", "image/heic"),
+    (b"heix", "image/heic"),
+    (b"qt  ", "video/quicktime"),
+    (b"M4A ", "audio/mp4"),
+    (b"M4V ", "video/mp4"),
+];
+
+/// Sniff the MIME type of `data`, or `None` if it isn't recognised
+pub(crate) fn sniff(data: &[u8]) -> Option<&'static str> {
+    let data = &data[..data.len().min(SNIFF_LENGTH)];
+    let at = |offset: usize, magic: &[u8]| data.get(offset..offset + magic.len()) == Some(magic);
+
+    if let Some(&(_, _, mime)) = SIGNATURES.iter().find(|(offset, magic, _)| at(*offset, magic)) {
+        return Some(mime);
+    }
+    if at(0, b"RIFF") {
+        return RIFF_TYPES.iter().find(|(form, _)| at(8, form)).map(|(_, mime)| *mime);
+    }
+    if at(4, b"ftyp") {
+        // Any other brand (isom, mp41, mp42, dash, ...) is MP4 video
+        let brand = data.get(8..12)?;
+        return Some(FTYP_BRANDS.iter().find(|(known, _)| *known == brand).map_or("video/mp4", |(_, mime)| *mime));
+    }
+    // MPEG audio without an ID3 tag starts straight with a frame sync
+    if data.len() >= 2 && data[0] == 0xff && data[1] & 0xe0 == 0xe0 && data[1] & 0x06 != 0 {
+        return Some("audio/mpeg");
+    }
+
+    sniff_text(data)
+}
+
+/// Recognise UTF-8 text, telling markup apart from plain text
+fn sniff_text(data: &[u8]) -> Option<&'static str> {
+    let data = data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data);
+    if data.is_empty() {
+        return None;
+    }
+
+    // A multi-byte character cut off by the end of the sample is still text
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&data[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    if text.chars().any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b')) {
+        return None;
+    }
+
+    let start = text.trim_start().chars().take(64).collect::<String>().to_ascii_lowercase();
+    Some(if start.starts_with("<svg") || (start.starts_with("<?xml") && text.contains("<svg")) {
+        "image/svg+xml"
+    } else if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "text/html"
+    } else {
+        "text/plain"
+    })
+}
+
+/// Detect the type of decrypted content from its leading bytes
+/// 
+/// # Arguments
+/// * `first_bytes` - Start of the plaintext as Uint8Array; the first 512 bytes are enough
+/// 
+/// # Returns
+/// MIME type such as `"image/png"`, `"video/mp4"`, `"application/pdf"` or
+/// `"text/plain"`, or `null` if the content isn't recognised
+#[wasm_bindgen]
+pub fn sniff_mime(first_bytes: &Uint8Array) -> Option<String> {
+    let length = (first_bytes.length() as usize).min(SNIFF_LENGTH) as u32;
+    sniff(&first_bytes.subarray(0, length).to_vec()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_formats_are_recognised_by_their_magic() {
+        let mut tar = vec![0u8; 512];
+        tar[..7].copy_from_slice(b"foo.txt");
+        tar[257..263].copy_from_slice(b"ustar\0");
+
+        let cases: &[(&[u8], &str)] = &[
+            (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", "image/png"),
+            (b"\xff\xd8\xff\xe0\0\x10JFIF", "image/jpeg"),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", "image/webp"),
+            (b"RIFF\x24\0\0\0WAVEfmt ", "audio/wav"),
+            (b"\0\0\0\x20ftypisom\0\0\x02\0", "video/mp4"),
+            (b"\0\0\0\x14ftypqt  \0\0\0\0", "video/quicktime"),
+            (b"\0\0\0\x1cftypavif\0\0\0\0", "image/avif"),
+            (b"\x1a\x45\xdf\xa3\x9f\x42\x86\x81", "video/webm"),
+            (b"\xff\xfb\x90\x64\0", "audio/mpeg"),
+            (b"%PDF-1.7\n%", "application/pdf"),
+            (&tar, "application/x-tar"),
+        ];
+        for (data, mime) in cases {
+            assert_eq!(sniff(data), Some(*mime), "{:02x?}", &data[..8]);
+        }
+
+        assert_eq!(sniff(b"RIFF\x24\0\0\0XXXX"), None);
+        assert_eq!(sniff(&[0x00, 0x01, 0x02, 0x03, 0x04]), None);
+        assert_eq!(sniff(b"\0\0\0\x20fty"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn text_is_recognised_even_when_cut_mid_character() {
+        assert_eq!(sniff(b"hello,\r\nworld\t!"), Some("text/plain"));
+        assert_eq!(sniff("caf\u{e9} \u{1f600}".as_bytes()), Some("text/plain"));
+        assert_eq!(sniff(&"\u{1f600}".as_bytes()[..3]), Some("text/plain"));
+        assert_eq!(sniff(b"\xef\xbb\xbf  <!DOCTYPE html><html>"), Some("text/html"));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\">"), Some("image/svg+xml"));
+
+        assert_eq!(sniff(b"\xc3\x28 invalid"), None);
+        assert_eq!(sniff(b"text\0with nul"), None);
+        assert_eq!(sniff(b"\xef\xbb\xbf"), None);
+    }
+}
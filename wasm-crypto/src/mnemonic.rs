@@ -0,0 +1,51 @@
+//! Share keys as BIP39 words, for reading a key out over the phone
+//! 
+//! The encoding and its errors live in `furl_core::mnemonic`; this module
+//! wraps them for JS and only accepts AES key sizes.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use furl_core::mnemonic::{from_mnemonic, to_mnemonic};
+use zeroize::Zeroizing;
+
+use crate::error::CryptoError;
+use crate::validate_aes_key;
+
+/// Encode an AES key as BIP39 English words
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// 
+/// # Returns
+/// 12, 18 or 24 words separated by single spaces
+#[wasm_bindgen]
+pub fn key_to_mnemonic(key: &Uint8Array) -> Result<String, JsValue> {
+    validate_aes_key(key)?;
+    let key = Zeroizing::new(key.to_vec());
+    Ok(to_mnemonic(&key)?)
+}
+
+/// Decode BIP39 words from `key_to_mnemonic` back into the key
+/// 
+/// # Arguments
+/// * `mnemonic` - 12, 18 or 24 words separated by whitespace, in any case
+/// 
+/// # Returns
+/// The key as Uint8Array. Throws `InvalidInput` for the wrong number of
+/// words or a word not on the BIP39 list (the message gives its position),
+/// and `InvalidFormat` if the checksum doesn't match.
+#[wasm_bindgen]
+pub fn mnemonic_to_key(mnemonic: &str) -> Result<Uint8Array, JsValue> {
+    let key = Zeroizing::new(from_mnemonic(mnemonic)?);
+    if !matches!(key.len(), 16 | 24 | 32) {
+        return Err(CryptoError::InvalidKeyLength(format!(
+            "Invalid key size: expected 16, 24 or 32 bytes (12, 18 or 24 words), got {}",
+            key.len()
+        ))
+        .into());
+    }
+
+    log!("Mnemonic: Decoded a {}-byte key", key.len());
+
+    Ok(Uint8Array::from(key.as_slice()))
+}
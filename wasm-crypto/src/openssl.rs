@@ -0,0 +1,285 @@
+//! Compatibility with `openssl enc` output
+//! 
+//! `openssl enc -aes-256-cbc|-aes-256-ctr` writes `Salted__`, an 8-byte salt
+//! and the ciphertext. The key and IV come from the passphrase through either
+//! EVP_BytesToKey (the default; MD5 before OpenSSL 1.1.0, SHA-256 since) or
+//! PBKDF2-HMAC-SHA256 with `-pbkdf2` (10000 iterations unless `-iter` is given).
+//! 
+//! CryptoJS's default `AES.encrypt(message, passphrase)` output is the same
+//! format with the MD5 EVP_BytesToKey and AES-256-CBC, Base64-encoded.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use cbc::cipher::BlockDecryptMut;
+use cbc::cipher::block_padding::Pkcs7;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+use crate::error::CryptoError;
+use crate::kdf::pbkdf2_sha256;
+use crate::{Aes256CbcDec, Aes256Ctr};
+
+const SALT_MAGIC: &[u8; 8] = b"Salted__";
+const DEFAULT_PBKDF2_ITERATIONS: u32 = 10_000;
+
+/// Errors produced while decrypting `openssl enc` output
+#[derive(Debug, PartialEq)]
+pub(crate) enum OpensslError {
+    MissingHeader,
+    InvalidBase64,
+    InvalidLength(usize),
+    InvalidPadding,
+    UnsupportedCipher(String),
+    UnsupportedDigest(String),
+    InvalidIterations,
+}
+
+impl fmt::Display for OpensslError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpensslError::MissingHeader => write!(f, "Invalid OpenSSL data: missing Salted__ header"),
+            OpensslError::InvalidBase64 => write!(f, "Invalid Base64 input"),
+            OpensslError::InvalidLength(n) => write!(f, "Invalid data size: expected a non-empty multiple of 16 bytes, got {}", n),
+            OpensslError::InvalidPadding => write!(f, "Invalid PKCS#7 padding: wrong passphrase or corrupted ciphertext"),
+            OpensslError::UnsupportedCipher(name) => write!(f, "Unsupported cipher: {} (expected aes-256-cbc or aes-256-ctr)", name),
+            OpensslError::UnsupportedDigest(name) => write!(f, "Unsupported digest: {} (expected sha256 or md5)", name),
+            OpensslError::InvalidIterations => write!(f, "Invalid iterations: must be at least 1"),
+        }
+    }
+}
+
+impl From<OpensslError> for CryptoError {
+    fn from(error: OpensslError) -> CryptoError {
+        let message = error.to_string();
+        match error {
+            OpensslError::MissingHeader | OpensslError::InvalidBase64 => CryptoError::InvalidFormat(message),
+            OpensslError::InvalidLength(_) | OpensslError::InvalidIterations => CryptoError::InvalidInput(message),
+            OpensslError::InvalidPadding => CryptoError::InvalidPadding(message),
+            OpensslError::UnsupportedCipher(_) | OpensslError::UnsupportedDigest(_) => CryptoError::Unsupported(message),
+        }
+    }
+}
+
+impl From<OpensslError> for JsValue {
+    fn from(error: OpensslError) -> JsValue {
+        CryptoError::from(error).into()
+    }
+}
+
+/// Digest used by EVP_BytesToKey (`-md`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum KdfDigest {
+    Md5,
+    Sha256,
+}
+
+impl KdfDigest {
+    fn from_name(name: Option<&str>) -> Result<Self, OpensslError> {
+        match name.map(str::to_ascii_lowercase).as_deref() {
+            None | Some("sha256") | Some("sha-256") => Ok(KdfDigest::Sha256),
+            Some("md5") => Ok(KdfDigest::Md5),
+            Some(other) => Err(OpensslError::UnsupportedDigest(other.to_string())),
+        }
+    }
+}
+
+/// Block cipher mode (`-aes-256-cbc` or `-aes-256-ctr`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CipherMode {
+    Cbc,
+    Ctr,
+}
+
+impl CipherMode {
+    fn from_name(name: &str) -> Result<Self, OpensslError> {
+        match name.to_ascii_lowercase().trim_start_matches('-') {
+            "aes-256-cbc" => Ok(CipherMode::Cbc),
+            "aes-256-ctr" => Ok(CipherMode::Ctr),
+            other => Err(OpensslError::UnsupportedCipher(other.to_string())),
+        }
+    }
+}
+
+/// OpenSSL's EVP_BytesToKey with one iteration:
+/// `D_i = H(D_{i-1} || password || salt)`, concatenated until `output` is full
+pub(crate) fn evp_bytes_to_key(digest: KdfDigest, password: &[u8], salt: &[u8], output: &mut [u8]) {
+    let mut previous: Vec<u8> = Vec::new();
+    let mut filled = 0;
+
+    while filled < output.len() {
+        let block = match digest {
+            KdfDigest::Md5 => Md5::new().chain_update(&previous).chain_update(password).chain_update(salt).finalize().to_vec(),
+            KdfDigest::Sha256 => Sha256::new().chain_update(&previous).chain_update(password).chain_update(salt).finalize().to_vec(),
+        };
+
+        let take = block.len().min(output.len() - filled);
+        output[filled..filled + take].copy_from_slice(&block[..take]);
+        filled += take;
+        previous = block;
+    }
+}
+
+/// Split `Salted__ || salt || ciphertext`
+pub(crate) fn split_salted(data: &[u8]) -> Result<(&[u8], &[u8]), OpensslError> {
+    if data.len() < 16 || &data[..8] != SALT_MAGIC {
+        return Err(OpensslError::MissingHeader);
+    }
+    Ok((&data[8..16], &data[16..]))
+}
+
+/// Decrypt AES-256 ciphertext with a key and IV derived by one of the OpenSSL KDFs
+pub(crate) fn decrypt_with_key_iv(mode: CipherMode, key_iv: &[u8; 48], ciphertext: &[u8]) -> Result<Vec<u8>, OpensslError> {
+    let (key, iv) = key_iv.split_at(32);
+
+    match mode {
+        CipherMode::Cbc => {
+            if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+                return Err(OpensslError::InvalidLength(ciphertext.len()));
+            }
+            Aes256CbcDec::new(key.into(), iv.into())
+                .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+                .map_err(|_| OpensslError::InvalidPadding)
+        }
+        CipherMode::Ctr => {
+            let mut plaintext = ciphertext.to_vec();
+            Aes256Ctr::new(key.into(), iv.into()).apply_keystream(&mut plaintext);
+            Ok(plaintext)
+        }
+    }
+}
+
+/// Decrypt an `openssl enc` payload
+pub(crate) fn decrypt(
+    data: &[u8],
+    passphrase: &[u8],
+    mode: CipherMode,
+    pbkdf2_iterations: Option<u32>,
+    digest: KdfDigest
+) -> Result<Vec<u8>, OpensslError> {
+    let (salt, ciphertext) = split_salted(data)?;
+
+    let mut key_iv = [0u8; 48];
+    match pbkdf2_iterations {
+        Some(0) => return Err(OpensslError::InvalidIterations),
+        Some(iterations) => pbkdf2_sha256(passphrase, salt, iterations, &mut key_iv, |_| {}),
+        None => evp_bytes_to_key(digest, passphrase, salt, &mut key_iv),
+    }
+
+    decrypt_with_key_iv(mode, &key_iv, ciphertext)
+}
+
+/// Decrypt a file produced by `openssl enc`
+/// 
+/// # Arguments
+/// * `data` - OpenSSL output as Uint8Array (starting with `Salted__`; Base64 output must be decoded first)
+/// * `passphrase` - Passphrase given to `-pass`/`-k`
+/// * `cipher` - `"aes-256-cbc"` or `"aes-256-ctr"`
+/// * `pbkdf2` - `true` if the file was made with `-pbkdf2` or `-iter`
+/// * `iterations` - PBKDF2 iterations from `-iter` (default: 10000)
+/// * `digest` - EVP_BytesToKey digest from `-md` when `pbkdf2` is false: `"sha256"` (default) or `"md5"`
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array. For CBC a wrong passphrase usually shows up
+/// as a JS `Error` whose `name` is `InvalidPadding`; CTR has no padding, so a
+/// wrong passphrase produces garbage.
+#[wasm_bindgen]
+pub fn decrypt_openssl_enc(
+    data: &Uint8Array,
+    passphrase: &str,
+    cipher: &str,
+    pbkdf2: bool,
+    iterations: Option<u32>,
+    digest: Option<String>
+) -> Result<Uint8Array, JsValue> {
+    let mode = CipherMode::from_name(cipher)?;
+    let digest = KdfDigest::from_name(digest.as_deref())?;
+    let iterations = pbkdf2.then(|| iterations.unwrap_or(DEFAULT_PBKDF2_ITERATIONS));
+
+    let plaintext = decrypt(&data.to_vec(), passphrase.as_bytes(), mode, iterations, digest)?;
+
+    log!("OpenSSL: Decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// Decode a CryptoJS OpenSSL-format Base64 blob and decrypt it
+pub(crate) fn decrypt_cryptojs_blob(blob: &str, passphrase: &[u8]) -> Result<Vec<u8>, OpensslError> {
+    // Tolerate line-wrapped Base64 such as `openssl enc -a` output
+    let compact: String = blob.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    let data = STANDARD.decode(compact).map_err(|_| OpensslError::InvalidBase64)?;
+
+    decrypt(&data, passphrase, CipherMode::Cbc, None, KdfDigest::Md5)
+}
+
+/// Decrypt a CryptoJS `AES.encrypt(message, passphrase).toString()` blob
+/// 
+/// # Arguments
+/// * `blob` - Base64 string starting with `U2FsdGVkX1` (`Salted__`)
+/// * `passphrase` - Passphrase passed to CryptoJS
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array (decode with `TextDecoder` for text). A wrong
+/// passphrase is usually reported as a JS `Error` whose `name` is `InvalidPadding`.
+#[wasm_bindgen]
+pub fn decrypt_cryptojs(blob: &str, passphrase: &str) -> Result<Uint8Array, JsValue> {
+    let plaintext = decrypt_cryptojs_blob(blob, passphrase.as_bytes())?;
+
+    log!("CryptoJS: Decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_hex;
+
+    const PLAINTEXT: &[u8] = b"furl openssl compatibility test\n";
+    const PASSPHRASE: &[u8] = b"secret";
+
+    // Generated with OpenSSL 3.0: `openssl enc -<cipher> -pass pass:secret <kdf options>`
+    const VECTORS: [(CipherMode, Option<u32>, KdfDigest, &str); 7] = [
+        (CipherMode::Cbc, None, KdfDigest::Sha256,
+            "53616c7465645f5f1e024d420a670b351ceaf2e44e17ebb18a0e35eb2ed43a9b6f4fff523d0094fd46a6d677ffbeebc9ec86d7d91dbae354b2c16e6742aa5fd0"),
+        (CipherMode::Cbc, None, KdfDigest::Md5,
+            "53616c7465645f5fc7a9f07b854b9c839c07ce25d6156353a0881a0a7e5f800bb6272a34ea9d59174a70c5d61a798deb297c3132de85f2609e9470a2c397105d"),
+        (CipherMode::Cbc, Some(1000), KdfDigest::Sha256,
+            "53616c7465645f5fd281e8dd4504350ea9fd76477c2496d0d08bbd61671894b7570f937b9545cce6d873e2249dbf6ce29ffe2b880f50e9aaba31a95d4f8440cb"),
+        (CipherMode::Cbc, Some(10_000), KdfDigest::Sha256,
+            "53616c7465645f5f1599eb87a4a7b751a4acb335e63b60031b1ddfcff9b3de96d7d7a74c64ceaa148a1aeff3ce9cde5157df4925c81850b1b8706cd38160bbc7"),
+        (CipherMode::Ctr, None, KdfDigest::Sha256,
+            "53616c7465645f5f13a948fd44d7f73a26706abb22741a5bcd0b57ac04f249efab08a890c90daf205bce5814f7c1a5ab"),
+        (CipherMode::Ctr, None, KdfDigest::Md5,
+            "53616c7465645f5fd99fb1793e2846cd85ad06d560561be30987f7112b009bb4883eaff3358f663a1690a3f0d7776c46"),
+        (CipherMode::Ctr, Some(1000), KdfDigest::Sha256,
+            "53616c7465645f5f4573d70961257569888a4d2e4813f3940030094046dc025eabb4240d802c82d5ff17a2fe39bfa3c0"),
+    ];
+
+    #[test]
+    fn decrypts_openssl_output_for_every_kdf() {
+        for (mode, iterations, digest, data) in VECTORS {
+            assert_eq!(decrypt(&decode_hex(data).unwrap(), PASSPHRASE, mode, iterations, digest).unwrap(), PLAINTEXT);
+        }
+    }
+
+    #[test]
+    fn decrypts_cryptojs_blob() {
+        // Same format as CryptoJS.AES.encrypt("CryptoJS says hi", "hunter2").toString()
+        let blob = "U2FsdGVkX1/pqkzpORcVrkAaIinT727Z\nIxwO4+wt3rYLHDfK2+1fYt/MEpigU3id";
+        assert_eq!(decrypt_cryptojs_blob(blob, b"hunter2").unwrap(), b"CryptoJS says hi");
+        assert_eq!(decrypt_cryptojs_blob("not base64!", b"hunter2"), Err(OpensslError::InvalidBase64));
+    }
+
+    #[test]
+    fn rejects_missing_header_and_wrong_passphrase() {
+        let (mode, iterations, digest, data) = VECTORS[0];
+        let data = decode_hex(data).unwrap();
+
+        assert_eq!(decrypt(&data[16..], PASSPHRASE, mode, iterations, digest), Err(OpensslError::MissingHeader));
+        assert_eq!(decrypt(&data, b"wrong", mode, iterations, digest), Err(OpensslError::InvalidPadding));
+    }
+}
@@ -0,0 +1,253 @@
+//! Password-protected shares
+//! 
+//! Instead of putting the key in the link, the uploader derives it from a
+//! passphrase with Argon2id and publishes the salt and a short verifier next
+//! to the ciphertext. The viewer re-derives the key from what the visitor
+//! types and checks the verifier before touching the ciphertext, so a typo
+//! is reported as `WrongPassword` straight away instead of producing a
+//! download of garbage (AES-CTR has no tag to catch it).
+//! 
+//! The Argon2id output is split with HKDF-SHA256 into the content key and a
+//! verifier key; the verifier is HMAC-SHA256 of a fixed label under the
+//! latter, so publishing it reveals nothing about the content key.
+//! 
+//! Argon2id cost is read from an optional `options` object:
+//! 
+//! * `memory_kib` - memory in KiB (default 19456, i.e. 19 MiB)
+//! * `iterations` - passes over memory (default 2)
+//! * `parallelism` - lanes (default 1)
+//! 
+//! These defaults follow the OWASP recommendation. Whatever the uploader
+//! used must be published with the salt and passed again when unlocking.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use argon2::{Algorithm, Argon2, Params, Version};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use crate::counter::ctr_options_from_js;
+use crate::error::CryptoError;
+use crate::kdf::hkdf_sha256;
+use crate::keyhandle::KeyHandle;
+use crate::mac::bytes_equal;
+use crate::AesCtr;
+
+/// Shortest salt accepted, as for `derive_key_pbkdf2`
+const MIN_SALT_LEN: u32 = 8;
+
+/// Upper bound on `memory_kib` (1 GiB), well past what a browser tab can spare
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+
+/// HKDF `info` labels splitting the Argon2id output
+const CONTENT_KEY_INFO: &[u8] = b"furl v1 password content key";
+const VERIFIER_KEY_INFO: &[u8] = b"furl v1 password verifier key";
+
+/// Message the verifier authenticates
+const VERIFIER_LABEL: &[u8] = b"furl password verifier v1";
+
+/// Argon2id cost settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Argon2Options {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+impl Default for Argon2Options {
+    fn default() -> Self {
+        Argon2Options { memory_kib: 19 * 1024, iterations: 2, parallelism: 1 }
+    }
+}
+
+impl Argon2Options {
+    pub(crate) fn from_js(options: Option<&js_sys::Object>) -> Result<Self, CryptoError> {
+        let mut parsed = Argon2Options::default();
+        let Some(options) = options else {
+            return Ok(parsed);
+        };
+
+        for (name, field) in [
+            ("memory_kib", &mut parsed.memory_kib),
+            ("iterations", &mut parsed.iterations),
+            ("parallelism", &mut parsed.parallelism),
+        ] {
+            let value = js_sys::Reflect::get(options, &name.into())
+                .map_err(|_| CryptoError::InvalidInput(format!("Invalid options: {} could not be read", name)))?;
+            if value.is_undefined() {
+                continue;
+            }
+            *field = value
+                .as_f64()
+                .filter(|n| n.fract() == 0.0 && *n >= 1.0 && *n <= u32::MAX as f64)
+                .ok_or_else(|| CryptoError::InvalidInput(format!("Invalid {}: expected a positive whole number", name)))? as u32;
+        }
+
+        Ok(parsed)
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, CryptoError> {
+        if self.memory_kib > MAX_MEMORY_KIB {
+            return Err(CryptoError::InvalidInput(format!("Invalid memory_kib: at most {} KiB, got {}", MAX_MEMORY_KIB, self.memory_kib)));
+        }
+
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| CryptoError::InvalidInput(format!("Invalid Argon2id parameters: {}", e)))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+/// Content key and verifier derived from a passphrase
+struct PasswordKeys {
+    content_key: Zeroizing<Vec<u8>>,
+    verifier: [u8; 32],
+}
+
+fn derive_password_keys(passphrase: &str, salt: &[u8], options: &Argon2Options) -> Result<PasswordKeys, CryptoError> {
+    if passphrase.is_empty() {
+        return Err(CryptoError::InvalidInput("Invalid passphrase: must not be empty".into()));
+    }
+    if salt.len() < MIN_SALT_LEN as usize {
+        return Err(CryptoError::InvalidInput(format!("Invalid salt size: expected at least {} bytes, got {}", MIN_SALT_LEN, salt.len())));
+    }
+
+    let mut master = Zeroizing::new([0u8; 32]);
+    options
+        .argon2()?
+        .hash_password_into(passphrase.as_bytes(), salt, &mut master[..])
+        .map_err(|e| CryptoError::Internal(format!("Argon2id failed: {}", e)))?;
+
+    let mut content_key = Zeroizing::new(vec![0u8; 32]);
+    let mut verifier_key = Zeroizing::new([0u8; 32]);
+    hkdf_sha256(&master[..], Some(salt), CONTENT_KEY_INFO, &mut content_key)?;
+    hkdf_sha256(&master[..], Some(salt), VERIFIER_KEY_INFO, &mut verifier_key[..])?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&verifier_key[..]).expect("HMAC accepts keys of any length");
+    mac.update(VERIFIER_LABEL);
+
+    Ok(PasswordKeys { content_key, verifier: mac.finalize().into_bytes().into() })
+}
+
+/// Derive the content key, failing with `WrongPassword` unless `verifier` matches
+fn unlock(passphrase: &str, salt: &[u8], verifier: &[u8], options: &Argon2Options) -> Result<Zeroizing<Vec<u8>>, CryptoError> {
+    let keys = derive_password_keys(passphrase, salt, options)?;
+    if !bytes_equal(&keys.verifier, verifier) {
+        return Err(CryptoError::WrongPassword("Wrong password for this share".into()));
+    }
+
+    Ok(keys.content_key)
+}
+
+/// Derive the key and verifier for a new password-protected share
+/// 
+/// # Arguments
+/// * `passphrase` - Passphrase chosen by the uploader
+/// * `salt` - Random salt as Uint8Array (at least 8 bytes; 16 recommended), published with the share
+/// * `options` - Optional `{ memory_kib, iterations, parallelism }` Argon2id cost, published with the share
+/// 
+/// # Returns
+/// Object with `key` (KeyHandle for the 32-byte AES key) and `verifier`
+/// (32-byte Uint8Array to publish with the share)
+#[wasm_bindgen]
+pub fn create_password_share(passphrase: &str, salt: &Uint8Array, options: Option<js_sys::Object>) -> Result<js_sys::Object, JsValue> {
+    let keys = derive_password_keys(passphrase, &salt.to_vec(), &Argon2Options::from_js(options.as_ref())?)?;
+
+    let share = js_sys::Object::new();
+    js_sys::Reflect::set(&share, &"key".into(), &KeyHandle::from_bytes(keys.content_key.to_vec()).into())?;
+    js_sys::Reflect::set(&share, &"verifier".into(), &Uint8Array::from(&keys.verifier[..]))?;
+
+    log!("Password: Derived share key and verifier");
+
+    Ok(share)
+}
+
+/// Check a passphrase against a share's verifier and return its key
+/// 
+/// # Arguments
+/// * `passphrase` - Passphrase typed by the visitor
+/// * `salt` - Salt published with the share
+/// * `verifier` - Verifier published with the share
+/// * `options` - The share's `{ memory_kib, iterations, parallelism }`
+/// 
+/// # Returns
+/// KeyHandle for the content key; throws `WrongPassword` if the passphrase doesn't match
+#[wasm_bindgen]
+pub fn unlock_password_share(passphrase: &str, salt: &Uint8Array, verifier: &Uint8Array, options: Option<js_sys::Object>) -> Result<KeyHandle, JsValue> {
+    let key = unlock(passphrase, &salt.to_vec(), &verifier.to_vec(), &Argon2Options::from_js(options.as_ref())?)?;
+
+    log!("Password: Verifier matched");
+
+    Ok(KeyHandle::from_bytes(key.to_vec()))
+}
+
+/// Unlock a password-protected share and decrypt its AES-CTR ciphertext
+/// 
+/// The ciphertext is only decrypted once the passphrase has been verified.
+/// 
+/// # Arguments
+/// * `passphrase` - Passphrase typed by the visitor
+/// * `salt` - Salt published with the share
+/// * `verifier` - Verifier published with the share
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `encrypted_data` - Encrypted data as Uint8Array
+/// * `options` - Optional object with the share's Argon2id cost and AES-CTR `counter_width` / `rfc3686`
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array; throws `WrongPassword` if the passphrase doesn't match
+#[wasm_bindgen]
+pub fn decrypt_password_share(
+    passphrase: &str,
+    salt: &Uint8Array,
+    verifier: &Uint8Array,
+    iv: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    options: Option<js_sys::Object>
+) -> Result<Uint8Array, JsValue> {
+    let key = unlock(passphrase, &salt.to_vec(), &verifier.to_vec(), &Argon2Options::from_js(options.as_ref())?)?;
+
+    let mut data = encrypted_data.to_vec();
+    AesCtr::with_options(&key, &iv.to_vec(), &ctr_options_from_js(options.as_ref())?)?.apply_keystream(&mut data)?;
+
+    log!("Password: Decrypted {} bytes", data.len());
+
+    Ok(Uint8Array::from(data.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap settings so the tests stay fast
+    const FAST: Argon2Options = Argon2Options { memory_kib: 64, iterations: 1, parallelism: 1 };
+    const SALT: [u8; 16] = *b"furl-test-salt!!";
+
+    #[test]
+    fn correct_passphrase_unlocks_and_wrong_one_is_refused() {
+        let keys = derive_password_keys("correct horse", &SALT, &FAST).unwrap();
+        assert_eq!(keys.content_key.len(), 32);
+        assert_ne!(keys.content_key[..], keys.verifier[..]);
+
+        assert_eq!(unlock("correct horse", &SALT, &keys.verifier, &FAST).unwrap(), keys.content_key);
+        assert_eq!(unlock("correct horsE", &SALT, &keys.verifier, &FAST).unwrap_err().name(), "WrongPassword");
+        assert_eq!(unlock("correct horse", b"other-salt-bytes", &keys.verifier, &FAST).unwrap_err().name(), "WrongPassword");
+        assert_eq!(unlock("correct horse", &SALT, &keys.verifier[..16], &FAST).unwrap_err().name(), "WrongPassword");
+
+        let costlier = Argon2Options { iterations: 2, ..FAST };
+        assert_eq!(unlock("correct horse", &SALT, &keys.verifier, &costlier).unwrap_err().name(), "WrongPassword");
+    }
+
+    #[test]
+    fn bad_inputs_are_rejected_before_deriving() {
+        assert_eq!(derive_password_keys("", &SALT, &FAST).err().unwrap().name(), "InvalidInput");
+        assert_eq!(derive_password_keys("pw", &SALT[..7], &FAST).err().unwrap().name(), "InvalidInput");
+
+        for options in [
+            Argon2Options { memory_kib: 4, ..FAST },
+            Argon2Options { memory_kib: MAX_MEMORY_KIB + 1, ..FAST },
+            Argon2Options { iterations: 0, ..FAST },
+        ] {
+            assert_eq!(derive_password_keys("pw", &SALT, &options).err().unwrap().name(), "InvalidInput", "{:?}", options);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Structured progress reports for long-running operations
+//!
+//! Progress callbacks receive one object per update:
+//!
+//! * `bytes_done` / `total_bytes` - Bytes processed so far and in total (BigInt)
+//! * `chunk_index` / `total_chunks` - Index of the chunk just finished and the chunk count
+//! * `percent` - 0 to 100, reaching exactly 100 on the last update
+//! * `elapsed_ms` - Time since the operation started
+//! * `bytes_per_second` - Average throughput so far
+//! * `remaining_ms` - Estimated time left at that throughput
+//!
+//! `total_bytes`, `total_chunks`, `percent` and `remaining_ms` are `null` when
+//! the total isn't known in advance, as with a `ReadableStream`.
+
+use wasm_bindgen::prelude::*;
+
+/// Sends progress updates for one operation to an optional JS callback
+pub(crate) struct Progress<'a> {
+    callback: Option<&'a js_sys::Function>,
+    total_bytes: Option<u64>,
+    total_chunks: Option<u64>,
+    started_ms: f64,
+}
+
+impl<'a> Progress<'a> {
+    pub(crate) fn new(callback: Option<&'a js_sys::Function>, total_bytes: Option<u64>, chunk_size: Option<u64>) -> Self {
+        let total_chunks = total_bytes.zip(chunk_size).map(|(total, size)| total.div_ceil(size.max(1)));
+        let started_ms = if callback.is_some() { js_sys::Date::now() } else { 0.0 };
+
+        Progress { callback, total_bytes, total_chunks, started_ms }
+    }
+
+    /// Report `bytes_done` bytes processed, ending with chunk `chunk_index`
+    pub(crate) fn report(&self, bytes_done: u64, chunk_index: u64) {
+        let Some(callback) = self.callback else {
+            return;
+        };
+
+        let elapsed_ms = js_sys::Date::now() - self.started_ms;
+        let estimate = Estimate::new(bytes_done, self.total_bytes, elapsed_ms);
+
+        let report = js_sys::Object::new();
+        let set = |name: &str, value: JsValue| {
+            let _ = js_sys::Reflect::set(&report, &name.into(), &value);
+        };
+        set("bytes_done", bytes_done.into());
+        set("total_bytes", self.total_bytes.map_or(JsValue::NULL, JsValue::from));
+        set("chunk_index", (chunk_index as f64).into());
+        set("total_chunks", self.total_chunks.map_or(JsValue::NULL, |chunks| (chunks as f64).into()));
+        set("percent", estimate.percent.map_or(JsValue::NULL, JsValue::from));
+        set("elapsed_ms", elapsed_ms.into());
+        set("bytes_per_second", estimate.bytes_per_second.into());
+        set("remaining_ms", estimate.remaining_ms.map_or(JsValue::NULL, JsValue::from));
+
+        let _ = callback.call1(&JsValue::NULL, &report);
+    }
+}
+
+/// Percent complete, throughput and time remaining for one update
+#[derive(Debug, PartialEq)]
+struct Estimate {
+    percent: Option<f64>,
+    bytes_per_second: f64,
+    remaining_ms: Option<f64>,
+}
+
+impl Estimate {
+    fn new(bytes_done: u64, total_bytes: Option<u64>, elapsed_ms: f64) -> Self {
+        let bytes_per_second = if elapsed_ms > 0.0 { bytes_done as f64 * 1000.0 / elapsed_ms } else { 0.0 };
+
+        let percent = total_bytes.map(|total| match total {
+            0 => 100.0,
+            total => bytes_done.min(total) as f64 * 100.0 / total as f64,
+        });
+
+        let remaining_ms = total_bytes.and_then(|total| match total.saturating_sub(bytes_done) {
+            0 => Some(0.0),
+            _ if bytes_per_second == 0.0 => None,
+            left => Some(left as f64 * 1000.0 / bytes_per_second),
+        });
+
+        Estimate { percent, bytes_per_second, remaining_ms }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_from_known_total() {
+        let estimate = Estimate::new(25, Some(100), 500.0);
+        assert_eq!(estimate, Estimate { percent: Some(25.0), bytes_per_second: 50.0, remaining_ms: Some(1500.0) });
+
+        let done = Estimate::new(100, Some(100), 2000.0);
+        assert_eq!(done.percent, Some(100.0));
+        assert_eq!(done.remaining_ms, Some(0.0));
+    }
+
+    #[test]
+    fn estimate_without_total_or_elapsed_time() {
+        let streaming = Estimate::new(1000, None, 100.0);
+        assert_eq!(streaming, Estimate { percent: None, bytes_per_second: 10000.0, remaining_ms: None });
+
+        let instant = Estimate::new(10, Some(100), 0.0);
+        assert_eq!(instant.bytes_per_second, 0.0);
+        assert_eq!(instant.remaining_ms, None);
+
+        assert_eq!(Estimate::new(0, Some(0), 0.0).percent, Some(100.0));
+    }
+}
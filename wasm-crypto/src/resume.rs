@@ -0,0 +1,298 @@
+//! Resumable downloads from out-of-order ranges
+//! 
+//! `Decryptor` needs its ciphertext in one unbroken sequence, so a dropped
+//! connection used to mean starting again from zero. `ResumableDecryptor`
+//! instead takes each chunk together with its byte offset in the file,
+//! decrypts it at that keystream position and records which ranges have
+//! arrived. Chunks may come in any order, overlap or be delivered twice
+//! (e.g. a retried request that had partly succeeded); re-delivered bytes
+//! decrypt to the same plaintext and are only counted once.
+//! 
+//! After a failure the viewer asks `next_range` for the first gap and
+//! fetches just that:
+//! 
+//! ```javascript
+//! const download = new ResumableDecryptor(key, iv, BigInt(length));
+//! for (let range; (range = download.next_range(4n << 20n)); ) {
+//!     const response = await fetchWithRetry(url, { headers: { Range: range } });
+//!     const offset = BigInt(response.headers.get('Content-Range').match(/\d+/)[0]);
+//!     await writable.write({ type: 'write', position: Number(offset), data: download.ingest(offset, await response.bytes()) });
+//! }
+//! ```
+//! 
+//! `export_state` captures the received ranges (not the data or the key) so
+//! a download can be picked up again after a reload.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::counter::ctr_options_from_js;
+use crate::error::CryptoError;
+use crate::{decryptor_state_check, validate_aes_key, AesCtr};
+
+const STATE_VERSION: u8 = 1;
+/// version | total length | check | range count
+const STATE_HEADER_LEN: usize = 1 + 8 + 8 + 4;
+
+/// Sorted, non-overlapping, non-adjacent half-open byte ranges
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct RangeSet {
+    ranges: Vec<(u64, u64)>,
+}
+
+impl RangeSet {
+    /// Mark `start..end` as received, merging with whatever it touches
+    fn insert(&mut self, start: u64, end: u64) {
+        if start >= end {
+            return;
+        }
+
+        // Ranges wholly before or after the new one, with a gap between
+        let first = self.ranges.partition_point(|&(_, range_end)| range_end < start);
+        let last = self.ranges.partition_point(|&(range_start, _)| range_start <= end);
+
+        let merged = match self.ranges[first..last] {
+            [] => (start, end),
+            ref touching => (start.min(touching[0].0), end.max(touching[touching.len() - 1].1)),
+        };
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    fn covered(&self) -> u64 {
+        self.ranges.iter().map(|(start, end)| end - start).sum()
+    }
+
+    /// Gaps left in `0..total`
+    fn missing(&self, total: u64) -> Vec<(u64, u64)> {
+        let mut gaps = Vec::new();
+        let mut cursor = 0;
+        for &(start, end) in &self.ranges {
+            if start > cursor {
+                gaps.push((cursor, start));
+            }
+            cursor = end;
+        }
+        if cursor < total {
+            gaps.push((cursor, total));
+        }
+        gaps
+    }
+
+    fn encode(&self, total: u64, check: &[u8; 8]) -> Vec<u8> {
+        let mut state = Vec::with_capacity(STATE_HEADER_LEN + self.ranges.len() * 16);
+        state.push(STATE_VERSION);
+        state.extend_from_slice(&total.to_be_bytes());
+        state.extend_from_slice(check);
+        state.extend_from_slice(&(self.ranges.len() as u32).to_be_bytes());
+        for (start, end) in &self.ranges {
+            state.extend_from_slice(&start.to_be_bytes());
+            state.extend_from_slice(&end.to_be_bytes());
+        }
+        state
+    }
+
+    /// Parse `encode` output into the total length, check value and ranges
+    fn decode(state: &[u8]) -> Result<(u64, [u8; 8], RangeSet), CryptoError> {
+        let invalid = || CryptoError::InvalidFormat("Invalid download state: malformed".into());
+        let u64_at = |at: usize| state.get(at..at + 8).map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap())).ok_or_else(invalid);
+
+        match state.first() {
+            Some(&STATE_VERSION) => {}
+            Some(version) => return Err(CryptoError::Unsupported(format!("Unsupported download state version: {}", version))),
+            None => return Err(invalid()),
+        }
+        if state.len() < STATE_HEADER_LEN {
+            return Err(invalid());
+        }
+        let total = u64_at(1)?;
+        let check: [u8; 8] = state[9..17].try_into().unwrap();
+        let count = u32::from_be_bytes(state[17..21].try_into().unwrap()) as usize;
+        if count.checked_mul(16).and_then(|len| len.checked_add(STATE_HEADER_LEN)) != Some(state.len()) {
+            return Err(invalid());
+        }
+
+        let mut ranges = RangeSet::default();
+        let mut previous_end = None;
+        for i in 0..count {
+            let (start, end) = (u64_at(STATE_HEADER_LEN + i * 16)?, u64_at(STATE_HEADER_LEN + i * 16 + 8)?);
+            // Reject anything `insert` could not have produced
+            if start >= end || end > total || previous_end.is_some_and(|previous| start <= previous) {
+                return Err(invalid());
+            }
+            ranges.ranges.push((start, end));
+            previous_end = Some(end);
+        }
+
+        Ok((total, check, ranges))
+    }
+}
+
+/// AES-CTR decryptor that accepts chunks by file offset and tracks coverage
+/// 
+/// Construct it with the ciphertext's total length, `ingest` chunks as
+/// they arrive, and use `next_range` or `missing` to find what's left.
+#[wasm_bindgen]
+pub struct ResumableDecryptor {
+    cipher: AesCtr,
+    total_length: u64,
+    received: RangeSet,
+    state_check: [u8; 8],
+}
+
+#[wasm_bindgen]
+impl ResumableDecryptor {
+    /// Create a decryptor for a file of `total_length` ciphertext bytes
+    /// 
+    /// # Arguments
+    /// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+    /// * `iv` - 16-byte initialization vector as Uint8Array
+    /// * `total_length` - Size of the whole ciphertext (BigInt)
+    /// * `options` - Optional `{ counter_width, rfc3686 }` object
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array, iv: &Uint8Array, total_length: u64, options: Option<js_sys::Object>) -> Result<ResumableDecryptor, JsValue> {
+        validate_aes_key(key)?;
+        let (key, iv) = (key.to_vec(), iv.to_vec());
+        let cipher = AesCtr::with_options(&key, &iv, &ctr_options_from_js(options.as_ref())?)?;
+
+        log!("ResumableDecryptor: Created for {} bytes", total_length);
+
+        Ok(ResumableDecryptor { cipher, total_length, received: RangeSet::default(), state_check: decryptor_state_check(&key, &iv) })
+    }
+
+    /// Recreate a decryptor from `export_state()`, e.g. after a reload
+    /// 
+    /// # Arguments
+    /// * `key` - The same AES key as Uint8Array
+    /// * `iv` - The same 16-byte IV as Uint8Array
+    /// * `state` - Bytes returned by `export_state()`
+    /// * `options` - The same `{ counter_width, rfc3686 }` options
+    pub fn import_state(key: &Uint8Array, iv: &Uint8Array, state: &Uint8Array, options: Option<js_sys::Object>) -> Result<ResumableDecryptor, JsValue> {
+        let (total_length, check, received) = RangeSet::decode(&state.to_vec())?;
+        let mut decryptor = ResumableDecryptor::new(key, iv, total_length, options)?;
+        if check != decryptor.state_check {
+            return Err(CryptoError::InvalidKey("Download state was exported with a different key or IV".into()).into());
+        }
+        decryptor.received = received;
+
+        log!("ResumableDecryptor: Resumed with {} of {} bytes", decryptor.received.covered(), total_length);
+
+        Ok(decryptor)
+    }
+
+    /// Decrypt a chunk of ciphertext that starts at `offset` in the file
+    /// 
+    /// # Arguments
+    /// * `offset` - Byte offset of the chunk's first byte (BigInt)
+    /// * `chunk` - Ciphertext as Uint8Array
+    /// 
+    /// # Returns
+    /// The chunk's plaintext, to be written at `offset`; throws
+    /// `InvalidInput` if the chunk runs past `total_length`
+    pub fn ingest(&mut self, offset: u64, chunk: &Uint8Array) -> Result<Uint8Array, JsValue> {
+        let end = offset
+            .checked_add(chunk.length() as u64)
+            .filter(|&end| end <= self.total_length)
+            .ok_or_else(|| CryptoError::InvalidInput(format!(
+                "Chunk at {} of {} bytes runs past the {} byte file",
+                offset, chunk.length(), self.total_length
+            )))?;
+
+        let mut data = chunk.to_vec();
+        self.cipher.seek(offset)?;
+        self.cipher.apply_keystream(&mut data)?;
+        self.received.insert(offset, end);
+
+        Ok(Uint8Array::from(data.as_slice()))
+    }
+
+    /// `Range` header value for the first missing bytes, or `undefined` once complete
+    /// 
+    /// # Arguments
+    /// * `max_bytes` - Optional cap on the size of the range (BigInt)
+    pub fn next_range(&self, max_bytes: Option<u64>) -> Option<String> {
+        let (start, end) = *self.received.missing(self.total_length).first()?;
+        let end = max_bytes.filter(|&max| max > 0).map_or(end, |max| end.min(start.saturating_add(max)));
+        Some(format!("bytes={}-{}", start, end - 1))
+    }
+
+    /// Gaps still to fetch, as `{ start, end }` objects (BigInt, `end` exclusive)
+    pub fn missing(&self) -> Result<js_sys::Array, JsValue> {
+        let gaps = js_sys::Array::new();
+        for (start, end) in self.received.missing(self.total_length) {
+            let gap = js_sys::Object::new();
+            js_sys::Reflect::set(&gap, &"start".into(), &start.into())?;
+            js_sys::Reflect::set(&gap, &"end".into(), &end.into())?;
+            gaps.push(&gap);
+        }
+        Ok(gaps)
+    }
+
+    /// Distinct ciphertext bytes received so far (BigInt)
+    #[wasm_bindgen(getter)]
+    pub fn bytes_received(&self) -> u64 {
+        self.received.covered()
+    }
+
+    /// Size of the whole ciphertext (BigInt)
+    #[wasm_bindgen(getter)]
+    pub fn total_length(&self) -> u64 {
+        self.total_length
+    }
+
+    /// Whether every byte has been received
+    #[wasm_bindgen(getter)]
+    pub fn is_complete(&self) -> bool {
+        self.received.covered() == self.total_length
+    }
+
+    /// Snapshot the received ranges; contains no key material
+    pub fn export_state(&self) -> Uint8Array {
+        Uint8Array::from(self.received.encode(self.total_length, &self.state_check).as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(u64, u64)]) -> RangeSet {
+        let mut set = RangeSet::default();
+        for &(start, end) in ranges {
+            set.insert(start, end);
+        }
+        set
+    }
+
+    #[test]
+    fn ranges_merge_and_report_gaps() {
+        let received = set(&[(100, 200), (300, 400), (0, 50), (150, 250), (250, 260), (400, 400)]);
+        assert_eq!(received.ranges, [(0, 50), (100, 260), (300, 400)]);
+        assert_eq!(received.covered(), 50 + 160 + 100);
+        assert_eq!(received.missing(500), [(50, 100), (260, 300), (400, 500)]);
+
+        // A re-delivered or spanning chunk swallows everything it covers
+        let received = set(&[(100, 200), (300, 400), (100, 200), (50, 450)]);
+        assert_eq!(received.ranges, [(50, 450)]);
+        assert_eq!(set(&[(0, 10), (10, 20)]).ranges, [(0, 20)]);
+        assert_eq!(set(&[(0, 500)]).missing(500), []);
+        assert_eq!(RangeSet::default().missing(0), []);
+    }
+
+    #[test]
+    fn state_round_trips_and_rejects_damage() {
+        let received = set(&[(0, 10), (20, 30)]);
+        let state = received.encode(100, &[7; 8]);
+        assert_eq!(RangeSet::decode(&state).unwrap(), (100, [7; 8], received));
+
+        assert_eq!(RangeSet::decode(&state[..state.len() - 1]).unwrap_err().name(), "InvalidFormat");
+        assert_eq!(RangeSet::decode(&[]).unwrap_err().name(), "InvalidFormat");
+        assert_eq!(RangeSet::decode(&[2]).unwrap_err().name(), "Unsupported");
+
+        let past_end = set(&[(90, 110)]).encode(100, &[7; 8]);
+        assert_eq!(RangeSet::decode(&past_end).unwrap_err().name(), "InvalidFormat");
+
+        let mut unsorted = set(&[(0, 10), (20, 30)]);
+        unsorted.ranges.swap(0, 1);
+        assert_eq!(RangeSet::decode(&unsorted.encode(100, &[7; 8])).unwrap_err().name(), "InvalidFormat");
+    }
+}
@@ -0,0 +1,331 @@
+//! libsodium `crypto_secretstream_xchacha20poly1305` compatibility
+//! 
+//! Files produced by libsodium-based uploaders are a 24-byte header followed
+//! by messages of `1 + len + 16` bytes (encrypted tag byte, ciphertext,
+//! Poly1305 MAC). Each message advances the internal nonce by its MAC, so
+//! messages must be pulled in order and a stream ends with a `FINAL` tag.
+//! 
+//! Tags: `0` MESSAGE, `1` PUSH, `2` REKEY, `3` FINAL.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use chacha20::ChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::cipher::consts::U10;
+use poly1305::Poly1305;
+use poly1305::universal_hash::KeyInit;
+use rand_core::{OsRng, RngCore};
+
+use crate::error::CryptoError;
+use crate::mac::bytes_equal;
+
+pub(crate) const HEADER_LEN: usize = 24;
+pub(crate) const ABYTES: usize = 17;
+
+pub(crate) const TAG_MESSAGE: u8 = 0;
+pub(crate) const TAG_PUSH: u8 = 1;
+pub(crate) const TAG_REKEY: u8 = 2;
+pub(crate) const TAG_FINAL: u8 = TAG_PUSH | TAG_REKEY;
+
+/// Stream state: subkey plus 12-byte nonce (4-byte LE counter || 8-byte inonce)
+pub(crate) struct SecretStream {
+    key: [u8; 32],
+    nonce: [u8; 12],
+}
+
+impl SecretStream {
+    /// Derive the stream state from the key and header (`init_push` / `init_pull`)
+    pub(crate) fn new(key: &[u8; 32], header: &[u8; HEADER_LEN]) -> Self {
+        let subkey = chacha20::hchacha::<U10>(key.into(), header[..16].into());
+
+        let mut stream = SecretStream { key: subkey.into(), nonce: [0u8; 12] };
+        stream.nonce[4..].copy_from_slice(&header[16..]);
+        stream.reset_counter();
+        stream
+    }
+
+    /// Encrypt one message (`crypto_secretstream_xchacha20poly1305_push`)
+    pub(crate) fn push(&mut self, message: &[u8], ad: &[u8], tag: u8) -> Vec<u8> {
+        let (mut cipher, mac_key) = self.start();
+
+        let mut block = [0u8; 64];
+        block[0] = tag;
+        cipher.apply_keystream(&mut block);
+
+        let mut out = Vec::with_capacity(ABYTES + message.len());
+        out.push(block[0]);
+
+        let start = out.len();
+        out.extend_from_slice(message);
+        cipher.apply_keystream(&mut out[start..]);
+
+        let tag_bytes = compute_mac(&mac_key, ad, &block, &out[start..]);
+        out.extend_from_slice(&tag_bytes);
+
+        self.advance(&tag_bytes, tag);
+        out
+    }
+
+    /// Verify and decrypt one message (`crypto_secretstream_xchacha20poly1305_pull`),
+    /// returning the plaintext and its tag, or `None` if authentication fails
+    pub(crate) fn pull(&mut self, input: &[u8], ad: &[u8]) -> Option<(Vec<u8>, u8)> {
+        if input.len() < ABYTES {
+            return None;
+        }
+
+        let (mut cipher, mac_key) = self.start();
+
+        let mut block = [0u8; 64];
+        block[0] = input[0];
+        cipher.apply_keystream(&mut block);
+        let tag = block[0];
+        block[0] = input[0];
+
+        let ciphertext = &input[1..input.len() - 16];
+        let expected = &input[input.len() - 16..];
+        let computed = compute_mac(&mac_key, ad, &block, ciphertext);
+        if !bytes_equal(&computed, expected) {
+            return None;
+        }
+
+        let mut message = ciphertext.to_vec();
+        cipher.apply_keystream(&mut message);
+
+        self.advance(&computed, tag);
+        Some((message, tag))
+    }
+
+    /// Replace the key and inonce with fresh keystream (`crypto_secretstream_xchacha20poly1305_rekey`)
+    pub(crate) fn rekey(&mut self) {
+        let mut next = [0u8; 40];
+        next[..32].copy_from_slice(&self.key);
+        next[32..].copy_from_slice(&self.nonce[4..]);
+        ChaCha20::new(&self.key.into(), &self.nonce.into()).apply_keystream(&mut next);
+
+        self.key.copy_from_slice(&next[..32]);
+        self.nonce[4..].copy_from_slice(&next[32..]);
+        self.reset_counter();
+    }
+
+    /// Keystream block 0 is the Poly1305 key; the cipher is left at block 1
+    fn start(&self) -> (ChaCha20, [u8; 32]) {
+        let mut cipher = ChaCha20::new(&self.key.into(), &self.nonce.into());
+        let mut block = [0u8; 64];
+        cipher.apply_keystream(&mut block);
+
+        let mut mac_key = [0u8; 32];
+        mac_key.copy_from_slice(&block[..32]);
+        (cipher, mac_key)
+    }
+
+    fn advance(&mut self, mac: &[u8; 16], tag: u8) {
+        self.nonce[4..].iter_mut().zip(mac).for_each(|(n, m)| *n ^= m);
+
+        let counter = u32::from_le_bytes([self.nonce[0], self.nonce[1], self.nonce[2], self.nonce[3]]).wrapping_add(1);
+        self.nonce[..4].copy_from_slice(&counter.to_le_bytes());
+
+        if tag & TAG_REKEY != 0 || counter == 0 {
+            self.rekey();
+        }
+    }
+
+    fn reset_counter(&mut self) {
+        self.nonce[..4].copy_from_slice(&1u32.to_le_bytes());
+    }
+}
+
+/// Poly1305 over `ad || pad || block || ciphertext || pad || lengths`
+/// 
+/// libsodium pads the ciphertext with `len % 16` zero bytes rather than up
+/// to a block boundary, so the input is assembled unaligned and hashed in
+/// one pass instead of with `update_padded`.
+fn compute_mac(mac_key: &[u8; 32], ad: &[u8], block: &[u8; 64], ciphertext: &[u8]) -> [u8; 16] {
+    let ad_pad = (16 - ad.len() % 16) % 16;
+    let ciphertext_pad = ciphertext.len() % 16;
+
+    let mut input = Vec::with_capacity(ad.len() + ad_pad + 64 + ciphertext.len() + ciphertext_pad + 16);
+    input.extend_from_slice(ad);
+    input.resize(input.len() + ad_pad, 0);
+    input.extend_from_slice(block);
+    input.extend_from_slice(ciphertext);
+    input.resize(input.len() + ciphertext_pad, 0);
+    input.extend_from_slice(&(ad.len() as u64).to_le_bytes());
+    input.extend_from_slice(&(64 + ciphertext.len() as u64).to_le_bytes());
+
+    Poly1305::new(mac_key.into()).compute_unpadded(&input).into()
+}
+
+fn stream_key(key: &Uint8Array) -> Result<[u8; 32], CryptoError> {
+    key.to_vec()
+        .try_into()
+        .map_err(|_| CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 bytes, got {}", key.length())))
+}
+
+/// Encrypting side of a libsodium-compatible secretstream
+#[wasm_bindgen]
+pub struct SecretStreamPush {
+    stream: SecretStream,
+    header: [u8; HEADER_LEN],
+}
+
+#[wasm_bindgen]
+impl SecretStreamPush {
+    /// Start a stream with a random header
+    /// 
+    /// # Arguments
+    /// * `key` - 32-byte key as Uint8Array
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array) -> Result<SecretStreamPush, JsValue> {
+        let key = stream_key(key)?;
+        let mut header = [0u8; HEADER_LEN];
+        OsRng.fill_bytes(&mut header);
+
+        Ok(SecretStreamPush { stream: SecretStream::new(&key, &header), header })
+    }
+
+    /// 24-byte header to send before the first message
+    #[wasm_bindgen(getter)]
+    pub fn header(&self) -> Uint8Array {
+        Uint8Array::from(&self.header[..])
+    }
+
+    /// Encrypt one message
+    /// 
+    /// # Arguments
+    /// * `message` - Plaintext as Uint8Array
+    /// * `tag` - Message tag (default `0`; use `3` for the last message)
+    /// * `ad` - Optional additional data as Uint8Array
+    /// 
+    /// # Returns
+    /// Ciphertext as Uint8Array (17 bytes longer than the message)
+    pub fn push(&mut self, message: &Uint8Array, tag: Option<u8>, ad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+        let tag = tag.unwrap_or(TAG_MESSAGE);
+        if tag > TAG_FINAL {
+            return Err(CryptoError::InvalidInput(format!("Invalid tag: expected 0 to 3, got {}", tag)).into());
+        }
+
+        let ad = ad.map(|ad| ad.to_vec()).unwrap_or_default();
+        let out = self.stream.push(&message.to_vec(), &ad, tag);
+
+        Ok(Uint8Array::from(out.as_slice()))
+    }
+
+    /// Explicitly rekey the stream
+    pub fn rekey(&mut self) {
+        self.stream.rekey();
+    }
+}
+
+/// Decrypting side of a libsodium-compatible secretstream
+#[wasm_bindgen]
+pub struct SecretStreamPull {
+    stream: SecretStream,
+    tag: u8,
+}
+
+#[wasm_bindgen]
+impl SecretStreamPull {
+    /// Start pulling a stream
+    /// 
+    /// # Arguments
+    /// * `key` - 32-byte key as Uint8Array
+    /// * `header` - 24-byte stream header as Uint8Array
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &Uint8Array, header: &Uint8Array) -> Result<SecretStreamPull, JsValue> {
+        let key = stream_key(key)?;
+        let header: [u8; HEADER_LEN] = header
+            .to_vec()
+            .try_into()
+            .map_err(|_| CryptoError::InvalidInput(format!("Invalid header size: expected {} bytes, got {}", HEADER_LEN, header.length())))?;
+
+        Ok(SecretStreamPull { stream: SecretStream::new(&key, &header), tag: TAG_MESSAGE })
+    }
+
+    /// Tag of the last message pulled (`3` once the stream is complete)
+    #[wasm_bindgen(getter)]
+    pub fn tag(&self) -> u8 {
+        self.tag
+    }
+
+    /// Verify and decrypt one message
+    /// 
+    /// # Arguments
+    /// * `ciphertext` - One pushed message as Uint8Array
+    /// * `ad` - Optional additional data as Uint8Array
+    /// 
+    /// # Returns
+    /// Plaintext as Uint8Array, or an error if the message is corrupted,
+    /// out of order or pulled after the final message
+    pub fn pull(&mut self, ciphertext: &Uint8Array, ad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+        if self.tag == TAG_FINAL {
+            return Err(CryptoError::InvalidState("Stream has already ended with a final message".into()).into());
+        }
+
+        if (ciphertext.length() as usize) < ABYTES {
+            return Err(CryptoError::InvalidInput(format!("Invalid message size: expected at least {} bytes, got {}", ABYTES, ciphertext.length())).into());
+        }
+
+        let ad = ad.map(|ad| ad.to_vec()).unwrap_or_default();
+        let (message, tag) = self
+            .stream
+            .pull(&ciphertext.to_vec(), &ad)
+            .ok_or_else(|| CryptoError::AuthenticationFailed("Secretstream authentication failed: message is corrupted or out of order".into()))?;
+        self.tag = tag;
+
+        Ok(Uint8Array::from(message.as_slice()))
+    }
+
+    /// Explicitly rekey the stream (must mirror the sender's `rekey` calls)
+    pub fn rekey(&mut self) {
+        self.stream.rekey();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_hex;
+
+    const KEY: [u8; 32] = [0x11; 32];
+
+    /// Stream produced by libsodium 1.0.18 `crypto_secretstream_xchacha20poly1305_push`
+    const HEADER: &str = "be10a7e59c36f5da209d7b63cce472af047abe28367e70f3";
+    const MESSAGES: [(&[u8], &[u8], u8, &str); 4] = [
+        (b"hello", b"", TAG_MESSAGE, "2ebdfd23491f74803413e4649f4a55db3ead82b7b80d"),
+        (b"", b"ad", TAG_MESSAGE, "0341a4ec44693b3dfd0cf9be1914e11719"),
+        (b"rekeyed chunk", b"", TAG_REKEY, "cae702ee1ceed6e4d777f97657ca2776bd1b0ae192ccc927fbe7bff0ab23"),
+        (b"world!world!world!world!world!", b"", TAG_FINAL,
+            "c8e0352bc9e10f76a5e054eff17a8c9701208f5638c48436b2ce1e807ddab7d41a972a9d5236d1f613742fc09fe2aa"),
+    ];
+
+    fn header() -> [u8; HEADER_LEN] {
+        decode_hex(HEADER).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn push_matches_libsodium() {
+        let mut stream = SecretStream::new(&KEY, &header());
+        for (message, ad, tag, expected) in MESSAGES {
+            assert_eq!(stream.push(message, ad, tag), decode_hex(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn pull_decrypts_libsodium_stream() {
+        let mut stream = SecretStream::new(&KEY, &header());
+        for (message, ad, tag, ciphertext) in MESSAGES {
+            assert_eq!(stream.pull(&decode_hex(ciphertext).unwrap(), ad), Some((message.to_vec(), tag)));
+        }
+    }
+
+    #[test]
+    fn pull_rejects_reordered_or_modified_messages() {
+        let mut stream = SecretStream::new(&KEY, &header());
+        assert_eq!(stream.pull(&decode_hex(MESSAGES[1].3).unwrap(), MESSAGES[1].1), None);
+
+        let mut stream = SecretStream::new(&KEY, &header());
+        let mut modified = decode_hex(MESSAGES[0].3).unwrap();
+        modified[3] ^= 1;
+        assert_eq!(stream.pull(&modified, b""), None);
+    }
+}
@@ -0,0 +1,315 @@
+//! Known-answer self-tests run inside the deployed binary
+//! 
+//! A miscompiled, truncated or tampered wasm file can still load and return
+//! plausible-looking output. `self_test` runs each primitive against
+//! published test vectors (output from libsodium where there are none) so
+//! the viewer can refuse to decrypt (fail closed) if any of them disagree.
+//! A vector that doesn't decode as hex becomes empty, so it fails its test
+//! rather than panicking.
+
+use wasm_bindgen::prelude::*;
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use cbc::cipher::{BlockDecryptMut, KeyIvInit};
+use cbc::cipher::block_padding::Pkcs7;
+use chacha20::ChaCha20;
+use chacha20::cipher::{StreamCipher, StreamCipherSeek};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::encoding::decode_hex;
+use crate::kdf::{hkdf_sha256, pbkdf2_sha256};
+use crate::keywrap::{aes_kw_unwrap, aes_kw_wrap};
+use crate::mac::bytes_equal;
+use crate::secretstream::{SecretStream, TAG_MESSAGE};
+use crate::sign::ed25519_verify;
+use crate::x25519::unseal;
+use crate::{AesCtr, Aes256CbcDec};
+
+// NIST SP 800-38A F.5 (CTR) and F.2.5 (CBC) share the plaintext and IV
+const SP800_38A_PLAINTEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51";
+const SP800_38A_COUNTER: &str = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+const SP800_38A_CBC_IV: &str = "000102030405060708090a0b0c0d0e0f";
+const AES_128_KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+const AES_256_KEY: &str = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+
+// The plaintext of RFC 8439 sections 2.4.2 and 2.8.2 and of the XChaCha20 draft
+const SUNSCREEN: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+/// A named test returning whether the output matched
+type KnownAnswerTest = (&'static str, fn() -> bool);
+
+/// Every test, in the order it's reported
+const TESTS: &[KnownAnswerTest] = &[
+    ("aes-128-ctr", aes_128_ctr),
+    ("aes-256-ctr", aes_256_ctr),
+    ("aes-ctr-seek", aes_ctr_seek),
+    ("aes-256-cbc", aes_256_cbc),
+    ("aes-256-gcm", aes_256_gcm),
+    ("chacha20", chacha20),
+    ("chacha20-poly1305", chacha20_poly1305),
+    ("xchacha20-poly1305", xchacha20_poly1305),
+    ("secretstream", secretstream),
+    ("sha-256", sha_256),
+    ("sha-512", sha_512),
+    ("blake3", blake3_hash),
+    ("hmac-sha256", hmac_sha256),
+    ("hkdf-sha256", hkdf),
+    ("pbkdf2-sha256", pbkdf2),
+    ("aes-kw", aes_kw),
+    ("ed25519", ed25519),
+    ("x25519-sealed-box", sealed_box),
+];
+
+/// Run the AES-CTR keystream from `offset` over `data`
+fn ctr(key: &str, data: &[u8], offset: u64) -> Vec<u8> {
+    let Ok(mut cipher) = AesCtr::new(&decode_hex(key).unwrap_or_default(), &decode_hex(SP800_38A_COUNTER).unwrap_or_default()) else {
+        return Vec::new();
+    };
+    if cipher.seek(offset).is_err() {
+        return Vec::new();
+    }
+
+    let mut output = data.to_vec();
+    match cipher.apply_keystream(&mut output) {
+        Ok(()) => output,
+        Err(_) => Vec::new(),
+    }
+}
+
+/// NIST SP 800-38A F.5.1, first two blocks
+fn aes_128_ctr() -> bool {
+    ctr(AES_128_KEY, &decode_hex(SP800_38A_PLAINTEXT).unwrap_or_default(), 0) == decode_hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff").unwrap_or_default()
+}
+
+/// NIST SP 800-38A F.5.5, first two blocks
+fn aes_256_ctr() -> bool {
+    ctr(AES_256_KEY, &decode_hex(SP800_38A_PLAINTEXT).unwrap_or_default(), 0) == decode_hex("601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5").unwrap_or_default()
+}
+
+/// F.5.1 again, decrypting from the middle of the first block as a Range request would
+fn aes_ctr_seek() -> bool {
+    let ciphertext = decode_hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff").unwrap_or_default();
+    ctr(AES_128_KEY, &ciphertext[5..], 5) == decode_hex(SP800_38A_PLAINTEXT).unwrap_or_default()[5..]
+}
+
+/// NIST SP 800-38A F.2.5 ciphertext followed by a full PKCS#7 padding block
+fn aes_256_cbc() -> bool {
+    let ciphertext = decode_hex("f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d3a3aa5e0213db1a9901f9036cf5102d2").unwrap_or_default();
+    Aes256CbcDec::new_from_slices(&decode_hex(AES_256_KEY).unwrap_or_default(), &decode_hex(SP800_38A_CBC_IV).unwrap_or_default())
+        .ok()
+        .and_then(|cipher| cipher.decrypt_padded_vec_mut::<Pkcs7>(&ciphertext).ok())
+        == Some(decode_hex(SP800_38A_PLAINTEXT).unwrap_or_default())
+}
+
+/// McGrew & Viega GCM test case 14 (all-zero key, IV and plaintext), plus a flipped tag bit
+fn aes_256_gcm() -> bool {
+    let Ok(cipher) = Aes256Gcm::new_from_slice(&[0u8; 32]) else {
+        return false;
+    };
+    let nonce = aes_gcm::Nonce::from_slice(&[0u8; 12]);
+    let mut sealed = decode_hex("cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919").unwrap_or_default();
+
+    let opened = cipher.decrypt(nonce, sealed.as_slice()).ok() == Some(vec![0u8; 16]);
+    sealed[31] ^= 1;
+    opened && cipher.decrypt(nonce, sealed.as_slice()).is_err()
+}
+
+/// RFC 8439 section 2.4.2, which starts at block counter 1
+fn chacha20() -> bool {
+    let key: Vec<u8> = (0x00..0x20).collect();
+    let Ok(mut cipher) = ChaCha20::new_from_slices(&key, &decode_hex("000000000000004a00000000").unwrap_or_default()) else {
+        return false;
+    };
+    let mut output = SUNSCREEN.to_vec();
+    cipher.seek(64u64);
+    cipher.apply_keystream(&mut output);
+
+    output == decode_hex(concat!(
+        "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0bf91b65c5524733ab8f593dabcd62b357",
+        "1639d624e65152ab8f530c359f0861d807ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736",
+        "5af90bbf74a35be6b40b8eedf2785e42874d"
+    )).unwrap_or_default()
+}
+
+/// RFC 8439 section 2.8.2
+fn chacha20_poly1305() -> bool {
+    let key: Vec<u8> = (0x80..0xa0).collect();
+    let Ok(cipher) = ChaCha20Poly1305::new_from_slice(&key) else {
+        return false;
+    };
+    let sealed = decode_hex(concat!(
+        "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b",
+        "1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+        "3ff4def08e4b7a9de576d26586cec64b6116",
+        "1ae10b594f09e26a7e902ecbd0600691"
+    )).unwrap_or_default();
+    let payload = Payload { msg: &sealed, aad: &decode_hex("50515253c0c1c2c3c4c5c6c7").unwrap_or_default() };
+
+    cipher.decrypt(chacha20poly1305::Nonce::from_slice(&decode_hex("070000004041424344454647").unwrap_or_default()), payload).ok().as_deref() == Some(SUNSCREEN)
+}
+
+/// draft-irtf-cfrg-xchacha-03 appendix A.3.1
+fn xchacha20_poly1305() -> bool {
+    let key: Vec<u8> = (0x80..0xa0).collect();
+    let Ok(cipher) = XChaCha20Poly1305::new_from_slice(&key) else {
+        return false;
+    };
+    let sealed = decode_hex(concat!(
+        "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb731c7f1b0b4aa6440bf3a82f4eda7e39",
+        "ae64c6708c54c216cb96b72e1213b4522f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9",
+        "21f9664c97637da9768812f615c68b13b52e",
+        "c0875924c1c7987947deafd8780acf49"
+    )).unwrap_or_default();
+    let payload = Payload { msg: &sealed, aad: &decode_hex("50515253c0c1c2c3c4c5c6c7").unwrap_or_default() };
+    let nonce = decode_hex("404142434445464748494a4b4c4d4e4f5051525354555657").unwrap_or_default();
+
+    cipher.decrypt(chacha20poly1305::XNonce::from_slice(&nonce), payload).ok().as_deref() == Some(SUNSCREEN)
+}
+
+/// First message of a stream pushed by libsodium 1.0.18, the vector `secretstream` tests against
+fn secretstream() -> bool {
+    let Ok(header) = decode_hex("be10a7e59c36f5da209d7b63cce472af047abe28367e70f3").unwrap_or_default().try_into() else {
+        return false;
+    };
+    let mut stream = SecretStream::new(&[0x11; 32], &header);
+    stream.pull(&decode_hex("2ebdfd23491f74803413e4649f4a55db3ead82b7b80d").unwrap_or_default(), b"") == Some((b"hello".to_vec(), TAG_MESSAGE))
+}
+
+/// FIPS 180-2 "abc"
+fn sha_256() -> bool {
+    Sha256::digest(b"abc")[..] == decode_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").unwrap_or_default()
+}
+
+/// FIPS 180-2 "abc"
+fn sha_512() -> bool {
+    Sha512::digest(b"abc")[..]
+        == decode_hex(concat!(
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a",
+            "2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        )).unwrap_or_default()
+}
+
+/// BLAKE3 reference vector for empty input
+fn blake3_hash() -> bool {
+    blake3::hash(b"").as_bytes()[..] == decode_hex("af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262").unwrap_or_default()
+}
+
+/// RFC 4231 test case 2, checked with the same comparison as `hmac_verify`
+fn hmac_sha256() -> bool {
+    let Ok(mut mac) = <Hmac<Sha256> as Mac>::new_from_slice(b"Jefe") else {
+        return false;
+    };
+    mac.update(b"what do ya want for nothing?");
+    bytes_equal(&mac.finalize().into_bytes(), &decode_hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843").unwrap_or_default())
+}
+
+/// RFC 5869 test case 1
+fn hkdf() -> bool {
+    let mut okm = [0u8; 42];
+    hkdf_sha256(&[0x0b; 22], Some(&decode_hex("000102030405060708090a0b0c").unwrap_or_default()), &decode_hex("f0f1f2f3f4f5f6f7f8f9").unwrap_or_default(), &mut okm).is_ok()
+        && okm[..] == decode_hex("3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865").unwrap_or_default()
+}
+
+/// Widely published PBKDF2-HMAC-SHA256 vector: "password" / "salt", one iteration
+fn pbkdf2() -> bool {
+    let mut derived = [0u8; 32];
+    pbkdf2_sha256(b"password", b"salt", 1, &mut derived, |_| {});
+    derived[..] == decode_hex("120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b").unwrap_or_default()
+}
+
+/// RFC 3394 section 4.1, both ways
+fn aes_kw() -> bool {
+    let kek = decode_hex("000102030405060708090a0b0c0d0e0f").unwrap_or_default();
+    let key = decode_hex("00112233445566778899aabbccddeeff").unwrap_or_default();
+    let wrapped = decode_hex("1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5").unwrap_or_default();
+    aes_kw_wrap(&kek, &key).as_ref() == Some(&wrapped) && aes_kw_unwrap(&kek, &wrapped) == Some(key)
+}
+
+/// RFC 8032 section 7.1 test 1 (empty message), plus a flipped signature bit
+fn ed25519() -> bool {
+    let public_key = decode_hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a").unwrap_or_default();
+    let mut signature = decode_hex(concat!(
+        "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555",
+        "fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+    )).unwrap_or_default();
+
+    let valid = ed25519_verify(&public_key, b"", &signature) == Ok(true);
+    signature[0] ^= 1;
+    valid && ed25519_verify(&public_key, b"", &signature) == Ok(false)
+}
+
+/// A sealed box made by libsodium 1.0.18 `crypto_box_seal` for a fixed secret key
+fn sealed_box() -> bool {
+    let secret_key: [u8; 32] = core::array::from_fn(|i| 0x20 + i as u8);
+    let mut sealed = decode_hex(concat!(
+        "0c0e376212aef1d540e88ca5a7a388ae49f49d01698f463b57efda90bfae9566",
+        "36b3c80bd5650cb7f47483d0b659f9662af9b93108247142773fac8f6080c1"
+    )).unwrap_or_default();
+
+    let opened = unseal(secret_key, &sealed).as_deref() == Some(&b"furl sealed box"[..]);
+    sealed[40] ^= 1;
+    opened && unseal(secret_key, &sealed).is_none()
+}
+
+/// Run every known-answer test against this build of the module
+/// 
+/// Call once after `init()` and refuse to decrypt unless `passed` is true:
+/// 
+/// ```javascript
+/// const report = self_test();
+/// if (!report.passed) throw new Error(`Crypto self-test failed: ${report.failed.join(', ')}`);
+/// ```
+/// 
+/// # Returns
+/// Object with:
+/// * `passed` - `true` only if every test passed
+/// * `results` - Array of `{ name, passed }`, one per test
+/// * `failed` - Names of the failing tests
+#[wasm_bindgen]
+pub fn self_test() -> Result<js_sys::Object, JsValue> {
+    let results = js_sys::Array::new();
+    let failed = js_sys::Array::new();
+
+    for (name, test) in TESTS {
+        let passed = test();
+        if !passed {
+            log_at!(crate::logging::LogLevel::Error, "Self-test: {} failed", name);
+            failed.push(&JsValue::from_str(name));
+        }
+
+        let result = js_sys::Object::new();
+        js_sys::Reflect::set(&result, &"name".into(), &JsValue::from_str(name))?;
+        js_sys::Reflect::set(&result, &"passed".into(), &passed.into())?;
+        results.push(&result);
+    }
+
+    log!("Self-test: {} of {} tests passed", TESTS.len() as u32 - failed.length(), TESTS.len());
+
+    let report = js_sys::Object::new();
+    js_sys::Reflect::set(&report, &"passed".into(), &(failed.length() == 0).into())?;
+    js_sys::Reflect::set(&report, &"results".into(), &results)?;
+    js_sys::Reflect::set(&report, &"failed".into(), &failed)?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_known_answer_test_passes() {
+        for (name, test) in TESTS {
+            assert!(test(), "{} failed", name);
+        }
+    }
+
+    #[test]
+    fn corrupted_vectors_are_detected() {
+        assert!(ctr(AES_128_KEY, &decode_hex(SP800_38A_PLAINTEXT).unwrap_or_default(), 1) != decode_hex("874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff").unwrap_or_default());
+        assert!(ctr("2b7e", &decode_hex(SP800_38A_PLAINTEXT).unwrap_or_default(), 0).is_empty());
+        assert!(decode_hex("not hex").is_err());
+    }
+}
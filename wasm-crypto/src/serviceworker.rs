@@ -0,0 +1,81 @@
+//! Decrypting responses inside a service worker
+//! 
+//! A service worker can answer requests for a virtual URL with decrypted
+//! content, so a page can point `<video src>` or `<img src>` straight at an
+//! encrypted share. `decrypt_response` takes the ciphertext `Response` the
+//! worker fetched from the origin and returns a normal `Response` whose body
+//! streams through the decrypt `TransformStream`.
+//! 
+//! AES-CTR keeps lengths, so the status, `Content-Length` and `Content-Range`
+//! of the origin's response stay correct: a `206` answer to the browser's
+//! own `Range` request (which `<video>` sends when seeking) decrypts from the
+//! offset in its `Content-Range` and is passed on as a `206`. See
+//! `web/furl-sw.js` for the worker that uses it.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use js_sys::Uint8Array;
+
+use crate::counter::ctr_options_from_js;
+use crate::decompress::Decompressor;
+use crate::error::CryptoError;
+use crate::share::parse_content_range;
+use crate::{decrypt_transform, validate_aes_key, Decryptor};
+
+/// Turn an encrypted `Response` into one that streams the plaintext
+/// 
+/// # Arguments
+/// * `response` - Response from `fetch` holding AES-CTR ciphertext, complete (200) or partial (206)
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `iv` - 16-byte initialization vector as Uint8Array
+/// * `options` - Optional `{ content_type, offset, counter_width, rfc3686 }`;
+///   `offset` overrides where the body starts in the file, which otherwise
+///   comes from `Content-Range` (or 0)
+/// 
+/// # Returns
+/// A Response with the same status and headers, `Content-Type` replaced
+/// when `content_type` is given, and a decrypted body
+#[wasm_bindgen]
+pub fn decrypt_response(response: &web_sys::Response, key: &Uint8Array, iv: &Uint8Array, options: Option<js_sys::Object>) -> Result<web_sys::Response, JsValue> {
+    validate_aes_key(key)?;
+
+    let option = |name: &str| match &options {
+        Some(options) => js_sys::Reflect::get(options, &name.into()).map(|value| Some(value).filter(|value| !value.is_undefined())),
+        None => Ok(None),
+    };
+    let headers = web_sys::Headers::new_with_headers(&response.headers())?;
+
+    let offset = match option("offset")? {
+        Some(offset) => offset
+            .as_f64()
+            .filter(|offset| offset.fract() == 0.0 && *offset >= 0.0 && *offset <= js_sys::Number::MAX_SAFE_INTEGER)
+            .ok_or_else(|| CryptoError::InvalidInput("Invalid offset: expected a whole number of bytes".into()))? as u64,
+        None if response.status() == 206 => {
+            let header = headers.get("Content-Range")?.unwrap_or_default();
+            parse_content_range(&header)
+                .map(|range| range.start)
+                .ok_or_else(|| CryptoError::InvalidInput(format!("Partial response has no usable Content-Range ({:?}); pass offset", header)))?
+        }
+        None => 0,
+    };
+    if let Some(content_type) = option("content_type")? {
+        let content_type = content_type.as_string().ok_or_else(|| CryptoError::InvalidInput("Invalid content_type: expected a string".into()))?;
+        headers.set("Content-Type", &content_type)?;
+    }
+
+    let decryptor = Decryptor::from_bytes(&key.to_vec(), &iv.to_vec(), &ctr_options_from_js(options.as_ref())?, false)?.starting_at(offset)?;
+    let body = response
+        .body()
+        .map(|body| Ok::<_, JsValue>(body.pipe_through(decrypt_transform(decryptor, Decompressor::new(None))?.unchecked_ref())))
+        .transpose()?;
+
+    let init = web_sys::ResponseInit::new();
+    init.set_status(response.status());
+    init.set_status_text(&response.status_text());
+    init.set_headers(&headers);
+
+    log!("ServiceWorker: Decrypting {} response from byte {}", response.status(), offset);
+
+    web_sys::Response::new_with_opt_readable_stream_and_init(body.as_ref(), &init)
+}
+
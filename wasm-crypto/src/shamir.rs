@@ -0,0 +1,67 @@
+//! Splitting a share key into parts for separate channels
+//! 
+//! `furl_core::shamir` does the secret sharing and documents the part
+//! format; this module moves the parts in and out of JS as URL-safe base64
+//! text, so each can be pasted into a message or a link on its own.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand_core::{OsRng, RngCore};
+use furl_core::shamir::{combine, split};
+use zeroize::Zeroizing;
+
+use crate::encoding::decode_base64;
+use crate::error::CryptoError;
+use crate::validate_aes_key;
+
+/// Split an AES key into `count` parts, any `threshold` of which rebuild it
+/// 
+/// Send the parts over different channels (e.g. the link by email and one
+/// part by chat); fewer than `threshold` of them reveal nothing about the key,
+/// not even a checksum of it.
+/// 
+/// # Arguments
+/// * `key` - 16, 24 or 32-byte AES key as Uint8Array
+/// * `threshold` - Parts needed to rebuild the key (2-255)
+/// * `count` - Parts to make (`threshold`-255)
+/// 
+/// # Returns
+/// Array of `count` URL-safe base64 strings
+#[wasm_bindgen]
+pub fn split_key(key: &Uint8Array, threshold: u8, count: u8) -> Result<js_sys::Array, JsValue> {
+    validate_aes_key(key)?;
+    let key = Zeroizing::new(key.to_vec());
+    let shares = split(&key, threshold, count, |buffer| OsRng.fill_bytes(buffer))?;
+
+    log!("Shamir: Split a {}-byte key {} of {}", key.len(), threshold, count);
+
+    Ok(shares.iter().map(|share| JsValue::from(URL_SAFE_NO_PAD.encode(share))).collect())
+}
+
+/// Rebuild an AES key from parts made by `split_key`
+/// 
+/// # Arguments
+/// * `shares` - Array of at least `threshold` part strings, in any order
+/// 
+/// # Returns
+/// The key as Uint8Array. Throws `InvalidInput` for too few parts or parts
+/// of different splits, and `AuthenticationFailed` if a part is corrupted.
+#[wasm_bindgen]
+pub fn combine_key(shares: &js_sys::Array) -> Result<Uint8Array, JsValue> {
+    let shares = shares
+        .iter()
+        .enumerate()
+        .map(|(index, share)| {
+            let text = share.as_string().ok_or_else(|| CryptoError::InvalidInput(format!("Invalid share {}: expected a string", index)))?;
+            decode_base64(text.trim())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let parts: Vec<&[u8]> = shares.iter().map(Vec::as_slice).collect();
+    let key = Zeroizing::new(combine(&parts)?);
+
+    log!("Shamir: Combined {} shares into a {}-byte key", parts.len(), key.len());
+
+    Ok(Uint8Array::from(key.as_slice()))
+}
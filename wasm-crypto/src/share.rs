@@ -0,0 +1,378 @@
+//! One-call share download
+//! 
+//! `open_share` replaces the glue every viewer used to write around `fetch`:
+//! it downloads the ciphertext in `Range` requests, retries a failed request
+//! from the byte it had reached, decrypts each part as it arrives, checks the
+//! plaintext hash when one is known and resolves to a `Blob`. The plaintext
+//! parts are handed straight to the Blob, so wasm memory holds only one part
+//! at a time. It uses the global `fetch`, so it works on the main thread and
+//! in workers.
+//! 
+//! Options, all optional:
+//! 
+//! * `chunk_size` - bytes per Range request (default 4 MiB)
+//! * `retries` - extra attempts for a request that fails or gets a 408, 429 or 5xx status (default 3)
+//! * `sha256` - expected hex SHA-256 of the plaintext; a mismatch throws `AuthenticationFailed`
+//! * `content_type` - type of the resulting Blob; sniffed from the plaintext if absent
+//! * `signal` - AbortSignal cancelling the download
+//! * `progress` - callback receiving a progress report after each part
+//! * `counter_width` / `rfc3686` - AES-CTR counter layout, as for `Decryptor`
+//! 
+//! A server that ignores `Range` and answers 200 is handled too, though the
+//! whole body then arrives in one part. Cross-origin hosts must list
+//! `Content-Range` in `Access-Control-Expose-Headers` for the total size to
+//! be known before the last part.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use js_sys::Uint8Array;
+use sha2::{Digest, Sha256};
+
+use crate::counter::ctr_options_from_js;
+use crate::encoding::decode_hex;
+use crate::error::CryptoError;
+use crate::mac::bytes_equal;
+use crate::mime::sniff;
+use crate::progress::Progress;
+use crate::sharelink::parse_fragment;
+use crate::{check_aborted, Decryptor};
+
+/// Default bytes per Range request
+const DEFAULT_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Default extra attempts per request
+const DEFAULT_RETRIES: u32 = 3;
+
+/// Backoff before the first retry, doubled on each further one
+const RETRY_BASE_MS: f64 = 250.0;
+const RETRY_MAX_MS: f64 = 8000.0;
+
+/// `Content-Range: bytes start-end/total`, with `total` possibly `*`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ContentRange {
+    pub(crate) start: u64,
+    end: u64,
+    total: Option<u64>,
+}
+
+pub(crate) fn parse_content_range(header: &str) -> Option<ContentRange> {
+    let (range, total) = header.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.parse().ok()?, end.parse::<u64>().ok()?);
+    let total = match total {
+        "*" => None,
+        total => Some(total.parse().ok()?),
+    };
+
+    if end < start || total.is_some_and(|total| end >= total) {
+        return None;
+    }
+    Some(ContentRange { start, end, total })
+}
+
+/// Whether a failed request is worth repeating
+fn is_retryable(status: u16) -> bool {
+    matches!(status, 408 | 429) || (500..600).contains(&status)
+}
+
+/// Wait before retry number `attempt` (0 for the first retry)
+fn retry_delay_ms(attempt: u32) -> f64 {
+    (RETRY_BASE_MS * 2f64.powi(attempt.min(16) as i32)).min(RETRY_MAX_MS)
+}
+
+/// Key and IV for the download
+struct ShareSecret {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+}
+
+impl ShareSecret {
+    /// Accept a link fragment, or `{ key, iv }` Uint8Arrays
+    fn from_js(key_material: &JsValue) -> Result<Self, CryptoError> {
+        if let Some(fragment) = key_material.as_string() {
+            let fragment = parse_fragment(&fragment)?;
+            return Ok(ShareSecret { key: fragment.key, iv: fragment.iv.to_vec() });
+        }
+
+        let field = |name: &str| {
+            js_sys::Reflect::get(key_material, &name.into())
+                .ok()
+                .and_then(|value| value.dyn_into::<Uint8Array>().ok())
+                .map(|value| value.to_vec())
+                .ok_or_else(|| CryptoError::InvalidInput(format!("Invalid key material: {} must be a Uint8Array", name)))
+        };
+        Ok(ShareSecret { key: field("key")?, iv: field("iv")? })
+    }
+}
+
+/// The download settings `open_share` understands
+struct ShareOptions {
+    chunk_size: u64,
+    retries: u32,
+    sha256: Option<Vec<u8>>,
+    content_type: Option<String>,
+    signal: Option<web_sys::AbortSignal>,
+    progress: Option<js_sys::Function>,
+}
+
+impl ShareOptions {
+    fn from_js(options: Option<&js_sys::Object>) -> Result<Self, CryptoError> {
+        let mut parsed = ShareOptions {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            retries: DEFAULT_RETRIES,
+            sha256: None,
+            content_type: None,
+            signal: None,
+            progress: None,
+        };
+        let Some(options) = options else {
+            return Ok(parsed);
+        };
+
+        let get = |name: &str| {
+            js_sys::Reflect::get(options, &name.into())
+                .map(|value| Some(value).filter(|value| !value.is_undefined() && !value.is_null()))
+                .map_err(|_| CryptoError::InvalidInput(format!("Invalid options: {} could not be read", name)))
+        };
+        let whole = |name: &str, value: JsValue, min: f64| {
+            value
+                .as_f64()
+                .filter(|n| n.fract() == 0.0 && *n >= min && *n <= js_sys::Number::MAX_SAFE_INTEGER)
+                .ok_or_else(|| CryptoError::InvalidInput(format!("Invalid {}: expected a whole number of at least {}", name, min)))
+        };
+
+        if let Some(chunk_size) = get("chunk_size")? {
+            parsed.chunk_size = whole("chunk_size", chunk_size, 1.0)? as u64;
+        }
+        if let Some(retries) = get("retries")? {
+            parsed.retries = whole("retries", retries, 0.0)?.min(u32::MAX as f64) as u32;
+        }
+        if let Some(sha256) = get("sha256")? {
+            let digest = sha256.as_string().map(|hex| decode_hex(&hex)).transpose()?;
+            parsed.sha256 = Some(
+                digest
+                    .filter(|digest| digest.len() == 32)
+                    .ok_or_else(|| CryptoError::InvalidInput("Invalid sha256: expected 64 hex characters".into()))?,
+            );
+        }
+        if let Some(content_type) = get("content_type")? {
+            parsed.content_type = Some(content_type.as_string().ok_or_else(|| CryptoError::InvalidInput("Invalid content_type: expected a string".into()))?);
+        }
+        if let Some(signal) = get("signal")? {
+            parsed.signal = Some(signal.dyn_into().map_err(|_| CryptoError::InvalidInput("Invalid signal: expected an AbortSignal".into()))?);
+        }
+        if let Some(progress) = get("progress")? {
+            parsed.progress = Some(progress.dyn_into().map_err(|_| CryptoError::InvalidInput("Invalid progress: expected a function".into()))?);
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// One ranged response, fully read
+struct Part {
+    status: u16,
+    content_range: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Resolve after `ms` milliseconds via the global `setTimeout`
+async fn sleep(ms: f64) -> Result<(), JsValue> {
+    let set_timeout: js_sys::Function = js_sys::Reflect::get(&js_sys::global(), &"setTimeout".into())?
+        .dyn_into()
+        .map_err(|_| CryptoError::Unsupported("setTimeout is not available in this environment".into()))?;
+
+    let timer = js_sys::Promise::new(&mut |resolve, _reject| {
+        let _ = set_timeout.call2(&JsValue::NULL, &resolve, &JsValue::from(ms));
+    });
+    JsFuture::from(timer).await?;
+
+    Ok(())
+}
+
+/// Fetch `bytes=start-end` of `url` once and read the whole body
+async fn fetch_once(fetch: &js_sys::Function, url: &str, start: u64, end: u64, signal: Option<&web_sys::AbortSignal>) -> Result<Part, JsValue> {
+    let headers = js_sys::Object::new();
+    js_sys::Reflect::set(&headers, &"Range".into(), &format!("bytes={}-{}", start, end).into())?;
+    let init = js_sys::Object::new();
+    js_sys::Reflect::set(&init, &"headers".into(), &headers)?;
+    if let Some(signal) = signal {
+        js_sys::Reflect::set(&init, &"signal".into(), signal)?;
+    }
+
+    let response: web_sys::Response = JsFuture::from(fetch.call2(&js_sys::global(), &url.into(), &init)?.dyn_into::<js_sys::Promise>()?)
+        .await?
+        .dyn_into()?;
+    let status = response.status();
+    let content_range = response.headers().get("Content-Range")?;
+    let body = if response.ok() {
+        Uint8Array::new(&JsFuture::from(response.array_buffer()?).await?).to_vec()
+    } else {
+        Vec::new()
+    };
+
+    Ok(Part { status, content_range, body })
+}
+
+/// Fetch one range, retrying network failures and transient statuses
+async fn fetch_range(
+    fetch: &js_sys::Function,
+    url: &str,
+    start: u64,
+    end: u64,
+    options: &ShareOptions
+) -> Result<Part, JsValue> {
+    let mut attempt = 0;
+    loop {
+        check_aborted(options.signal.as_ref())?;
+
+        let failure = match fetch_once(fetch, url, start, end, options.signal.as_ref()).await {
+            Ok(part) if !is_retryable(part.status) => return Ok(part),
+            Ok(part) => format!("server answered {}", part.status),
+            Err(error) => {
+                // An abort surfaces as a rejected fetch; report it as such
+                check_aborted(options.signal.as_ref())?;
+                error.dyn_ref::<js_sys::Error>().map_or_else(|| "request failed".to_string(), |error| String::from(error.message()))
+            }
+        };
+
+        if attempt >= options.retries {
+            return Err(CryptoError::NetworkError(format!(
+                "Download of bytes {}-{} failed after {} attempts: {}",
+                start, end, attempt + 1, failure
+            )).into());
+        }
+
+        log!("Share: Bytes {}-{} failed ({}), retrying", start, end, failure);
+
+        sleep(retry_delay_ms(attempt)).await?;
+        attempt += 1;
+    }
+}
+
+/// Download, decrypt and check a share in one call
+/// 
+/// # Arguments
+/// * `url` - URL of the ciphertext
+/// * `key_material` - The link fragment (`location.hash`), or `{ key, iv }` Uint8Arrays
+/// * `options` - Optional `{ chunk_size, retries, sha256, content_type, signal, progress, counter_width, rfc3686 }`, see the module docs
+/// 
+/// # Returns
+/// Promise resolving to the plaintext as a Blob; rejects with `NetworkError`
+/// once a request has used up its retries, and `AuthenticationFailed` if the
+/// plaintext doesn't match `sha256`
+#[wasm_bindgen]
+pub async fn open_share(url: String, key_material: JsValue, options: Option<js_sys::Object>) -> Result<web_sys::Blob, JsValue> {
+    let secret = ShareSecret::from_js(&key_material)?;
+    let settings = ShareOptions::from_js(options.as_ref())?;
+    let mut decryptor = Decryptor::from_bytes(&secret.key, &secret.iv, &ctr_options_from_js(options.as_ref())?, false)?;
+    let fetch: js_sys::Function = js_sys::Reflect::get(&js_sys::global(), &"fetch".into())?
+        .dyn_into()
+        .map_err(|_| CryptoError::Unsupported("fetch is not available in this environment".into()))?;
+
+    log!("Share: Opening {} in {} byte parts", url, settings.chunk_size);
+
+    let parts = js_sys::Array::new();
+    let mut hasher = settings.sha256.as_ref().map(|_| Sha256::new());
+    let mut content_type = settings.content_type.clone();
+    let mut total: Option<u64> = None;
+    let mut offset = 0u64;
+    let mut chunk_index = 0;
+    let mut progress = None;
+
+    loop {
+        if total.is_some_and(|total| offset >= total) {
+            break;
+        }
+        let requested_end = offset + settings.chunk_size - 1;
+        let end = total.map_or(requested_end, |total| requested_end.min(total - 1));
+
+        let part = fetch_range(&fetch, &url, offset, end, &settings).await?;
+        let complete = match part.status {
+            206 => {
+                if let Some(header) = &part.content_range {
+                    let range = parse_content_range(header)
+                        .ok_or_else(|| CryptoError::NetworkError(format!("Server sent an invalid Content-Range: {:?}", header)))?;
+                    if range.start != offset || range.end - range.start + 1 != part.body.len() as u64 {
+                        return Err(CryptoError::NetworkError(format!("Server sent bytes {}-{} when {}-{} were requested", range.start, range.end, offset, end)).into());
+                    }
+                    total = range.total.or(total);
+                }
+                part.body.is_empty() || (total.is_none() && (part.body.len() as u64) < end - offset + 1)
+            }
+            // The server ignored Range and sent the whole file
+            200 if offset == 0 => true,
+            200 => return Err(CryptoError::NetworkError("Server stopped honouring Range requests mid-download".into()).into()),
+            // An unknown length that was an exact multiple of the part size
+            416 if offset > 0 && total.is_none() => break,
+            status => return Err(CryptoError::NetworkError(format!("Download failed with HTTP status {}", status)).into()),
+        };
+
+        let mut data = part.body;
+        decryptor.apply(&mut data)?;
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update(&data);
+        }
+        if content_type.is_none() && offset == 0 {
+            content_type = sniff(&data).map(str::to_string);
+        }
+        offset += data.len() as u64;
+        if !data.is_empty() {
+            parts.push(&Uint8Array::from(data.as_slice()));
+        }
+
+        // Created after the first response, once the total is usually known
+        progress
+            .get_or_insert_with(|| Progress::new(settings.progress.as_ref(), total, Some(settings.chunk_size)))
+            .report(offset, chunk_index);
+        chunk_index += 1;
+
+        if complete {
+            break;
+        }
+    }
+
+    decryptor.finalize()?;
+
+    if let (Some(hasher), Some(expected)) = (hasher, &settings.sha256) {
+        if !bytes_equal(&hasher.finalize(), expected) {
+            return Err(CryptoError::AuthenticationFailed("Share integrity check failed: plaintext doesn't match sha256".into()).into());
+        }
+    }
+
+    log!("Share: Opened {} bytes in {} parts", offset, chunk_index);
+
+    let blob_options = web_sys::BlobPropertyBag::new();
+    blob_options.set_type(content_type.as_deref().unwrap_or("application/octet-stream"));
+    web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &blob_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_range_headers_are_parsed() {
+        assert_eq!(parse_content_range("bytes 0-99/1000"), Some(ContentRange { start: 0, end: 99, total: Some(1000) }));
+        assert_eq!(parse_content_range("bytes 100-199/*"), Some(ContentRange { start: 100, end: 199, total: None }));
+
+        for header in ["bytes 5-4/10", "bytes 0-10/10", "bytes */10", "items 0-1/2", "bytes 0-1", "bytes a-b/c"] {
+            assert_eq!(parse_content_range(header), None, "{}", header);
+        }
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried_with_backoff() {
+        for status in [408, 429, 500, 503] {
+            assert!(is_retryable(status), "{}", status);
+        }
+        for status in [200, 206, 403, 404, 416] {
+            assert!(!is_retryable(status), "{}", status);
+        }
+
+        assert_eq!([0, 1, 2].map(retry_delay_ms), [250.0, 500.0, 1000.0]);
+        assert_eq!(retry_delay_ms(10), RETRY_MAX_MS);
+        assert_eq!(retry_delay_ms(u32::MAX), RETRY_MAX_MS);
+    }
+}
@@ -0,0 +1,84 @@
+//! furl link fragments in JS
+//! 
+//! `ShareLink` wraps `furl_core::sharelink::parse_fragment`, which documents
+//! the fragment format. The key stays out of JS until `take_key` moves it
+//! into a KeyHandle.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::error::CryptoError;
+use crate::keyhandle::KeyHandle;
+
+pub(crate) use furl_core::sharelink::{format_fragment, parse_fragment, Fragment};
+
+/// A parsed furl link fragment
+/// 
+/// The key stays in wasm memory until `take_key` moves it into a KeyHandle.
+#[wasm_bindgen]
+pub struct ShareLink {
+    fragment: Fragment,
+    key_taken: bool,
+}
+
+#[wasm_bindgen]
+impl ShareLink {
+    /// Parse and validate a link fragment
+    /// 
+    /// # Arguments
+    /// * `fragment` - `location.hash`, with or without the leading `#`
+    /// 
+    /// # Returns
+    /// ShareLink; throws `Unsupported` for a newer link version, and
+    /// `InvalidFormat`, `InvalidKeyLength` or `InvalidIv` for a damaged link
+    #[wasm_bindgen(constructor)]
+    pub fn new(fragment: &str) -> Result<ShareLink, JsValue> {
+        let fragment = parse_fragment(fragment)?;
+
+        log!("ShareLink: Parsed v{} link with a {}-byte key", fragment.version, fragment.key.len());
+
+        Ok(ShareLink { fragment, key_taken: false })
+    }
+
+    /// Fragment format version
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u32 {
+        self.fragment.version
+    }
+
+    /// 16-byte AES-CTR IV
+    #[wasm_bindgen(getter)]
+    pub fn iv(&self) -> Uint8Array {
+        Uint8Array::from(&self.fragment.iv[..])
+    }
+
+    /// Original filename, if the link carries one
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> Option<String> {
+        self.fragment.name.clone()
+    }
+
+    /// Uploader's Ed25519 public key, if the link names one
+    /// 
+    /// Pass it to `new Manifest(json, public_key)`, which then refuses to
+    /// decrypt until the manifest's signature has been verified against it.
+    #[wasm_bindgen(getter)]
+    pub fn public_key(&self) -> Option<Uint8Array> {
+        self.fragment.public_key.map(|key| Uint8Array::from(&key[..]))
+    }
+
+    /// Move the key into a KeyHandle; can only be called once
+    pub fn take_key(&mut self) -> Result<KeyHandle, JsValue> {
+        if self.key_taken {
+            return Err(CryptoError::InvalidState("ShareLink key has already been taken".into()).into());
+        }
+
+        self.key_taken = true;
+        Ok(KeyHandle::from_bytes(std::mem::take(&mut self.fragment.key)))
+    }
+
+    /// Value of a parameter this version doesn't define, or `undefined`
+    pub fn param(&self, name: &str) -> Option<String> {
+        self.fragment.extra.iter().find(|(param, _)| param == name).map(|(_, value)| value.clone())
+    }
+}
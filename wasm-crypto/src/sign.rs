@@ -0,0 +1,44 @@
+//! Digital signature verification for signed manifests and files
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use ed25519_dalek::{Signature, VerifyingKey};
+
+use crate::error::CryptoError;
+
+/// Verify an Ed25519 signature
+/// 
+/// Uses strict verification (RFC 8032 with small-order and non-canonical
+/// encodings rejected), so a signature can't be malleated into a second
+/// valid one for the same message.
+/// 
+/// # Arguments
+/// * `public_key` - 32-byte Ed25519 public key as Uint8Array
+/// * `message` - Signed message as Uint8Array
+/// * `signature` - 64-byte signature as Uint8Array
+/// 
+/// # Returns
+/// `true` if the signature is valid for `message` under `public_key`
+#[wasm_bindgen]
+pub fn verify_ed25519(public_key: &Uint8Array, message: &Uint8Array, signature: &Uint8Array) -> Result<bool, JsValue> {
+    let valid = ed25519_verify(&public_key.to_vec(), &message.to_vec(), &signature.to_vec())?;
+
+    log!("Ed25519: Signature verification {}", if valid { "succeeded" } else { "failed" });
+
+    Ok(valid)
+}
+
+/// Strict Ed25519 verification over raw bytes, checking key and signature sizes
+pub(crate) fn ed25519_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<bool, CryptoError> {
+    let public_key: [u8; 32] = public_key
+        .try_into()
+        .map_err(|_| CryptoError::InvalidKeyLength(format!("Invalid public key size: expected 32 bytes, got {}", public_key.len())))?;
+    let signature: [u8; 64] = signature
+        .try_into()
+        .map_err(|_| CryptoError::InvalidInput(format!("Invalid signature size: expected 64 bytes, got {}", signature.len())))?;
+
+    let verifying_key = VerifyingKey::from_bytes(&public_key)
+        .map_err(|_| CryptoError::InvalidKey("Invalid public key: not a valid Ed25519 point".into()))?;
+
+    Ok(verifying_key.verify_strict(message, &Signature::from_bytes(&signature)).is_ok())
+}
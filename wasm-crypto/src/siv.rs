@@ -0,0 +1,300 @@
+//! Nonce-misuse-resistant AEADs
+//! 
+//! With AES-GCM or ChaCha20-Poly1305, encrypting two messages under the same
+//! key and nonce leaks their XOR and lets an attacker forge tags. That is a
+//! real risk when chunk nonces are derived deterministically (from a chunk
+//! index, say) and a chunk is ever re-encrypted with new contents.
+//! AES-GCM-SIV (RFC 8452) derives the keystream from the message itself, so
+//! a repeated nonce only reveals whether two messages were identical.
+//! 
+//! AES-SIV (RFC 5297) takes no nonce at all: the same key, associated data
+//! and plaintext always give the same ciphertext. That suits small records
+//! that should be comparable without being readable, such as encrypted
+//! filenames or deduplication keys.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use aes_gcm_siv::{Aes128GcmSiv, Aes256GcmSiv, Nonce};
+use aes_gcm_siv::aead::{Aead, KeyInit, Payload};
+use aes_siv::siv::{Aes128Siv, Aes256Siv};
+
+use crate::aad_bytes;
+use crate::error::CryptoError;
+
+/// AES-GCM-SIV with a 16 or 32-byte key
+enum GcmSiv {
+    Aes128(Box<Aes128GcmSiv>),
+    Aes256(Box<Aes256GcmSiv>),
+}
+
+impl GcmSiv {
+    fn new(key: &[u8], nonce: &[u8]) -> Result<Self, CryptoError> {
+        if nonce.len() != 12 {
+            return Err(CryptoError::InvalidIv(format!("Invalid nonce size: expected 12 bytes, got {}", nonce.len())));
+        }
+
+        let invalid_key = |_| CryptoError::InvalidKey("Invalid key format".into());
+        match key.len() {
+            16 => Ok(GcmSiv::Aes128(Box::new(Aes128GcmSiv::new_from_slice(key).map_err(invalid_key)?))),
+            32 => Ok(GcmSiv::Aes256(Box::new(Aes256GcmSiv::new_from_slice(key).map_err(invalid_key)?))),
+            n => Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 16 or 32 bytes, got {}", n))),
+        }
+    }
+
+    fn seal(&self, nonce: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let nonce = Nonce::from_slice(nonce);
+        let payload = Payload { msg: plaintext, aad };
+        match self {
+            GcmSiv::Aes128(cipher) => cipher.encrypt(nonce, payload),
+            GcmSiv::Aes256(cipher) => cipher.encrypt(nonce, payload),
+        }
+        .map_err(|_| CryptoError::Internal("Encryption failed".into()))
+    }
+
+    fn open(&self, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if ciphertext.len() < 16 {
+            return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", ciphertext.len())));
+        }
+
+        let nonce = Nonce::from_slice(nonce);
+        let payload = Payload { msg: ciphertext, aad };
+        match self {
+            GcmSiv::Aes128(cipher) => cipher.decrypt(nonce, payload),
+            GcmSiv::Aes256(cipher) => cipher.decrypt(nonce, payload),
+        }
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))
+    }
+}
+
+/// AES-SIV with a 32-byte (AES-128) or 64-byte (AES-256) key
+enum Siv {
+    Aes128(Box<Aes128Siv>),
+    Aes256(Box<Aes256Siv>),
+}
+
+impl Siv {
+    fn new(key: &[u8]) -> Result<Self, CryptoError> {
+        let invalid_key = |_| CryptoError::InvalidKey("Invalid key format".into());
+        match key.len() {
+            32 => Ok(Siv::Aes128(Box::new(Aes128Siv::new_from_slice(key).map_err(invalid_key)?))),
+            64 => Ok(Siv::Aes256(Box::new(Aes256Siv::new_from_slice(key).map_err(invalid_key)?))),
+            n => Err(CryptoError::InvalidKeyLength(format!("Invalid key size: expected 32 or 64 bytes, got {}", n))),
+        }
+    }
+
+    /// `aad` is the single associated-data component, if any
+    fn seal(&mut self, aad: Option<&[u8]>, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        match self {
+            Siv::Aes128(cipher) => cipher.encrypt(aad, plaintext),
+            Siv::Aes256(cipher) => cipher.encrypt(aad, plaintext),
+        }
+        .map_err(|_| CryptoError::Internal("Encryption failed".into()))
+    }
+
+    fn open(&mut self, aad: Option<&[u8]>, ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if ciphertext.len() < 16 {
+            return Err(CryptoError::InvalidInput(format!("Invalid data size: expected at least a 16-byte tag, got {} bytes", ciphertext.len())));
+        }
+
+        match self {
+            Siv::Aes128(cipher) => cipher.decrypt(aad, ciphertext),
+            Siv::Aes256(cipher) => cipher.decrypt(aad, ciphertext),
+        }
+        .map_err(|_| CryptoError::AuthenticationFailed("Authentication failed: ciphertext, tag or associated data has been modified".into()))
+    }
+}
+
+/// Encrypt data using AES-GCM-SIV (RFC 8452)
+/// 
+/// Safe to use with deterministic nonces: if a nonce is ever repeated, the
+/// only thing revealed is whether two messages were identical.
+/// 
+/// # Arguments
+/// * `key` - 16 or 32-byte key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `aad` - Optional associated data to authenticate but not encrypt
+/// 
+/// # Returns
+/// Ciphertext followed by the 16-byte tag as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_aes_gcm_siv(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    let nonce_bytes = nonce.to_vec();
+    let cipher = GcmSiv::new(&key.to_vec(), &nonce_bytes)?;
+
+    log!("AES-GCM-SIV: Encrypting {} bytes", data.length());
+
+    let ciphertext = cipher.seal(&nonce_bytes, &data.to_vec(), &aad_bytes(aad.as_ref()))?;
+
+    Ok(Uint8Array::from(ciphertext.as_slice()))
+}
+
+/// Decrypt and authenticate data using AES-GCM-SIV (RFC 8452)
+/// 
+/// # Arguments
+/// * `key` - 16 or 32-byte key as Uint8Array
+/// * `nonce` - 12-byte nonce as Uint8Array
+/// * `encrypted_data` - Ciphertext followed by the 16-byte tag
+/// * `aad` - Optional associated data the tag was computed over
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the tag does not verify
+#[wasm_bindgen]
+pub fn decrypt_aes_gcm_siv(
+    key: &Uint8Array,
+    nonce: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    let nonce_bytes = nonce.to_vec();
+    let cipher = GcmSiv::new(&key.to_vec(), &nonce_bytes)?;
+
+    log!("AES-GCM-SIV: Decrypting {} bytes", encrypted_data.length().saturating_sub(16));
+
+    let plaintext = cipher.open(&nonce_bytes, &encrypted_data.to_vec(), &aad_bytes(aad.as_ref()))?;
+
+    log!("AES-GCM-SIV: Successfully decrypted {} bytes", plaintext.len());
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+/// Encrypt data deterministically using AES-SIV (RFC 5297)
+/// 
+/// The same key, associated data and plaintext always produce the same
+/// ciphertext, so equal records can be matched without decrypting them;
+/// nothing else about the plaintext is revealed. Bind context such as a
+/// file id through `aad` so a record can't be moved to another file.
+/// 
+/// # Arguments
+/// * `key` - 32-byte (AES-128-SIV) or 64-byte (AES-256-SIV) key as Uint8Array
+/// * `data` - Plaintext as Uint8Array
+/// * `aad` - Optional associated data, authenticated but not encrypted.
+///   An empty array is a component of its own, so it isn't the same as
+///   passing nothing
+/// 
+/// # Returns
+/// The 16-byte synthetic IV followed by the ciphertext, as Uint8Array
+#[wasm_bindgen]
+pub fn encrypt_aes_siv(
+    key: &Uint8Array,
+    data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    let mut cipher = Siv::new(&key.to_vec())?;
+    let aad = aad.map(|aad| aad.to_vec());
+
+    log!("AES-SIV: Encrypting {} bytes", data.length());
+
+    let ciphertext = cipher.seal(aad.as_deref(), &data.to_vec())?;
+
+    Ok(Uint8Array::from(ciphertext.as_slice()))
+}
+
+/// Decrypt and authenticate data using AES-SIV (RFC 5297)
+/// 
+/// # Arguments
+/// * `key` - 32-byte (AES-128-SIV) or 64-byte (AES-256-SIV) key as Uint8Array
+/// * `encrypted_data` - The 16-byte synthetic IV followed by the ciphertext
+/// * `aad` - The associated data given to `encrypt_aes_siv`, if any
+/// 
+/// # Returns
+/// Decrypted data as Uint8Array, or an error if the data or `aad` don't verify
+#[wasm_bindgen]
+pub fn decrypt_aes_siv(
+    key: &Uint8Array,
+    encrypted_data: &Uint8Array,
+    aad: Option<Uint8Array>
+) -> Result<Uint8Array, JsValue> {
+    let mut cipher = Siv::new(&key.to_vec())?;
+    let aad = aad.map(|aad| aad.to_vec());
+
+    log!("AES-SIV: Decrypting {} bytes", encrypted_data.length().saturating_sub(16));
+
+    let plaintext = cipher.open(aad.as_deref(), &encrypted_data.to_vec())?;
+
+    Ok(Uint8Array::from(plaintext.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::decode_hex;
+
+    #[test]
+    fn rfc_8452_vectors() {
+        let nonce = decode_hex("030000000000000000000000").unwrap();
+        let key128 = decode_hex("01000000000000000000000000000000").unwrap();
+        let key256 = decode_hex("0100000000000000000000000000000000000000000000000000000000000000").unwrap();
+        // Appendix C.1 and C.2, without and with associated data
+        for (key, aad, plaintext, expected) in [
+            (&key128, "", "0100000000000000", "b5d839330ac7b786578782fff6013b815b287c22493a364c"),
+            (&key256, "", "0100000000000000", "c2ef328e5c71c83b843122130f7364b761e0b97427e3df28"),
+            (&key128, "01", "0200000000000000", "1e6daba35669f4273b0a1a2560969cdf790d99759abd1508"),
+            (&key256, "01", "0200000000000000", "1de22967237a813291213f267e3b452f02d01ae33e4ec854"),
+        ] {
+            let cipher = GcmSiv::new(key, &nonce).unwrap();
+            let (aad, plaintext, expected) = (decode_hex(aad).unwrap(), decode_hex(plaintext).unwrap(), decode_hex(expected).unwrap());
+            assert_eq!(cipher.seal(&nonce, &plaintext, &aad).unwrap(), expected);
+            assert_eq!(cipher.open(&nonce, &expected, &aad).unwrap(), plaintext);
+            assert!(matches!(cipher.open(&nonce, &expected, b"other"), Err(CryptoError::AuthenticationFailed(_))));
+        }
+    }
+
+    #[test]
+    fn a_repeated_nonce_only_reveals_equal_messages() {
+        let key = [9u8; 32];
+        let nonce = [0u8; 12];
+        let cipher = GcmSiv::new(&key, &nonce).unwrap();
+
+        let first = cipher.seal(&nonce, b"chunk contents, version one", &[]).unwrap();
+        let second = cipher.seal(&nonce, b"chunk contents, version two", &[]).unwrap();
+        assert_eq!(first, cipher.seal(&nonce, b"chunk contents, version one", &[]).unwrap());
+        // A CTR-based mode would reuse the keystream, so the shared 20-byte
+        // prefix would encrypt identically
+        assert_ne!(first[..20], second[..20]);
+    }
+
+    #[test]
+    fn rfc_5297_deterministic_vector() {
+        // Appendix A.1
+        let key = decode_hex("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff").unwrap();
+        let aad = decode_hex("101112131415161718191a1b1c1d1e1f2021222324252627").unwrap();
+        let plaintext = decode_hex("112233445566778899aabbccddee").unwrap();
+        let expected = decode_hex("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c").unwrap();
+
+        let mut cipher = Siv::new(&key).unwrap();
+        assert_eq!(cipher.seal(Some(&aad), &plaintext).unwrap(), expected);
+        assert_eq!(cipher.open(Some(&aad), &expected).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn aes_256_siv_binds_the_associated_data() {
+        let key: Vec<u8> = (0..64).collect();
+        let mut cipher = Siv::new(&key).unwrap();
+
+        let bound = cipher.seal(Some(b"file-id:42"), b"report.pdf").unwrap();
+        assert_eq!(bound, decode_hex("e7f5309f1a7fbbd869894b72d475e664c49f21831548420eaf54").unwrap());
+        assert_eq!(cipher.seal(None, b"report.pdf").unwrap(), decode_hex("9eeb6b14df4b18900e2a3640408ae69c33ed68203997229ea016").unwrap());
+
+        assert!(matches!(cipher.open(Some(b"file-id:43"), &bound), Err(CryptoError::AuthenticationFailed(_))));
+        assert!(matches!(cipher.open(None, &bound), Err(CryptoError::AuthenticationFailed(_))));
+        assert!(matches!(Siv::new(&[0u8; 16]), Err(CryptoError::InvalidKeyLength(_))));
+    }
+
+    #[test]
+    fn tampering_and_bad_sizes_are_rejected() {
+        let cipher = GcmSiv::new(&[1u8; 16], &[2u8; 12]).unwrap();
+        let mut sealed = cipher.seal(&[2u8; 12], b"record", &[]).unwrap();
+        sealed[0] ^= 1;
+        assert!(matches!(cipher.open(&[2u8; 12], &sealed, &[]), Err(CryptoError::AuthenticationFailed(_))));
+        assert!(matches!(cipher.open(&[2u8; 12], &[0u8; 15], &[]), Err(CryptoError::InvalidInput(_))));
+
+        assert!(matches!(GcmSiv::new(&[0u8; 24], &[0u8; 12]), Err(CryptoError::InvalidKeyLength(_))));
+        assert!(matches!(GcmSiv::new(&[0u8; 16], &[0u8; 24]), Err(CryptoError::InvalidIv(_))));
+    }
+}
@@ -0,0 +1,159 @@
+//! A reused wasm-side buffer for data crossing the JS boundary
+//! 
+//! `Uint8Array::to_vec` allocates a fresh buffer for every call, and with the
+//! zeroizing allocator every buffer is wiped again as it's freed, so the
+//! one-shot calls used to pay for an allocation, a copy in, a wipe and a
+//! copy out. The hot AES-CTR paths instead copy the input into one buffer
+//! per thread that keeps its capacity between calls, and decrypt it there.
+//! Calls that copy the plaintext out (`decrypt_aes_ctr`,
+//! `decrypt_aes_ctr_range` and the `CipherContext` decrypts) zero the
+//! region they used before returning.
+//! 
+//! The `*_view` variants go one step further and return a Uint8Array view
+//! of that buffer rather than a copy, so their plaintext stays in it. A
+//! view is owned by the module, not by the caller:
+//! 
+//! * It is overwritten by the next call that stages data on the same thread
+//!   (any `*_view` call, `decrypt_aes_ctr`, `decrypt_aes_ctr_range` or a
+//!   `CipherContext` decrypt, the last three leaving zeroes behind)
+//! * It is detached (its length drops to 0) whenever wasm memory grows,
+//!   which any other call may cause
+//! * It can't be transferred with `postMessage`; posting it copies it
+//! * `.slice()` it to keep the bytes; `release_views()` or `wipe_all()`
+//!   zeroes and frees the buffer
+//! 
+//! So a view suits code that consumes the plaintext straight away, e.g.
+//! writing it to a stream or a canvas, before calling into the module again.
+
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use zeroize::Zeroize;
+
+use crate::error::CryptoError;
+use crate::metrics;
+
+thread_local! {
+    /// Grows to the largest input staged so far and is never shrunk, so
+    /// bytes past the current length may be stale plaintext from a view call
+    /// (wiped on release)
+    static STAGING: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `body` over a `len`-byte slice of the staging buffer
+/// 
+/// The slice holds whatever was staged before; callers overwrite it. If the
+/// buffer is already in use (a callback re-entering the module) a fresh
+/// buffer is used for this call instead.
+fn with_buffer<T>(len: usize, body: impl FnOnce(&mut [u8]) -> T) -> Result<T, CryptoError> {
+    STAGING.with(|staging| match staging.try_borrow_mut() {
+        Ok(mut buffer) => {
+            let staged = buffer.len();
+            if staged < len {
+                buffer.try_reserve_exact(len - staged).map_err(|_| CryptoError::OutOfMemory(format!("Failed to allocate {} bytes", len)))?;
+                buffer.resize(len, 0);
+            }
+            Ok(body(&mut buffer[..len]))
+        }
+        Err(_) => Ok(body(&mut crate::error::try_zeroed(len)?)),
+    })
+}
+
+/// `with_buffer`, zeroing the slice once `body` is done with it
+fn with_wiped_buffer<T>(len: usize, body: impl FnOnce(&mut [u8]) -> T) -> Result<T, CryptoError> {
+    with_buffer(len, |buffer| {
+        let result = body(buffer);
+        buffer.zeroize();
+        result
+    })
+}
+
+/// Copy `data` into the staging buffer and run `body` over the copy
+/// 
+/// The copy is zeroed when `body` returns, so `body` must copy out
+/// whatever it keeps.
+pub(crate) fn with_staged<T>(data: &Uint8Array, body: impl FnOnce(&mut [u8]) -> T) -> Result<T, CryptoError> {
+    with_wiped_buffer(data.length() as usize, |buffer| {
+        metrics::copy(|| data.copy_to(buffer));
+        body(buffer)
+    })
+}
+
+/// `with_staged` for the `*_view` calls, leaving the bytes in the buffer
+pub(crate) fn with_staged_view<T>(data: &Uint8Array, body: impl FnOnce(&mut [u8]) -> T) -> Result<T, CryptoError> {
+    with_buffer(data.length() as usize, |buffer| {
+        metrics::copy(|| data.copy_to(buffer));
+        body(buffer)
+    })
+}
+
+/// A Uint8Array view of `bytes`, which must be the staging buffer
+/// 
+/// Only call this as the last step of a `with_staged_view` body that never calls
+/// into JS (so it can't be handed a re-entrant call's temporary buffer),
+/// once nothing else will allocate before the view is handed back.
+pub(crate) fn view(bytes: &[u8]) -> Uint8Array {
+    // SAFETY: the view outlives this borrow, but it only ever aliases the
+    // staging buffer, which is never freed except by `release_views`; the
+    // module docs spell out that it's overwritten by the next staged call
+    // and detached when memory grows
+    unsafe { Uint8Array::view(bytes) }
+}
+
+/// Zero and free the buffer behind the `*_view` results on this thread
+/// 
+/// Any view still held reads as zeroes or is detached afterwards. Call
+/// after a large file to give the memory back to the allocator.
+#[wasm_bindgen]
+pub fn release_views() {
+    STAGING.with(|staging| {
+        if let Ok(mut buffer) = staging.try_borrow_mut() {
+            buffer.zeroize();
+            *buffer = Vec::new();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_buffer_is_reused_between_calls() {
+        let first = with_buffer(1000, |buffer| {
+            buffer.fill(7);
+            buffer.as_ptr() as usize
+        })
+        .unwrap();
+        let second = with_buffer(10, |buffer| (buffer.as_ptr() as usize, buffer.len())).unwrap();
+        assert_eq!(second, (first, 10));
+
+        release_views();
+        STAGING.with(|staging| assert_eq!(staging.borrow().capacity(), 0));
+    }
+
+    #[test]
+    fn copied_out_calls_leave_zeroes_behind() {
+        let copied = with_wiped_buffer(100, |buffer| {
+            buffer.fill(7);
+            buffer.to_vec()
+        })
+        .unwrap();
+        assert_eq!(copied, [7; 100]);
+        STAGING.with(|staging| assert!(staging.borrow()[..100].iter().all(|&byte| byte == 0)));
+
+        with_buffer(100, |buffer| buffer.fill(7)).unwrap();
+        STAGING.with(|staging| assert!(staging.borrow()[..100].iter().all(|&byte| byte == 7)));
+        release_views();
+    }
+
+    #[test]
+    fn nested_calls_get_their_own_buffer() {
+        let (outer, inner) = with_buffer(64, |outer| {
+            let inner = with_buffer(64, |inner| inner.as_ptr() as usize).unwrap();
+            (outer.as_ptr() as usize, inner)
+        })
+        .unwrap();
+        assert_ne!(outer, inner);
+    }
+}
@@ -0,0 +1,149 @@
+//! STREAM chunked AEAD with automatic rekeying
+//! 
+//! For long-running streams (live capture, large uploads) sealed and opened
+//! segment by segment. Each segment's nonce carries its position and a
+//! last-segment flag, so reordered, dropped or truncated segments fail to
+//! authenticate; the key ratchets forward every `rekey_interval` segments.
+//! 
+//! The construction and its errors live in `furl_core::stream`; this module
+//! wraps them for JS. Framing the sealed segments (e.g. length prefixes) is
+//! left to the transport.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use furl_core::container::Algorithm;
+use furl_core::stream::{StreamDecryptor, StreamEncryptor, DEFAULT_REKEY_INTERVAL};
+
+use crate::aad_bytes;
+
+/// Seals a STREAM segment by segment
+/// 
+/// ```javascript
+/// const prefix = crypto.getRandomValues(new Uint8Array(7));
+/// const stream = new AeadStreamEncryptor(key, prefix);
+/// for (const segment of segments) send(stream.encrypt_next(segment));
+/// send(stream.encrypt_last(tail));
+/// ```
+#[wasm_bindgen]
+pub struct AeadStreamEncryptor {
+    inner: StreamEncryptor,
+}
+
+#[wasm_bindgen]
+impl AeadStreamEncryptor {
+    /// Start a stream
+    /// 
+    /// # Arguments
+    /// * `key` - 32-byte key as Uint8Array
+    /// * `nonce_prefix` - 7-byte random prefix as Uint8Array (never reuse with the same key)
+    /// * `algorithm` - `"aes-256-gcm"` (default) or `"chacha20-poly1305"`
+    /// * `rekey_interval` - Segments sealed under each key before it is ratcheted (default: 2^20)
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        key: &Uint8Array,
+        nonce_prefix: &Uint8Array,
+        algorithm: Option<String>,
+        rekey_interval: Option<u32>
+    ) -> Result<AeadStreamEncryptor, JsValue> {
+        let algorithm = Algorithm::from_name(algorithm.as_deref())?;
+        let inner = StreamEncryptor::new(&key.to_vec(), &nonce_prefix.to_vec(), algorithm, rekey_interval.unwrap_or(DEFAULT_REKEY_INTERVAL))?;
+
+        Ok(AeadStreamEncryptor { inner })
+    }
+
+    /// Seal a segment that isn't the last
+    /// 
+    /// # Arguments
+    /// * `segment` - Plaintext as Uint8Array (at most 16MB)
+    /// * `aad` - Optional associated data for this segment
+    /// 
+    /// # Returns
+    /// Ciphertext followed by the 16-byte tag as Uint8Array
+    pub fn encrypt_next(&mut self, segment: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+        let sealed = self.inner.encrypt_next(&segment.to_vec(), &aad_bytes(aad.as_ref()))?;
+        Ok(Uint8Array::from(sealed.as_slice()))
+    }
+
+    /// Seal the last segment (which may be empty) and end the stream
+    /// 
+    /// # Arguments
+    /// * `segment` - Plaintext as Uint8Array (at most 16MB)
+    /// * `aad` - Optional associated data for this segment
+    /// 
+    /// # Returns
+    /// Ciphertext followed by the 16-byte tag as Uint8Array
+    pub fn encrypt_last(&mut self, segment: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+        let sealed = self.inner.encrypt_last(&segment.to_vec(), &aad_bytes(aad.as_ref()))?;
+        Ok(Uint8Array::from(sealed.as_slice()))
+    }
+}
+
+/// Opens a STREAM segment by segment, in order
+/// 
+/// The receiver must know which segment is the last (from the framing, or
+/// because the transport ended) and open it with `decrypt_last`; a segment
+/// opened in the wrong position or with the wrong flag throws
+/// `AuthenticationFailed`, and `finalize` throws `Truncated` if the last
+/// segment never arrived.
+#[wasm_bindgen]
+pub struct AeadStreamDecryptor {
+    inner: StreamDecryptor,
+}
+
+#[wasm_bindgen]
+impl AeadStreamDecryptor {
+    /// Start opening a stream; the arguments must match the encryptor's
+    /// 
+    /// # Arguments
+    /// * `key` - 32-byte key as Uint8Array
+    /// * `nonce_prefix` - The stream's 7-byte prefix as Uint8Array
+    /// * `algorithm` - `"aes-256-gcm"` (default) or `"chacha20-poly1305"`
+    /// * `rekey_interval` - Segments per key (default: 2^20)
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        key: &Uint8Array,
+        nonce_prefix: &Uint8Array,
+        algorithm: Option<String>,
+        rekey_interval: Option<u32>
+    ) -> Result<AeadStreamDecryptor, JsValue> {
+        let algorithm = Algorithm::from_name(algorithm.as_deref())?;
+        let inner = StreamDecryptor::new(&key.to_vec(), &nonce_prefix.to_vec(), algorithm, rekey_interval.unwrap_or(DEFAULT_REKEY_INTERVAL))?;
+
+        Ok(AeadStreamDecryptor { inner })
+    }
+
+    /// Open the next segment, which must not be the last
+    /// 
+    /// # Arguments
+    /// * `sealed` - Ciphertext followed by the 16-byte tag as Uint8Array
+    /// * `aad` - Optional associated data the segment was sealed with
+    /// 
+    /// # Returns
+    /// Plaintext as Uint8Array, or an error if the segment does not verify
+    /// in this position
+    pub fn decrypt_next(&mut self, sealed: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+        let plaintext = self.inner.decrypt_next(&sealed.to_vec(), &aad_bytes(aad.as_ref()))?;
+        Ok(Uint8Array::from(plaintext.as_slice()))
+    }
+
+    /// Open the last segment and end the stream
+    /// 
+    /// # Arguments
+    /// * `sealed` - Ciphertext followed by the 16-byte tag as Uint8Array
+    /// * `aad` - Optional associated data the segment was sealed with
+    /// 
+    /// # Returns
+    /// Plaintext as Uint8Array, or an error if the segment does not verify
+    /// as the last one
+    pub fn decrypt_last(&mut self, sealed: &Uint8Array, aad: Option<Uint8Array>) -> Result<Uint8Array, JsValue> {
+        let plaintext = self.inner.decrypt_last(&sealed.to_vec(), &aad_bytes(aad.as_ref()))?;
+        Ok(Uint8Array::from(plaintext.as_slice()))
+    }
+
+    /// Finish the stream, failing with `Truncated` if the last segment wasn't opened
+    pub fn finalize(&self) -> Result<(), JsValue> {
+        self.inner.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,376 @@
+//! Tar archives for multi-file shares
+//! 
+//! A folder share decrypts to a single `.tar`. `TarReader` indexes the
+//! archive's headers once so the viewer can list the files and extract just
+//! the ones the user opens, without separate downloads. ustar, GNU long
+//! names (`L`) and PAX `path` / `size` records are understood; entry names
+//! are reported exactly as stored, so callers writing to disk must sanitise
+//! them.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+
+use crate::error::CryptoError;
+
+const BLOCK: usize = 512;
+
+/// What a tar entry is, from its type flag
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    Hardlink,
+    Other,
+}
+
+impl EntryKind {
+    fn from_flag(flag: u8) -> Self {
+        match flag {
+            b'0' | b'\0' | b'7' => EntryKind::File,
+            b'5' => EntryKind::Directory,
+            b'2' => EntryKind::Symlink,
+            b'1' => EntryKind::Hardlink,
+            _ => EntryKind::Other,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            EntryKind::File => "file",
+            EntryKind::Directory => "directory",
+            EntryKind::Symlink => "symlink",
+            EntryKind::Hardlink => "hardlink",
+            EntryKind::Other => "other",
+        }
+    }
+}
+
+/// One member of the archive; `offset` is where its contents start
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct TarEntry {
+    name: String,
+    kind: EntryKind,
+    offset: usize,
+    size: usize,
+}
+
+/// Read a NUL-terminated header field
+fn field(header: &[u8], range: std::ops::Range<usize>) -> &[u8] {
+    let field = &header[range];
+    let end = field.iter().position(|&byte| byte == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+/// Parse a numeric field: octal text, or GNU base-256 when the top bit is set
+fn parse_number(field: &[u8]) -> Result<u64, CryptoError> {
+    if field.first().is_some_and(|&byte| byte & 0x80 != 0) {
+        return field[1..].iter().try_fold(u64::from(field[0] & 0x7f), |value, &byte| {
+            value.checked_mul(256).map(|value| value | u64::from(byte))
+        })
+        .ok_or_else(|| CryptoError::InvalidFormat("Invalid tar header: number is too large".into()));
+    }
+
+    let text = std::str::from_utf8(field)
+        .map_err(|_| CryptoError::InvalidFormat("Invalid tar header: number is not text".into()))?;
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| CryptoError::InvalidFormat(format!("Invalid tar header: bad octal number {:?}", text)))
+}
+
+/// Check the header checksum, which is computed with the checksum field as spaces
+fn checksum_matches(header: &[u8]) -> Result<bool, CryptoError> {
+    let expected = parse_number(&header[148..156])?;
+    let unsigned: u64 = header.iter().enumerate().map(|(i, &byte)| if (148..156).contains(&i) { 32 } else { u64::from(byte) }).sum();
+    // Some old writers summed signed bytes
+    let signed: i64 = header.iter().enumerate().map(|(i, &byte)| if (148..156).contains(&i) { 32 } else { i64::from(byte as i8) }).sum();
+
+    Ok(expected == unsigned || expected as i64 == signed)
+}
+
+/// Read `path` and `size` from PAX extended header records (`"<len> key=value\n"`)
+fn parse_pax(records: &[u8], name: &mut Option<String>, size: &mut Option<u64>) -> Result<(), CryptoError> {
+    let invalid = || CryptoError::InvalidFormat("Invalid tar archive: malformed PAX header".into());
+    let mut rest = records;
+
+    while !rest.is_empty() && rest[0] != 0 {
+        let space = rest.iter().position(|&byte| byte == b' ').ok_or_else(invalid)?;
+        let length: usize = std::str::from_utf8(&rest[..space]).ok().and_then(|text| text.parse().ok()).ok_or_else(invalid)?;
+        if length <= space + 1 || length > rest.len() || rest[length - 1] != b'\n' {
+            return Err(invalid());
+        }
+
+        let record = &rest[space + 1..length - 1];
+        if let Some(equals) = record.iter().position(|&byte| byte == b'=') {
+            let value = &record[equals + 1..];
+            match &record[..equals] {
+                b"path" => *name = Some(String::from_utf8_lossy(value).into_owned()),
+                b"size" => *size = Some(std::str::from_utf8(value).ok().and_then(|text| text.parse().ok()).ok_or_else(invalid)?),
+                _ => {}
+            }
+        }
+        rest = &rest[length..];
+    }
+
+    Ok(())
+}
+
+/// Index every entry in an archive
+fn parse_archive(data: &[u8]) -> Result<Vec<TarEntry>, CryptoError> {
+    let mut entries = Vec::new();
+    let mut position = 0;
+    // Set by GNU `L` and PAX `x` headers, applying to the next entry only
+    let mut long_name: Option<String> = None;
+    let mut pax_size: Option<u64> = None;
+
+    while position + BLOCK <= data.len() {
+        let header = &data[position..position + BLOCK];
+        // A zero block marks the end of the archive
+        if header.iter().all(|&byte| byte == 0) {
+            return Ok(entries);
+        }
+        if !checksum_matches(header)? {
+            return Err(CryptoError::InvalidFormat(format!("Invalid tar header at byte {}: checksum mismatch", position)));
+        }
+
+        let size = match pax_size.take() {
+            Some(size) => size,
+            None => parse_number(&header[124..136])?,
+        };
+        let offset = position + BLOCK;
+        let end = usize::try_from(size).ok().and_then(|size| offset.checked_add(size)).filter(|&end| end <= data.len()).ok_or_else(|| {
+            CryptoError::Truncated(format!("Tar archive is truncated: entry at byte {} needs {} bytes", position, size))
+        })?;
+        let contents = &data[offset..end];
+
+        match header[156] {
+            b'L' => long_name = Some(String::from_utf8_lossy(field(contents, 0..contents.len())).into_owned()),
+            b'x' => parse_pax(contents, &mut long_name, &mut pax_size)?,
+            // Global PAX records only carry defaults we don't use
+            b'g' => {}
+            flag => {
+                let name = long_name.take().unwrap_or_else(|| {
+                    let name = String::from_utf8_lossy(field(header, 0..100)).into_owned();
+                    let prefix = field(header, 345..500);
+                    if &header[257..262] == b"ustar" && !prefix.is_empty() {
+                        format!("{}/{}", String::from_utf8_lossy(prefix), name)
+                    } else {
+                        name
+                    }
+                });
+                let kind = EntryKind::from_flag(flag);
+                // Links and directories have no contents even if a size is recorded
+                let size = if kind == EntryKind::File || kind == EntryKind::Other { contents.len() } else { 0 };
+                entries.push(TarEntry { name, kind, offset, size });
+            }
+        }
+
+        position = offset + contents.len().div_ceil(BLOCK) * BLOCK;
+    }
+
+    // Archives cut off before the end-of-archive blocks are still readable
+    // as long as every entry they do contain is complete
+    if position < data.len() {
+        return Err(CryptoError::Truncated(format!("Tar archive is truncated: partial header at byte {}", position)));
+    }
+    Ok(entries)
+}
+
+/// Reader over an in-memory tar archive
+/// 
+/// Besides `entries()` and `extract()`, `next()` follows the JS iterator
+/// protocol, so all files can be walked in order with
+/// `for (const { name, size, bytes } of { [Symbol.iterator]: () => reader })`.
+#[wasm_bindgen]
+pub struct TarReader {
+    data: Vec<u8>,
+    entries: Vec<TarEntry>,
+    cursor: usize,
+}
+
+#[wasm_bindgen]
+impl TarReader {
+    /// Index an archive
+    /// 
+    /// # Arguments
+    /// * `data` - Whole tar file as Uint8Array
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &Uint8Array) -> Result<TarReader, JsValue> {
+        let data = data.to_vec();
+        let entries = parse_archive(&data)?;
+
+        log!("Tar: Opened archive with {} entries", entries.len());
+
+        Ok(TarReader { data, entries, cursor: 0 })
+    }
+
+    /// Number of entries in the archive
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> u32 {
+        self.entries.len() as u32
+    }
+
+    /// List the archive
+    /// 
+    /// # Returns
+    /// Array of objects with `index`, `name`, `size` (BigInt), `kind`
+    /// (`"file"`, `"directory"`, `"symlink"`, `"hardlink"` or `"other"`) and `is_dir`
+    pub fn entries(&self) -> Result<js_sys::Array, JsValue> {
+        let entries = js_sys::Array::new();
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let object = js_sys::Object::new();
+            js_sys::Reflect::set(&object, &"index".into(), &(index as u32).into())?;
+            js_sys::Reflect::set(&object, &"name".into(), &entry.name.as_str().into())?;
+            js_sys::Reflect::set(&object, &"size".into(), &(entry.size as u64).into())?;
+            js_sys::Reflect::set(&object, &"kind".into(), &entry.kind.name().into())?;
+            js_sys::Reflect::set(&object, &"is_dir".into(), &(entry.kind == EntryKind::Directory).into())?;
+            entries.push(&object);
+        }
+
+        Ok(entries)
+    }
+
+    /// Contents of one entry (empty for directories and links)
+    /// 
+    /// # Arguments
+    /// * `index` - Entry index from `entries()`
+    pub fn extract(&self, index: u32) -> Result<Uint8Array, JsValue> {
+        let entry = self.entries.get(index as usize).ok_or_else(|| {
+            CryptoError::InvalidInput(format!("Invalid entry index: archive has {} entries, got {}", self.entries.len(), index))
+        })?;
+
+        log!("Tar: Extracted entry {} ({} bytes)", index, entry.size);
+
+        Ok(Uint8Array::from(&self.data[entry.offset..entry.offset + entry.size]))
+    }
+
+    /// Contents of the entry with the given name (the last one, if the name repeats)
+    pub fn extract_by_name(&self, name: &str) -> Result<Uint8Array, JsValue> {
+        let index = self
+            .entries
+            .iter()
+            .rposition(|entry| entry.name == name)
+            .ok_or_else(|| CryptoError::NotFound(format!("Tar entry not found: {}", name)))?;
+
+        self.extract(index as u32)
+    }
+
+    /// Next entry as an iterator result
+    /// 
+    /// # Returns
+    /// `{ done: false, value: { name, size, kind, bytes } }` for each entry in
+    /// order, then `{ done: true }`
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<js_sys::Object, JsValue> {
+        let result = js_sys::Object::new();
+        let Some(entry) = self.entries.get(self.cursor) else {
+            js_sys::Reflect::set(&result, &"done".into(), &true.into())?;
+            return Ok(result);
+        };
+        self.cursor += 1;
+
+        let value = js_sys::Object::new();
+        js_sys::Reflect::set(&value, &"name".into(), &entry.name.as_str().into())?;
+        js_sys::Reflect::set(&value, &"size".into(), &(entry.size as u64).into())?;
+        js_sys::Reflect::set(&value, &"kind".into(), &entry.kind.name().into())?;
+        js_sys::Reflect::set(&value, &"bytes".into(), &Uint8Array::from(&self.data[entry.offset..entry.offset + entry.size]))?;
+
+        js_sys::Reflect::set(&result, &"done".into(), &false.into())?;
+        js_sys::Reflect::set(&result, &"value".into(), &value)?;
+        Ok(result)
+    }
+
+    /// Start `next()` again from the first entry
+    pub fn rewind(&mut self) {
+        self.cursor = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ustar header block with a valid checksum
+    fn header(name: &str, prefix: &str, flag: u8, size: usize) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK];
+        block[..name.len()].copy_from_slice(name.as_bytes());
+        block[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        block[156] = flag;
+        block[257..263].copy_from_slice(b"ustar\0");
+        block[263..265].copy_from_slice(b"00");
+        block[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        block[148..156].fill(b' ');
+        let checksum: u32 = block.iter().map(|&byte| u32::from(byte)).sum();
+        block[148..155].copy_from_slice(format!("{:06o}\0", checksum).as_bytes());
+        block
+    }
+
+    fn member(name: &str, prefix: &str, flag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut bytes = header(name, prefix, flag, contents.len());
+        bytes.extend_from_slice(contents);
+        bytes.resize(bytes.len().div_ceil(BLOCK) * BLOCK, 0);
+        bytes
+    }
+
+    fn archive(members: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = members.concat();
+        bytes.extend_from_slice(&[0u8; 2 * BLOCK]);
+        bytes
+    }
+
+    #[test]
+    fn lists_and_extracts_entries() {
+        let long = "deeply/nested/".repeat(10) + "report.csv";
+        // 24 is the record's own length, including the digits
+        let pax = "24 path=notes/\u{e9}t\u{e9}.txt\n";
+        assert_eq!(pax.len(), 24);
+        let data = archive(&[
+            member("photos/", "", b'5', b""),
+            member("cat.jpg", "photos", b'0', b"\xff\xd8jpeg bytes"),
+            member("././@LongLink", "", b'L', long.as_bytes()),
+            member("truncated-name", "", b'0', &[7u8; 600]),
+            member("PaxHeader", "", b'x', pax.as_bytes()),
+            member("short", "", b'0', b"bonjour"),
+            member("link", "", b'2', b""),
+        ]);
+
+        let entries = parse_archive(&data).unwrap();
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["photos/", "photos/cat.jpg", long.as_str(), "notes/\u{e9}t\u{e9}.txt", "link"]);
+        assert_eq!(entries[0].kind, EntryKind::Directory);
+        assert_eq!(entries[4].kind, EntryKind::Symlink);
+
+        let contents = |index: usize| &data[entries[index].offset..entries[index].offset + entries[index].size];
+        assert_eq!(contents(1), b"\xff\xd8jpeg bytes");
+        assert_eq!(contents(2), &[7u8; 600][..]);
+        assert_eq!(contents(3), b"bonjour");
+    }
+
+    #[test]
+    fn corrupted_and_truncated_archives_are_rejected() {
+        let data = archive(&[member("a.txt", "", b'0', &[1u8; 1000])]);
+
+        let mut corrupted = data.clone();
+        corrupted[10] ^= 1;
+        assert_eq!(parse_archive(&corrupted).unwrap_err().name(), "InvalidFormat");
+
+        assert_eq!(parse_archive(&data[..BLOCK + 700]).unwrap_err().name(), "Truncated");
+        assert_eq!(parse_archive(&data[..BLOCK * 3 + 100]).unwrap_err().name(), "Truncated");
+
+        // Missing end-of-archive blocks are tolerated
+        assert_eq!(parse_archive(&data[..BLOCK * 3]).unwrap().len(), 1);
+        assert!(parse_archive(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn numbers_accept_octal_and_base_256() {
+        assert_eq!(parse_number(b"00000001750 "), Ok(1000));
+        assert_eq!(parse_number(b"\0\0\0"), Ok(0));
+        assert_eq!(parse_number(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0x02, 0, 0]), Ok(0x20000));
+        assert!(parse_number(b"0009").is_err());
+    }
+}
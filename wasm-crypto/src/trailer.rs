@@ -0,0 +1,194 @@
+//! Length commitment trailer for AES-CTR streams
+//! 
+//! CTR has no tag, so a download cut short still "decrypts" to a shorter
+//! file without complaint. The uploader can append a 40-byte trailer that
+//! commits to the ciphertext's total length:
+//! 
+//! ```text
+//! mac_key = HKDF-SHA256(ikm = key, info = "furl length commitment" || iv, 32 bytes)
+//! trailer = length (u64 BE) || HMAC-SHA256(mac_key, length (u64 BE))
+//! ```
+//! 
+//! A `Decryptor` opened with `{ length_trailer: true }` holds back the last
+//! 40 bytes it has been given, so the trailer is never decrypted as data,
+//! and `finalize` checks it against the bytes actually decrypted. A stream
+//! missing its end (where the held-back bytes are ciphertext, not a valid
+//! trailer) or bytes from the middle fails with `Truncated`.
+//! 
+//! The trailer authenticates the length only, not the content; use an AEAD
+//! or the manifest's hashes for that.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use furl_core::kdf::hkdf_sha256;
+use zeroize::Zeroizing;
+
+use crate::error::CryptoError;
+use crate::validate_aes_key;
+
+/// Length of the trailer appended to the ciphertext
+pub(crate) const TRAILER_LEN: usize = 40;
+
+const INFO: &[u8] = b"furl length commitment";
+
+/// Derives the trailer MAC key and computes trailers under it
+pub(crate) struct LengthCommitment {
+    mac_key: Zeroizing<[u8; 32]>,
+}
+
+impl LengthCommitment {
+    pub(crate) fn new(key: &[u8], iv: &[u8]) -> Result<Self, CryptoError> {
+        let mut info = INFO.to_vec();
+        info.extend_from_slice(iv);
+        let mut mac_key = Zeroizing::new([0u8; 32]);
+        hkdf_sha256(key, None, &info, &mut mac_key[..])?;
+        Ok(LengthCommitment { mac_key })
+    }
+
+    fn mac(&self, length: u64) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.mac_key[..]).expect("HMAC accepts any key length");
+        mac.update(&length.to_be_bytes());
+        mac
+    }
+
+    pub(crate) fn trailer(&self, length: u64) -> [u8; TRAILER_LEN] {
+        let mut trailer = [0u8; TRAILER_LEN];
+        trailer[..8].copy_from_slice(&length.to_be_bytes());
+        trailer[8..].copy_from_slice(&self.mac(length).finalize().into_bytes());
+        trailer
+    }
+
+    /// Check `trailer` against the `length` bytes that came before it
+    pub(crate) fn check(&self, trailer: &[u8], length: u64) -> Result<(), CryptoError> {
+        if trailer.len() < TRAILER_LEN {
+            return Err(CryptoError::Truncated(format!(
+                "Stream ended after {} bytes, before its {}-byte length trailer",
+                length + trailer.len() as u64,
+                TRAILER_LEN
+            )));
+        }
+
+        let committed = u64::from_be_bytes(trailer[..8].try_into().unwrap());
+        if self.mac(committed).verify_slice(&trailer[8..]).is_err() {
+            return Err(CryptoError::Truncated("Length trailer does not verify: the stream is truncated or was modified".into()));
+        }
+        if length < committed {
+            return Err(CryptoError::Truncated(format!("Stream is truncated: expected {} bytes, got {}", committed, length)));
+        }
+        if length > committed {
+            return Err(CryptoError::AuthenticationFailed(format!("Stream is longer than its length trailer: expected {} bytes, got {}", committed, length)));
+        }
+        Ok(())
+    }
+}
+
+/// The trailer bytes a streaming decryptor holds back until `finalize`
+pub(crate) struct TrailerCheck {
+    commitment: LengthCommitment,
+    held: Vec<u8>,
+}
+
+impl TrailerCheck {
+    pub(crate) fn new(key: &[u8], iv: &[u8]) -> Result<Self, CryptoError> {
+        Ok(TrailerCheck { commitment: LengthCommitment::new(key, iv)?, held: Vec::with_capacity(TRAILER_LEN) })
+    }
+
+    /// Keep the last `TRAILER_LEN` bytes seen so far, leaving in `data` the
+    /// bytes that are now known to precede the trailer
+    pub(crate) fn hold_back(&mut self, data: &mut Vec<u8>) {
+        self.held.append(data);
+        let tail = self.held.split_off(self.held.len().saturating_sub(TRAILER_LEN));
+        *data = std::mem::replace(&mut self.held, tail);
+    }
+
+    pub(crate) fn check(&self, length: u64) -> Result<(), CryptoError> {
+        self.commitment.check(&self.held, length)
+    }
+}
+
+/// Whether an options object asks for `length_trailer`
+pub(crate) fn length_trailer_from_js(options: Option<&js_sys::Object>) -> Result<bool, CryptoError> {
+    let Some(options) = options else {
+        return Ok(false);
+    };
+    let value = js_sys::Reflect::get(options, &"length_trailer".into())
+        .map_err(|_| CryptoError::InvalidInput("Invalid options: length_trailer could not be read".into()))?;
+    Ok(value.is_truthy())
+}
+
+/// Length commitment trailer to append to an AES-CTR ciphertext
+/// 
+/// Append it after the last ciphertext byte, and decrypt with
+/// `{ length_trailer: true }` so `finalize` detects a truncated stream.
+/// 
+/// # Arguments
+/// * `key` - The 16, 24 or 32-byte AES key the data was encrypted with, as Uint8Array
+/// * `iv` - The IV the data was encrypted with, as Uint8Array
+/// * `length` - Total ciphertext length in bytes, without the trailer (BigInt)
+/// 
+/// # Returns
+/// 40-byte trailer as Uint8Array
+#[wasm_bindgen]
+pub fn ctr_length_trailer(key: &Uint8Array, iv: &Uint8Array, length: u64) -> Result<Uint8Array, JsValue> {
+    validate_aes_key(key)?;
+    let trailer = LengthCommitment::new(&key.to_vec(), &iv.to_vec())?.trailer(length);
+
+    log!("WASM: Length trailer for {} bytes", length);
+
+    Ok(Uint8Array::from(&trailer[..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [9; 32];
+    const IV: [u8; 16] = [4; 16];
+
+    /// Feed `stream` in chunks of `step`, then finalize with the bytes decrypted
+    fn run(stream: &[u8], step: usize) -> (usize, Result<(), CryptoError>) {
+        let mut check = TrailerCheck::new(&KEY, &IV).unwrap();
+        let mut released = 0;
+        for chunk in stream.chunks(step) {
+            let mut data = chunk.to_vec();
+            check.hold_back(&mut data);
+            released += data.len();
+        }
+        (released, check.check(released as u64))
+    }
+
+    #[test]
+    fn trailer_is_held_back_and_verified() {
+        let mut stream = vec![0xab; 1000];
+        stream.extend_from_slice(&LengthCommitment::new(&KEY, &IV).unwrap().trailer(1000));
+        for step in [1, 7, 40, 41, 1040] {
+            let (released, result) = run(&stream, step);
+            assert_eq!(released, 1000, "chunks of {}", step);
+            assert!(result.is_ok(), "chunks of {}", step);
+        }
+    }
+
+    #[test]
+    fn missing_bytes_are_truncated() {
+        let mut stream = vec![0xab; 1000];
+        stream.extend_from_slice(&LengthCommitment::new(&KEY, &IV).unwrap().trailer(1000));
+
+        assert_eq!(run(&stream[..1039], 100).1.unwrap_err().name(), "Truncated");
+        assert_eq!(run(&stream[..500], 100).1.unwrap_err().name(), "Truncated");
+        assert_eq!(run(&stream[..30], 100).1.unwrap_err().name(), "Truncated");
+        let spliced = [&stream[..400], &stream[500..]].concat();
+        assert_eq!(run(&spliced, 100).1.unwrap_err().name(), "Truncated");
+        let padded = [&stream[..1000], &[0; 5], &stream[1000..]].concat();
+        assert_eq!(run(&padded, 100).1.unwrap_err().name(), "AuthenticationFailed");
+    }
+
+    #[test]
+    fn trailer_is_bound_to_key_and_iv() {
+        let trailer = LengthCommitment::new(&KEY, &IV).unwrap().trailer(1000);
+        assert!(LengthCommitment::new(&KEY, &[5; 16]).unwrap().check(&trailer, 1000).is_err());
+        assert!(LengthCommitment::new(&[8; 32], &IV).unwrap().check(&trailer, 1000).is_err());
+        assert_eq!(trailer[..8], 1000u64.to_be_bytes());
+    }
+}
@@ -0,0 +1,346 @@
+//! Upload-side share preparation
+//! 
+//! `prepare_share` is the send half of `open_share` and `Manifest`: it reads
+//! a `File` or `Blob` one slice at a time, encrypts it with AES-256-CTR under
+//! a fresh random key and IV, hashes every chunk's plaintext and the whole
+//! file, and returns the ciphertext parts together with a manifest and the
+//! link fragment. The pages only move bytes; no key or counter handling is
+//! left in JS.
+//! 
+//! The manifest is the single-file form of the multi-file manifest, so
+//! `new Manifest(...)` opens it, with each chunk's plaintext and ciphertext
+//! hashes listed under `chunks` for checking parts fetched on their own
+//! (`verify_chunk` checks the ciphertext before anything is decrypted):
+//! 
+//! ```json
+//! {
+//!   "version": 1,
+//!   "files": [
+//!     { "name": "report.pdf", "offset": 0, "length": 9437184,
+//!       "iv": "<base64>", "sha256": "<hex>", "content_type": "application/pdf",
+//!       "chunks": [{ "offset": 0, "length": 4194304, "sha256": "<hex>",
+//!                    "ciphertext_sha256": "<hex>" }, ...] }
+//!   ]
+//! }
+//! ```
+//! 
+//! Options, all optional:
+//! 
+//! * `chunk_size` - plaintext bytes per part (default 4 MiB)
+//! * `name` - filename for the manifest and link; defaults to `File.name`
+//! * `content_type` - defaults to `Blob.type`, or is sniffed from the first part
+//! * `not_before` / `expires_at` - validity window in Unix seconds, see `Manifest`
+//! * `on_part` - called with `{ index, offset, data }` for each part as it is
+//!   encrypted, so it can be uploaded straight away; a returned Promise is
+//!   awaited before the next part, and the parts are then not collected
+//! * `progress` - callback receiving a progress report after each part
+//! * `signal` - AbortSignal stopping between parts
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use js_sys::Uint8Array;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::encoding::encode_hex;
+use crate::error::CryptoError;
+use crate::keyhandle::KeyHandle;
+use crate::manifest::MANIFEST_VERSION;
+use crate::mime::sniff;
+use crate::progress::Progress;
+use crate::sharelink::format_fragment;
+use crate::{check_aborted, AesCtr};
+
+/// Default plaintext bytes per part, matching `open_share`'s ranges
+const DEFAULT_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Largest part accepted, so one slice can't exhaust wasm memory
+const MAX_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// One encrypted part as listed in the manifest
+#[derive(Debug, PartialEq, Eq)]
+struct ChunkRecord {
+    offset: u64,
+    length: u64,
+    sha256: [u8; 32],
+    ciphertext_sha256: [u8; 32],
+}
+
+/// What the manifest says about the file besides its chunks
+#[derive(Debug, Default)]
+struct FileDetails {
+    name: String,
+    content_type: Option<String>,
+    not_before: Option<u64>,
+    expires_at: Option<u64>,
+}
+
+/// Encrypts consecutive parts of one file and builds its manifest
+struct ShareEncryptor {
+    cipher: AesCtr,
+    iv: [u8; 16],
+    file_hash: Sha256,
+    chunks: Vec<ChunkRecord>,
+    length: u64,
+}
+
+impl ShareEncryptor {
+    fn new(key: &[u8], iv: [u8; 16]) -> Result<Self, CryptoError> {
+        Ok(ShareEncryptor { cipher: AesCtr::new(key, &iv)?, iv, file_hash: Sha256::new(), chunks: Vec::new(), length: 0 })
+    }
+
+    /// Hash the next part's plaintext, encrypt it in place, then hash the ciphertext
+    fn encrypt_part(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.file_hash.update(&*data);
+        let sha256 = Sha256::digest(&*data).into();
+        self.cipher.apply_keystream(data)?;
+        self.chunks.push(ChunkRecord { offset: self.length, length: data.len() as u64, sha256, ciphertext_sha256: Sha256::digest(&*data).into() });
+        self.length += data.len() as u64;
+        Ok(())
+    }
+
+    fn manifest(self, details: &FileDetails) -> Result<Vec<u8>, CryptoError> {
+        if let (Some(not_before), Some(expires_at)) = (details.not_before, details.expires_at) {
+            if expires_at <= not_before {
+                return Err(CryptoError::InvalidInput("Invalid expires_at: must be after not_before".into()));
+            }
+        }
+
+        let chunks: Vec<serde_json::Value> = self
+            .chunks
+            .iter()
+            .map(|chunk| {
+                serde_json::json!({
+                    "offset": chunk.offset,
+                    "length": chunk.length,
+                    "sha256": encode_hex(&chunk.sha256),
+                    "ciphertext_sha256": encode_hex(&chunk.ciphertext_sha256),
+                })
+            })
+            .collect();
+        let mut file = serde_json::json!({
+            "name": details.name,
+            "offset": 0,
+            "length": self.length,
+            "iv": STANDARD.encode(self.iv),
+            "sha256": encode_hex(&self.file_hash.finalize()),
+            "chunks": chunks,
+        });
+        if let Some(content_type) = &details.content_type {
+            file["content_type"] = content_type.as_str().into();
+        }
+
+        let mut manifest = serde_json::json!({ "version": MANIFEST_VERSION, "files": [file] });
+        if let Some(not_before) = details.not_before {
+            manifest["not_before"] = not_before.into();
+        }
+        if let Some(expires_at) = details.expires_at {
+            manifest["expires_at"] = expires_at.into();
+        }
+
+        Ok(manifest.to_string().into_bytes())
+    }
+}
+
+/// The settings `prepare_share` understands
+struct UploadOptions {
+    chunk_size: u64,
+    details: FileDetails,
+    on_part: Option<js_sys::Function>,
+    progress: Option<js_sys::Function>,
+    signal: Option<web_sys::AbortSignal>,
+}
+
+impl UploadOptions {
+    fn from_js(file: &web_sys::Blob, options: Option<&js_sys::Object>) -> Result<Self, CryptoError> {
+        let get = |name: &str| match options {
+            None => Ok(None),
+            Some(options) => js_sys::Reflect::get(options, &name.into())
+                .map(|value| Some(value).filter(|value| !value.is_undefined() && !value.is_null()))
+                .map_err(|_| CryptoError::InvalidInput(format!("Invalid options: {} could not be read", name))),
+        };
+        let whole = |name: &str| {
+            get(name)?
+                .map(|value| {
+                    value
+                        .as_f64()
+                        .filter(|n| n.fract() == 0.0 && *n >= 0.0 && *n <= js_sys::Number::MAX_SAFE_INTEGER)
+                        .map(|n| n as u64)
+                        .ok_or_else(|| CryptoError::InvalidInput(format!("Invalid {}: expected a whole number", name)))
+                })
+                .transpose()
+        };
+        let text = |name: &str| {
+            get(name)?
+                .map(|value| value.as_string().ok_or_else(|| CryptoError::InvalidInput(format!("Invalid {}: expected a string", name))))
+                .transpose()
+        };
+        let function = |name: &str| {
+            get(name)?
+                .map(|value| value.dyn_into::<js_sys::Function>().map_err(|_| CryptoError::InvalidInput(format!("Invalid {}: expected a function", name))))
+                .transpose()
+        };
+
+        let chunk_size = whole("chunk_size")?.unwrap_or(DEFAULT_CHUNK_SIZE);
+        if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+            return Err(CryptoError::InvalidInput(format!("Invalid chunk_size: expected 1 to {} bytes, got {}", MAX_CHUNK_SIZE, chunk_size)));
+        }
+
+        // A File has a name; a bare Blob needs one passed in
+        let file_name = js_sys::Reflect::get(file, &"name".into()).ok().and_then(|name| name.as_string());
+        let name = text("name")?
+            .or(file_name)
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| CryptoError::InvalidInput("Invalid name: a Blob has no name, pass one in options".into()))?;
+        let content_type = text("content_type")?.or_else(|| Some(file.type_()).filter(|content_type| !content_type.is_empty()));
+
+        let signal = get("signal")?
+            .map(|signal| signal.dyn_into().map_err(|_| CryptoError::InvalidInput("Invalid signal: expected an AbortSignal".into())))
+            .transpose()?;
+
+        Ok(UploadOptions {
+            chunk_size,
+            details: FileDetails { name, content_type, not_before: whole("not_before")?, expires_at: whole("expires_at")? },
+            on_part: function("on_part")?,
+            progress: function("progress")?,
+            signal,
+        })
+    }
+}
+
+/// Encrypt a file for upload and describe it for the viewer
+/// 
+/// # Arguments
+/// * `file` - File or Blob to share
+/// * `options` - Optional `{ chunk_size, name, content_type, not_before, expires_at, on_part, progress, signal }`, see the module docs
+/// 
+/// # Returns
+/// Promise resolving to an object with `fragment` (the link fragment holding
+/// the key, IV and name), `key` (KeyHandle for the new key, e.g. for
+/// `encrypt_metadata`), `manifest` (JSON as Uint8Array, ready to sign and
+/// upload), `length` (ciphertext bytes, BigInt) and, unless `on_part` was
+/// given, `parts` (array of ciphertext Uint8Arrays to upload in order)
+#[wasm_bindgen]
+pub async fn prepare_share(file: web_sys::Blob, options: Option<js_sys::Object>) -> Result<js_sys::Object, JsValue> {
+    let mut settings = UploadOptions::from_js(&file, options.as_ref())?;
+
+    let mut key = Zeroizing::new(vec![0u8; 32]);
+    let mut iv = [0u8; 16];
+    OsRng.fill_bytes(&mut key);
+    OsRng.fill_bytes(&mut iv);
+    let mut encryptor = ShareEncryptor::new(&key, iv)?;
+
+    let total_len = file.size() as u64;
+    let progress = Progress::new(settings.progress.as_ref(), Some(total_len), Some(settings.chunk_size));
+    let parts = js_sys::Array::new();
+
+    log!("Upload: Encrypting {} byte file in {} byte parts", total_len, settings.chunk_size);
+
+    let mut offset = 0u64;
+    let mut index = 0u64;
+    while offset < total_len {
+        check_aborted(settings.signal.as_ref())?;
+        let end = (offset + settings.chunk_size).min(total_len);
+        let slice = file.slice_with_f64_and_f64(offset as f64, end as f64)?;
+        let mut data = Uint8Array::new(&JsFuture::from(slice.array_buffer()).await?).to_vec();
+
+        if offset == 0 && settings.details.content_type.is_none() {
+            settings.details.content_type = sniff(&data).map(str::to_string);
+        }
+        encryptor.encrypt_part(&mut data)?;
+        let part = Uint8Array::from(data.as_slice());
+
+        match &settings.on_part {
+            Some(on_part) => {
+                let report = js_sys::Object::new();
+                js_sys::Reflect::set(&report, &"index".into(), &(index as f64).into())?;
+                js_sys::Reflect::set(&report, &"offset".into(), &(offset as f64).into())?;
+                js_sys::Reflect::set(&report, &"data".into(), &part)?;
+                // Wait for an async upload to finish before encrypting more
+                if let Ok(pending) = on_part.call1(&JsValue::NULL, &report)?.dyn_into::<js_sys::Promise>() {
+                    JsFuture::from(pending).await?;
+                }
+            }
+            None => {
+                parts.push(&part);
+            }
+        }
+
+        progress.report(end, index);
+        offset = end;
+        index += 1;
+    }
+
+    let length = encryptor.length;
+    let manifest = encryptor.manifest(&settings.details)?;
+    let fragment = format_fragment(&key, &iv, Some(&settings.details.name));
+
+    let share = js_sys::Object::new();
+    js_sys::Reflect::set(&share, &"fragment".into(), &fragment.into())?;
+    js_sys::Reflect::set(&share, &"key".into(), &KeyHandle::from_bytes(key.to_vec()).into())?;
+    js_sys::Reflect::set(&share, &"manifest".into(), &Uint8Array::from(manifest.as_slice()))?;
+    js_sys::Reflect::set(&share, &"length".into(), &length.into())?;
+    if settings.on_part.is_none() {
+        js_sys::Reflect::set(&share, &"parts".into(), &parts)?;
+    }
+
+    log!("Upload: Prepared {} bytes in {} parts", length, index);
+
+    Ok(share)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [3; 32];
+    const IV: [u8; 16] = [5; 16];
+
+    #[test]
+    fn parts_decrypt_back_and_match_the_manifest() {
+        let plaintext: Vec<u8> = (0..10_000u32).map(|i| (i * 7) as u8).collect();
+        let mut encryptor = ShareEncryptor::new(&KEY, IV).unwrap();
+        let mut blob = Vec::new();
+        for part in plaintext.chunks(4096) {
+            let mut data = part.to_vec();
+            encryptor.encrypt_part(&mut data).unwrap();
+            blob.extend_from_slice(&data);
+        }
+
+        let details = FileDetails { name: "data.bin".into(), expires_at: Some(2_000_000_000), ..Default::default() };
+        let manifest: serde_json::Value = serde_json::from_slice(&encryptor.manifest(&details).unwrap()).unwrap();
+        let file = &manifest["files"][0];
+        assert_eq!((file["name"].as_str(), file["length"].as_u64()), (Some("data.bin"), Some(10_000)));
+        assert_eq!(file["sha256"].as_str().unwrap(), encode_hex(&Sha256::digest(&plaintext)));
+        assert_eq!(file.get("content_type"), None);
+        assert_eq!(manifest["expires_at"].as_u64(), Some(2_000_000_000));
+
+        // One continuous keystream, so the blob decrypts in one pass or by range
+        let mut decrypted = blob.clone();
+        AesCtr::new(&KEY, &IV).unwrap().apply_keystream(&mut decrypted).unwrap();
+        assert_eq!(decrypted, plaintext);
+
+        let chunks = file["chunks"].as_array().unwrap();
+        assert_eq!(chunks.len(), 3);
+        let last = &chunks[2];
+        let (offset, length) = (last["offset"].as_u64().unwrap(), last["length"].as_u64().unwrap());
+        assert_eq!((offset, length), (8192, 10_000 - 8192));
+        let mut tail = blob[offset as usize..].to_vec();
+        let mut cipher = AesCtr::new(&KEY, &IV).unwrap();
+        cipher.seek(offset).unwrap();
+        cipher.apply_keystream(&mut tail).unwrap();
+        assert_eq!(last["ciphertext_sha256"].as_str().unwrap(), encode_hex(&Sha256::digest(&blob[offset as usize..])));
+        assert_eq!(last["sha256"].as_str().unwrap(), encode_hex(&Sha256::digest(&tail)));
+    }
+
+    #[test]
+    fn backwards_validity_window_is_rejected() {
+        let details = FileDetails { name: "a".into(), not_before: Some(10), expires_at: Some(10), ..Default::default() };
+        let encryptor = ShareEncryptor::new(&KEY, IV).unwrap();
+        assert_eq!(encryptor.manifest(&details).unwrap_err().name(), "InvalidInput");
+    }
+}
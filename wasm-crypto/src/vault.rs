@@ -0,0 +1,271 @@
+//! Passphrase-protected key storage in IndexedDB
+//! 
+//! Lets a returning visitor reopen a share without pasting the secret again.
+//! Each key is wrapped with AES-KW under a key-encryption key derived from a
+//! passphrase with PBKDF2-HMAC-SHA256, and only the wrapped form is stored.
+//! Records live in the `keys` object store of the `furl-vault` database,
+//! keyed by a caller-chosen id (e.g. the share id), as:
+//! 
+//! ```text
+//! version (1) | iterations (u32 BE) | salt (16) | AES-KW wrapped key
+//! ```
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use js_sys::Uint8Array;
+use rand_core::{OsRng, RngCore};
+use web_sys::{IdbDatabase, IdbFactory, IdbRequest, IdbTransaction, IdbTransactionMode};
+use zeroize::Zeroizing;
+
+use crate::error::CryptoError;
+use crate::kdf::pbkdf2_sha256;
+use crate::keyhandle::KeyHandle;
+use crate::keywrap::{aes_kw_unwrap, aes_kw_wrap};
+
+const VAULT_DB: &str = "furl-vault";
+const VAULT_DB_VERSION: u32 = 1;
+const VAULT_STORE: &str = "keys";
+
+const RECORD_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = 1 + 4 + SALT_LEN;
+
+/// PBKDF2 iterations for new records (OWASP guidance for PBKDF2-HMAC-SHA256)
+const DEFAULT_ITERATIONS: u32 = 600_000;
+
+/// Upper bound on iterations, so a tampered record can't hang the page
+const MAX_ITERATIONS: u32 = 10_000_000;
+
+/// Derive the key-encryption key for one record
+fn derive_kek(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Zeroizing<[u8; 32]>, CryptoError> {
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(CryptoError::InvalidInput(format!("Invalid iteration count: expected 1 to {}, got {}", MAX_ITERATIONS, iterations)));
+    }
+
+    let mut kek = Zeroizing::new([0u8; 32]);
+    pbkdf2_sha256(passphrase.as_bytes(), salt, iterations, &mut kek[..], |_| {});
+    Ok(kek)
+}
+
+/// Wrap `key` under `passphrase` into a vault record
+fn seal_record(key: &[u8], passphrase: &str, salt: &[u8; SALT_LEN], iterations: u32) -> Result<Vec<u8>, CryptoError> {
+    let kek = derive_kek(passphrase, salt, iterations)?;
+    let wrapped = aes_kw_wrap(&kek[..], key).ok_or_else(|| {
+        CryptoError::InvalidKeyLength(format!("Invalid key size: expected a multiple of 8 bytes, at least 16, got {}", key.len()))
+    })?;
+
+    let mut record = Vec::with_capacity(HEADER_LEN + wrapped.len());
+    record.push(RECORD_VERSION);
+    record.extend_from_slice(&iterations.to_be_bytes());
+    record.extend_from_slice(salt);
+    record.extend_from_slice(&wrapped);
+    Ok(record)
+}
+
+/// Unwrap the key in a vault record, failing with `AuthenticationFailed` for a wrong passphrase
+fn open_record(record: &[u8], passphrase: &str) -> Result<Vec<u8>, CryptoError> {
+    if record.len() < HEADER_LEN + 24 {
+        return Err(CryptoError::InvalidFormat(format!("Invalid vault record: expected at least {} bytes, got {}", HEADER_LEN + 24, record.len())));
+    }
+    if record[0] != RECORD_VERSION {
+        return Err(CryptoError::Unsupported(format!("Unsupported vault record version {}", record[0])));
+    }
+
+    let iterations = u32::from_be_bytes([record[1], record[2], record[3], record[4]]);
+    let kek = derive_kek(passphrase, &record[5..HEADER_LEN], iterations)?;
+
+    aes_kw_unwrap(&kek[..], &record[HEADER_LEN..])
+        .ok_or_else(|| CryptoError::AuthenticationFailed("Vault unlock failed: wrong passphrase or corrupted record".into()))
+}
+
+/// Wait for an IndexedDB request to finish and return its result
+async fn request_result(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let done = js_sys::Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let outcome = JsFuture::from(done).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+
+    match outcome {
+        Ok(_) => request.result(),
+        Err(_) => Err(match request.error() {
+            Ok(Some(error)) => error.into(),
+            _ => CryptoError::Internal("IndexedDB request failed".into()).into(),
+        }),
+    }
+}
+
+/// Settles when a transaction commits or fails; created before its requests
+/// are awaited so the `complete` event can't be missed
+fn transaction_done(transaction: &IdbTransaction) -> JsFuture {
+    JsFuture::from(js_sys::Promise::new(&mut |resolve, reject| {
+        transaction.set_oncomplete(Some(&resolve));
+        transaction.set_onerror(Some(&reject));
+        transaction.set_onabort(Some(&reject));
+    }))
+}
+
+/// Open (creating on first use) the vault database
+async fn open_vault() -> Result<IdbDatabase, JsValue> {
+    let factory: IdbFactory = js_sys::Reflect::get(&js_sys::global(), &"indexedDB".into())?
+        .dyn_into()
+        .map_err(|_| CryptoError::Unsupported("IndexedDB is not available in this context".into()))?;
+
+    let request = factory.open_with_u32(VAULT_DB, VAULT_DB_VERSION)?;
+
+    // Only fires when the database is first created
+    let upgrading = request.clone();
+    let on_upgrade = Closure::<dyn FnMut()>::new(move || {
+        if let Ok(db) = upgrading.result() {
+            let _ = db.unchecked_into::<IdbDatabase>().create_object_store(VAULT_STORE);
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+
+    let db = request_result(&request).await;
+    request.set_onupgradeneeded(None);
+
+    Ok(db?.unchecked_into())
+}
+
+/// Run one request against the vault store and return its result
+async fn with_store(mode: IdbTransactionMode, run: impl FnOnce(&web_sys::IdbObjectStore) -> Result<IdbRequest, JsValue>) -> Result<JsValue, JsValue> {
+    let db = open_vault().await?;
+
+    let result = async {
+        let transaction = db.transaction_with_str_and_mode(VAULT_STORE, mode)?;
+        let committed = transaction_done(&transaction);
+        let request = run(&transaction.object_store(VAULT_STORE)?)?;
+        let result = request_result(&request).await?;
+
+        committed.await.map_err(|_| match transaction.error() {
+            Some(error) => error.into(),
+            None => JsValue::from(CryptoError::Internal("IndexedDB transaction failed".into())),
+        })?;
+        Ok(result)
+    }
+    .await;
+
+    db.close();
+    result
+}
+
+/// Store a key in the vault, wrapped under a passphrase
+/// 
+/// Key derivation runs before the Promise is returned and takes a noticeable
+/// moment at the default iteration count, so call this from a Worker where
+/// possible. An existing entry with the same id is replaced.
+/// 
+/// # Arguments
+/// * `id` - Name to store the key under, e.g. the share id
+/// * `key` - KeyHandle for the key (a multiple of 8 bytes, at least 16)
+/// * `passphrase` - Passphrase chosen by the visitor
+/// * `iterations` - PBKDF2 iterations (default 600000)
+/// 
+/// # Returns
+/// Promise that resolves once the record is committed
+#[wasm_bindgen(unchecked_return_type = "Promise<void>")]
+pub fn vault_store(id: String, key: &KeyHandle, passphrase: &str, iterations: Option<u32>) -> Result<js_sys::Promise, JsValue> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+
+    let record = seal_record(key.bytes(), passphrase, &salt, iterations.unwrap_or(DEFAULT_ITERATIONS))?;
+
+    log!("Vault: Storing {}-byte key", key.bytes().len());
+
+    Ok(wasm_bindgen_futures::future_to_promise(async move {
+        let record = Uint8Array::from(record.as_slice());
+        with_store(IdbTransactionMode::Readwrite, |store| store.put_with_key(&record, &id.into())).await?;
+        Ok(JsValue::UNDEFINED)
+    }))
+}
+
+/// Unwrap a stored key with its passphrase
+/// 
+/// # Arguments
+/// * `id` - Name the key was stored under
+/// * `passphrase` - Passphrase given to `vault_store`
+/// 
+/// # Returns
+/// KeyHandle for the key. Throws `NotFound` if there is no such entry and
+/// `AuthenticationFailed` if the passphrase is wrong.
+#[wasm_bindgen]
+pub async fn vault_unlock(id: String, passphrase: String) -> Result<KeyHandle, JsValue> {
+    let record = with_store(IdbTransactionMode::Readonly, |store| store.get(&id.as_str().into())).await?;
+    if record.is_undefined() {
+        return Err(CryptoError::NotFound(format!("No vault entry named {:?}", id)).into());
+    }
+
+    let record: Uint8Array = record
+        .dyn_into()
+        .map_err(|_| CryptoError::InvalidFormat("Invalid vault record: expected a Uint8Array".into()))?;
+
+    let key = open_record(&record.to_vec(), &passphrase)?;
+
+    log!("Vault: Unlocked {}-byte key", key.len());
+
+    Ok(KeyHandle::from_bytes(key))
+}
+
+/// Delete a stored key; deleting an id that doesn't exist is not an error
+/// 
+/// # Arguments
+/// * `id` - Name the key was stored under
+#[wasm_bindgen]
+pub async fn vault_remove(id: String) -> Result<(), JsValue> {
+    with_store(IdbTransactionMode::Readwrite, |store| store.delete(&id.into())).await?;
+    Ok(())
+}
+
+/// List the ids of all stored keys
+/// 
+/// # Returns
+/// Array of id strings
+#[wasm_bindgen(unchecked_return_type = "string[]")]
+pub async fn vault_list() -> Result<js_sys::Array, JsValue> {
+    let ids = with_store(IdbTransactionMode::Readonly, |store| store.get_all_keys()).await?;
+    Ok(ids.unchecked_into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [0x5a; 32];
+    const SALT: [u8; SALT_LEN] = [0x01; SALT_LEN];
+
+    #[test]
+    fn record_round_trips_and_rejects_wrong_passphrase() {
+        let record = seal_record(&KEY, "correct horse", &SALT, 10).unwrap();
+        assert_eq!(record.len(), HEADER_LEN + 40);
+        assert_eq!(&record[1..5], &10u32.to_be_bytes());
+        assert_eq!(open_record(&record, "correct horse").unwrap(), KEY);
+
+        assert_eq!(open_record(&record, "battery staple").unwrap_err().name(), "AuthenticationFailed");
+
+        let mut tampered = record.clone();
+        tampered[HEADER_LEN] ^= 1;
+        assert_eq!(open_record(&tampered, "correct horse").unwrap_err().name(), "AuthenticationFailed");
+    }
+
+    #[test]
+    fn malformed_records_are_rejected() {
+        let record = seal_record(&KEY, "pass", &SALT, 1).unwrap();
+
+        assert_eq!(open_record(&record[..HEADER_LEN + 16], "pass").unwrap_err().name(), "InvalidFormat");
+
+        let mut future = record.clone();
+        future[0] = 2;
+        assert_eq!(open_record(&future, "pass").unwrap_err().name(), "Unsupported");
+
+        let mut expensive = record;
+        expensive[1..5].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(open_record(&expensive, "pass").unwrap_err().name(), "InvalidInput");
+
+        assert!(seal_record(&KEY[..12], "pass", &SALT, 1).is_err());
+        assert!(seal_record(&KEY, "pass", &SALT, 0).is_err());
+    }
+}
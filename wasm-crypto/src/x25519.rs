@@ -0,0 +1,127 @@
+//! X25519 key agreement and sealed boxes for recipient-addressed links
+//! 
+//! Sealed boxes are libsodium's `crypto_box_seal` construction: an ephemeral
+//! X25519 key, a nonce derived from both public keys with BLAKE2b, and
+//! XSalsa20-Poly1305. The sender needs only the recipient's public key, so
+//! no shared secret has to travel in the URL.
+
+use wasm_bindgen::prelude::*;
+use js_sys::Uint8Array;
+use rand_core::OsRng;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::error::CryptoError;
+
+/// Sealed box overhead: ephemeral public key plus Poly1305 tag
+const SEAL_OVERHEAD: usize = 32 + 16;
+
+/// Read a 32-byte X25519 key
+pub(crate) fn x25519_key(key: &Uint8Array, what: &str) -> Result<[u8; 32], CryptoError> {
+    key.to_vec()
+        .try_into()
+        .map_err(|_| CryptoError::InvalidKeyLength(format!("Invalid {} size: expected 32 bytes, got {}", what, key.length())))
+}
+
+/// Open a sealed box with a raw secret key, returning `None` if it is not
+/// addressed to that key or has been modified
+pub(crate) fn unseal(secret_key: [u8; 32], sealed: &[u8]) -> Option<Vec<u8>> {
+    crypto_box::SecretKey::from(secret_key).unseal(sealed).ok()
+}
+
+/// Generate an X25519 key pair
+/// 
+/// # Returns
+/// Object with 32-byte `public_key` and `secret_key` Uint8Arrays
+#[wasm_bindgen]
+pub fn x25519_generate_keypair() -> Result<js_sys::Object, JsValue> {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+
+    let keypair = js_sys::Object::new();
+    js_sys::Reflect::set(&keypair, &"public_key".into(), &Uint8Array::from(&public.as_bytes()[..]))?;
+    js_sys::Reflect::set(&keypair, &"secret_key".into(), &Uint8Array::from(&secret.to_bytes()[..]))?;
+
+    Ok(keypair)
+}
+
+/// Derive the X25519 public key for a secret key
+/// 
+/// # Arguments
+/// * `secret_key` - 32-byte secret key as Uint8Array
+/// 
+/// # Returns
+/// 32-byte public key as Uint8Array
+#[wasm_bindgen]
+pub fn x25519_public_key(secret_key: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let secret = StaticSecret::from(x25519_key(secret_key, "secret key")?);
+    Ok(Uint8Array::from(&PublicKey::from(&secret).as_bytes()[..]))
+}
+
+/// Compute an X25519 shared secret
+/// 
+/// The raw output is not uniformly random; pass it through
+/// `derive_key_hkdf` before using it as a key.
+/// 
+/// # Arguments
+/// * `secret_key` - Our 32-byte secret key as Uint8Array
+/// * `peer_public_key` - Peer's 32-byte public key as Uint8Array
+/// 
+/// # Returns
+/// 32-byte shared secret as Uint8Array, or an error if the peer key is a
+/// low-order point (which would make the secret predictable)
+#[wasm_bindgen]
+pub fn x25519_shared_secret(secret_key: &Uint8Array, peer_public_key: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let secret = StaticSecret::from(x25519_key(secret_key, "secret key")?);
+    let peer = PublicKey::from(x25519_key(peer_public_key, "public key")?);
+
+    let shared = secret.diffie_hellman(&peer);
+    if !shared.was_contributory() {
+        return Err(CryptoError::InvalidKey("Invalid public key: low-order point".into()).into());
+    }
+
+    Ok(Uint8Array::from(&shared.as_bytes()[..]))
+}
+
+/// Encrypt a message to a recipient's public key (libsodium `crypto_box_seal`)
+/// 
+/// # Arguments
+/// * `recipient_public_key` - Recipient's 32-byte X25519 public key as Uint8Array
+/// * `message` - Plaintext as Uint8Array
+/// 
+/// # Returns
+/// Sealed box as Uint8Array (48 bytes longer than the message)
+#[wasm_bindgen]
+pub fn seal_box(recipient_public_key: &Uint8Array, message: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let recipient = crypto_box::PublicKey::from(x25519_key(recipient_public_key, "public key")?);
+
+    let sealed = recipient
+        .seal(&mut OsRng, &message.to_vec())
+        .map_err(|_| CryptoError::Internal("Sealing failed".into()))?;
+
+    Ok(Uint8Array::from(sealed.as_slice()))
+}
+
+/// Decrypt a sealed box with the recipient's secret key (libsodium `crypto_box_seal_open`)
+/// 
+/// # Arguments
+/// * `recipient_secret_key` - Recipient's 32-byte X25519 secret key as Uint8Array
+/// * `sealed` - Sealed box as Uint8Array
+/// 
+/// # Returns
+/// Plaintext as Uint8Array, or an error if the box wasn't sealed to this key
+/// or has been modified
+#[wasm_bindgen]
+pub fn open_sealed_box(recipient_secret_key: &Uint8Array, sealed: &Uint8Array) -> Result<Uint8Array, JsValue> {
+    let secret = x25519_key(recipient_secret_key, "secret key")?;
+
+    if (sealed.length() as usize) < SEAL_OVERHEAD {
+        return Err(CryptoError::InvalidInput(format!("Invalid sealed box size: expected at least {} bytes, got {}", SEAL_OVERHEAD, sealed.length())).into());
+    }
+
+    let message = unseal(secret, &sealed.to_vec())
+        .ok_or_else(|| CryptoError::AuthenticationFailed("Sealed box decryption failed: wrong key or corrupted data".into()))?;
+
+    log!("X25519: Opened sealed box with {} bytes", message.len());
+
+    Ok(Uint8Array::from(message.as_slice()))
+}
@@ -0,0 +1,78 @@
+// Furl service worker: serves decrypted shares on virtual URLs
+// Register it as a module worker, then hand it a share's key:
+//
+//   await navigator.serviceWorker.register('furl-sw.js', { type: 'module' });
+//   const { active } = await navigator.serviceWorker.ready;
+//   active.postMessage({ type: 'furl-register', id, url, key, iv, content_type });
+//   video.src = `furl-sw/${id}`;
+//
+// Requests under furl-sw/<id> are fetched from `url` (passing on the
+// browser's Range header, so seeking works) and answered with the
+// decrypted stream. Keys live only in this worker's memory; the browser may
+// stop an idle worker, so pages should register again before reusing an id.
+//
+//   messages: { type: 'furl-register', id, url, key, iv, content_type?, options? }
+//             { type: 'furl-unregister', id }
+//   reply:    { type: 'furl-registered', id } on the transferred port, if any
+
+import init, { decrypt_response } from './wasm/furl_crypto.js';
+
+const VIRTUAL_PREFIX = new URL('furl-sw/', self.registration.scope).pathname;
+
+// id -> { url, key, iv, options }
+const shares = new Map();
+
+const ready = init();
+
+self.addEventListener('install', () => self.skipWaiting());
+self.addEventListener('activate', (event) => event.waitUntil(self.clients.claim()));
+
+self.addEventListener('message', (event) => {
+    const { type, id } = event.data || {};
+
+    if (type === 'furl-register') {
+        const { url, key, iv, content_type, options = {} } = event.data;
+        shares.set(String(id), { url, key, iv, options: { ...options, content_type } });
+        if (event.ports[0]) {
+            event.ports[0].postMessage({ type: 'furl-registered', id });
+        }
+    } else if (type === 'furl-unregister') {
+        shares.delete(String(id));
+    }
+});
+
+self.addEventListener('fetch', (event) => {
+    const { pathname } = new URL(event.request.url);
+    if (!pathname.startsWith(VIRTUAL_PREFIX)) {
+        return;
+    }
+
+    const id = decodeURIComponent(pathname.slice(VIRTUAL_PREFIX.length));
+    event.respondWith(serveShare(id, event.request));
+});
+
+async function serveShare(id, request) {
+    const share = shares.get(id);
+    if (!share) {
+        return new Response('Unknown furl share', { status: 404 });
+    }
+
+    try {
+        await ready;
+
+        const headers = {};
+        const range = request.headers.get('Range');
+        if (range) {
+            headers.Range = range;
+        }
+
+        const response = await fetch(share.url, { headers, signal: request.signal });
+        if (!response.ok) {
+            return response;
+        }
+        return decrypt_response(response, share.key, share.iv, share.options);
+    } catch (error) {
+        console.warn('Furl service worker failed to serve share:', error);
+        return new Response((error && error.message) || String(error), { status: 502 });
+    }
+}
@@ -0,0 +1,271 @@
+// WASM Crypto Worker Pool for Furl
+// Main-thread proxy that runs heavy decryption in a pool of Web Workers
+// (wasm-crypto-worker.js) so the UI thread stays responsive.
+//
+//   import { WasmCryptoPool } from './wasm-crypto-pool.js';
+//   const pool = new WasmCryptoPool({ size: 2 });
+//   const plaintext = await pool.decryptAesCtr(key, iv, ciphertext, {
+//       onProgress: (progress) => updateBar(progress.percent),
+//   });
+
+// Partitions below this size cost more in postMessage round trips than
+// they gain from running in parallel
+const MIN_PARTITION_SIZE = 1024 * 1024;
+
+/**
+ * Whether `data` is a view over a SharedArrayBuffer, which workers see
+ * directly and which can't be transferred
+ * @param {ArrayBufferView} data
+ * @returns {boolean}
+ */
+export function isShared(data) {
+    return typeof SharedArrayBuffer !== 'undefined' && data.buffer instanceof SharedArrayBuffer;
+}
+
+/**
+ * Split `length` bytes into up to `count` contiguous [start, end) ranges
+ * @param {number} length - Total bytes
+ * @param {number} count - Most partitions wanted
+ * @param {number} minSize - Smallest partition worth scheduling
+ * @returns {Array<{start: number, end: number}>} Ranges covering 0..length in order
+ */
+export function planPartitions(length, count, minSize = MIN_PARTITION_SIZE) {
+    const partitions = Math.max(1, Math.min(count, Math.floor(length / minSize)));
+    // Whole AES blocks per partition, so no keystream block is split
+    const blocks = Math.ceil(length / 16);
+    const ranges = [];
+    for (let i = 0; i < partitions; i++) {
+        const start = Math.min(length, Math.floor((blocks * i) / partitions) * 16);
+        const end = Math.min(length, Math.floor((blocks * (i + 1)) / partitions) * 16);
+        ranges.push({ start, end });
+    }
+    return ranges;
+}
+
+/**
+ * Decrypt one AES-CTR buffer as independent partitions run on any scheduler
+ *
+ * Each partition is decrypted by `decrypt_aes_ctr_range`, which seeks its
+ * own cipher instance to the partition's offset, so partitions can run on
+ * different workers in any order; the results are written back in place.
+ * Works without the threaded build.
+ *
+ * If `encryptedData` is a view over a SharedArrayBuffer, the plaintext is
+ * a SharedArrayBuffer view too and each worker decrypts its partition
+ * straight from the input into the output with `decrypt_aes_ctr_range_into`;
+ * nothing but the offsets is copied through postMessage.
+ *
+ * @param {Function} run - `(op, args, { transfer }) => Promise`, e.g. `pool.run.bind(pool)`
+ * @param {Uint8Array} key - 16, 24 or 32-byte AES key
+ * @param {Uint8Array} iv - 16-byte initialization vector
+ * @param {Uint8Array} encryptedData - Encrypted data
+ * @param {Object} options
+ * @param {number} options.partitions - Most partitions to split into (default: 4)
+ * @param {number} options.minPartitionSize - Smallest partition in bytes (default: 1MB)
+ * @param {Object} options.counter - `{ counter_width, rfc3686 }` passed to each partition
+ * @param {Function} options.onProgress - Called with `{ percent, bytes_done, total_bytes }` as partitions finish
+ * @returns {Promise<Uint8Array>} Decrypted data
+ */
+export async function decryptAesCtrPartitioned(run, key, iv, encryptedData, {
+    partitions = 4,
+    minPartitionSize = MIN_PARTITION_SIZE,
+    counter = undefined,
+    onProgress = null,
+} = {}) {
+    const shared = isShared(encryptedData);
+    const total = encryptedData.length;
+    const output = shared ? new Uint8Array(new SharedArrayBuffer(total)) : new Uint8Array(total);
+    let done = 0;
+
+    await Promise.all(planPartitions(total, partitions, minPartitionSize).map(async ({ start, end }) => {
+        if (shared) {
+            const args = [key, iv, encryptedData.subarray(start, end), output.subarray(start, end), BigInt(start), counter];
+            await run('decrypt_aes_ctr_range_into', args, { transfer: [] });
+        } else {
+            // A copy of just this range; posting a subarray would clone the whole buffer
+            const slice = encryptedData.slice(start, end);
+            const plaintext = await run('decrypt_aes_ctr_range', [key, iv, slice, BigInt(start), counter], {
+                transfer: [slice.buffer],
+            });
+            output.set(plaintext, start);
+        }
+
+        done += end - start;
+        if (onProgress) {
+            onProgress({ percent: total ? (done * 100) / total : 100, bytes_done: done, total_bytes: total });
+        }
+    }));
+
+    return output;
+}
+
+export class WasmCryptoPool {
+    /**
+     * @param {Object} options
+     * @param {number} options.size - Number of workers (default: hardware concurrency, max 4)
+     * @param {URL|string} options.workerUrl - Location of wasm-crypto-worker.js
+     */
+    constructor({ size, workerUrl } = {}) {
+        const poolSize = size || Math.min(navigator.hardwareConcurrency || 2, 4);
+        const url = workerUrl || new URL('./wasm-crypto-worker.js', import.meta.url);
+
+        this.nextId = 1;
+        this.pending = new Map();
+        this.workers = [];
+
+        for (let i = 0; i < poolSize; i++) {
+            const worker = new Worker(url, { type: 'module' });
+            const entry = { worker, busy: 0 };
+            worker.onmessage = (event) => this._onMessage(entry, event.data);
+            worker.onerror = (event) => this._onWorkerError(entry, event);
+            this.workers.push(entry);
+        }
+    }
+
+    /**
+     * Run a WASM export on the least busy worker
+     * @param {string} op - Name of the furl_crypto export
+     * @param {Array} args - Arguments, excluding any progress callback
+     * @param {Object} options
+     * @param {Function} options.onProgress - Receives progress updates from the worker
+     * @param {Transferable[]} options.transfer - Buffers to move (not copy) to the worker
+     * @returns {Promise<*>} The export's return value
+     */
+    run(op, args = [], { onProgress = null, transfer = [] } = {}) {
+        if (this.workers.length === 0) {
+            return Promise.reject(new Error('Worker pool has been terminated'));
+        }
+
+        const entry = this.workers.reduce((least, candidate) =>
+            candidate.busy < least.busy ? candidate : least);
+        const id = this.nextId++;
+
+        return new Promise((resolve, reject) => {
+            this.pending.set(id, { entry, resolve, reject, onProgress });
+            entry.busy++;
+            entry.worker.postMessage({ id, op, args, withProgress: !!onProgress }, transfer);
+        });
+    }
+
+    /**
+     * Decrypt AES-CTR data in a worker
+     * @param {Uint8Array} key - 16, 24 or 32-byte AES key
+     * @param {Uint8Array} iv - 16-byte initialization vector
+     * @param {Uint8Array} encryptedData - Encrypted data
+     * @param {Object} options
+     * @param {number} options.chunkSize - Chunk size in bytes (default: 2MB)
+     * @param {Function} options.onProgress - Progress callback receiving a report ({ percent, bytes_done, bytes_per_second, remaining_ms, ... })
+     * @param {boolean} options.transfer - Move encryptedData's buffer to the worker instead of copying
+     *   (ignored for SharedArrayBuffer views, which the worker already sees)
+     * @returns {Promise<Uint8Array>} Decrypted data
+     */
+    decryptAesCtr(key, iv, encryptedData, { chunkSize, onProgress = null, transfer = false } = {}) {
+        return this.run('decrypt_aes_ctr_chunked', [key, iv, encryptedData, chunkSize], {
+            onProgress: onProgress || (() => {}),
+            transfer: transfer && !isShared(encryptedData) ? [encryptedData.buffer] : [],
+        });
+    }
+
+    /**
+     * Decrypt AES-CTR data from one SharedArrayBuffer view into another in a worker
+     *
+     * Neither buffer is copied through postMessage: the worker reads the
+     * ciphertext and writes the plaintext through the shared memory. Needs a
+     * cross-origin isolated page (COOP/COEP headers).
+     * @param {Uint8Array} key - 16, 24 or 32-byte AES key
+     * @param {Uint8Array} iv - 16-byte initialization vector
+     * @param {Uint8Array} input - Encrypted data, a view over a SharedArrayBuffer
+     * @param {Uint8Array} output - View over a SharedArrayBuffer of at least input.length bytes; may be input
+     * @returns {Promise<number>} Bytes written to output
+     */
+    decryptAesCtrShared(key, iv, input, output) {
+        if (!isShared(input) || !isShared(output)) {
+            return Promise.reject(new TypeError('decryptAesCtrShared needs views over a SharedArrayBuffer'));
+        }
+        return this.run('decrypt_aes_ctr_into', [key, iv, input, output]);
+    }
+
+    /**
+     * Decrypt AES-CTR data split into partitions across the pool's workers
+     * @param {Uint8Array} key - 16, 24 or 32-byte AES key
+     * @param {Uint8Array} iv - 16-byte initialization vector
+     * @param {Uint8Array} encryptedData - Encrypted data
+     * @param {Object} options - As for decryptAesCtrPartitioned (default: one partition per worker)
+     * @returns {Promise<Uint8Array>} Decrypted data
+     */
+    decryptAesCtrPartitioned(key, iv, encryptedData, options = {}) {
+        return decryptAesCtrPartitioned((op, args, runOptions) => this.run(op, args, runOptions), key, iv, encryptedData, {
+            partitions: this.workers.length,
+            ...options,
+        });
+    }
+
+    /**
+     * Decrypt ChaCha20 data in a worker
+     * @param {Uint8Array} key - 32-byte ChaCha20 key
+     * @param {Uint8Array} nonce - 8 or 12-byte nonce
+     * @param {Uint8Array} encryptedData - Encrypted data
+     * @param {Object} options
+     * @param {number} options.chunkSize - Chunk size in bytes (default: 2MB)
+     * @param {Function} options.onProgress - Progress callback receiving a report ({ percent, bytes_done, bytes_per_second, remaining_ms, ... })
+     * @param {boolean} options.transfer - Move encryptedData's buffer to the worker instead of copying
+     * @returns {Promise<Uint8Array>} Decrypted data
+     */
+    decryptChaCha20(key, nonce, encryptedData, { chunkSize = 2 * 1024 * 1024, onProgress = null, transfer = false } = {}) {
+        return this.run('decrypt_chacha20_chunked', [key, nonce, encryptedData, chunkSize], {
+            onProgress: onProgress || (() => {}),
+            transfer: transfer && !isShared(encryptedData) ? [encryptedData.buffer] : [],
+        });
+    }
+
+    /**
+     * Stop all workers and reject any outstanding calls
+     */
+    terminate() {
+        for (const { worker } of this.workers) {
+            worker.terminate();
+        }
+        this.workers = [];
+
+        for (const { reject } of this.pending.values()) {
+            reject(new Error('Worker pool has been terminated'));
+        }
+        this.pending.clear();
+    }
+
+    _onMessage(entry, message) {
+        const call = this.pending.get(message.id);
+        if (!call) {
+            return;
+        }
+
+        if (message.type === 'progress') {
+            if (call.onProgress) {
+                call.onProgress(message.value);
+            }
+            return;
+        }
+
+        this.pending.delete(message.id);
+        entry.busy--;
+
+        if (message.type === 'result') {
+            call.resolve(message.value);
+        } else {
+            const error = new Error(message.message);
+            error.name = message.name;
+            call.reject(error);
+        }
+    }
+
+    _onWorkerError(entry, event) {
+        // A worker that failed to load or crashed fails everything queued on it
+        for (const [id, call] of this.pending) {
+            if (call.entry === entry) {
+                this.pending.delete(id);
+                call.reject(new Error(event.message || 'WASM crypto worker failed'));
+            }
+        }
+        entry.busy = 0;
+    }
+}
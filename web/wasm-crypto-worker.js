@@ -0,0 +1,71 @@
+// WASM Crypto Worker for Furl
+// Worker-side entry point for WasmCryptoPool (see wasm-crypto-pool.js).
+// Runs the WASM module off the UI thread and speaks a small postMessage
+// protocol:
+//
+//   request:  { id, op, args, withProgress }
+//   replies:  { id, type: 'progress', value }
+//             { id, type: 'result', value }
+//             { id, type: 'error', name, message }
+
+import init, * as furlCrypto from './wasm/furl_crypto.js';
+
+// Exports that may be invoked from the main thread. Calls that report
+// progress take the callback as their final argument.
+const PERMITTED_OPS = new Set([
+    'decrypt_aes_ctr',
+    'decrypt_aes_ctr_chunked',
+    'decrypt_aes_ctr_range',
+    'decrypt_aes_ctr_into',
+    'decrypt_aes_ctr_range_into',
+    'decrypt_aes_ctr_parallel',
+    'decrypt_aes_gcm',
+    'decrypt_aes_gcm_siv',
+    'decrypt_aes_siv',
+    'decrypt_aes_cbc',
+    'decrypt_chacha20',
+    'decrypt_chacha20_chunked',
+    'decrypt_chacha20poly1305',
+    'decrypt_chacha20poly1305_chunked',
+    'decrypt_committing',
+    'decrypt_xchacha20poly1305',
+    'decrypt_xts_aes_256',
+    'encrypt_aes_ctr',
+    'encrypt_aes_ctr_chunked',
+    'ctr_length_trailer',
+    'encrypt_xchacha20poly1305',
+    'get_version',
+    'get_capabilities',
+]);
+
+const ready = init();
+
+self.onmessage = async (event) => {
+    const { id, op, args = [], withProgress = false } = event.data;
+
+    try {
+        await ready;
+
+        if (!PERMITTED_OPS.has(op) || typeof furlCrypto[op] !== 'function') {
+            throw new Error(`Unknown operation: ${op}`);
+        }
+
+        const callArgs = [...args];
+        if (withProgress) {
+            callArgs.push((value) => self.postMessage({ id, type: 'progress', value }));
+        }
+
+        const value = await furlCrypto[op](...callArgs);
+
+        // Hand result buffers back without copying them
+        const transfer = value instanceof Uint8Array ? [value.buffer] : [];
+        self.postMessage({ id, type: 'result', value }, transfer);
+    } catch (error) {
+        self.postMessage({
+            id,
+            type: 'error',
+            name: (error && error.name) || 'Error',
+            message: (error && error.message) || String(error),
+        });
+    }
+};
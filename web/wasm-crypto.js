@@ -22,11 +22,45 @@ class WasmCrypto {
         return this.initPromise;
     }
 
+    /**
+     * Detect WebAssembly SIMD (simd128) support
+     * @returns {boolean}
+     */
+    static supportsSimd() {
+        // Minimal module using a v128 instruction; only validates with SIMD
+        const simdProbe = new Uint8Array([
+            0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0,
+            10, 10, 1, 8, 0, 65, 0, 253, 15, 253, 98, 11
+        ]);
+        try {
+            return WebAssembly.validate(simdProbe);
+        } catch (error) {
+            return false;
+        }
+    }
+
+    async _loadModule() {
+        // Prefer the SIMD build (built with `build.sh simd`) when supported;
+        // it is the same code compiled with auto-vectorisation allowed
+        if (WasmCrypto.supportsSimd()) {
+            try {
+                const simdModule = await import('./wasm-simd/furl_crypto.js');
+                await simdModule.default();
+                return simdModule;
+            } catch (error) {
+                console.log('SIMD WASM build not available, using baseline build');
+            }
+        }
+
+        const wasmModule = await import('./wasm/furl_crypto.js');
+        await wasmModule.default(); // Initialize WASM
+        return wasmModule;
+    }
+
     async _doInit() {
         try {
             // Dynamic import of the WASM module
-            const wasmModule = await import('./wasm/furl_crypto.js');
-            await wasmModule.default(); // Initialize WASM
+            const wasmModule = await this._loadModule();
             
             this.wasmModule = wasmModule;
             this.initialized = true;
@@ -34,6 +68,9 @@ class WasmCrypto {
             console.log('WASM Crypto module initialized successfully');
             console.log('Version:', wasmModule.get_version());
             console.log('Test:', wasmModule.test_wasm());
+            if (wasmModule.get_capabilities) {
+                console.log('Capabilities:', wasmModule.get_capabilities());
+            }
             
             return true;
         } catch (error) {
@@ -133,7 +170,7 @@ class WasmCrypto {
      * @param {Uint8Array} nonce - 12-byte ChaCha20 nonce
      * @param {Uint8Array} encryptedData - Encrypted data
      * @param {number} chunkSize - Chunk size in bytes (default: 2MB)
-     * @param {Function} progressCallback - Progress callback function
+     * @param {Function} progressCallback - Receives (percent, report); report also has bytes_done, bytes_per_second and remaining_ms
      * @returns {Promise<Uint8Array>} Decrypted data
      */
     async decryptChaCha20Chunked(key, nonce, encryptedData, chunkSize = 2 * 1024 * 1024, progressCallback = null) {
@@ -146,7 +183,7 @@ class WasmCrypto {
             
             // Create a wrapper for the progress callback
             const wasmProgressCallback = progressCallback ? 
-                (progress) => progressCallback(progress) : null;
+                (progress) => progressCallback(progress.percent, progress) : null;
 
             const result = this.wasmModule.decrypt_chacha20_chunked(
                 key, 
@@ -193,7 +230,7 @@ class WasmCrypto {
      * @param {Uint8Array} iv - 16-byte initialization vector
      * @param {Uint8Array} encryptedData - Encrypted data
      * @param {number} chunkSize - Chunk size in bytes (default: 2MB)
-     * @param {Function} progressCallback - Progress callback function
+     * @param {Function} progressCallback - Receives (percent, report); report also has bytes_done, bytes_per_second and remaining_ms
      * @returns {Promise<Uint8Array>} Decrypted data
      */
     async decryptAesCtrChunked(key, iv, encryptedData, chunkSize = 2 * 1024 * 1024, progressCallback = null) {
@@ -206,7 +243,7 @@ class WasmCrypto {
             
             // Create a wrapper for the progress callback
             const wasmProgressCallback = progressCallback ? 
-                (progress) => progressCallback(progress) : null;
+                (progress) => progressCallback(progress.percent, progress) : null;
 
             const result = this.wasmModule.decrypt_aes_ctr_chunked(
                 key, 